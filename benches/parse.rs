@@ -0,0 +1,49 @@
+use std::{env, fs, path::PathBuf, str::FromStr};
+use criterion::{criterion_group, criterion_main, Criterion};
+use pngme::{mmap::MappedPng, Chunk, ChunkType, LazyPng, Png};
+
+const IDAT_CHUNKS: usize = 64;
+const IDAT_SIZE: usize = 1 << 20;
+
+fn large_png() -> PathBuf {
+    let file_path = env::temp_dir().join("pngme-bench-large.png");
+    if file_path.exists() {
+        return file_path;
+    }
+
+    let mut chunks: Vec<Chunk> = (0..IDAT_CHUNKS)
+        .map(|index| Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![index as u8; IDAT_SIZE]))
+        .collect();
+    chunks.push(Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"needle".to_vec()));
+    chunks.push(Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()));
+    fs::write(&file_path, Png::from_chunks(chunks).as_bytes()).unwrap();
+    file_path
+}
+
+fn extract_chunk(c: &mut Criterion) {
+    let file_path = large_png();
+    let mut group = c.benchmark_group("extract ruSt from 64 MiB");
+    group.sample_size(10);
+
+    group.bench_function("fs::read", |b| b.iter(|| {
+        let bytes = fs::read(&file_path).unwrap();
+        let png = Png::try_from(bytes.as_slice()).unwrap();
+        png.chunk_by_type("ruSt").unwrap().data().to_vec()
+    }));
+
+    group.bench_function("lazy", |b| b.iter(|| {
+        let mut png = LazyPng::open(&file_path).unwrap();
+        let index = png.position_by_type("ruSt").unwrap();
+        png.read_data(index).unwrap()
+    }));
+
+    group.bench_function("mmap", |b| b.iter(|| {
+        let png = MappedPng::open(&file_path).unwrap();
+        png.chunk_by_type("ruSt").unwrap().unwrap().data().to_vec()
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, extract_chunk);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+fn main() {
+    // Only the ffi feature has anything to generate
+    #[cfg(feature = "ffi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-env-changed=PNGME_UPDATE_HEADER");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("cbindgen.toml is invalid");
+        let bindings = cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/ffi.rs", crate_dir))
+            .generate()
+            .expect("cbindgen could not read src/ffi.rs");
+        // The checked-in header is only replaced on request, so builds never
+        // write to the source tree; a test checks it's up to date
+        bindings.write_to_file(format!("{}/pngme.h", out_dir));
+        if std::env::var_os("PNGME_UPDATE_HEADER").is_some() {
+            bindings.write_to_file(format!("{}/include/pngme.h", crate_dir));
+        }
+    }
+}
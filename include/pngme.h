@@ -0,0 +1,85 @@
+#ifndef PNGME_H
+#define PNGME_H
+
+/* Generated by cbindgen from src/ffi.rs; don't edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// A parsed PNG, owned by the caller until it is passed to `pngme_free`.
+typedef struct PngmePng PngmePng;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The message of the last error on this thread, or NULL if nothing has
+// failed yet. The string stays valid until the next failing call on the
+// same thread.
+const char *pngme_last_error(void);
+
+// Parses the `len` bytes at `data` as a PNG. Returns NULL on failure.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes.
+struct PngmePng *pngme_parse(const uint8_t *data, size_t len);
+
+// Frees a PNG from `pngme_parse`. NULL is ignored.
+//
+// # Safety
+//
+// `png` must come from `pngme_parse` and not have been freed already.
+void pngme_free(struct PngmePng *png);
+
+// The number of chunks in the PNG, IHDR and IEND included.
+//
+// # Safety
+//
+// `png` must be a live PNG from `pngme_parse`.
+size_t pngme_chunk_count(const struct PngmePng *png);
+
+// Points `data` and `len` at the data of the first chunk of type
+// `chunk_type`. The data belongs to the PNG and stays valid until it is
+// changed or freed. Returns 0 on success and -1 if there is no such chunk.
+//
+// # Safety
+//
+// `png` must be a live PNG from `pngme_parse`, `chunk_type` a NUL-terminated
+// string, and `data` and `len` writable.
+int pngme_get_chunk(const struct PngmePng *png,
+                    const char *chunk_type,
+                    const uint8_t **data,
+                    size_t *len);
+
+// Adds a chunk of type `chunk_type` holding a copy of the `len` bytes at
+// `data`, before IEND. Returns 0 on success and -1 on failure.
+//
+// # Safety
+//
+// `png` must be a live PNG from `pngme_parse`, `chunk_type` a NUL-terminated
+// string, and `data` point to `len` readable bytes (or be NULL if `len` is 0).
+int pngme_add_chunk(struct PngmePng *png, const char *chunk_type, const uint8_t *data, size_t len);
+
+// Writes the PNG out to a new buffer, returned through `data` and `len`.
+// The buffer must be freed with `pngme_bytes_free`. Returns 0.
+//
+// # Safety
+//
+// `png` must be a live PNG from `pngme_parse`, and `data` and `len`
+// writable.
+int pngme_serialize(const struct PngmePng *png, uint8_t **data, size_t *len);
+
+// Frees a buffer from `pngme_serialize`. NULL is ignored.
+//
+// # Safety
+//
+// `data` and `len` must be exactly as `pngme_serialize` returned them, and
+// not have been freed already.
+void pngme_bytes_free(uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PNGME_H */
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::ApngError;
+
+/// The chunk type marking an animated PNG, with its frame and play counts.
+pub const ACTL: &str = "acTL";
+/// The chunk type of each frame's region, delay and compositing.
+pub const FCTL: &str = "fcTL";
+/// The chunk type of the image data of frames after the first.
+pub const FDAT: &str = "fdAT";
+
+/// Delays with a denominator of 0 count in hundredths of a second.
+const DEFAULT_DELAY_DEN: u16 = 100;
+
+fn check_chunk<'a>(chunk: &'a Chunk, expected_type: &str) -> Result<&'a [u8], ApngError> {
+    let chunk_type = chunk.chunk_type().to_string();
+    if chunk_type != expected_type {
+        return Err(ApngError::WrongType { chunk_type, expected: expected_type.to_string() });
+    }
+    Ok(chunk.data())
+}
+
+fn check_len(chunk_type: &'static str, data: &[u8], expected: usize) -> Result<(), ApngError> {
+    match data.len() == expected {
+        true => Ok(()),
+        false => Err(ApngError::WrongLength { chunk_type, expected, len: data.len() })
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+/// The animation as a whole, from acTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationControl {
+    pub num_frames: u32,
+    /// How many times to play the animation; 0 loops forever.
+    pub num_plays: u32
+}
+
+impl AnimationControl {
+    pub fn parse(data: &[u8]) -> Result<AnimationControl, ApngError> {
+        check_len(ACTL, data, 8)?;
+        match read_u32(data, 0) {
+            0 => Err(ApngError::NoFrames),
+            num_frames => Ok(AnimationControl { num_frames, num_plays: read_u32(data, 4) })
+        }
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<AnimationControl, ApngError> {
+        AnimationControl::parse(check_chunk(chunk, ACTL)?)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [self.num_frames.to_be_bytes(), self.num_plays.to_be_bytes()].concat();
+        Chunk::new(ChunkType::from_str(ACTL).unwrap(), data)
+    }
+}
+
+/// Shown as e.g. `12 frames, loops forever`.
+impl fmt::Display for AnimationControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} frame{}, ", self.num_frames, if self.num_frames == 1 { "" } else { "s" })?;
+        match self.num_plays {
+            0 => f.write_str("loops forever"),
+            1 => f.write_str("plays once"),
+            plays => write!(f, "plays {} times", plays)
+        }
+    }
+}
+
+/// What happens to a frame's region before the next frame is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisposeOp {
+    /// The frame is left as it is.
+    None,
+    /// The region is cleared to transparent black.
+    Background,
+    /// The region is restored to what it was before the frame.
+    Previous
+}
+
+/// How a frame is drawn onto the region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    /// The frame replaces the region, alpha included.
+    Source,
+    /// The frame is alpha-composited over the region.
+    Over
+}
+
+/// One frame's region of the canvas, delay and compositing, from fcTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameControl {
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    /// The delay is `delay_num / delay_den` seconds.
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub dispose_op: DisposeOp,
+    pub blend_op: BlendOp
+}
+
+impl FrameControl {
+    pub fn parse(data: &[u8]) -> Result<FrameControl, ApngError> {
+        check_len(FCTL, data, 26)?;
+        let dispose_op = match data[24] {
+            0 => DisposeOp::None,
+            1 => DisposeOp::Background,
+            2 => DisposeOp::Previous,
+            op => return Err(ApngError::UnknownDisposeOp { op })
+        };
+        let blend_op = match data[25] {
+            0 => BlendOp::Source,
+            1 => BlendOp::Over,
+            op => return Err(ApngError::UnknownBlendOp { op })
+        };
+        let frame = FrameControl {
+            sequence_number: read_u32(data, 0),
+            width: read_u32(data, 4),
+            height: read_u32(data, 8),
+            x_offset: read_u32(data, 12),
+            y_offset: read_u32(data, 16),
+            delay_num: read_u16(data, 20),
+            delay_den: read_u16(data, 22),
+            dispose_op,
+            blend_op
+        };
+        if frame.width == 0 || frame.height == 0 {
+            return Err(ApngError::EmptyFrame { sequence_number: frame.sequence_number });
+        }
+        Ok(frame)
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<FrameControl, ApngError> {
+        FrameControl::parse(check_chunk(chunk, FCTL)?)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [
+            &self.sequence_number.to_be_bytes()[..],
+            &self.width.to_be_bytes(),
+            &self.height.to_be_bytes(),
+            &self.x_offset.to_be_bytes(),
+            &self.y_offset.to_be_bytes(),
+            &self.delay_num.to_be_bytes(),
+            &self.delay_den.to_be_bytes(),
+            &[self.dispose_op as u8, self.blend_op as u8]
+        ].concat();
+        Chunk::new(ChunkType::from_str(FCTL).unwrap(), data)
+    }
+
+    /// How long the frame is shown, in seconds.
+    pub fn delay(&self) -> f64 {
+        let den = match self.delay_den {
+            0 => DEFAULT_DELAY_DEN,
+            den => den
+        };
+        self.delay_num as f64 / den as f64
+    }
+
+    /// Whether the frame's region lies within a `width` by `height` canvas.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        self.x_offset as u64 + self.width as u64 <= width as u64
+            && self.y_offset as u64 + self.height as u64 <= height as u64
+    }
+}
+
+/// Shown as e.g. `#0: 100x80 at (10, 20), 0.1 s, dispose none, blend over`.
+impl fmt::Display for FrameControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dispose = match self.dispose_op {
+            DisposeOp::None => "none",
+            DisposeOp::Background => "background",
+            DisposeOp::Previous => "previous"
+        };
+        let blend = match self.blend_op {
+            BlendOp::Source => "source",
+            BlendOp::Over => "over"
+        };
+        write!(
+            f,
+            "#{}: {}x{} at ({}, {}), {} s, dispose {}, blend {}",
+            self.sequence_number,
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+            (self.delay() * 1000.0).round() / 1000.0,
+            dispose,
+            blend
+        )
+    }
+}
+
+/// The sequence number fdAT data starts with, which orders it among the
+/// fcTL and fdAT chunks.
+pub fn frame_data_sequence_number(data: &[u8]) -> Result<u32, ApngError> {
+    match data.get(..4) {
+        Some(bytes) => Ok(read_u32(bytes, 0)),
+        None => Err(ApngError::WrongLength { chunk_type: FDAT, expected: 4, len: data.len() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> FrameControl {
+        FrameControl {
+            sequence_number: 1,
+            width: 100,
+            height: 80,
+            x_offset: 10,
+            y_offset: 20,
+            delay_num: 1,
+            delay_den: 0,
+            dispose_op: DisposeOp::Previous,
+            blend_op: BlendOp::Over
+        }
+    }
+
+    #[test]
+    fn test_animation_control() {
+        let actl = AnimationControl { num_frames: 12, num_plays: 0 };
+        let chunk = actl.to_chunk();
+
+        assert_eq!(chunk.data(), [0, 0, 0, 12, 0, 0, 0, 0]);
+        assert_eq!(AnimationControl::from_chunk(&chunk).unwrap(), actl);
+        assert_eq!(actl.to_string(), "12 frames, loops forever");
+        assert_eq!(AnimationControl { num_frames: 1, num_plays: 3 }.to_string(), "1 frame, plays 3 times");
+        assert!(matches!(AnimationControl::parse(&[0; 8]), Err(ApngError::NoFrames)));
+    }
+
+    #[test]
+    fn test_frame_control_round_trip() {
+        let chunk = frame().to_chunk();
+
+        assert_eq!(chunk.data().len(), 26);
+        assert_eq!(&chunk.data()[20..], [0, 1, 0, 0, 2, 1]);
+        assert_eq!(FrameControl::from_chunk(&chunk).unwrap(), frame());
+        assert_eq!(frame().delay(), 0.01);
+        assert_eq!(frame().to_string(), "#1: 100x80 at (10, 20), 0.01 s, dispose previous, blend over");
+        assert!(frame().fits(110, 100));
+        assert!(!frame().fits(109, 100));
+    }
+
+    #[test]
+    fn test_invalid_frame_control() {
+        let mut data = frame().to_chunk().data().to_vec();
+        data[24] = 3;
+        assert!(matches!(FrameControl::parse(&data), Err(ApngError::UnknownDisposeOp { op: 3 })));
+        data[24] = 0;
+        data[4..8].copy_from_slice(&[0; 4]);
+        assert!(matches!(FrameControl::parse(&data), Err(ApngError::EmptyFrame { sequence_number: 1 })));
+        assert!(matches!(FrameControl::parse(&data[..25]), Err(ApngError::WrongLength { chunk_type: "fcTL", .. })));
+        assert_eq!(frame_data_sequence_number(&[0, 0, 1, 0, 9]).unwrap(), 256);
+        assert!(frame_data_sequence_number(&[0, 0, 1]).is_err());
+    }
+}
@@ -1,41 +1,746 @@
 use std::path::PathBuf;
-use clap::{Parser, Subcommand, Args};
+use std::str::FromStr;
+use clap::{ArgAction, Parser, Subcommand, Args, ValueEnum};
+use clap_complete::Shell;
+use pngme::crypto::KdfParams;
+use pngme::timestamp::Timestamp;
+use pngme::{scatter, text, Chunk, ChunkType, ChunkTypeError, TextError};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about=None)]
 pub struct Cli {
     #[command(subcommand)]
-    pub command: Commands
+    pub command: Commands,
+    /// Print results as human-readable text or as JSON
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+    /// Treat a directory argument as every PNG found beneath it
+    #[arg(short, long, global = true)]
+    pub recursive: bool,
+    /// Read defaults from this TOML file instead of
+    /// ~/.config/pngme/config.toml
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Show what pngme is doing: -v for parsing progress and timing, -vv
+    /// for every chunk and its offset
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Print nothing but errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// Never color the output, even on a terminal
+    #[arg(long, global = true)]
+    pub no_color: bool
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    Deflate,
+    Zstd
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnConflict {
+    Fail,
+    KeepBoth,
+    KeepFirst,
+    KeepLast
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
-    Encode(EncodeArgs),
+    // Boxed, as its many options make it far larger than the other variants
+    Encode(Box<EncodeArgs>),
     Decode(DecodeArgs),
     Remove(RemoveArgs),
-    Print(PrintArgs)
+    Print(PrintArgs),
+    List(ListArgs),
+    Check(CheckArgs),
+    Scrub(ScrubArgs),
+    Hexdump(HexdumpArgs),
+    /// Recompute the CRC of every chunk, e.g. after editing one by hand
+    FixCrc(FixCrcArgs),
+    /// Attach an ed25519 signature over the image or one chunk's data
+    Sign(SignArgs),
+    /// Check every signature in the file against a public key
+    Verify(VerifyArgs),
+    /// Generate an X25519 key pair for `encode --recipient`
+    Keygen(KeygenArgs),
+    /// Read or write tEXt keyword/value metadata, which other image tools understand
+    Text(TextArgs),
+    /// Record when the image was last modified in its tIME chunk
+    Touch(TouchArgs),
+    /// Set the image's resolution in its pHYs chunk
+    SetDpi(SetDpiArgs),
+    /// Extract or embed the image's ICC color profile (iCCP)
+    Icc(IccArgs),
+    /// Export or import the image's EXIF metadata (eXIf)
+    Exif(ExifArgs),
+    /// Print the colors of the image's palette (PLTE)
+    Palette(PaletteArgs),
+    /// Hide a message in the low bits of the image's pixels instead of a chunk
+    Lsb(LsbArgs),
+    /// Estimate how much each way of hiding a message can carry, and what it costs
+    Capacity(CapacityArgs),
+    /// Score PNGs by how likely they are to hide data, for triaging many files
+    Detect(DetectArgs),
+    /// Serve encode, decode, list and remove over HTTP, one PNG per request
+    Serve(ServeArgs),
+    /// Print a completion script for bash, zsh, fish, PowerShell or elvish
+    Completions(CompletionsArgs),
+    /// Print the man page of pngme or one of its subcommands, or write them
+    /// all into a directory
+    Man(ManArgs),
+    /// Browse the chunks of a PNG in the terminal, view them as hex or text
+    /// and delete them
+    Tui(TuiArgs),
+    /// Show the chunks added, removed or changed between two PNGs
+    Diff(DiffArgs),
+    /// Copy chunks of the given types from one PNG into another
+    CopyChunks(CopyChunksArgs),
+    /// Gather the payload chunks of several PNGs into the first of them
+    Merge(MergeArgs),
+    /// Sum up the chunks of a PNG, or of every PNG in a directory
+    Stats(StatsArgs),
+    /// Find the PNGs under a directory that have chunks outside the PNG
+    /// specification, reading only their chunk headers
+    Scan(ScanArgs),
+    /// Write the data of every payload chunk to a file of its own
+    Extract(ExtractArgs),
+    /// Build a PNG from a spec file naming a base image and the chunks to
+    /// add to it
+    Compose(ComposeArgs),
+    /// Watch a directory and encode or decode each PNG written to it
+    Watch(WatchArgs)
 }
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct EncodeArgs {
     pub file_path: PathBuf,
-    pub chunk_type: String,
-    pub message: String
+    /// Left out with --chunk-key, which derives the type instead
+    #[arg(required_unless_present_any = ["chunk_key", "scatter_key"])]
+    pub chunk_type: Option<String>,
+    #[arg(required_unless_present_any = ["message_file", "clipboard", "chunk_key", "scatter_key"])]
+    pub message: Option<String>,
+    #[arg(long, conflicts_with = "message")]
+    pub message_file: Option<PathBuf>,
+    /// Take the message from the system clipboard
+    #[arg(long, conflicts_with_all = ["message", "message_file"])]
+    pub clipboard: bool,
+    /// The message is base64 and is decoded to binary before it is embedded
+    #[arg(long)]
+    pub base64: bool,
+    /// Record the message's MIME type and length in an envelope
+    #[arg(long)]
+    pub envelope: bool,
+    /// MIME type for the envelope, which it implies; guessed from the data
+    /// when omitted
+    #[arg(long, value_name = "MIME")]
+    pub content_type: Option<String>,
+    /// Derive an inconspicuous private chunk type from this key instead of
+    /// naming one, e.g. `encode image.png --chunk-key <KEY> <MESSAGE>`
+    #[arg(long, value_name = "KEY")]
+    pub chunk_key: Option<String>,
+    /// Allow a chunk type whose reserved bit (third letter) is lowercase
+    #[arg(long)]
+    pub allow_reserved: bool,
+    /// Allow writing critical or standard chunk types, which can break the image
+    #[arg(long)]
+    pub force: bool,
+    /// Compress the message, when that makes it smaller
+    #[arg(long)]
+    pub compress: bool,
+    /// Compression method for --compress
+    #[arg(long, value_enum, value_name = "METHOD", default_value = "deflate")]
+    pub compression_method: Compression,
+    /// Compression level for --compress: 0-9 for deflate, 1-22 for zstd
+    #[arg(long, value_name = "N", requires = "compress", allow_negative_numbers = true)]
+    pub level: Option<i32>,
+    /// Encrypt the message with AES-256-GCM under a key derived from a passphrase
+    #[arg(long)]
+    pub encrypt: bool,
+    /// Passphrase for --encrypt; prompted for on the terminal when omitted
+    #[arg(long, requires = "encrypt")]
+    pub passphrase: Option<String>,
+    /// Encrypt with a 32-byte key read from this file (raw or base64) instead of a passphrase
+    #[arg(long, value_name = "FILE", requires = "encrypt", conflicts_with = "passphrase")]
+    pub key_file: Option<PathBuf>,
+    /// Argon2id memory cost in KiB for --encrypt [default: 65536]
+    #[arg(long, value_name = "KIB", requires = "encrypt", conflicts_with = "key_file", value_parser = clap::value_parser!(u32).range(8..=KdfParams::MAX_MEMORY_KIB as i64))]
+    pub argon2_memory: Option<u32>,
+    /// Argon2id iteration count for --encrypt [default: 3]
+    #[arg(long, value_name = "N", requires = "encrypt", conflicts_with = "key_file", value_parser = clap::value_parser!(u32).range(1..=KdfParams::MAX_ITERATIONS as i64))]
+    pub argon2_iterations: Option<u32>,
+    /// Argon2id parallelism for --encrypt [default: 1]
+    #[arg(long, value_name = "N", requires = "encrypt", conflicts_with = "key_file")]
+    pub argon2_parallelism: Option<u8>,
+    /// Encrypt into two slots that each open with their own passphrase; the
+    /// second holds --hidden-message, or random bytes that look the same
+    #[arg(long, requires = "encrypt", conflicts_with = "key_file")]
+    pub deniable: bool,
+    /// A second message for --deniable, which only --hidden-passphrase decrypts
+    #[arg(long, requires = "deniable", conflicts_with_all = ["envelope", "content_type", "compress"])]
+    pub hidden_message: Option<String>,
+    #[arg(long, value_name = "FILE", requires = "deniable", conflicts_with_all = ["hidden_message", "envelope", "content_type", "compress"])]
+    pub hidden_message_file: Option<PathBuf>,
+    /// Passphrase for --hidden-message; prompted for on the terminal when omitted
+    #[arg(long, requires = "deniable")]
+    pub hidden_passphrase: Option<String>,
+    /// Encrypt the message to an age recipient (age1...); may be repeated
+    #[arg(long, value_name = "RECIPIENT", conflicts_with = "encrypt")]
+    pub age_recipient: Vec<String>,
+    /// Encrypt the message to an X25519 public key from `pngme keygen`
+    #[arg(long, value_name = "PUBKEY", conflicts_with_all = ["encrypt", "age_recipient"])]
+    pub recipient: Option<String>,
+    /// Prefix the message with an HMAC-SHA256 tag under this shared key
+    #[arg(long, value_name = "KEY")]
+    pub hmac_key: Option<String>,
+    /// Split payloads larger than this across several chunks, e.g. 8000000
+    /// for readers built on libpng, which rejects larger ancillary chunks
+    #[arg(long, value_name = "BYTES", default_value_t = Chunk::MAX_LENGTH, value_parser = clap::value_parser!(u32).range(64..=Chunk::MAX_LENGTH as i64))]
+    pub max_chunk_size: u32,
+    /// Also add this many private chunks of random data, sized like the
+    /// payload, to hide which chunk is the real one
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub decoys: usize,
+    /// Scatter the payload across many small chunks whose types and order
+    /// are derived from this key, in place of the chunk type
+    #[arg(long, value_name = "KEY", conflicts_with_all = ["chunk_key", "decoys"])]
+    pub scatter_key: Option<String>,
+    /// The size of each piece for --scatter-key
+    #[arg(long, value_name = "BYTES", default_value_t = 128, requires = "scatter_key", value_parser = clap::value_parser!(u32).range(scatter::MIN_PIECE_LEN as i64..=Chunk::MAX_LENGTH as i64))]
+    pub scatter_size: u32,
+    /// When the image is a JPEG, hide the payload in comment segments rather
+    /// than APP15 ones
+    #[arg(long)]
+    pub jpeg_comment: bool,
+    /// Copy the PNG through chunk by chunk instead of reading it whole, so
+    /// images of any size take little memory
+    #[arg(long)]
+    pub stream: bool,
+    /// Take defaults from the [profile.NAME] table of the config file, on
+    /// top of those in [encode]
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// The chunk type from the config file, used when only a message is given
+    #[arg(skip)]
+    pub default_chunk_type: Option<String>,
+    #[command(flatten)]
+    pub edit: EditArgs
 }
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct DecodeArgs {
     pub file_path: PathBuf,
-    pub chunk_type: String
+    #[arg(value_parser = parse_chunk_type, required_unless_present_any = ["chunk_key", "scatter_key"])]
+    pub chunk_type: Option<String>,
+    /// Find the chunk written with `encode --chunk-key` under the same key
+    #[arg(long, value_name = "KEY", conflicts_with = "chunk_type")]
+    pub chunk_key: Option<String>,
+    /// Gather the pieces written with `encode --scatter-key` under the same key
+    #[arg(long, value_name = "KEY", conflicts_with_all = ["chunk_type", "chunk_key"])]
+    pub scatter_key: Option<String>,
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Copy the message to the system clipboard instead of printing it
+    #[arg(long, conflicts_with = "output")]
+    pub clipboard: bool,
+    /// Write the message as a QR code to this PNG instead of printing it
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["output", "clipboard"])]
+    pub qr: Option<PathBuf>,
+    #[arg(long, conflicts_with_all = ["output", "clipboard", "qr"])]
+    pub all: bool,
+    /// Which of several chunks of the same type to decode, counting from 0
+    #[arg(long, conflicts_with = "all")]
+    pub index: Option<usize>,
+    /// Print the message as base64, for binary payloads
+    #[arg(long)]
+    pub base64: bool,
+    /// Passphrase for decrypting messages written with `encode --encrypt`;
+    /// prompted for on the terminal when omitted
+    #[arg(long)]
+    pub passphrase: Option<String>,
+    /// Key file for decrypting messages written with `encode --key-file`
+    #[arg(long, value_name = "FILE")]
+    pub key_file: Option<PathBuf>,
+    /// Private key from `pngme keygen` for messages written with `encode --recipient`
+    #[arg(long, value_name = "FILE")]
+    pub private_key: Option<PathBuf>,
+    /// age identity file for decrypting messages written with `encode --age-recipient`
+    #[arg(long, value_name = "FILE")]
+    pub age_identity: Option<PathBuf>,
+    /// Shared key for checking messages written with `encode --hmac-key`
+    #[arg(long, value_name = "KEY")]
+    pub hmac_key: Option<String>
 }
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct RemoveArgs {
     pub file_path: PathBuf,
-    pub chunk_type: String
+    #[arg(value_parser = parse_chunk_type)]
+    pub chunk_type: String,
+    /// Remove every chunk of this type
+    #[arg(long)]
+    pub all: bool,
+    /// Which of several chunks of the same type to remove, counting from 0
+    #[arg(long, conflicts_with = "all")]
+    pub index: Option<usize>,
+    #[command(flatten)]
+    pub edit: EditArgs
 }
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct PrintArgs {
     pub file_path: PathBuf
-}
\ No newline at end of file
+}
+
+#[derive(Args, Clone)]
+pub struct ListArgs {
+    pub file_path: PathBuf
+}
+
+#[derive(Args, Clone)]
+pub struct CheckArgs {
+    pub file_path: PathBuf
+}
+
+#[derive(Args, Clone)]
+pub struct ScrubArgs {
+    pub file_path: PathBuf,
+    #[arg(long, value_delimiter = ',')]
+    pub keep: Vec<String>,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct FixCrcArgs {
+    pub file_path: PathBuf,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct SignArgs {
+    pub file_path: PathBuf,
+    /// PKCS#8 PEM private key, e.g. from `openssl genpkey -algorithm ed25519`
+    #[arg(long, value_name = "FILE")]
+    pub key: PathBuf,
+    /// Sign only the data of this chunk instead of the whole image
+    #[arg(long, value_parser = parse_chunk_type)]
+    pub chunk: Option<String>,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct VerifyArgs {
+    pub file_path: PathBuf,
+    /// PEM public key, e.g. from `openssl pkey -pubout`
+    #[arg(long, value_name = "FILE")]
+    pub public_key: PathBuf
+}
+
+#[derive(Args, Clone)]
+pub struct HexdumpArgs {
+    pub file_path: PathBuf,
+    #[arg(value_parser = parse_chunk_type)]
+    pub chunk_type: String
+}
+
+#[derive(Args, Clone)]
+pub struct KeygenArgs {
+    /// Writes the private key to <PREFIX>.key and the public key to <PREFIX>.pub
+    pub prefix: PathBuf,
+    /// Overwrite existing key files
+    #[arg(long)]
+    pub force: bool
+}
+
+#[derive(Args, Clone)]
+pub struct TextArgs {
+    #[command(subcommand)]
+    pub command: TextCommand
+}
+
+#[derive(Subcommand, Clone)]
+pub enum TextCommand {
+    /// Write a keyword's text, replacing any existing value
+    Set(TextSetArgs),
+    /// Print one keyword's text, or every keyword's
+    Get(TextGetArgs)
+}
+
+#[derive(Args, Clone)]
+pub struct TextSetArgs {
+    pub file_path: PathBuf,
+    /// e.g. Title, Author, Description or Comment
+    #[arg(value_parser = parse_keyword)]
+    pub keyword: String,
+    pub text: String,
+    /// Deflate the text, writing zTXt instead of tEXt or a compressed iTXt
+    #[arg(long)]
+    pub compress: bool,
+    /// Write an iTXt chunk in this language, e.g. en or ja-JP; iTXt is also
+    /// used whenever the text isn't Latin-1
+    #[arg(long, value_name = "TAG", value_parser = parse_language_tag)]
+    pub language: Option<String>,
+    /// The keyword in the text's language, written to an iTXt chunk
+    #[arg(long, value_name = "TEXT")]
+    pub translated_keyword: Option<String>,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct TextGetArgs {
+    pub file_path: PathBuf,
+    #[arg(value_parser = parse_keyword)]
+    pub keyword: Option<String>,
+    /// Only print iTXt text in this language
+    #[arg(long, value_name = "TAG", value_parser = parse_language_tag)]
+    pub language: Option<String>
+}
+
+#[derive(Args, Clone)]
+pub struct TouchArgs {
+    pub file_path: PathBuf,
+    /// An RFC 3339 timestamp such as 2023-07-21T09:30:00Z, converted to UTC;
+    /// defaults to now
+    #[arg(long, value_name = "RFC3339")]
+    pub time: Option<Timestamp>,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct SetDpiArgs {
+    pub file_path: PathBuf,
+    /// Dots per inch, stored as pixels per meter, e.g. 300
+    pub dpi: f64,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct IccArgs {
+    #[command(subcommand)]
+    pub command: IccCommand
+}
+
+#[derive(Subcommand, Clone)]
+pub enum IccCommand {
+    /// Write the embedded profile to a file
+    Extract(IccExtractArgs),
+    /// Embed a profile, replacing any existing one
+    Embed(IccEmbedArgs)
+}
+
+#[derive(Args, Clone)]
+pub struct IccExtractArgs {
+    pub file_path: PathBuf,
+    /// Where to write the profile, or - for stdout
+    pub output: PathBuf
+}
+
+#[derive(Args, Clone)]
+pub struct IccEmbedArgs {
+    pub file_path: PathBuf,
+    /// The ICC profile, e.g. an .icc or .icm file
+    pub profile: PathBuf,
+    /// The profile name stored in the chunk [default: the profile's file name]
+    #[arg(long, value_parser = parse_keyword)]
+    pub name: Option<String>,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct ExifArgs {
+    #[command(subcommand)]
+    pub command: ExifCommand
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ExifCommand {
+    /// Write the raw EXIF data to a file
+    Export(ExifExportArgs),
+    /// Store EXIF data from a file, replacing any existing eXIf chunk
+    Import(ExifImportArgs)
+}
+
+#[derive(Args, Clone)]
+pub struct ExifExportArgs {
+    pub file_path: PathBuf,
+    /// Where to write the EXIF data, or - for stdout
+    pub output: PathBuf
+}
+
+#[derive(Args, Clone)]
+pub struct ExifImportArgs {
+    pub file_path: PathBuf,
+    /// The EXIF data, as a raw TIFF blob or a JPEG APP1 payload starting with Exif\0\0
+    pub exif: PathBuf,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct PaletteArgs {
+    pub file_path: PathBuf,
+    /// Show each color as a swatch, for terminals with 24-bit color
+    #[arg(long)]
+    pub swatches: bool
+}
+
+#[derive(Args, Clone)]
+pub struct LsbArgs {
+    #[command(subcommand)]
+    pub command: LsbCommand
+}
+
+#[derive(Subcommand, Clone)]
+pub enum LsbCommand {
+    /// Hide a message in the pixel data, re-encoding IDAT
+    Encode(LsbEncodeArgs),
+    /// Read back a message hidden with `lsb encode`
+    Decode(LsbDecodeArgs)
+}
+
+#[derive(Args, Clone)]
+pub struct LsbEncodeArgs {
+    pub file_path: PathBuf,
+    #[arg(required_unless_present = "message_file")]
+    pub message: Option<String>,
+    #[arg(long, conflicts_with = "message")]
+    pub message_file: Option<PathBuf>,
+    /// The message is base64 and is decoded to binary before it is embedded
+    #[arg(long)]
+    pub base64: bool,
+    /// Low-order bits of each color sample to use; 2 doubles the capacity
+    /// but is easier to spot
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub bits: u8,
+    /// Compress the message, when that makes it smaller
+    #[arg(long)]
+    pub compress: bool,
+    /// Compression method for --compress
+    #[arg(long, value_enum, value_name = "METHOD", default_value = "deflate")]
+    pub compression_method: Compression,
+    /// Encrypt the message with AES-256-GCM under a key derived from a passphrase
+    #[arg(long)]
+    pub encrypt: bool,
+    /// Passphrase for --encrypt; prompted for on the terminal when omitted
+    #[arg(long, requires = "encrypt")]
+    pub passphrase: Option<String>,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct LsbDecodeArgs {
+    pub file_path: PathBuf,
+    /// The --bits the message was encoded with
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub bits: u8,
+    /// Passphrase for messages written with `lsb encode --encrypt`; prompted
+    /// for on the terminal when omitted
+    #[arg(long)]
+    pub passphrase: Option<String>,
+    #[arg(short, long)]
+    pub output: Option<PathBuf>
+}
+
+#[derive(Args, Clone)]
+pub struct CapacityArgs {
+    pub file_path: PathBuf,
+    /// Estimate the overhead for a payload of this many bytes, rather than
+    /// for filling the pixels
+    #[arg(long, value_name = "BYTES")]
+    pub size: Option<u64>
+}
+
+#[derive(Args, Clone)]
+pub struct DetectArgs {
+    /// A PNG, a glob such as "images/*.png", or a directory with --recursive
+    pub file_path: PathBuf,
+    /// Only report files scoring at least this, out of 100
+    #[arg(long, value_name = "SCORE", default_value_t = 0)]
+    pub min_score: u32
+}
+
+#[derive(Args, Clone)]
+pub struct ServeArgs {
+    /// The address and port to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub listen: String,
+    /// How many requests to handle at once
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub threads: u16,
+    /// Reject request bodies larger than this many bytes
+    #[arg(long, value_name = "BYTES", default_value_t = 64 * 1024 * 1024)]
+    pub max_body: u64
+}
+
+#[derive(Args, Clone)]
+pub struct ManArgs {
+    /// The subcommand to print the page of, e.g. `text set`; pngme's own
+    /// page when left out
+    #[arg(conflicts_with = "dir")]
+    pub command: Vec<String>,
+    /// Write pngme.1 and a page for every subcommand, such as
+    /// pngme-encode.1, into this directory
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>
+}
+
+#[derive(Args, Clone)]
+pub struct TuiArgs {
+    pub file_path: PathBuf,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct DiffArgs {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    /// Also list the chunks that are the same in both
+    #[arg(long)]
+    pub all: bool,
+    /// Show the hex dump lines that differ in each changed chunk
+    #[arg(long)]
+    pub data: bool
+}
+
+#[derive(Args, Clone)]
+pub struct CopyChunksArgs {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    #[arg(long, value_delimiter = ',', required = true, value_parser = parse_chunk_type)]
+    pub types: Vec<String>,
+    /// Also copy chunks that are unsafe to copy (uppercase fourth letter)
+    /// into an image with other critical chunks
+    #[arg(long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct MergeArgs {
+    /// The image to merge into, then those to take payloads from
+    #[arg(num_args = 2.., required = true, value_name = "FILE")]
+    pub file_paths: Vec<PathBuf>,
+    /// What to do when two images have chunks of one type with different
+    /// data
+    #[arg(long, value_enum, default_value = "fail")]
+    pub on_conflict: OnConflict,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct StatsArgs {
+    /// A PNG, a glob such as "images/*.png", or a directory
+    pub file_path: PathBuf,
+    /// How many of the largest chunks to list
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub top: usize
+}
+
+#[derive(Args, Clone)]
+pub struct ScanArgs {
+    pub dir: PathBuf,
+    /// Check every file by its contents, not just those named *.png
+    #[arg(long)]
+    pub all_files: bool
+}
+
+#[derive(Args, Clone)]
+pub struct ExtractArgs {
+    pub file_path: PathBuf,
+    /// The directory to write the files into, created if missing
+    #[arg(short, long, value_name = "DIR", default_value = ".")]
+    pub dir: PathBuf,
+    /// Replace files that are already there
+    #[arg(long)]
+    pub force: bool
+}
+
+#[derive(Args, Clone)]
+pub struct ComposeArgs {
+    /// A JSON or TOML file describing the image
+    pub spec: PathBuf,
+    #[command(flatten)]
+    pub edit: EditArgs
+}
+
+#[derive(Args, Clone)]
+pub struct CompletionsArgs {
+    #[arg(value_enum)]
+    pub shell: Shell
+}
+
+#[derive(Args, Clone)]
+pub struct WatchArgs {
+    pub dir: PathBuf,
+    #[arg(long, value_parser = parse_chunk_type)]
+    pub chunk: String,
+    #[arg(long, value_name = "MESSAGE")]
+    pub encode: Option<String>,
+    /// Allow a chunk type whose reserved bit (third letter) is lowercase
+    #[arg(long)]
+    pub allow_reserved: bool,
+    /// Allow writing critical or standard chunk types, which can break the image
+    #[arg(long)]
+    pub force: bool
+}
+
+#[derive(Args, Clone)]
+pub struct EditArgs {
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Write edited images into this directory under their own names,
+    /// instead of over the originals
+    #[arg(long, value_name = "DIR", conflicts_with = "output")]
+    pub output_dir: Option<PathBuf>,
+    #[arg(long)]
+    pub dry_run: bool,
+    #[arg(long)]
+    pub backup: bool,
+    #[arg(long, value_name = "SUFFIX")]
+    pub backup_suffix: Option<String>,
+    #[arg(long, value_name = "DIR")]
+    pub backup_dir: Option<PathBuf>,
+    /// Keep bytes found after IEND without warning about them
+    #[arg(long, conflicts_with = "strip_trailing")]
+    pub keep_trailing: bool,
+    /// Drop bytes found after IEND when rewriting the file
+    #[arg(long)]
+    pub strip_trailing: bool,
+    /// Print the result as a data:image/png;base64 URI instead of writing a file
+    #[arg(long, conflicts_with_all = ["output", "output_dir", "dry_run", "backup", "backup_suffix", "backup_dir"])]
+    pub data_uri: bool
+}
+
+fn parse_chunk_type(s: &str) -> Result<String, ChunkTypeError> {
+    ChunkType::from_str(s)?;
+    Ok(s.to_string())
+}
+
+fn parse_keyword(s: &str) -> Result<String, TextError> {
+    text::validate_keyword(s)?;
+    Ok(s.to_string())
+}
+
+fn parse_language_tag(s: &str) -> Result<String, TextError> {
+    text::validate_language_tag(s)?;
+    Ok(s.to_string())
+}
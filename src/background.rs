@@ -0,0 +1,125 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::BackgroundError;
+use crate::ihdr::{ColorType, Ihdr};
+
+/// The chunk type of the default background color.
+pub const BKGD: &str = "bKGD";
+
+/// The color to show the image against, laid out according to the image's
+/// color type. Samples are always stored as 16 bits, whatever the bit depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    /// For grayscale images, with or without alpha.
+    Gray(u16),
+    /// For RGB images, with or without alpha.
+    Rgb(u16, u16, u16),
+    /// For indexed images, an entry of PLTE.
+    PaletteIndex(u8)
+}
+
+impl Background {
+    pub fn parse(data: &[u8], color_type: ColorType) -> Result<Background, BackgroundError> {
+        let expected = match color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgb | ColorType::Rgba => 6,
+            ColorType::Indexed => 1
+        };
+        if data.len() != expected {
+            return Err(BackgroundError::WrongLength { color_type, expected, len: data.len() });
+        }
+        let sample = |index: usize| u16::from_be_bytes([data[index * 2], data[index * 2 + 1]]);
+        Ok(match color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => Background::Gray(sample(0)),
+            ColorType::Rgb | ColorType::Rgba => Background::Rgb(sample(0), sample(1), sample(2)),
+            ColorType::Indexed => Background::PaletteIndex(data[0])
+        })
+    }
+
+    pub fn from_chunk(chunk: &Chunk, color_type: ColorType) -> Result<Background, BackgroundError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != BKGD {
+            return Err(BackgroundError::NotBkgd { chunk_type });
+        }
+        Background::parse(chunk.data(), color_type)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match *self {
+            Background::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Background::Rgb(red, green, blue) => [red, green, blue].iter().flat_map(|sample| sample.to_be_bytes()).collect(),
+            Background::PaletteIndex(index) => vec![index]
+        };
+        Chunk::new(ChunkType::from_str(BKGD).unwrap(), data)
+    }
+
+    /// Checks the samples fit the image's bit depth and the palette index
+    /// names one of `palette_entries`.
+    pub fn validate(&self, ihdr: &Ihdr, palette_entries: usize) -> Result<(), BackgroundError> {
+        let samples = match *self {
+            Background::Gray(gray) => vec![gray],
+            Background::Rgb(red, green, blue) => vec![red, green, blue],
+            Background::PaletteIndex(index) if index as usize >= palette_entries => {
+                return Err(BackgroundError::IndexOutOfRange { index, palette_entries });
+            },
+            Background::PaletteIndex(_) => vec![]
+        };
+        match samples.into_iter().find(|&sample| sample > ihdr.max_sample()) {
+            Some(sample) => Err(BackgroundError::SampleOutOfRange { sample, bit_depth: ihdr.bit_depth }),
+            None => Ok(())
+        }
+    }
+}
+
+/// Shown as e.g. `background RGB (255, 255, 255)`.
+impl fmt::Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Background::Gray(gray) => write!(f, "background gray {}", gray),
+            Background::Rgb(red, green, blue) => write!(f, "background RGB ({}, {}, {})", red, green, blue),
+            Background::PaletteIndex(index) => write!(f, "background palette entry {}", index)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_round_trip() {
+        let background = Background::Rgb(255, 128, 0);
+        let chunk = background.to_chunk();
+
+        assert_eq!(chunk.chunk_type().to_string(), "bKGD");
+        assert_eq!(chunk.data(), [0, 255, 0, 128, 0, 0]);
+        assert_eq!(Background::from_chunk(&chunk, ColorType::Rgba).unwrap(), background);
+        assert_eq!(background.to_string(), "background RGB (255, 128, 0)");
+        assert_eq!(Background::parse(&[0, 7], ColorType::GrayscaleAlpha).unwrap(), Background::Gray(7));
+        assert_eq!(Background::parse(&[3], ColorType::Indexed).unwrap().to_string(), "background palette entry 3");
+    }
+
+    #[test]
+    fn test_background_must_suit_image() {
+        assert!(matches!(
+            Background::parse(&[0, 0], ColorType::Rgb),
+            Err(BackgroundError::WrongLength { expected: 6, len: 2, .. })
+        ));
+
+        let gray = Ihdr::new(1, 1, 4, ColorType::Grayscale).unwrap();
+        assert!(Background::Gray(15).validate(&gray, 0).is_ok());
+        assert!(matches!(
+            Background::Gray(16).validate(&gray, 0),
+            Err(BackgroundError::SampleOutOfRange { sample: 16, bit_depth: 4 })
+        ));
+        let indexed = Ihdr::new(1, 1, 8, ColorType::Indexed).unwrap();
+        assert!(Background::PaletteIndex(3).validate(&indexed, 4).is_ok());
+        assert!(matches!(
+            Background::PaletteIndex(4).validate(&indexed, 4),
+            Err(BackgroundError::IndexOutOfRange { index: 4, palette_entries: 4 })
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use log::{debug, error, info};
+use crate::args::Format;
+use crate::progress::{Progress, Unit};
+use crate::remote;
+use crate::s3;
+use anyhow::Result;
+
+const GLOB_CHARACTERS: [char; 3] = ['*', '?', '['];
+
+pub struct Batch {
+    pub recursive: bool,
+    pub format: Format
+}
+
+impl Batch {
+    pub fn run<F>(&self, file_path: &Path, has_output: bool, mut command: F) -> Result<()>
+    where
+        F: FnMut(PathBuf) -> Result<()>
+    {
+        let file_paths = expand_paths(file_path, self.recursive)?;
+        debug!("{} matches {} file(s)", file_path.display(), file_paths.len());
+        if file_paths.len() == 1 {
+            return command(file_paths.into_iter().next().unwrap());
+        }
+
+        if has_output {
+            return Err(anyhow!("--output cannot be used when {} matches several files", file_path.display()));
+        }
+
+        let total = file_paths.len();
+        let mut failed = 0;
+        let mut progress = Progress::new("Processing", Unit::Files, total as u64);
+        for file_path in file_paths {
+            progress.clear();
+            if self.format == Format::Text {
+                println!("==> {} <==", file_path.display());
+            }
+            if let Err(error) = command(file_path.clone()) {
+                error!("{}: {}", file_path.display(), error);
+                failed += 1;
+            }
+            progress.inc(1);
+        }
+        progress.clear();
+
+        info!("Processed {} file(s): {} succeeded, {} failed", total, total - failed, failed);
+        if failed > 0 {
+            return Err(anyhow!("{} of {} file(s) failed", failed, total));
+        }
+        Ok(())
+    }
+}
+
+pub fn expand_paths(file_path: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    if recursive && file_path.is_dir() {
+        let mut file_paths = Vec::new();
+        walk(file_path, is_png_path, &mut file_paths)?;
+        file_paths.sort();
+        return non_empty(file_path, file_paths);
+    }
+
+    // Query strings are full of glob characters
+    let pattern = file_path.to_string_lossy();
+    if !pattern.contains(GLOB_CHARACTERS) || remote::is_url(file_path) {
+        return Ok(vec![file_path.to_path_buf()]);
+    }
+
+    let file_paths = match s3::is_s3(file_path) {
+        true => s3::glob(file_path, &GLOB_CHARACTERS)?,
+        false => glob::glob(&pattern)?.collect::<std::result::Result<Vec<PathBuf>, _>>()?
+    };
+    non_empty(file_path, file_paths)
+}
+
+fn non_empty(file_path: &Path, file_paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    if file_paths.is_empty() {
+        return Err(anyhow!("No PNG files found for {}", file_path.display()));
+    }
+    Ok(file_paths)
+}
+
+/// Every file under `dir`, whatever its name, for finding PNGs that were
+/// renamed to hide what they are.
+pub fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut file_paths = Vec::new();
+    walk(dir, |_| true, &mut file_paths)?;
+    file_paths.sort();
+    Ok(file_paths)
+}
+
+fn walk(dir: &Path, keep: fn(&Path) -> bool, file_paths: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, keep, file_paths)?;
+        } else if keep(&path) {
+            file_paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub fn is_png_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("pngme-batch-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        for file in ["a.png", "b.PNG", "notes.txt", "nested/c.png"] {
+            fs::write(dir.join(file), []).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_plain_path_is_kept() {
+        let file_paths = expand_paths(Path::new("missing.png"), false).unwrap();
+        assert_eq!(file_paths, vec![PathBuf::from("missing.png")]);
+    }
+
+    #[test]
+    fn test_glob_pattern() {
+        let dir = testing_dir("glob");
+        let file_paths = expand_paths(&dir.join("*.png"), false).unwrap();
+        assert_eq!(file_paths, vec![dir.join("a.png")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recursive_directory() {
+        let dir = testing_dir("recursive");
+        let file_paths = expand_paths(&dir, true).unwrap();
+        assert_eq!(file_paths, vec![dir.join("a.png"), dir.join("b.PNG"), dir.join("nested/c.png")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_walk_files() {
+        let dir = testing_dir("walk");
+        let file_paths = walk_files(&dir).unwrap();
+        assert_eq!(file_paths, vec![dir.join("a.png"), dir.join("b.PNG"), dir.join("nested/c.png"), dir.join("notes.txt")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_no_matches() {
+        let dir = testing_dir("empty");
+        assert!(expand_paths(&dir.join("*.gif"), false).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use crate::{
+    Result,
+    check,
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::PngError,
+    png::Png
+};
+
+/// Assembles a PNG chunk by chunk and checks the result against the PNG
+/// ordering rules before handing it out. IEND is added by `build()`.
+pub struct PngBuilder {
+    chunks: Vec<Chunk>
+}
+
+impl PngBuilder {
+    pub fn new(width: u32, height: u32, bit_depth: u8, color_type: u8) -> PngBuilder {
+        let mut data = Vec::with_capacity(13);
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        // Compression, filter and interlace methods all default to 0
+        data.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+
+        PngBuilder::from_ihdr(Chunk::new(ChunkType::from_str("IHDR").unwrap(), data))
+    }
+
+    pub fn from_ihdr(ihdr: Chunk) -> PngBuilder {
+        PngBuilder {
+            chunks: vec![ihdr]
+        }
+    }
+
+    pub fn from_png(png: Png) -> PngBuilder {
+        let mut chunks = png.into_chunks();
+        chunks.retain(|chunk| chunk.chunk_type().to_string() != "IEND");
+
+        PngBuilder {
+            chunks
+        }
+    }
+
+    pub fn chunk(mut self, chunk: Chunk) -> PngBuilder {
+        self.chunks.push(chunk);
+        self
+    }
+
+    pub fn data_chunk(self, chunk_type: &str, data: Vec<u8>) -> Result<PngBuilder> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        // Where the chunk would start in the built PNG
+        let offset = Png::STANDARD_HEADER.len() + self.chunks.iter().map(|chunk| 12 + chunk.length() as usize).sum::<usize>();
+        let chunk = Chunk::try_new(chunk_type, data).map_err(|source| PngError::InvalidChunk { offset, source })?;
+        Ok(self.chunk(chunk))
+    }
+
+    pub fn build(mut self) -> Result<Png> {
+        let ends_with_iend = self.chunks
+            .last()
+            .is_some_and(|chunk| chunk.chunk_type().to_string() == "IEND");
+        if !ends_with_iend {
+            self.chunks.push(Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()));
+        }
+
+        let png = Png::from_chunks(self.chunks);
+        let problems: Vec<_> = check::validate(&png.as_bytes())
+            .into_iter()
+            .filter(check::Problem::is_error)
+            .collect();
+        if !problems.is_empty() {
+            return Err(PngError::InvalidStructure { problems });
+        }
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_build_minimal_png() {
+        let png = PngBuilder::new(1, 1, 8, 2)
+            .data_chunk("IDAT", vec![1, 2, 3]).unwrap()
+            .data_chunk("tEXt", b"Comment\0hi".to_vec()).unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(chunk_types(&png), vec!["IHDR", "IDAT", "tEXt", "IEND"]);
+        assert_eq!(png.chunks()[0].data(), &[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_build_rejects_bad_ordering() {
+        let error = PngBuilder::new(1, 1, 8, 2)
+            .data_chunk("IDAT", vec![1]).unwrap()
+            .data_chunk("tEXt", Vec::new()).unwrap()
+            .data_chunk("IDAT", vec![2]).unwrap()
+            .build()
+            .unwrap_err();
+
+        assert!(error.to_string().contains("interrupts the run of IDAT"));
+    }
+
+    #[test]
+    fn test_build_requires_palette() {
+        let result = PngBuilder::new(1, 1, 8, 3)
+            .data_chunk("IDAT", vec![1]).unwrap()
+            .build();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_from_png_moves_iend_to_the_end() {
+        let png = PngBuilder::new(1, 1, 8, 0)
+            .data_chunk("IDAT", vec![1]).unwrap()
+            .build()
+            .unwrap();
+
+        let png = PngBuilder::from_png(png)
+            .data_chunk("ruSt", b"hello".to_vec()).unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(chunk_types(&png), vec!["IHDR", "IDAT", "ruSt", "IEND"]);
+    }
+}
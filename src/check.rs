@@ -0,0 +1,787 @@
+use std::fmt;
+use crate::{
+    apng::{self, AnimationControl, FrameControl},
+    background::Background,
+    chunk::Chunk,
+    chunk_ref::ChunkRef,
+    chunk_type::ChunkType,
+    color::{self, Chromaticities, Gamma, RenderingIntent},
+    error::ColorError,
+    ihdr::{ColorType, Ihdr},
+    palette::{Histogram, Palette, SuggestedPalette},
+    png::Png,
+    significant_bits::SignificantBits,
+    transparency::Transparency
+};
+
+/// Ancillary chunks that have to come before PLTE and IDAT.
+pub(crate) const BEFORE_PLTE: [&[u8;4]; 5] = [b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB"];
+/// Ancillary chunks that have to come after PLTE, if there is one, and before IDAT.
+pub(crate) const AFTER_PLTE: [&[u8;4]; 3] = [b"bKGD", b"hIST", b"tRNS"];
+/// Ancillary chunks that only have to come before IDAT.
+pub(crate) const BEFORE_IDAT: [&[u8;4]; 4] = [b"acTL", b"eXIf", b"pHYs", b"sPLT"];
+/// Chunks that may appear at most once.
+pub(crate) const SINGLE: [&[u8;4]; 13] = [
+    b"PLTE", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB",
+    b"bKGD", b"hIST", b"tRNS", b"acTL", b"eXIf", b"pHYs", b"tIME"
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub offset: usize,
+    pub message: String,
+    pub severity: Severity
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset {}: {}", self.offset, self.message)
+    }
+}
+
+impl Problem {
+    fn new(offset: usize, message: impl Into<String>) -> Problem {
+        Problem {
+            offset,
+            message: message.into(),
+            severity: Severity::Error
+        }
+    }
+
+    fn warning(offset: usize, message: impl Into<String>) -> Problem {
+        Problem {
+            severity: Severity::Warning,
+            ..Problem::new(offset, message)
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+struct RawChunk<'a> {
+    offset: usize,
+    chunk_type: [u8;4],
+    data: &'a [u8]
+}
+
+impl RawChunk<'_> {
+    fn is(&self, chunk_type: &[u8;4]) -> bool {
+        &self.chunk_type == chunk_type
+    }
+
+    fn type_name(&self) -> String {
+        String::from_utf8_lossy(&self.chunk_type).into_owned()
+    }
+}
+
+pub fn validate(bytes: &[u8]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    if bytes.len() < Png::STANDARD_HEADER.len() || bytes[..8] != Png::STANDARD_HEADER {
+        problems.push(Problem::new(0, "The signature doesn't match the PNG standard header"));
+        return problems;
+    }
+
+    let chunks = scan_chunks(bytes, &mut problems);
+    check_ordering(&chunks, bytes.len(), &mut problems);
+
+    problems.sort_by_key(|problem| problem.offset);
+    problems
+}
+
+/// Checks an already parsed Png against the chunk ordering rules.
+pub fn validate_ordering(png: &Png) -> Vec<Problem> {
+    let chunks: Vec<RawChunk> = png
+        .chunks_with_offsets()
+        .map(|(offset, chunk)| RawChunk {
+            offset,
+            chunk_type: chunk.chunk_type().bytes(),
+            data: chunk.data()
+        })
+        .collect();
+
+    let mut problems = Vec::new();
+    check_ordering(&chunks, png.byte_len(), &mut problems);
+
+    problems.sort_by_key(|problem| problem.offset);
+    problems
+}
+
+fn scan_chunks<'a>(bytes: &'a [u8], problems: &mut Vec<Problem>) -> Vec<RawChunk<'a>> {
+    let mut chunks = Vec::new();
+    let mut offset = Png::STANDARD_HEADER.len();
+
+    while offset < bytes.len() {
+        if chunks.iter().any(|chunk: &RawChunk| chunk.is(b"IEND")) && !is_whole_chunk(&bytes[offset..]) {
+            problems.push(Problem::warning(offset, format!(
+                "{} bytes of trailing data follow IEND",
+                bytes.len() - offset
+            )));
+            break;
+        }
+
+        let (parsed, _) = match ChunkRef::parse(&bytes[offset..]) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                problems.push(Problem::new(offset, error.to_string()));
+                break;
+            }
+        };
+
+        let chunk_type = parsed.chunk_type_bytes();
+        let data = parsed.data();
+        let crc = parsed.crc();
+        let chunk = RawChunk { offset, chunk_type, data };
+
+        match ChunkType::try_from(chunk_type) {
+            Ok(parsed) if !parsed.is_valid() => problems.push(Problem::new(offset, format!(
+                "Chunk type {} has the reserved bit set",
+                chunk.type_name()
+            ))),
+            Ok(_) => (),
+            Err(_) => problems.push(Problem::new(offset, format!(
+                "Chunk type {:?} is not made of ASCII letters",
+                chunk_type
+            )))
+        }
+
+        let expected_crc = Chunk::checksum(&chunk_type, data);
+        if expected_crc != crc {
+            problems.push(Problem::new(offset, format!(
+                "Chunk {} has CRC {:#010x} but its contents hash to {:#010x}",
+                chunk.type_name(),
+                crc,
+                expected_crc
+            )));
+        }
+
+        chunks.push(chunk);
+        offset += parsed.byte_len();
+    }
+
+    chunks
+}
+
+fn is_whole_chunk(bytes: &[u8]) -> bool {
+    ChunkRef::parse(bytes).is_ok_and(|(chunk, _)| chunk.to_chunk().is_ok())
+}
+
+fn check_ordering(chunks: &[RawChunk], file_length: usize, problems: &mut Vec<Problem>) {
+    let first_offset = Png::STANDARD_HEADER.len();
+
+    match chunks.first() {
+        Some(chunk) if chunk.is(b"IHDR") => {
+            if let Err(error) = Ihdr::parse(chunk.data) {
+                problems.push(Problem::new(chunk.offset, error.to_string()));
+            }
+        },
+        Some(chunk) => problems.push(Problem::new(chunk.offset, format!(
+            "The first chunk must be IHDR but is {}",
+            chunk.type_name()
+        ))),
+        None => problems.push(Problem::new(first_offset, "The file contains no chunks"))
+    }
+
+    for chunk in chunks.iter().skip(1).filter(|chunk| chunk.is(b"IHDR")) {
+        problems.push(Problem::new(chunk.offset, "IHDR appears more than once"));
+    }
+
+    match chunks.iter().position(|chunk| chunk.is(b"IEND")) {
+        Some(iend) => {
+            for chunk in &chunks[iend + 1..] {
+                problems.push(Problem::new(chunk.offset, format!(
+                    "Chunk {} appears after IEND, which must be the last chunk",
+                    chunk.type_name()
+                )));
+            }
+        },
+        None => problems.push(Problem::new(file_length, "The file contains no IEND chunk"))
+    }
+
+    let idat_indices: Vec<usize> = chunks
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| chunk.is(b"IDAT"))
+        .map(|(index, _)| index)
+        .collect();
+
+    match (idat_indices.first(), idat_indices.last()) {
+        (Some(&first), Some(&last)) => {
+            for chunk in &chunks[first..=last] {
+                if !chunk.is(b"IDAT") {
+                    problems.push(Problem::new(chunk.offset, format!(
+                        "Chunk {} interrupts the run of IDAT chunks",
+                        chunk.type_name()
+                    )));
+                }
+            }
+
+            let plte = chunks.iter().position(|chunk| chunk.is(b"PLTE"));
+            if let Some(plte) = plte.filter(|&plte| plte > first) {
+                problems.push(Problem::new(chunks[plte].offset, "PLTE must appear before the first IDAT"));
+            }
+        },
+        _ => problems.push(Problem::new(first_offset, "The file contains no IDAT chunk"))
+    }
+
+    check_placement(chunks, problems);
+    check_color(chunks, problems);
+
+    let ihdr = chunks
+        .first()
+        .filter(|chunk| chunk.is(b"IHDR"))
+        .and_then(|chunk| Ihdr::parse(chunk.data).ok());
+    if let Some(ihdr) = &ihdr {
+        check_palette(chunks, ihdr, problems);
+        check_samples(chunks, ihdr, problems);
+        check_animation(chunks, ihdr, problems);
+    }
+    let is_indexed = ihdr.is_some_and(|ihdr| ihdr.color_type == ColorType::Indexed);
+    if is_indexed && !chunks.iter().any(|chunk| chunk.is(b"PLTE")) {
+        problems.push(Problem::new(first_offset, "Indexed-color images require a PLTE chunk"));
+    }
+}
+
+fn check_placement(chunks: &[RawChunk], problems: &mut Vec<Problem>) {
+    let plte = chunks.iter().position(|chunk| chunk.is(b"PLTE"));
+    let idat = chunks.iter().position(|chunk| chunk.is(b"IDAT"));
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let is_any = |types: &[&[u8;4]]| types.contains(&&chunk.chunk_type);
+
+        if is_any(&BEFORE_PLTE) && plte.is_some_and(|plte| index > plte) {
+            problems.push(Problem::new(chunk.offset, format!(
+                "Chunk {} must appear before PLTE",
+                chunk.type_name()
+            )));
+        }
+        if is_any(&AFTER_PLTE) && plte.is_some_and(|plte| index < plte) {
+            problems.push(Problem::new(chunk.offset, format!(
+                "Chunk {} must appear after PLTE",
+                chunk.type_name()
+            )));
+        }
+        let before_idat = is_any(&BEFORE_PLTE) || is_any(&AFTER_PLTE) || is_any(&BEFORE_IDAT);
+        if before_idat && idat.is_some_and(|idat| index > idat) {
+            problems.push(Problem::new(chunk.offset, format!(
+                "Chunk {} must appear before the first IDAT",
+                chunk.type_name()
+            )));
+        }
+
+        if is_any(&SINGLE) && chunks[..index].iter().any(|earlier| earlier.chunk_type == chunk.chunk_type) {
+            problems.push(Problem::new(chunk.offset, format!(
+                "Chunk {} appears more than once",
+                chunk.type_name()
+            )));
+        }
+    }
+}
+
+/// Reports malformed gAMA, cHRM and sRGB chunks, and color chunks that
+/// contradict sRGB, which decoders may resolve differently.
+fn check_color(chunks: &[RawChunk], problems: &mut Vec<Problem>) {
+    let gama = parse_color(chunks, b"gAMA", Gamma::parse, problems);
+    let chrm = parse_color(chunks, b"cHRM", Chromaticities::parse, problems);
+    if parse_color(chunks, b"sRGB", RenderingIntent::parse, problems).is_none() {
+        return;
+    }
+
+    if let Some((offset, gamma)) = gama.filter(|(_, gamma)| *gamma != color::SRGB_GAMMA) {
+        problems.push(Problem::warning(offset, format!(
+            "gAMA ({}) contradicts sRGB, which implies {}",
+            gamma,
+            color::SRGB_GAMMA
+        )));
+    }
+    if let Some((offset, chromaticities)) = chrm.filter(|(_, chromaticities)| {
+        !chromaticities.approx_eq(&color::SRGB_CHROMATICITIES)
+    }) {
+        problems.push(Problem::warning(offset, format!(
+            "cHRM ({}) contradicts sRGB, which implies {}",
+            chromaticities,
+            color::SRGB_CHROMATICITIES
+        )));
+    }
+    if let Some(iccp) = chunks.iter().find(|chunk| chunk.is(b"iCCP")) {
+        problems.push(Problem::warning(
+            iccp.offset,
+            "sRGB and iCCP both describe the color space; only one of them should be present"
+        ));
+    }
+}
+
+fn parse_color<T>(
+    chunks: &[RawChunk],
+    chunk_type: &[u8;4],
+    parse: fn(&[u8]) -> Result<T, ColorError>,
+    problems: &mut Vec<Problem>
+) -> Option<(usize, T)> {
+    let chunk = chunks.iter().find(|chunk| chunk.is(chunk_type))?;
+    match parse(chunk.data) {
+        Ok(value) => Some((chunk.offset, value)),
+        Err(error) => {
+            problems.push(Problem::new(chunk.offset, error.to_string()));
+            None
+        }
+    }
+}
+
+/// Reports a malformed PLTE, or one the color type doesn't allow or the bit
+/// depth can't index, and malformed hIST and sPLT chunks.
+fn check_palette(chunks: &[RawChunk], ihdr: &Ihdr, problems: &mut Vec<Problem>) {
+    let plte = chunks.iter().find(|chunk| chunk.is(b"PLTE"));
+    if let Some(plte) = plte {
+        if let Err(error) = Palette::parse(plte.data).and_then(|palette| palette.validate(ihdr)) {
+            problems.push(Problem::new(plte.offset, error.to_string()));
+        }
+    }
+
+    if let Some(hist) = chunks.iter().find(|chunk| chunk.is(b"hIST")) {
+        let palette_entries = plte.map_or(0, |plte| plte.data.len() / 3);
+        if let Err(error) = Histogram::parse(hist.data).and_then(|histogram| histogram.validate(palette_entries)) {
+            problems.push(Problem::new(hist.offset, error.to_string()));
+        }
+    }
+
+    let mut names = Vec::new();
+    for splt in chunks.iter().filter(|chunk| chunk.is(b"sPLT")) {
+        match SuggestedPalette::parse(splt.data) {
+            Ok(palette) if names.contains(&palette.name) => problems.push(Problem::new(splt.offset, format!(
+                "Suggested palette {:?} appears more than once",
+                palette.name
+            ))),
+            Ok(palette) => names.push(palette.name),
+            Err(error) => problems.push(Problem::new(splt.offset, error.to_string()))
+        }
+    }
+}
+
+/// Reports bKGD, tRNS and sBIT chunks whose layout doesn't suit the color
+/// type, or whose values don't fit the bit depth or the palette.
+fn check_samples(chunks: &[RawChunk], ihdr: &Ihdr, problems: &mut Vec<Problem>) {
+    let palette_entries = chunks
+        .iter()
+        .find(|chunk| chunk.is(b"PLTE"))
+        .map_or(0, |plte| plte.data.len() / 3);
+
+    if let Some(bkgd) = chunks.iter().find(|chunk| chunk.is(b"bKGD")) {
+        let result = Background::parse(bkgd.data, ihdr.color_type)
+            .and_then(|background| background.validate(ihdr, palette_entries));
+        if let Err(error) = result {
+            problems.push(Problem::new(bkgd.offset, error.to_string()));
+        }
+    }
+    if let Some(trns) = chunks.iter().find(|chunk| chunk.is(b"tRNS")) {
+        let result = Transparency::parse(trns.data, ihdr.color_type)
+            .and_then(|transparency| transparency.validate(ihdr, palette_entries));
+        if let Err(error) = result {
+            problems.push(Problem::new(trns.offset, error.to_string()));
+        }
+    }
+    if let Some(sbit) = chunks.iter().find(|chunk| chunk.is(b"sBIT")) {
+        let result = SignificantBits::parse(sbit.data, ihdr.color_type).and_then(|bits| bits.validate(ihdr));
+        if let Err(error) = result {
+            problems.push(Problem::new(sbit.offset, error.to_string()));
+        }
+    }
+}
+
+/// Reports malformed APNG chunks, frames outside the canvas, a frame count
+/// that doesn't match acTL, and fcTL and fdAT sequence numbers that don't
+/// count up from 0, which decoders rely on to put the frames in order.
+fn check_animation(chunks: &[RawChunk], ihdr: &Ihdr, problems: &mut Vec<Problem>) {
+    let is_animation_chunk = |chunk: &&RawChunk| chunk.is(b"fcTL") || chunk.is(b"fdAT");
+    let Some(actl) = chunks.iter().find(|chunk| chunk.is(b"acTL")) else {
+        if let Some(chunk) = chunks.iter().find(is_animation_chunk) {
+            problems.push(Problem::warning(chunk.offset, format!(
+                "Chunk {} is ignored without an acTL chunk",
+                chunk.type_name()
+            )));
+        }
+        return;
+    };
+    let animation = AnimationControl::parse(actl.data)
+        .map_err(|error| problems.push(Problem::new(actl.offset, error.to_string())))
+        .ok();
+
+    let first_idat = chunks.iter().position(|chunk| chunk.is(b"IDAT"));
+    let mut expected_sequence_number = 0;
+    let mut frames = 0;
+    for (index, chunk) in chunks.iter().enumerate().filter(|(_, chunk)| is_animation_chunk(chunk)) {
+        let sequence_number = match chunk.is(b"fcTL") {
+            true => FrameControl::parse(chunk.data).map(|frame| {
+                frames += 1;
+                check_frame(&frame, ihdr, first_idat.is_some_and(|idat| index < idat), chunk.offset, problems);
+                frame.sequence_number
+            }),
+            false => apng::frame_data_sequence_number(chunk.data)
+        };
+        let sequence_number = match sequence_number {
+            Ok(sequence_number) => sequence_number,
+            Err(error) => {
+                problems.push(Problem::new(chunk.offset, error.to_string()));
+                continue;
+            }
+        };
+        if sequence_number != expected_sequence_number {
+            problems.push(Problem::new(chunk.offset, format!(
+                "Chunk {} has sequence number {} but {} was expected",
+                chunk.type_name(),
+                sequence_number,
+                expected_sequence_number
+            )));
+        }
+        expected_sequence_number = sequence_number.wrapping_add(1);
+    }
+
+    if let Some(animation) = animation.filter(|animation| animation.num_frames != frames) {
+        problems.push(Problem::new(actl.offset, format!(
+            "acTL declares {} frames but there are {} fcTL chunks",
+            animation.num_frames,
+            frames
+        )));
+    }
+}
+
+/// Frames have to lie within the canvas, and a frame before IDAT, whose data
+/// is the default image, has to cover all of it.
+fn check_frame(frame: &FrameControl, ihdr: &Ihdr, is_default_image: bool, offset: usize, problems: &mut Vec<Problem>) {
+    let covers_canvas = frame.width == ihdr.width && frame.height == ihdr.height && frame.x_offset == 0 && frame.y_offset == 0;
+    if is_default_image && !covers_canvas {
+        problems.push(Problem::new(offset, format!(
+            "Frame #{} uses the IDAT image but doesn't cover the {}x{} canvas",
+            frame.sequence_number,
+            ihdr.width,
+            ihdr.height
+        )));
+    } else if !frame.fits(ihdr.width, ihdr.height) {
+        problems.push(Problem::new(offset, format!(
+            "Frame #{} extends beyond the {}x{} canvas",
+            frame.sequence_number,
+            ihdr.width,
+            ihdr.height
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk_bytes(chunk_type: &str, data: &[u8]) -> Vec<u8> {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec()).as_bytes()
+    }
+
+    fn png_bytes(chunks: &[Vec<u8>]) -> Vec<u8> {
+        Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(chunks.iter().flatten().copied())
+            .collect()
+    }
+
+    fn ihdr(color_type: u8) -> Vec<u8> {
+        chunk_bytes("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, color_type, 0, 0, 0])
+    }
+
+    #[test]
+    fn test_valid_png() {
+        let bytes = png_bytes(&[
+            ihdr(2),
+            chunk_bytes("IDAT", &[1, 2, 3]),
+            chunk_bytes("IDAT", &[4, 5, 6]),
+            chunk_bytes("IEND", &[])
+        ]);
+
+        assert_eq!(validate(&bytes), vec![]);
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let problems = validate(&[0, 1, 2, 3]);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].offset, 0);
+    }
+
+    #[test]
+    fn test_reports_every_bad_crc() {
+        let mut bad_idat = chunk_bytes("IDAT", &[1, 2, 3]);
+        *bad_idat.last_mut().unwrap() ^= 0xff;
+        let mut bad_iend = chunk_bytes("IEND", &[]);
+        *bad_iend.last_mut().unwrap() ^= 0xff;
+
+        let bytes = png_bytes(&[ihdr(2), bad_idat, bad_iend]);
+        let problems = validate(&bytes);
+
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].offset, 33);
+        assert_eq!(problems[1].offset, 48);
+    }
+
+    #[test]
+    fn test_ordering_problems() {
+        let bytes = png_bytes(&[
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("teXt", &[]),
+            chunk_bytes("IDAT", &[2]),
+            ihdr(2)
+        ]);
+        let problems = validate(&bytes);
+
+        assert!(problems.iter().any(|p| p.message.contains("first chunk must be IHDR")));
+        assert!(problems.iter().any(|p| p.message.contains("no IEND")));
+        assert!(problems.iter().any(|p| p.message.contains("interrupts the run of IDAT")));
+    }
+
+    #[test]
+    fn test_missing_palette() {
+        let bytes = png_bytes(&[
+            ihdr(ColorType::Indexed.to_byte()),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&bytes);
+
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("PLTE"));
+    }
+
+    #[test]
+    fn test_truncated_chunk() {
+        let mut bytes = png_bytes(&[ihdr(2), chunk_bytes("IDAT", &[1, 2, 3])]);
+        bytes.truncate(bytes.len() - 2);
+        let problems = validate(&bytes);
+
+        assert!(problems.iter().any(|p| p.offset == 33 && p.message.contains("exceeds")));
+    }
+
+    #[test]
+    fn test_ancillary_placement() {
+        let bytes = png_bytes(&[
+            ihdr(ColorType::Indexed.to_byte()),
+            chunk_bytes("tRNS", &[0]),
+            chunk_bytes("PLTE", &[0, 0, 0]),
+            chunk_bytes("gAMA", &[0, 0, 0, 1]),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("pHYs", &[0; 9]),
+            chunk_bytes("tIME", &[0; 7]),
+            chunk_bytes("tIME", &[0; 7]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let messages: Vec<String> = validate(&bytes)
+            .into_iter()
+            .map(|problem| problem.message)
+            .collect();
+
+        assert_eq!(messages, vec![
+            "Chunk tRNS must appear after PLTE",
+            "Chunk gAMA must appear before PLTE",
+            "Chunk pHYs must appear before the first IDAT",
+            "Chunk tIME appears more than once"
+        ]);
+    }
+
+    #[test]
+    fn test_validate_ordering_of_png() {
+        let png = Png::try_from(png_bytes(&[
+            ihdr(2),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[]),
+            chunk_bytes("ruSt", b"late")
+        ]).as_slice()).unwrap();
+        let problems = validate_ordering(&png);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].offset, 58);
+    }
+
+    #[test]
+    fn test_trailing_data_is_a_warning() {
+        let mut bytes = png_bytes(&[
+            ihdr(2),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        bytes.extend_from_slice(b"PK\x03\x04 appended archive");
+        let problems = validate(&bytes);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].offset, 58);
+        assert_eq!(problems[0].severity, Severity::Warning);
+        assert!(problems[0].message.contains("21 bytes of trailing data"));
+    }
+
+    #[test]
+    fn test_invalid_ihdr_fields() {
+        let bytes = png_bytes(&[
+            chunk_bytes("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 16, 3, 0, 0, 0]),
+            chunk_bytes("PLTE", &[0, 0, 0]),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&bytes);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].offset, 8);
+        assert!(problems[0].message.contains("Bit depth 16 isn't allowed for indexed images"));
+    }
+
+    #[test]
+    fn test_color_chunks_contradicting_srgb() {
+        let bytes = png_bytes(&[
+            ihdr(2),
+            chunk_bytes("gAMA", &100000u32.to_be_bytes()),
+            chunk_bytes("sRGB", &[0]),
+            chunk_bytes("iCCP", b"icc\0\0"),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&bytes);
+
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(|problem| problem.severity == Severity::Warning));
+        assert!(problems[0].message.contains("gAMA (gamma 1 (1/1)) contradicts sRGB"));
+        assert!(problems[1].message.contains("sRGB and iCCP"));
+    }
+
+    #[test]
+    fn test_malformed_color_chunks() {
+        let bytes = png_bytes(&[
+            ihdr(2),
+            chunk_bytes("gAMA", &[0, 0, 0, 0]),
+            chunk_bytes("sRGB", &[7]),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&bytes);
+
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(Problem::is_error));
+        assert!(problems[0].message.contains("gamma of 0"));
+        assert!(problems[1].message.contains("rendering intent 7"));
+    }
+
+    #[test]
+    fn test_background_and_transparency_must_suit_ihdr() {
+        let rgb = png_bytes(&[
+            ihdr(2),
+            chunk_bytes("bKGD", &[0, 0]),
+            chunk_bytes("tRNS", &[0, 0, 1, 0, 0, 0]),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&rgb);
+
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].message.contains("bKGD holds 6 bytes for RGB images but this one has 2"));
+        assert!(problems[1].message.contains("sample 256 doesn't fit the bit depth 8"));
+
+        let indexed = png_bytes(&[
+            ihdr(3),
+            chunk_bytes("PLTE", &[0; 6]),
+            chunk_bytes("tRNS", &[0, 0, 0]),
+            chunk_bytes("bKGD", &[1]),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&indexed);
+
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("3 alpha values but the palette only 2 entries"));
+    }
+
+    #[test]
+    fn test_palette_must_suit_ihdr() {
+        let grayscale = png_bytes(&[
+            ihdr(0),
+            chunk_bytes("PLTE", &[0; 3]),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&grayscale);
+
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("PLTE isn't allowed in grayscale images"));
+
+        let indexed = png_bytes(&[
+            ihdr(3),
+            chunk_bytes("PLTE", &[0; 4]),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&indexed);
+
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("is 4 bytes long"));
+    }
+
+    #[test]
+    fn test_palette_companions_must_fit() {
+        let bytes = png_bytes(&[
+            ihdr(3),
+            chunk_bytes("sBIT", &[8, 9, 8]),
+            chunk_bytes("PLTE", &[0; 6]),
+            chunk_bytes("hIST", &[0; 6]),
+            chunk_bytes("sPLT", b"web\0\x08"),
+            chunk_bytes("sPLT", b"web\0\x10"),
+            chunk_bytes("IDAT", &[1]),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&bytes);
+
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].message.contains("9 significant bits"));
+        assert!(problems[1].message.contains("hIST has 3 entries but the palette has 2"));
+        assert!(problems[2].message.contains("\"web\" appears more than once"));
+    }
+
+    #[test]
+    fn test_apng_sequence_numbers() {
+        let frame = |sequence_number: u32, width: u32| {
+            let mut data = [0; 26];
+            data[..4].copy_from_slice(&sequence_number.to_be_bytes());
+            data[4..8].copy_from_slice(&width.to_be_bytes());
+            data[8..12].copy_from_slice(&1u32.to_be_bytes());
+            chunk_bytes("fcTL", &data)
+        };
+        let fdat = |sequence_number: u32| chunk_bytes("fdAT", &[&sequence_number.to_be_bytes()[..], &[1]].concat());
+
+        let valid = png_bytes(&[
+            ihdr(2),
+            chunk_bytes("acTL", &[0, 0, 0, 2, 0, 0, 0, 0]),
+            frame(0, 1),
+            chunk_bytes("IDAT", &[1]),
+            frame(1, 1),
+            fdat(2),
+            chunk_bytes("IEND", &[])
+        ]);
+        assert_eq!(validate(&valid), vec![]);
+
+        let invalid = png_bytes(&[
+            ihdr(2),
+            chunk_bytes("acTL", &[0, 0, 0, 3, 0, 0, 0, 0]),
+            frame(0, 1),
+            chunk_bytes("IDAT", &[1]),
+            frame(2, 2),
+            fdat(2),
+            chunk_bytes("IEND", &[])
+        ]);
+        let problems = validate(&invalid);
+
+        assert_eq!(problems.len(), 4);
+        assert!(problems[0].message.contains("acTL declares 3 frames but there are 2"));
+        assert!(problems[1].message.contains("Frame #2 extends beyond the 1x1 canvas"));
+        assert!(problems[2].message.contains("fcTL has sequence number 2 but 1 was expected"));
+        assert!(problems[3].message.contains("fdAT has sequence number 2 but 3 was expected"));
+    }
+}
@@ -1,14 +1,15 @@
 use std::fmt;
+use std::io::{self, Write};
 use crate::{
+    chunk_ref::ChunkRef,
     chunk_type::ChunkType,
-    Result,
-    Error
+    error::ChunkError,
+    Result
 };
 use crc::{Crc, CRC_32_ISO_HDLC};
-use anyhow::anyhow;
 
 
-const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+pub(crate) const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
 
 #[derive(Debug)]
 pub struct Chunk {
@@ -22,106 +23,73 @@ impl fmt::Display for Chunk {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.length)?;
         write!(f, "{}", self.chunk_type())?;
-        write!(f, "{}", self.data_as_string().unwrap())?;
+        write!(f, "{}", String::from_utf8_lossy(&self.data))?;
         write!(f, "{}", self.crc)
     }
 }
 
 impl TryFrom<&[u8]> for Chunk {
-    type Error = Error;
+    type Error = ChunkError;
 
-    fn try_from(value: &[u8]) -> Result<Self> {
-        let bytes_length: u32 = value.len().try_into().unwrap();
-
-        if bytes_length < 12 {
-            return Err(anyhow!("There is not enough bytes to for required fields"))
+    fn try_from(value: &[u8]) -> Result<Self, ChunkError> {
+        let (chunk, rest) = ChunkRef::parse(value)?;
+        if !rest.is_empty() {
+            return Err(ChunkError::TrailingBytes { trailing: rest.len() })
         }
 
-        let mut value = value.into_iter();
-
-        let length_bytes: [u8;4] = value
-            .by_ref()
-            .take(4)
-            .copied()
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap();
-
-        let length = u32::from_be_bytes(length_bytes);
-
-        if bytes_length != 12 + length {
-            return Err(anyhow!("There is not enough bytes"))
-        } 
+        chunk.to_chunk()
+    }
+}
 
-        let chunk_type_bytes: [u8;4] = value
-            .by_ref()
-            .take(4)
-            .copied()
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap();
-        let chunk_type = ChunkType::try_from(chunk_type_bytes)?;
-
-        let data: Box<[u8]> = value
-            .by_ref()
-            .take(length.try_into().unwrap())
-            .copied()
-            .collect();
+impl Chunk {
+    /// The largest data length the PNG specification allows, 2^31 - 1.
+    pub const MAX_LENGTH: u32 = (1 << 31) - 1;
 
-        let crc_bytes: [u8;4] = value
-            .by_ref()
-            .take(4)
-            .copied()
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap();
-        let crc = u32::from_be_bytes(crc_bytes);
+    /// Panics if `data` is longer than `MAX_LENGTH`; use `try_new` for untrusted sizes.
+    pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
+        match Chunk::try_new(chunk_type, data) {
+            Ok(chunk) => chunk,
+            Err(error) => panic!("{}", error)
+        }
+    }
 
-        let crc_input: Vec<u8> = chunk_type_bytes
-            .iter()
-            .chain(data.as_ref())
-            .copied()
-            .collect();
-        if CRC.checksum(&crc_input) != crc {
-            return Err(anyhow!("The crc checksum is invalid"))
+    pub fn try_new(chunk_type: ChunkType, data: Vec<u8>) -> Result<Chunk, ChunkError> {
+        if data.len() > Chunk::MAX_LENGTH as usize {
+            return Err(ChunkError::LengthTooLarge { length: data.len() });
         }
 
+        let data = data.into_boxed_slice();
+        let crc: u32 = Chunk::checksum(&chunk_type.bytes(), &data);
+
         Ok(Chunk {
-            length,
+            length: data.len() as u32,
             chunk_type,
             data,
             crc
         })
     }
-}
-
-impl Chunk {
-    pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
-        let data = data.into_boxed_slice();
-        let length: u32 = data.len().try_into().unwrap();
-        
-        let crc_input: Vec<u8> = chunk_type
-            .bytes()
-            .iter()
-            .chain(data.iter())
-            .copied()
-            .collect();
-
-        let crc: u32 = CRC.checksum(crc_input.as_slice());
 
+    pub(crate) fn from_parts(chunk_type: ChunkType, data: Box<[u8]>, crc: u32) -> Chunk {
         Chunk {
-            length,
+            length: data.len() as u32,
             chunk_type,
             data,
             crc
         }
     }
 
-    fn length(&self) -> u32 {
+    pub fn checksum(chunk_type_bytes: &[u8;4], data: &[u8]) -> u32 {
+        let mut digest = CRC.digest();
+        digest.update(chunk_type_bytes);
+        digest.update(data);
+        digest.finalize()
+    }
+
+    pub fn length(&self) -> u32 {
         self.length
     }
 
-    fn crc(&self) -> u32 {
+    pub fn crc(&self) -> u32 {
         self.crc
     }
 
@@ -129,15 +97,24 @@ impl Chunk {
         &self.chunk_type
     }
 
-    fn data(&self) -> &[u8] {
+    pub fn data(&self) -> &[u8] {
         &self.data
     }
 
-    pub fn data_as_string(&self) -> Result<String> {
-        match String::from_utf8(self.data.to_vec()) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(anyhow!(e))
-        }
+    pub fn data_as_string(&self) -> Result<String, ChunkError> {
+        Ok(String::from_utf8(self.data.to_vec())?)
+    }
+
+    pub fn byte_len(&self) -> usize {
+        12 + self.data.len()
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.length.to_be_bytes())?;
+        writer.write_all(&self.chunk_type.bytes())?;
+        writer.write_all(&self.data)?;
+        writer.write_all(&self.crc.to_be_bytes())?;
+        Ok(())
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
@@ -152,6 +129,55 @@ impl Chunk {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Chunk {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Chunk", 4)?;
+        state.serialize_field("chunk_type", &self.chunk_type)?;
+        state.serialize_field("length", &self.length)?;
+        state.serialize_field("crc", &self.crc)?;
+        state.serialize_field("data", &STANDARD.encode(&self.data))?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Chunk {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use serde::de::Error as _;
+
+        #[derive(serde::Deserialize)]
+        struct SerializedChunk {
+            chunk_type: ChunkType,
+            length: Option<u32>,
+            crc: Option<u32>,
+            data: String
+        }
+
+        let serialized = SerializedChunk::deserialize(deserializer)?;
+        let data = STANDARD.decode(&serialized.data).map_err(D::Error::custom)?;
+        let chunk = Chunk::try_new(serialized.chunk_type, data).map_err(D::Error::custom)?;
+
+        if serialized.length.is_some_and(|length| length != chunk.length) {
+            return Err(D::Error::custom(format!(
+                "chunk {} declares length {:?} but its data is {} bytes",
+                chunk.chunk_type, serialized.length.unwrap(), chunk.length
+            )));
+        }
+        if serialized.crc.is_some_and(|crc| crc != chunk.crc) {
+            return Err(D::Error::custom(format!(
+                "chunk {} declares CRC {:#010x} but its contents hash to {:#010x}",
+                chunk.chunk_type, serialized.crc.unwrap(), chunk.crc
+            )));
+        }
+        Ok(chunk)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,6 +223,20 @@ mod tests {
         assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
     }
 
+    #[test]
+    fn test_chunk_byte_len() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.byte_len(), chunk.as_bytes().len());
+    }
+
+    #[test]
+    fn test_chunk_write_to() {
+        let chunk = testing_chunk();
+        let mut bytes = Vec::new();
+        chunk.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, chunk.as_bytes());
+    }
+
     #[test]
     fn test_chunk_string() {
         let chunk = testing_chunk();
@@ -279,4 +319,42 @@ mod tests {
         
         let _chunk_string = format!("{}", chunk);
     }
+
+    #[test]
+    fn test_chunk_display_non_utf8() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0x68, 0xff, 0x69]);
+        assert_eq!(chunk.to_string(), format!("3RuSth\u{FFFD}i{}", chunk.crc()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_data_is_base64() {
+        let chunk = testing_chunk();
+        let json = serde_json::to_value(&chunk).unwrap();
+        assert_eq!(json["chunk_type"], "RuSt");
+        assert_eq!(json["data"], "VGhpcyBpcyB3aGVyZSB5b3VyIHNlY3JldCBtZXNzYWdlIHdpbGwgYmUh");
+
+        let restored: Chunk = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.as_bytes(), chunk.as_bytes());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_rejects_bad_crc() {
+        let json = r#"{"chunk_type":"RuSt","crc":1,"data":"aGk="}"#;
+        assert!(serde_json::from_str::<Chunk>(json).is_err());
+        assert!(serde_json::from_str::<Chunk>(r#"{"chunk_type":"RuS","data":""}"#).is_err());
+    }
+
+    #[test]
+    fn test_chunk_length_limit() {
+        assert_eq!(Chunk::MAX_LENGTH, 2147483647);
+
+        let mut bytes = testing_chunk().as_bytes();
+        bytes[0] = 0x80;
+        assert!(matches!(
+            Chunk::try_from(bytes.as_ref()),
+            Err(ChunkError::LengthTooLarge { length: 2147483690 })
+        ));
+    }
 }
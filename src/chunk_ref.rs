@@ -0,0 +1,187 @@
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::{ChunkError, ChunkTypeError, PngError},
+    Result
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRef<'a> {
+    chunk_type: [u8;4],
+    data: &'a [u8],
+    crc: u32
+}
+
+impl<'a> ChunkRef<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<(ChunkRef<'a>, &'a [u8]), ChunkError> {
+        if bytes.len() < 12 {
+            return Err(ChunkError::Truncated { remaining: bytes.len() });
+        }
+
+        let length = u32::from_be_bytes(bytes[0..4].try_into().unwrap()) as usize;
+        if length > Chunk::MAX_LENGTH as usize {
+            return Err(ChunkError::LengthTooLarge { length });
+        }
+        if bytes.len() - 12 < length {
+            return Err(ChunkError::LengthExceedsInput {
+                length,
+                remaining: bytes.len() - 12
+            });
+        }
+
+        let (chunk_bytes, rest) = bytes.split_at(12 + length);
+        let chunk = ChunkRef {
+            chunk_type: chunk_bytes[4..8].try_into().unwrap(),
+            data: &chunk_bytes[8..8 + length],
+            crc: u32::from_be_bytes(chunk_bytes[8 + length..].try_into().unwrap())
+        };
+
+        Ok((chunk, rest))
+    }
+
+    pub fn chunk_type_bytes(&self) -> [u8;4] {
+        self.chunk_type
+    }
+
+    pub fn chunk_type(&self) -> Result<ChunkType, ChunkTypeError> {
+        ChunkType::try_from(self.chunk_type)
+    }
+
+    pub fn length(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    pub fn byte_len(&self) -> usize {
+        12 + self.data.len()
+    }
+
+    pub fn is_crc_valid(&self) -> bool {
+        Chunk::checksum(&self.chunk_type, self.data) == self.crc
+    }
+
+    /// Checks the CRC and copies the data into an owned `Chunk`.
+    pub fn to_chunk(&self) -> Result<Chunk, ChunkError> {
+        let chunk_type = self.chunk_type()?;
+        let expected = Chunk::checksum(&self.chunk_type, self.data);
+        if expected != self.crc {
+            return Err(ChunkError::InvalidCrc { expected, found: self.crc });
+        }
+        Ok(Chunk::from_parts(chunk_type, self.data.into(), self.crc))
+    }
+}
+
+pub struct ChunkRefs<'a> {
+    bytes: &'a [u8],
+    offset: usize
+}
+
+impl<'a> ChunkRefs<'a> {
+    pub fn new(bytes: &'a [u8], offset: usize) -> ChunkRefs<'a> {
+        ChunkRefs {
+            bytes,
+            offset
+        }
+    }
+}
+
+impl<'a> Iterator for ChunkRefs<'a> {
+    type Item = Result<(usize, ChunkRef<'a>), PngError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        match ChunkRef::parse(self.bytes) {
+            Ok((chunk, rest)) => {
+                let offset = self.offset;
+                self.bytes = rest;
+                self.offset += chunk.byte_len();
+                Some(Ok((offset, chunk)))
+            },
+            Err(source) => {
+                self.bytes = &[];
+                Some(Err(PngError::InvalidChunk { offset: self.offset, source }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        let first = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"first".to_vec());
+        let second = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"second!".to_vec());
+        [first.as_bytes(), second.as_bytes()].concat()
+    }
+
+    #[test]
+    fn test_parse_borrows_input() {
+        let bytes = testing_bytes();
+        let (chunk, rest) = ChunkRef::parse(&bytes).unwrap();
+
+        assert_eq!(&chunk.chunk_type_bytes(), b"RuSt");
+        assert_eq!(chunk.data(), b"first");
+        assert_eq!(chunk.data().as_ptr(), bytes[8..].as_ptr());
+        assert!(chunk.is_crc_valid());
+        assert_eq!(rest.len(), 19);
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let bytes = testing_bytes();
+        assert!(matches!(ChunkRef::parse(&bytes[..11]), Err(ChunkError::Truncated { remaining: 11 })));
+        assert!(matches!(
+            ChunkRef::parse(&bytes[..16]),
+            Err(ChunkError::LengthExceedsInput { length: 5, remaining: 4 })
+        ));
+
+        let mut oversized = bytes.clone();
+        oversized[0] = 0x80;
+        assert!(matches!(ChunkRef::parse(&oversized), Err(ChunkError::LengthTooLarge { .. })));
+    }
+
+    #[test]
+    fn test_to_chunk_rejects_bad_crc() {
+        let mut bytes = testing_bytes();
+        bytes[16] ^= 0xff;
+        let (chunk, _) = ChunkRef::parse(&bytes).unwrap();
+
+        assert!(!chunk.is_crc_valid());
+        assert!(matches!(chunk.to_chunk(), Err(ChunkError::InvalidCrc { .. })));
+    }
+
+    #[test]
+    fn test_chunk_refs_offsets() {
+        let bytes = testing_bytes();
+        let chunks: Vec<(usize, ChunkRef)> = ChunkRefs::new(&bytes, 8)
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0, 8);
+        assert_eq!(chunks[1].0, 25);
+        assert_eq!(chunks[1].1.data(), b"second!");
+    }
+
+    #[test]
+    fn test_chunk_refs_stops_after_error() {
+        let bytes = testing_bytes();
+        let mut chunks = ChunkRefs::new(&bytes[..bytes.len() - 1], 0);
+
+        assert!(chunks.next().unwrap().is_ok());
+        assert!(chunks.next().unwrap().is_err());
+        assert!(chunks.next().is_none());
+    }
+}
@@ -1,18 +1,26 @@
 use std::{str::FromStr, fmt};
-use crate::{Result, Error};
-use anyhow::anyhow;
-
-#[derive(Debug, PartialEq, Eq)]
+use crate::{Result, error::ChunkTypeError};
+
+/// Chunk types registered by the PNG specification and its APNG extension.
+const STANDARD_TYPES: [&[u8;4]; 25] = [
+    b"IHDR", b"PLTE", b"IDAT", b"IEND",
+    b"cHRM", b"cICP", b"cLLI", b"gAMA", b"iCCP", b"mDCV", b"sBIT", b"sRGB",
+    b"bKGD", b"hIST", b"tRNS", b"eXIf", b"pHYs", b"sPLT",
+    b"tIME", b"iTXt", b"tEXt", b"zTXt",
+    b"acTL", b"fcTL", b"fdAT"
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChunkType {
     value: [u8; 4]
 }
 
 impl TryFrom<[u8;4]> for ChunkType {
-    type Error = Error;
+    type Error = ChunkTypeError;
 
-    fn try_from(value: [u8;4]) -> Result<Self> {
+    fn try_from(value: [u8;4]) -> Result<Self, ChunkTypeError> {
         if !ChunkType::is_bytes_all_ascii(value) {
-            Err(anyhow!("The bytes are not between 65-90 or 97-122, i.e. ASCII"))
+            Err(ChunkTypeError::NotAlphabetic { bytes: value })
         } else {
             Ok(ChunkType {
                 value
@@ -22,11 +30,10 @@ impl TryFrom<[u8;4]> for ChunkType {
 }
 
 impl FromStr for ChunkType {
-    type Err = Error;
+    type Err = ChunkTypeError;
     
-    fn from_str(s: &str) -> Result<Self> {
-        let bytes: [u8;4] = s.as_bytes().try_into().unwrap();
-        ChunkType::try_from(bytes)
+    fn from_str(s: &str) -> Result<Self, ChunkTypeError> {
+        ChunkType::parse(s, true)
     }
 }
 
@@ -38,8 +45,25 @@ impl fmt::Display for ChunkType {
 }
 
 impl ChunkType {
+    /// Parses a chunk type, rejecting a set reserved bit unless `allow_reserved` is true.
+    pub fn parse(s: &str, allow_reserved: bool) -> Result<ChunkType, ChunkTypeError> {
+        let bytes: [u8;4] = s
+            .as_bytes()
+            .try_into()
+            .map_err(|_| ChunkTypeError::WrongLength {
+                chunk_type: s.to_string(),
+                len: s.len()
+            })?;
+        let chunk_type = ChunkType::try_from(bytes)?;
+
+        if !allow_reserved && !ChunkType::is_bytes_reserved_bit_valid(bytes) {
+            return Err(ChunkTypeError::ReservedBitSet { chunk_type: s.to_string() });
+        }
+        Ok(chunk_type)
+    }
+
     fn is_bytes_all_ascii(value: [u8;4]) -> bool {
-        value.iter().all(|&x| (65<=x&&x<=90) || (97<=x&&x<=122))
+        value.iter().all(|x| (65..=90).contains(x) || (97..=122).contains(x))
     }
 
     fn is_bytes_reserved_bit_valid(value: [u8;4]) -> bool {
@@ -52,33 +76,59 @@ impl ChunkType {
         self.value
     }
 
-    fn is_valid(&self) -> bool {
+    pub fn is_valid(&self) -> bool {
         ChunkType::is_bytes_all_ascii(self.value) && ChunkType::is_bytes_reserved_bit_valid(self.value)
     }
 
-    fn is_critical(&self) -> bool {
+    pub fn is_critical(&self) -> bool {
         let first_byte = self.value[0];
         let bit_5 = first_byte & (1 << 5);
         bit_5 == 0
     }
 
-    fn is_public(&self) -> bool {
+    pub fn is_public(&self) -> bool {
         let second_byte = self.value[1];
         let bit_5 = second_byte & (1 << 5);
         bit_5 == 0
     }
 
-    fn is_reserved_bit_valid(&self) -> bool {
+    pub fn is_reserved_bit_valid(&self) -> bool {
         ChunkType::is_bytes_reserved_bit_valid(self.value)
     }
 
-    fn is_safe_to_copy(&self) -> bool {
+    pub fn is_safe_to_copy(&self) -> bool {
         let fourth_byte = self.value[3];
         let bit_5 = fourth_byte & (1 << 5);
         bit_5 != 0
     }
+
+    pub fn is_standard(&self) -> bool {
+        STANDARD_TYPES.contains(&&self.value)
+    }
+
+    /// The chunk types the PNG specification defines, critical ones first.
+    pub fn standard_types() -> impl Iterator<Item = ChunkType> {
+        STANDARD_TYPES.iter().map(|value| ChunkType { value: **value })
+    }
+}
+
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChunkType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
 
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ChunkType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use serde::de::Error as _;
+
+        let s = String::deserialize(deserializer)?;
+        ChunkType::from_str(&s).map_err(D::Error::custom)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -177,4 +227,25 @@ mod tests {
         let _chunk_string = format!("{}", chunk_type_1);
         let _are_chunks_equal = chunk_type_1 == chunk_type_2;
     }
+
+    #[test]
+    pub fn test_chunk_type_wrong_length() {
+        for s in ["", "Rus", "rust123", "RüSt"] {
+            assert!(matches!(ChunkType::from_str(s), Err(ChunkTypeError::WrongLength { .. })));
+        }
+    }
+
+    #[test]
+    pub fn test_chunk_type_parse_reserved() {
+        assert!(ChunkType::parse("Rust", true).is_ok());
+        assert!(matches!(ChunkType::parse("Rust", false), Err(ChunkTypeError::ReservedBitSet { .. })));
+        assert!(ChunkType::parse("RuSt", false).is_ok());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_standard() {
+        assert!(ChunkType::from_str("IDAT").unwrap().is_standard());
+        assert!(ChunkType::from_str("tEXt").unwrap().is_standard());
+        assert!(!ChunkType::from_str("ruSt").unwrap().is_standard());
+    }
 }
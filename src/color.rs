@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::ColorError;
+
+/// The chunk type of the image gamma.
+pub const GAMA: &str = "gAMA";
+/// The chunk type of the primary chromaticities and white point.
+pub const CHRM: &str = "cHRM";
+/// The chunk type marking the image as sRGB.
+pub const SRGB: &str = "sRGB";
+
+/// gAMA and cHRM store their values multiplied by 100000.
+const SCALE: f64 = 100_000.0;
+
+/// The gAMA value sRGB images should carry, 1/2.2.
+pub const SRGB_GAMMA: Gamma = Gamma(45455);
+/// The cHRM values sRGB images should carry, from ITU-R BT.709.
+pub const SRGB_CHROMATICITIES: Chromaticities = Chromaticities {
+    white: (31270, 32900),
+    red: (64000, 33000),
+    green: (30000, 60000),
+    blue: (15000, 6000)
+};
+
+fn check_chunk<'a>(chunk: &'a Chunk, expected_type: &str) -> Result<&'a [u8], ColorError> {
+    let chunk_type = chunk.chunk_type().to_string();
+    if chunk_type != expected_type {
+        return Err(ColorError::WrongType { chunk_type, expected: expected_type.to_string() });
+    }
+    Ok(chunk.data())
+}
+
+fn check_len(chunk_type: &'static str, data: &[u8], expected: usize) -> Result<(), ColorError> {
+    match data.len() == expected {
+        true => Ok(()),
+        false => Err(ColorError::WrongLength { chunk_type, expected, len: data.len() })
+    }
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    u32::from_be_bytes(data[index * 4..index * 4 + 4].try_into().unwrap())
+}
+
+/// The exponent relating image samples to the light they stand for, times
+/// 100000, from gAMA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gamma(pub u32);
+
+impl Gamma {
+    pub fn parse(data: &[u8]) -> Result<Gamma, ColorError> {
+        check_len(GAMA, data, 4)?;
+        match read_u32(data, 0) {
+            0 => Err(ColorError::ZeroGamma),
+            gamma => Ok(Gamma(gamma))
+        }
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Gamma, ColorError> {
+        Gamma::parse(check_chunk(chunk, GAMA)?)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::from_str(GAMA).unwrap(), self.0.to_be_bytes().to_vec())
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0 as f64 / SCALE
+    }
+}
+
+/// Shown as e.g. `gamma 0.45455 (1/2.2)`.
+impl fmt::Display for Gamma {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gamma {} (1/{})", self.value(), (SCALE / self.0 as f64 * 100.0).round() / 100.0)
+    }
+}
+
+/// CIE 1931 x,y chromaticities of the white point and the three primaries,
+/// each times 100000, from cHRM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chromaticities {
+    pub white: (u32, u32),
+    pub red: (u32, u32),
+    pub green: (u32, u32),
+    pub blue: (u32, u32)
+}
+
+impl Chromaticities {
+    pub fn parse(data: &[u8]) -> Result<Chromaticities, ColorError> {
+        check_len(CHRM, data, 32)?;
+        let point = |index| (read_u32(data, index), read_u32(data, index + 1));
+        Ok(Chromaticities { white: point(0), red: point(2), green: point(4), blue: point(6) })
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Chromaticities, ColorError> {
+        Chromaticities::parse(check_chunk(chunk, CHRM)?)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [self.white, self.red, self.green, self.blue]
+            .iter()
+            .flat_map(|(x, y)| [x.to_be_bytes(), y.to_be_bytes()])
+            .flatten()
+            .collect();
+        Chunk::new(ChunkType::from_str(CHRM).unwrap(), data)
+    }
+
+    /// Whether every coordinate is within 0.01 of `other`'s, which is how
+    /// closely readers are expected to compare cHRM against sRGB.
+    pub fn approx_eq(&self, other: &Chromaticities) -> bool {
+        let points = |c: &Chromaticities| [c.white, c.red, c.green, c.blue];
+        points(self)
+            .iter()
+            .zip(points(other).iter())
+            .all(|((x, y), (other_x, other_y))| x.abs_diff(*other_x) <= 1000 && y.abs_diff(*other_y) <= 1000)
+    }
+}
+
+/// Shown as e.g. `white (0.3127, 0.329), red (0.64, 0.33), ...`.
+impl fmt::Display for Chromaticities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let points = [("white", self.white), ("red", self.red), ("green", self.green), ("blue", self.blue)]
+            .iter()
+            .map(|(name, (x, y))| format!("{} ({}, {})", name, *x as f64 / SCALE, *y as f64 / SCALE))
+            .collect::<Vec<String>>();
+        f.write_str(&points.join(", "))
+    }
+}
+
+/// How an sRGB image should be mapped to other color spaces, from sRGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderingIntent {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric
+}
+
+impl RenderingIntent {
+    pub fn parse(data: &[u8]) -> Result<RenderingIntent, ColorError> {
+        check_len(SRGB, data, 1)?;
+        match data[0] {
+            0 => Ok(RenderingIntent::Perceptual),
+            1 => Ok(RenderingIntent::RelativeColorimetric),
+            2 => Ok(RenderingIntent::Saturation),
+            3 => Ok(RenderingIntent::AbsoluteColorimetric),
+            intent => Err(ColorError::UnknownRenderingIntent { intent })
+        }
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<RenderingIntent, ColorError> {
+        RenderingIntent::parse(check_chunk(chunk, SRGB)?)
+    }
+
+    pub fn to_chunk(self) -> Chunk {
+        Chunk::new(ChunkType::from_str(SRGB).unwrap(), vec![self as u8])
+    }
+}
+
+impl fmt::Display for RenderingIntent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RenderingIntent::Perceptual => "sRGB, perceptual rendering intent",
+            RenderingIntent::RelativeColorimetric => "sRGB, relative colorimetric rendering intent",
+            RenderingIntent::Saturation => "sRGB, saturation rendering intent",
+            RenderingIntent::AbsoluteColorimetric => "sRGB, absolute colorimetric rendering intent"
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma() {
+        let chunk = SRGB_GAMMA.to_chunk();
+
+        assert_eq!(chunk.data(), 45455u32.to_be_bytes());
+        assert_eq!(Gamma::from_chunk(&chunk).unwrap(), SRGB_GAMMA);
+        assert_eq!(SRGB_GAMMA.to_string(), "gamma 0.45455 (1/2.2)");
+        assert!(matches!(Gamma::parse(&[0; 4]), Err(ColorError::ZeroGamma)));
+        assert!(matches!(Gamma::parse(&[0; 3]), Err(ColorError::WrongLength { chunk_type: "gAMA", expected: 4, len: 3 })));
+    }
+
+    #[test]
+    fn test_chromaticities() {
+        let chunk = SRGB_CHROMATICITIES.to_chunk();
+
+        assert_eq!(chunk.data().len(), 32);
+        assert_eq!(&chunk.data()[..8], [0, 0, 0x7a, 0x26, 0, 0, 0x80, 0x84]);
+        assert_eq!(Chromaticities::from_chunk(&chunk).unwrap(), SRGB_CHROMATICITIES);
+        assert_eq!(
+            SRGB_CHROMATICITIES.to_string(),
+            "white (0.3127, 0.329), red (0.64, 0.33), green (0.3, 0.6), blue (0.15, 0.06)"
+        );
+    }
+
+    #[test]
+    fn test_rendering_intent() {
+        let chunk = RenderingIntent::Saturation.to_chunk();
+
+        assert_eq!(chunk.data(), [2]);
+        assert_eq!(RenderingIntent::from_chunk(&chunk).unwrap(), RenderingIntent::Saturation);
+        assert!(matches!(RenderingIntent::parse(&[4]), Err(ColorError::UnknownRenderingIntent { intent: 4 })));
+        let gama = Chunk::new(ChunkType::from_str("gAMA").unwrap(), vec![2]);
+        assert!(matches!(RenderingIntent::from_chunk(&gama), Err(ColorError::WrongType { .. })));
+    }
+}
@@ -1,66 +1,3046 @@
-use std::fs;
-use std::str::FromStr;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Cursor, IsTerminal, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::anyhow;
+use clap::{builder::PossibleValuesParser, Command, CommandFactory};
+use log::{error, info, warn};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use crate::args::{
+    Format,
+    Compression,
+    EditArgs,
     EncodeArgs,
     DecodeArgs,
     RemoveArgs,
-    PrintArgs
+    PrintArgs,
+    ListArgs,
+    CheckArgs,
+    ScrubArgs,
+    HexdumpArgs,
+    FixCrcArgs,
+    SignArgs,
+    VerifyArgs,
+    KeygenArgs,
+    TextSetArgs,
+    TextGetArgs,
+    TouchArgs,
+    SetDpiArgs,
+    IccExtractArgs,
+    IccEmbedArgs,
+    ExifExportArgs,
+    ExifImportArgs,
+    PaletteArgs,
+    LsbEncodeArgs,
+    LsbDecodeArgs,
+    CapacityArgs,
+    DetectArgs,
+    CompletionsArgs,
+    ManArgs,
+    TuiArgs,
+    DiffArgs,
+    CopyChunksArgs,
+    MergeArgs,
+    OnConflict,
+    StatsArgs,
+    ScanArgs,
+    ExtractArgs,
+    ComposeArgs,
+    WatchArgs,
+    Cli
 };
-use crate::chunk_type::ChunkType;
-use crate::png::Png;
-use crate::chunk::Chunk;
-use crate::Result;
+use pngme::apng::{self, AnimationControl, FrameControl};
+use pngme::background::{self, Background};
+use pngme::check::{validate, Problem, Severity};
+use pngme::detect::{self, Indicator, Report};
+use pngme::diff::Change;
+use pngme::exif::{self, Exif};
+use pngme::hexdump::format_hexdump;
+use pngme::icc::{self, IccProfile};
+use pngme::ihdr::{self, ColorType, Ihdr};
+use pngme::image_data::{self, ImageData};
+use pngme::container::{self, Container};
+use pngme::copy::{self, Skipped};
+use pngme::lsb;
+use pngme::merge::Conflict;
+use pngme::repair::{fix_crcs, Repair};
+use pngme::scatter;
+use pngme::signature::{self, Signed};
+use pngme::significant_bits::{self, SignificantBits};
+use pngme::stream;
+use pngme::stats::{Stats, TypeStats};
+use pngme::color::{self, Chromaticities, Gamma, RenderingIntent};
+use pngme::compress::{self, Method};
+use pngme::crypto::{self, KdfParams};
+use pngme::decoy;
+use pngme::envelope::{self, Envelope};
+use pngme::palette::{self, Histogram, Palette, SuggestedPalette};
+use pngme::payload::{self, Options};
+use pngme::phys::{self, Phys};
+use pngme::text::{self, Text};
+use pngme::timestamp::{self, Timestamp};
+use pngme::transparency::{self, Transparency};
+use pngme::{ChunkType, LazyPng, Png, Chunk, PngError};
+use anyhow::Result;
+use zeroize::Zeroizing;
+use crate::batch::{expand_paths, is_png_path, walk_files};
+use crate::compose::{ChunkSpec, Position};
+use crate::progress::{self, Progress, Unit};
+use crate::remote;
+use crate::s3;
+use crate::style;
+use crate::view::{ChunkView, ImageView, PngView, ProblemView};
+
+const STDIO_PATH: &str = "-";
+
+fn is_stdio(file_path: &Path) -> bool {
+    file_path == Path::new(STDIO_PATH)
+}
+
+fn read_input(file_path: &Path) -> Result<Vec<u8>> {
+    if !is_stdio(file_path) {
+        return Ok(progress::read(file_path)?);
+    }
+
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+const DATA_URI_SCHEME: &str = "data:";
+
+fn is_data_uri(file_path: &Path) -> bool {
+    file_path.to_str().is_some_and(|path| path.starts_with(DATA_URI_SCHEME))
+}
+
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>> {
+    let (media_type, data) = uri[DATA_URI_SCHEME.len()..]
+        .split_once(',')
+        .ok_or_else(|| anyhow!("The data URI has no comma before its data"))?;
+    if !media_type.ends_with(";base64") {
+        return Err(anyhow!("Only base64 data URIs are supported, not data:{}", media_type));
+    }
+    STANDARD
+        .decode(data.trim())
+        .map_err(|error| anyhow!("The data URI is not valid base64: {}", error))
+}
+
+fn encode_data_uri(media_type: &str, bytes: &[u8]) -> String {
+    format!("{}{};base64,{}", DATA_URI_SCHEME, media_type, STANDARD.encode(bytes))
+}
+
+/// Whether an input path names a local file rather than standard input, a
+/// data URI or an S3 object.
+fn is_local(file_path: &Path) -> bool {
+    !is_stdio(file_path) && !is_data_uri(file_path) && !s3::is_s3(file_path)
+}
+
+/// The bytes of an input image, which may also be an S3 object or a data
+/// URI, given in place of the path or piped to standard input.
+fn read_image(file_path: &Path) -> Result<Vec<u8>> {
+    if let Some(uri) = file_path.to_str().filter(|_| is_data_uri(file_path)) {
+        return decode_data_uri(uri);
+    }
+    if s3::is_s3(file_path) {
+        return s3::get(file_path);
+    }
+
+    let bytes = read_input(file_path)?;
+    match is_stdio(file_path) && bytes.starts_with(DATA_URI_SCHEME.as_bytes()) {
+        true => decode_data_uri(std::str::from_utf8(&bytes)?),
+        false => Ok(bytes)
+    }
+}
+
+fn read_png(file_path: &Path) -> Result<Png> {
+    let png = if is_local(file_path) {
+        Png::from_reader(progress::open(file_path)?)?
+    } else {
+        Png::try_from(read_image(file_path)?.as_slice())?
+    };
+    warn_ordering(file_path, &png);
+    Ok(png)
+}
+
+fn warn_ordering(file_path: &Path, png: &Png) {
+    for problem in png.ordering_problems() {
+        warn!("{}: {}", file_path.display(), problem);
+    }
+}
+
+/// An image that can carry a payload: a PNG, read whole or lazily, or any
+/// other format `container::open` recognises, which only takes whole
+/// payloads.
+enum Carrier<P = Png> {
+    Png(P),
+    Other(Box<dyn Container>)
+}
+
+fn read_carrier(file_path: &Path) -> Result<Carrier> {
+    let bytes = read_image(file_path)?;
+    if !is_png(&bytes) {
+        return Ok(Carrier::Other(container::open(&bytes)?));
+    }
+    let png = Png::try_from(bytes.as_slice())?;
+    warn_ordering(file_path, &png);
+    Ok(Carrier::Png(png))
+}
+
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+// Neither standard input nor a download can seek, so they're buffered in
+// memory first
+fn open_image(file_path: &Path) -> Result<Box<dyn ReadSeek>> {
+    Ok(if remote::is_url(file_path) {
+        Box::new(Cursor::new(remote::fetch(file_path)?))
+    } else if is_local(file_path) {
+        Box::new(BufReader::new(File::open(file_path)?))
+    } else {
+        Box::new(Cursor::new(read_image(file_path)?))
+    })
+}
+
+/// `read_carrier`, with PNGs read lazily. Other formats are read whole, as
+/// their blocks are only found by walking through all of them anyway.
+fn read_lazy_carrier(file_path: &Path) -> Result<Carrier<LazyPng<Box<dyn ReadSeek>>>> {
+    let mut reader = open_image(file_path)?;
+    let mut signature = Vec::new();
+    reader.by_ref().take(Png::STANDARD_HEADER.len() as u64).read_to_end(&mut signature)?;
+    reader.rewind()?;
+    if is_png(&signature) {
+        return Ok(Carrier::Png(LazyPng::from_reader(reader)?));
+    }
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(Carrier::Other(container::open(&bytes)?))
+}
+
+/// Whether `bytes` should be read as a PNG. Anything in no format pngme
+/// knows counts as one, so the PNG parser says what's wrong with it.
+fn is_png(bytes: &[u8]) -> bool {
+    matches!(container::media_type(bytes), Some("image/png") | None)
+}
+
+fn write_atomically<F>(file_path: &Path, write_contents: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>
+{
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", file_path.display()))?;
+    let temp_path = file_path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        process::id()
+    ));
+
+    // A leftover temp file is an error rather than something to write through
+    let temp_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(|error| anyhow!("{}: {}", temp_path.display(), error))?;
+    let write_temp = || -> Result<()> {
+        let mut writer = BufWriter::new(temp_file);
+        write_contents(&mut writer)?;
+        let file = writer.into_inner().map_err(|error| error.into_error())?;
+        if let Ok(metadata) = fs::metadata(file_path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        Ok(fs::rename(&temp_path, file_path)?)
+    };
+
+    if let Err(error) = write_temp() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error);
+    }
+
+    // Persist the rename itself; not every platform can open a directory for syncing.
+    let parent = file_path.parent().filter(|parent| !parent.as_os_str().is_empty());
+    if let Ok(dir) = File::open(parent.unwrap_or(Path::new("."))) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+fn write_with<F>(file_path: &Path, write_contents: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>
+{
+    if s3::is_s3(file_path) {
+        let mut bytes = Vec::new();
+        write_contents(&mut bytes)?;
+        return s3::put(file_path, bytes);
+    }
+    if !is_stdio(file_path) {
+        return write_atomically(file_path, write_contents);
+    }
+
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    write_contents(&mut stdout)?;
+    Ok(stdout.flush()?)
+}
+
+fn write_output(file_path: &Path, bytes: &[u8]) -> Result<()> {
+    write_with(file_path, |writer| Ok(writer.write_all(bytes)?))
+}
+
+fn write_png(file_path: &Path, png: &Png) -> Result<()> {
+    write_with(file_path, |writer| Ok(png.write_to(writer)?))
+}
+
+fn backup_path(file_path: &Path, backup_suffix: Option<&str>, backup_dir: Option<&Path>) -> Result<PathBuf> {
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", file_path.display()))?;
+    let backup_name = format!("{}{}", file_name.to_string_lossy(), backup_suffix.unwrap_or(".bak"));
+
+    Ok(match backup_dir {
+        Some(backup_dir) => backup_dir.join(backup_name),
+        None => file_path.with_file_name(backup_name)
+    })
+}
+
+fn save_png(file_path: PathBuf, png: &mut Png, original_size: usize, edit: EditArgs) -> Result<()> {
+    if edit.strip_trailing {
+        png.strip_trailing_data();
+    } else if !edit.keep_trailing && !png.trailing_data().is_empty() {
+        warn!(
+            "{}: kept {} bytes after IEND; use --strip-trailing to drop them",
+            file_path.display(),
+            png.trailing_data().len()
+        );
+    }
+
+    save_image(file_path, &png.as_bytes(), "image/png", original_size, edit)
+}
+
+/// `save_png` for a PNG streamed from `file_path` with `chunks` added,
+/// which is never held in memory.
+fn stream_png(file_path: PathBuf, chunks: Vec<Chunk>, edit: EditArgs) -> Result<()> {
+    let original_size = fs::metadata(&file_path)?.len() as usize;
+    let (keep_trailing, strip_trailing) = (edit.keep_trailing, edit.strip_trailing);
+    let input = file_path.clone();
+    let mut trailing_len = 0;
+    save_with(file_path.clone(), "image/png", original_size, edit, |writer| {
+        let spliced = stream::splice(progress::open(&input)?, writer, chunks, !strip_trailing).map_err(|error| match error {
+            PngError::InvalidSignature => anyhow!("{} is not a PNG, which --stream needs", input.display()),
+            error => error.into()
+        })?;
+        trailing_len = spliced.trailing_len;
+        Ok(())
+    })?;
+
+    // Only known once the image has been copied through
+    if !keep_trailing && !strip_trailing && trailing_len > 0 {
+        warn!("{}: kept {} bytes after IEND; use --strip-trailing to drop them", file_path.display(), trailing_len);
+    }
+    Ok(())
+}
+
+/// Writes an edited image of type `media_type` back, or wherever `EditArgs`
+/// says to.
+fn save_image(file_path: PathBuf, bytes: &[u8], media_type: &str, original_size: usize, edit: EditArgs) -> Result<()> {
+    save_with(file_path, media_type, original_size, edit, |writer| Ok(writer.write_all(bytes)?))
+}
+
+/// Counts the bytes written to it, for reporting a dry run.
+struct ByteCount(usize);
+
+impl Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `save_image`, with the image written by `write_contents`.
+fn save_with<F>(file_path: PathBuf, media_type: &str, original_size: usize, EditArgs {
+    output,
+    output_dir,
+    dry_run,
+    backup,
+    backup_suffix,
+    backup_dir,
+    keep_trailing: _,
+    strip_trailing: _,
+    data_uri
+}: EditArgs, write_contents: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>
+{
+    // Standard input and data URIs have no name to keep in --output-dir
+    let output = match (output, output_dir) {
+        (None, Some(output_dir)) if !is_stdio(&file_path) && !is_data_uri(&file_path) => {
+            let file_name = file_path
+                .file_name()
+                .ok_or_else(|| anyhow!("{} has no file name to keep in --output-dir", file_path.display()))?;
+            if !dry_run && !s3::is_s3(&output_dir) {
+                fs::create_dir_all(&output_dir)?;
+            }
+            Some(output_dir.join(file_name))
+        },
+        (output, _) => output
+    };
+
+    if is_data_uri(&file_path) && output.is_none() && !data_uri && !dry_run {
+        return Err(anyhow!("A data URI can't be written back to; pass --output or --data-uri"));
+    }
+
+    // The input is left alone, so there's nothing to back up
+    if data_uri {
+        let mut bytes = Vec::new();
+        write_contents(&mut bytes)?;
+        println!("{}", encode_data_uri(media_type, &bytes));
+        return Ok(());
+    }
+
+    let wants_backup = backup || backup_suffix.is_some() || backup_dir.is_some();
+    if wants_backup && output.is_none() && s3::is_s3(&file_path) {
+        return Err(anyhow!("Backups of S3 objects aren't supported; turn on versioning for the bucket instead"));
+    }
+    let backup = match output {
+        None if wants_backup && !is_stdio(&file_path) => {
+            Some(backup_path(&file_path, backup_suffix.as_deref(), backup_dir.as_deref())?)
+        },
+        _ => None
+    };
+    let file_path = output.unwrap_or(file_path);
+
+    if dry_run {
+        if let Some(backup) = &backup {
+            println!("Would back up {} to {}", file_path.display(), backup.display());
+        }
+        let mut count = ByteCount(0);
+        write_contents(&mut count)?;
+        println!(
+            "Would write {} bytes to {} (was {} bytes)",
+            count.0,
+            file_path.display(),
+            original_size
+        );
+        return Ok(());
+    }
+
+    if let Some(backup) = backup {
+        if let Some(backup_dir) = backup.parent() {
+            fs::create_dir_all(backup_dir)?;
+        }
+        fs::copy(&file_path, &backup)?;
+    }
+
+    write_with(file_path.as_path(), write_contents)
+}
+
+fn describe_chunk(chunk: &Chunk) -> String {
+    format!("{} ({} bytes)", chunk.chunk_type(), chunk.length())
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// The message to embed, given inline or read from a file, and decoded from
+/// base64 when `base64` is set.
+fn read_message(file_path: &Path, message: Option<String>, message_file: Option<PathBuf>, base64: bool) -> Result<Vec<u8>> {
+    let data = match (message, message_file) {
+        (_, Some(message_file)) => {
+            if is_stdio(&message_file) && is_stdio(file_path) {
+                return Err(anyhow!("The PNG and the message file cannot both be read from stdin"));
+            }
+            read_input(message_file.as_path())?
+        },
+        (Some(message), None) => message.into_bytes(),
+        (None, None) => return Err(anyhow!("Either a message or a message file must be given"))
+    };
+    match base64 {
+        true => STANDARD
+            .decode(data.trim_ascii())
+            .map_err(|error| anyhow!("The message is not valid base64: {}", error)),
+        false => Ok(data)
+    }
+}
 
 pub fn encode(EncodeArgs { 
     file_path, 
     chunk_type, 
-    message 
+    message,
+    message_file,
+    clipboard,
+    base64,
+    envelope,
+    content_type,
+    chunk_key,
+    allow_reserved,
+    force,
+    compress,
+    compression_method,
+    level,
+    encrypt,
+    passphrase,
+    key_file,
+    argon2_memory,
+    argon2_iterations,
+    argon2_parallelism,
+    deniable,
+    hidden_message,
+    hidden_message_file,
+    hidden_passphrase,
+    age_recipient,
+    recipient,
+    hmac_key,
+    max_chunk_size,
+    decoys,
+    scatter_key,
+    scatter_size,
+    jpeg_comment,
+    stream,
+    profile: _,
+    default_chunk_type,
+    edit
 }: EncodeArgs) -> Result<()> {
-    let mut png = Png::try_from_path(file_path.as_path())?;
+    // With --chunk-key or --scatter-key the only positional argument is the message
+    let derived_type = match (&chunk_key, &scatter_key) {
+        (Some(chunk_key), _) => Some(crypto::derive_chunk_type(chunk_key.as_bytes())),
+        (None, Some(scatter_key)) => Some(scatter::first_chunk_type(scatter_key.as_bytes())),
+        (None, None) => None
+    };
+    // With a chunk type in the config file it may be too
+    let (chunk_type, message) = match default_chunk_type.filter(|_| derived_type.is_none()) {
+        Some(default_chunk_type) if message.is_none() && message_file.is_none() && !clipboard => {
+            (Some(default_chunk_type), chunk_type)
+        },
+        default_chunk_type => (chunk_type.or(default_chunk_type), message)
+    };
+    let (chunk_type, message) = match (derived_type, chunk_type, message) {
+        (Some(_), Some(_), Some(_)) => {
+            return Err(anyhow!("--chunk-key and --scatter-key take the place of the chunk type; pass only the message"))
+        },
+        (Some(derived_type), chunk_type, message) => (derived_type, chunk_type.or(message)),
+        (None, Some(chunk_type), message) => (ChunkType::parse(chunk_type.as_str(), allow_reserved)?, message),
+        (None, None, _) => return Err(anyhow!("Either a chunk type or --chunk-key must be given"))
+    };
+    check_writable(&chunk_type, force)?;
+
+    let message = match (clipboard, message) {
+        (true, Some(_)) => return Err(anyhow!("--clipboard can't be combined with a message argument")),
+        (true, None) => Some(read_clipboard()?),
+        (false, message) => message
+    };
+    let data = read_message(&file_path, message, message_file, base64)?;
+    let hidden = match (hidden_message, hidden_message_file) {
+        (None, None) if hidden_passphrase.is_some() => {
+            return Err(anyhow!("--hidden-passphrase needs --hidden-message or --hidden-message-file"))
+        },
+        (None, None) => None,
+        (hidden_message, hidden_message_file) => {
+            Some(Zeroizing::new(read_message(&file_path, hidden_message, hidden_message_file, base64)?))
+        }
+    };
+    let mut options = Options {
+        authenticated: hmac_key.is_some(),
+        enveloped: envelope || content_type.is_some(),
+        ..Options::default()
+    };
+    let data = match (content_type.as_deref(), envelope) {
+        (Some(content_type), _) => Envelope::new(content_type, &data)?.to_bytes(),
+        (None, true) => Envelope::new(envelope::guess_content_type(&data), &data)?.to_bytes(),
+        (None, false) => data
+    };
+    // Compress before encrypting, as ciphertext doesn't compress
+    let data = match compress.then_some(compression_method) {
+        Some(compression) => {
+            let method = match compression {
+                Compression::Deflate => Method::Deflate,
+                Compression::Zstd => Method::Zstd
+            };
+            let compressed = match level {
+                Some(level) => compress::compress_with_level(&data, method, level)?,
+                None => compress::compress(&data, method)?
+            };
+            options.compressed = compressed.len() < data.len();
+            if options.compressed { compressed } else { data }
+        },
+        None => data
+    };
+    options.encrypted = encrypt || !age_recipient.is_empty() || recipient.is_some();
+    let data = if options.encrypted {
+        // The plaintext is wiped once it has been encrypted
+        let plaintext = Zeroizing::new(data);
+        if let Some(recipient) = recipient {
+            crypto::encrypt_to_recipient(&plaintext, &crypto::parse_public_key(&recipient)?)?
+        } else if let Some(key_file) = key_file {
+            crypto::encrypt_with_key(&plaintext, &*crypto::read_key_file(key_file.as_path())?)?
+        } else if encrypt {
+            let passphrase = match passphrase {
+                Some(passphrase) => Zeroizing::new(passphrase),
+                None => prompt_passphrase(true)?
+            };
+            let defaults = KdfParams::default();
+            let params = KdfParams {
+                memory_kib: argon2_memory.unwrap_or(defaults.memory_kib),
+                iterations: argon2_iterations.unwrap_or(defaults.iterations),
+                parallelism: argon2_parallelism.unwrap_or(defaults.parallelism)
+            };
+            if !deniable {
+                crypto::encrypt_with_params(&plaintext, passphrase.as_bytes(), params)?
+            } else if let Some(hidden) = hidden {
+                let hidden_passphrase = match hidden_passphrase {
+                    Some(hidden_passphrase) => Zeroizing::new(hidden_passphrase),
+                    None => prompt_secret("Hidden passphrase", true)?
+                };
+                crypto::encrypt_deniable(&plaintext, passphrase.as_bytes(), Some((&hidden[..], hidden_passphrase.as_bytes())), params)?
+            } else {
+                crypto::encrypt_deniable(&plaintext, passphrase.as_bytes(), None, params)?
+            }
+        } else {
+            crypto::encrypt_to_age(&plaintext, &age_recipient)?
+        }
+    } else {
+        data
+    };
+    // Encrypt-then-MAC, so a tampered payload is rejected before any decryption is attempted
+    let data = match hmac_key.map(Zeroizing::new) {
+        Some(hmac_key) => crypto::authenticate(&chunk_type, &data, hmac_key.as_bytes()),
+        None => data
+    };
+    // Bare age messages are left as they are, so the chunk can be handed to
+    // age directly
+    let data = match !options.authenticated && !age_recipient.is_empty() {
+        true => data,
+        false => payload::wrap(options, &data)
+    };
+
+    // A streamed PNG is copied through later and never read whole
+    let png = match stream {
+        true if !is_local(&file_path) => return Err(anyhow!("--stream needs the image in a local file")),
+        true => None,
+        false => match read_carrier(file_path.as_path())? {
+            Carrier::Png(png) => Some(png),
+            _ if scatter_key.is_some() || decoys > 0 => {
+                return Err(anyhow!("--scatter-key and --decoys only work in PNG carriers"));
+            },
+            Carrier::Other(mut container) => {
+                let original_size = container.to_bytes().len();
+                if jpeg_comment {
+                    container.prefer_comments();
+                }
+                container.add_block(&chunk_type, &data)?;
+                if edit.dry_run {
+                    println!("Would add {} bytes under {} ({})", data.len(), chunk_type, container.media_type());
+                }
+                return save_image(file_path, &container.to_bytes(), container.media_type(), original_size, edit);
+            }
+        }
+    };
+
+    let taken: Vec<ChunkType> = match &png {
+        Some(png) => png.chunks().iter().map(|chunk| chunk.chunk_type().clone()).collect(),
+        // Only the chunk headers are read, and only when new types mustn't clash with them
+        None if scatter_key.is_some() || decoys > 0 => LazyPng::open(file_path.as_path())?
+            .chunk_headers()
+            .iter()
+            .map(|header| header.chunk_type().clone())
+            .collect(),
+        None => Vec::new()
+    };
+    let decoys = decoy::decoys(&data, decoys, max_chunk_size as usize, &[&taken[..], std::slice::from_ref(&chunk_type)].concat());
 
-    let chunk_type = ChunkType::from_str(chunk_type.as_str())?;
-    png.append_chunk(Chunk::new(chunk_type, message.as_bytes().to_vec()));
+    let mut chunks = match (scatter_key, data.len() > max_chunk_size as usize) {
+        (Some(scatter_key), _) => scatter::scatter(&data, scatter_key.as_bytes(), scatter_size as usize, &taken)?,
+        (None, true) => split_payload(&chunk_type, &data, max_chunk_size as usize)?,
+        (None, false) => vec![Chunk::try_new(chunk_type, data)?]
+    };
+    if !decoys.is_empty() {
+        chunks.extend(decoys);
+        decoy::shuffle(&mut chunks);
+    }
+    if edit.dry_run {
+        for chunk in &chunks {
+            println!("Would add chunk {}", describe_chunk(chunk));
+        }
+    }
 
-    Ok(fs::write(file_path, png.as_bytes())?)
+    let Some(mut png) = png else {
+        return stream_png(file_path, chunks, edit);
+    };
+    let original_size = png.byte_len();
+    for chunk in chunks {
+        png.append_chunk(chunk);
+    }
+    save_png(file_path, &mut png, original_size, edit)
 }
 
 pub fn decode(DecodeArgs {
     file_path,
-    chunk_type
-}: DecodeArgs) -> Result<()> {
-    let png = Png::try_from_path(file_path.as_path())?;
+    chunk_type,
+    chunk_key,
+    scatter_key,
+    output,
+    clipboard,
+    qr,
+    all,
+    index,
+    base64,
+    passphrase,
+    key_file,
+    private_key,
+    age_identity,
+    hmac_key
+}: DecodeArgs, format: Format) -> Result<()> {
+    let mut keys = DecodeKeys {
+        passphrase: passphrase.map(Zeroizing::new),
+        key_file,
+        private_key,
+        age_identity,
+        hmac_key: hmac_key.map(Zeroizing::new)
+    };
+    // A scattered payload is found by its first piece, which leads to the rest
+    let chunk_type = match (&chunk_key, &scatter_key, chunk_type) {
+        (Some(chunk_key), _, _) => crypto::derive_chunk_type(chunk_key.as_bytes()).to_string(),
+        (None, Some(scatter_key), _) => scatter::first_chunk_type(scatter_key.as_bytes()).to_string(),
+        (None, None, Some(chunk_type)) => chunk_type,
+        (None, None, None) => return Err(anyhow!("Either a chunk type, --chunk-key or --scatter-key must be given"))
+    };
+    let not_found = || match (&chunk_key, &scatter_key) {
+        (Some(_), _) => anyhow!("No chunk was written with this --chunk-key"),
+        (None, Some(_)) => anyhow!("No payload was scattered with this --scatter-key"),
+        (None, None) => anyhow!("No such chunk_type found")
+    };
+    let payloads = match read_lazy_carrier(file_path.as_path())? {
+        Carrier::Other(_) if scatter_key.is_some() => {
+            return Err(anyhow!("--scatter-key only works in PNG carriers"))
+        },
+        // Other formats hold each payload whole
+        Carrier::Other(container) => {
+            let found = container.block_data(&chunk_type)?;
+            if found.is_empty() {
+                return Err(not_found());
+            }
+            let parsed_type = ChunkType::parse(&chunk_type, true)?;
+            select_indices(found.len(), &chunk_type, index, all)?
+                .into_iter()
+                .map(|index| Ok((index, open_payload(&parsed_type, &found[index], &mut keys, usize::MAX)?)))
+                .collect::<Result<Vec<(usize, Zeroizing<Vec<u8>>)>>>()?
+        },
+        Carrier::Png(mut png) => {
+            // Parts of split payloads are only reachable through their
+            // manifest, so they don't count towards the indices
+            let mut positions = Vec::new();
+            let mut parts = Vec::new();
+            for position in 0..png.chunk_headers().len() {
+                if png.chunk_headers()[position].chunk_type().to_string() != chunk_type {
+                    continue;
+                }
+                let prefix = png.read_data_prefix(position, PART_HEADER_LEN)?;
+                match prefix.len() == PART_HEADER_LEN && prefix.starts_with(&PART_MAGIC) {
+                    true => parts.push((position, prefix)),
+                    false => positions.push(position)
+                }
+            }
+            if positions.is_empty() {
+                return Err(not_found());
+            }
 
-    let chunk = match png.chunk_by_type(chunk_type.as_str()) {
-        Some(chunk) => chunk,
-        None => return Err(anyhow!("No such chunk_type found"))
+            select_indices(positions.len(), &chunk_type, index, all)?
+                .into_iter()
+                .map(|index| {
+                    let chunk = png.load_chunk(positions[index])?;
+                    if text::is_text(chunk.chunk_type()) {
+                        return Ok((index, Zeroizing::new(Text::from_chunk(&chunk)?.to_string().into_bytes())));
+                    }
+                    let data = match (&scatter_key, chunk.data().starts_with(&MANIFEST_MAGIC)) {
+                        (Some(scatter_key), _) => Cow::Owned(scatter::gather(scatter_key.as_bytes(), |chunk_type| {
+                            png.position_by_type(&chunk_type.to_string())
+                                .map(|position| png.read_data(position).map_err(anyhow::Error::from))
+                                .transpose()
+                        })?),
+                        (None, true) => Cow::Owned(join_parts(&mut png, chunk.data(), &parts)?),
+                        (None, false) => Cow::Borrowed(chunk.data())
+                    };
+                    Ok((index, open_payload(chunk.chunk_type(), &data, &mut keys, usize::MAX)?))
+                })
+                .collect::<Result<Vec<(usize, Zeroizing<Vec<u8>>)>>>()?
+        }
+    };
+
+    // Envelopes are opened in place, so the data stays in the zeroizing payloads
+    let opened = payloads
+        .iter()
+        .map(|(index, payload)| match envelope::is_enveloped(payload) {
+            true => {
+                let envelope = Envelope::parse(payload)?;
+                Ok((*index, Some(envelope.content_type), envelope.data))
+            },
+            false => Ok((*index, None, &payload[..]))
+        })
+        .collect::<Result<Vec<(usize, Option<&str>, &[u8])>>>()?;
+
+    // Encoded up front so the views can borrow it like the raw payloads
+    let encoded: Vec<Zeroizing<String>> = match base64 {
+        true => opened.iter().map(|(_, _, data)| Zeroizing::new(STANDARD.encode(data))).collect(),
+        false => Vec::new()
     };
 
-    println!("{}", chunk.data_as_string()?);
+    if let Some(output) = output {
+        return match encoded.first() {
+            Some(encoded) => write_output(output.as_path(), format!("{}\n", encoded.as_str()).as_bytes()),
+            None => write_output(output.as_path(), opened[0].2)
+        };
+    }
+    if clipboard {
+        let (index, _, data) = opened[0];
+        let message = match encoded.first() {
+            Some(encoded) => encoded.as_str(),
+            None => std::str::from_utf8(data).map_err(|_| anyhow!(
+                "Chunk {} [{}] holds binary data, which can't be copied as text; pass --base64",
+                chunk_type,
+                index
+            ))?
+        };
+        write_clipboard(message)?;
+        info!("Copied the message from chunk {} [{}] to the clipboard", chunk_type, index);
+        return Ok(());
+    }
+    if let Some(qr_path) = qr {
+        let data = encoded.first().map_or(opened[0].2, |encoded| encoded.as_bytes());
+        return write_png(qr_path.as_path(), &pngme::qr::render(data, QR_SCALE)?);
+    }
+
+    let mut decoded = Vec::new();
+    for (position, &(index, content_type, data)) in opened.iter().enumerate() {
+        let text = match content_type {
+            Some(content_type) if !Envelope { content_type, data }.is_text() => None,
+            _ => std::str::from_utf8(data).ok()
+        };
+        let message = match (encoded.get(position), text) {
+            (Some(encoded), _) => encoded.as_str(),
+            (None, Some(text)) => text,
+            // A lone binary payload can go to a pipe or file as it is, but
+            // not to a terminal
+            (None, None) if opened.len() == 1 && format == Format::Text && !io::stdout().is_terminal() => {
+                return Ok(io::stdout().write_all(data)?);
+            },
+            (None, None) => return Err(anyhow!(
+                "Chunk {} [{}] holds {} ({} bytes), which won't be printed here; pass --output <FILE> or --base64",
+                chunk_type,
+                index,
+                content_type.map_or("binary data".to_string(), |content_type| format!("{} data", content_type)),
+                data.len()
+            ))
+        };
+        decoded.push(DecodedView { index, chunk_type: chunk_type.as_str(), content_type, message });
+    }
+
+    match format {
+        Format::Text if all => {
+            for view in &decoded {
+                println!("[{}] {}", view.index, view.message);
+            }
+        },
+        Format::Text => println!("{}", decoded[0].message),
+        Format::Json if all => print_json(&decoded)?,
+        Format::Json => print_json(&decoded[0])?
+    }
     Ok(())
 }
 
+/// Pixels per QR code module, plenty for a phone camera pointed at a screen.
+const QR_SCALE: u32 = 8;
+
+/// The secrets decode may need to open a payload.
+pub struct DecodeKeys {
+    pub passphrase: Option<Zeroizing<String>>,
+    pub key_file: Option<PathBuf>,
+    pub private_key: Option<PathBuf>,
+    pub age_identity: Option<PathBuf>,
+    pub hmac_key: Option<Zeroizing<String>>
+}
+
+/// Marks the chunk listing the parts of a payload too large for one chunk.
+pub const MANIFEST_MAGIC: [u8;4] = *b"PMEm";
+/// Magic, id, part count, total length and SHA-256 of the payload.
+const MANIFEST_LEN: usize = 56;
+/// Marks one part of a split payload.
+pub const PART_MAGIC: [u8;4] = *b"PMEp";
+/// Magic, the 8-byte id shared with the manifest and the sequence number.
+const PART_HEADER_LEN: usize = 16;
+
+/// Splits `data` into a manifest chunk followed by parts whose chunk data
+/// is at most `max_len` bytes each.
+fn split_payload(chunk_type: &ChunkType, data: &[u8], max_len: usize) -> Result<Vec<Chunk>> {
+    let digest = Sha256::digest(data);
+    // The id only has to tell apart payloads split into the same image
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let id = Sha256::new().chain_update(digest).chain_update(nanos.to_be_bytes()).finalize();
+    let id = &id[..8];
+
+    let parts: Vec<&[u8]> = data.chunks(max_len - PART_HEADER_LEN).collect();
+    let manifest = [
+        &MANIFEST_MAGIC[..],
+        id,
+        &(parts.len() as u32).to_be_bytes(),
+        &(data.len() as u64).to_be_bytes(),
+        &digest
+    ].concat();
+
+    let mut chunks = vec![Chunk::try_new(chunk_type.clone(), manifest)?];
+    for (sequence, part) in parts.into_iter().enumerate() {
+        let part = [&PART_MAGIC[..], id, &(sequence as u32).to_be_bytes(), part].concat();
+        chunks.push(Chunk::try_new(chunk_type.clone(), part)?);
+    }
+    Ok(chunks)
+}
+
+/// Reassembles the payload described by `manifest` from `parts`, the
+/// positions and headers of the part chunks of the same type, in any order.
+fn join_parts<R: Read + Seek>(png: &mut LazyPng<R>, manifest: &[u8], parts: &[(usize, Vec<u8>)]) -> Result<Vec<u8>> {
+    if manifest.len() != MANIFEST_LEN {
+        return Err(anyhow!("The manifest of a split payload is malformed ({} bytes)", manifest.len()));
+    }
+    let id = &manifest[4..12];
+    let count = u32::from_be_bytes(manifest[12..16].try_into().unwrap()) as usize;
+    let total = u64::from_be_bytes(manifest[16..24].try_into().unwrap());
+    let digest = &manifest[24..];
+
+    let mut found: Vec<(u32, usize)> = parts
+        .iter()
+        .filter(|(_, header)| &header[4..12] == id)
+        .map(|(position, header)| (u32::from_be_bytes(header[12..16].try_into().unwrap()), *position))
+        .collect();
+    found.sort();
+    if found.len() != count || found.iter().enumerate().any(|(expected, (sequence, _))| *sequence as usize != expected) {
+        return Err(anyhow!("Found {} chunks for the {} parts of a split payload", found.len(), count));
+    }
+
+    let mut data = Vec::new();
+    for (_, position) in found {
+        data.extend_from_slice(&png.load_chunk(position)?.data()[PART_HEADER_LEN..]);
+    }
+    if data.len() as u64 != total || Sha256::digest(&data)[..] != *digest {
+        return Err(anyhow!("The parts of a split payload don't match its manifest"));
+    }
+    Ok(data)
+}
+
+fn read_clipboard() -> Result<String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|error| anyhow!("Could not read the clipboard: {}", error))
+}
+
+// On X11 the clipboard empties when its owner exits, so arboard hands the
+// text to the clipboard manager, if there is one, when it is dropped
+fn write_clipboard(text: &str) -> Result<()> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|error| anyhow!("Could not write to the clipboard: {}", error))
+}
+
+/// Returns the chunk's data after undoing whatever `encode` options it was
+/// written with. A compressed message may expand to at most `max_len` bytes.
+pub fn open_payload(chunk_type: &ChunkType, data: &[u8], keys: &mut DecodeKeys, max_len: usize) -> Result<Zeroizing<Vec<u8>>> {
+    // Payloads from before the header, and bare age messages, go by the
+    // markers of their layers alone
+    let (options, data) = match payload::is_pngme(data) {
+        true => {
+            let (options, data) = payload::parse(data).map_err(|error| anyhow!("Chunk {}: {}", chunk_type, error))?;
+            (Some(options), data)
+        },
+        false => (None, data)
+    };
+    let check_layer = |layer: &str, expected: fn(&Options) -> bool, found: bool| match options {
+        Some(options) if expected(&options) != found => Err(anyhow!(
+            "The pngme header of chunk {} doesn't match its payload ({} {})",
+            chunk_type,
+            layer,
+            if found { "found but not declared" } else { "declared but missing" }
+        )),
+        _ => Ok(())
+    };
+
+    check_layer("HMAC tag", |options| options.authenticated, crypto::is_authenticated(data))?;
+    let data = authenticate_payload(chunk_type, data, keys)?;
+
+    let encrypted = crypto::is_encrypted(&data) || crypto::is_age(&data);
+    check_layer("encryption", |options| options.encrypted, encrypted)?;
+    let data = decrypt_payload(chunk_type, data, keys)?;
+
+    check_layer("compression", |options| options.compressed, compress::is_compressed(&data))?;
+    let data = match compress::is_compressed(&data) {
+        true => Zeroizing::new(compress::decompress_with_limit(&data, max_len)?),
+        false => data
+    };
+
+    check_layer("envelope", |options| options.enveloped, envelope::is_enveloped(&data))?;
+    Ok(data)
+}
+
+/// Returns the chunk's data after checking its HMAC tag, if it was written
+/// with `--hmac-key`.
+fn authenticate_payload(chunk_type: &ChunkType, data: &[u8], keys: &DecodeKeys) -> Result<Zeroizing<Vec<u8>>> {
+    match &keys.hmac_key {
+        Some(hmac_key) if crypto::is_authenticated(data) => {
+            crypto::verify_tag(chunk_type, data, hmac_key.as_bytes())
+                .map_err(|error| anyhow!("Chunk {}: {}", chunk_type, error))
+        },
+        Some(_) => Err(anyhow!("Chunk {} has no HMAC tag, so it can't be authenticated", chunk_type)),
+        None if crypto::is_authenticated(data) => {
+            Err(anyhow!("Chunk {} has an HMAC tag; pass --hmac-key to check it", chunk_type))
+        },
+        None => Ok(Zeroizing::new(data.to_vec()))
+    }
+}
+
+/// Returns the chunk's data after decrypting it, if it was written with one
+/// of the encryption options.
+fn decrypt_payload(chunk_type: &ChunkType, data: Zeroizing<Vec<u8>>, keys: &mut DecodeKeys) -> Result<Zeroizing<Vec<u8>>> {
+    if crypto::is_recipient_encrypted(&data) {
+        return match &keys.private_key {
+            Some(private_key) => Ok(crypto::decrypt_with_private_key(&data, &*crypto::read_key_file(private_key)?)?),
+            None => Err(anyhow!("Chunk {} is encrypted to a recipient; pass --private-key to decrypt it", chunk_type))
+        };
+    }
+    if crypto::is_key_encrypted(&data) {
+        return match &keys.key_file {
+            Some(key_file) => Ok(crypto::decrypt_with_key(&data, &*crypto::read_key_file(key_file)?)?),
+            None => Err(anyhow!("Chunk {} is encrypted with a key file; pass --key-file to decrypt it", chunk_type))
+        };
+    }
+    if crypto::is_encrypted(&data) {
+        // Ask once and reuse the answer for the rest of the chunks
+        if keys.passphrase.is_none() {
+            keys.passphrase = Some(prompt_passphrase(false)?);
+        }
+        let passphrase = keys.passphrase.as_deref().map(String::as_str).unwrap_or_default();
+        return Ok(crypto::decrypt(&data, passphrase.as_bytes())?);
+    }
+    if crypto::is_age(&data) {
+        return match &keys.age_identity {
+            Some(age_identity) => Ok(crypto::decrypt_age(&data, age_identity)?),
+            None => Err(anyhow!("Chunk {} is age-encrypted; pass --age-identity to decrypt it", chunk_type))
+        };
+    }
+    Ok(data)
+}
+
+/// Reads a passphrase from the terminal without echoing it, asking twice
+/// when `confirm` is set so a typo doesn't lock the message away.
+fn prompt_passphrase(confirm: bool) -> Result<Zeroizing<String>> {
+    prompt_secret("Passphrase", confirm)
+}
+
+/// `prompt_passphrase` with another prompt, e.g. for the second passphrase of
+/// `encode --deniable`.
+fn prompt_secret(prompt: &str, confirm: bool) -> Result<Zeroizing<String>> {
+    let passphrase = rpassword::prompt_password(format!("{}: ", prompt))
+        .map(Zeroizing::new)
+        .map_err(|error| anyhow!("Could not read a passphrase from the terminal ({}); pass --passphrase instead", error))?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("The passphrase must not be empty"));
+    }
+
+    if confirm && *Zeroizing::new(rpassword::prompt_password(format!("Confirm {}: ", prompt.to_lowercase()))?) != *passphrase {
+        return Err(anyhow!("The passphrases don't match"));
+    }
+    Ok(passphrase)
+}
+
+/// Which of `count` payloads of type `chunk_type` to decode, as `--index`
+/// and `--all` ask.
+fn select_indices(count: usize, chunk_type: &str, index: Option<usize>, all: bool) -> Result<Vec<usize>> {
+    match index {
+        _ if all => Ok((0..count).collect()),
+        Some(index) if index < count => Ok(vec![index]),
+        Some(index) => Err(anyhow!(
+            "There is no {} chunk with index {}; available indices are {}",
+            chunk_type,
+            index,
+            describe_indices(count)
+        )),
+        None if count > 1 => Err(anyhow!(
+            "Found {} {} chunks; choose one with --index ({}) or decode them all with --all",
+            count,
+            chunk_type,
+            describe_indices(count)
+        )),
+        None => Ok(vec![0])
+    }
+}
+
+fn describe_indices(count: usize) -> String {
+    (0..count)
+        .map(|index| index.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[derive(Serialize)]
+pub struct DecodedView<'a> {
+    pub index: usize,
+    pub chunk_type: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<&'a str>,
+    pub message: &'a str
+}
+
 pub fn remove(RemoveArgs {
     file_path,
-    chunk_type
+    chunk_type,
+    all,
+    index,
+    edit
 }: RemoveArgs) -> Result<()> {
-    let mut png = Png::try_from_path(file_path.as_path())?;
+    let mut png = match read_carrier(file_path.as_path())? {
+        Carrier::Png(png) => png,
+        Carrier::Other(mut container) => {
+            let original_size = container.to_bytes().len();
+            let count = container.block_data(&chunk_type)?.len();
+            if count == 0 {
+                return Err(anyhow!("No such chunk_type found"));
+            }
+            // Removed from the back, so the indices of the rest don't shift
+            let indices: Vec<usize> = match index {
+                _ if all => (0..count).rev().collect(),
+                Some(index) => vec![index],
+                None => vec![0]
+            };
+            let parsed_type = ChunkType::parse(&chunk_type, true)?;
+            let removed = indices
+                .into_iter()
+                .map(|index| Ok(Chunk::try_new(parsed_type.clone(), container.remove_block(&chunk_type, index)?)?))
+                .collect::<Result<Vec<Chunk>>>()?;
+            report_removed(&removed, edit.dry_run);
+            return save_image(file_path, &container.to_bytes(), container.media_type(), original_size, edit);
+        }
+    };
+    let original_size = png.byte_len();
     
-    png.remove_chunk(chunk_type.as_str())?;
+    let removed = match index {
+        _ if all => png.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == chunk_type),
+        Some(index) => vec![png.remove_nth_chunk(chunk_type.as_str(), index)?],
+        None => vec![png.remove_chunk(chunk_type.as_str())?]
+    };
+    if removed.is_empty() {
+        return Err(anyhow!("No such chunk_type found"));
+    }
+    report_removed(&removed, edit.dry_run);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
 
-    Ok(fs::write(file_path, png.as_bytes())?)
+#[derive(Serialize)]
+struct PrintedChunkView {
+    #[serde(flatten)]
+    chunk: ChunkView,
+    text: Option<String>
+}
+
+#[derive(Serialize)]
+struct PrintedPngView {
+    header: [u8;8],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<ImageView>,
+    chunks: Vec<PrintedChunkView>
 }
 
 pub fn print(PrintArgs {
     file_path
-}: PrintArgs) -> Result<()> {
-    let png = Png::try_from_path(file_path.as_path())?;
+}: PrintArgs, format: Format) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+
+    if format == Format::Json {
+        let ihdr = png.ihdr().ok();
+        let PngView { header, chunks } = PngView::new(&png);
+        let chunks = chunks
+            .into_iter()
+            .zip(png.chunks())
+            .map(|(view, chunk)| PrintedChunkView {
+                chunk: view,
+                text: chunk_text(chunk, ihdr.as_ref()).ok()
+            })
+            .collect();
+        let image = ihdr.as_ref().map(ImageView::new);
+        return print_json(&PrintedPngView { header, image, chunks });
+    }
 
-    println!("{:?}", png.header());
+    let ihdr = png.ihdr()?;
+    println!("Width:      {}", ihdr.width);
+    println!("Height:     {}", ihdr.height);
+    println!("Bit depth:  {}", ihdr.bit_depth);
+    println!("Color type: {} ({})", ihdr.color_type, ihdr.color_type.to_byte());
+    println!("Interlace:  {}", if ihdr.interlaced { "Adam7" } else { "none" });
+    println!();
 
     for chunk in png.chunks() {
-        println!("{}", chunk.data_as_string()?);
+        println!("{}", chunk_text(chunk, Some(&ihdr))?);
+    }
+
+    Ok(())
+}
+
+/// The chunk's data as text, using `describer` for the chunk types it knows.
+/// `ihdr` is needed for chunks laid out according to the color type.
+fn chunk_text(chunk: &Chunk, ihdr: Option<&Ihdr>) -> Result<String> {
+    match describer(chunk.chunk_type()) {
+        Some(describe) => describe(chunk, ihdr),
+        None => Ok(chunk.data_as_string()?)
+    }
+}
+
+type Describe = fn(&Chunk, Option<&Ihdr>) -> Result<String>;
+
+/// How to show the data of a chunk type pngme understands, e.g. tEXt, zTXt
+/// and iTXt chunks as `keyword: text`.
+fn describer(chunk_type: &ChunkType) -> Option<Describe> {
+    match chunk_type.to_string().as_str() {
+        text::TEXT | text::COMPRESSED_TEXT | text::INTERNATIONAL_TEXT => Some(|chunk, _| {
+            Ok(Text::from_chunk(chunk)?.to_string())
+        }),
+        ihdr::IHDR => Some(|chunk, _| Ok(Ihdr::from_chunk(chunk)?.to_string())),
+        color::GAMA => Some(|chunk, _| Ok(Gamma::from_chunk(chunk)?.to_string())),
+        color::CHRM => Some(|chunk, _| Ok(Chromaticities::from_chunk(chunk)?.to_string())),
+        color::SRGB => Some(|chunk, _| Ok(RenderingIntent::from_chunk(chunk)?.to_string())),
+        icc::ICCP => Some(|chunk, _| Ok(IccProfile::from_chunk(chunk)?.to_string())),
+        significant_bits::SBIT => Some(|chunk, ihdr| {
+            Ok(SignificantBits::from_chunk(chunk, color_type_for(chunk, ihdr)?)?.to_string())
+        }),
+        palette::PLTE => Some(|chunk, _| Ok(Palette::from_chunk(chunk)?.to_string())),
+        palette::HIST => Some(|chunk, _| Ok(Histogram::from_chunk(chunk)?.to_string())),
+        palette::SPLT => Some(|chunk, _| Ok(SuggestedPalette::from_chunk(chunk)?.to_string())),
+        background::BKGD => Some(|chunk, ihdr| {
+            Ok(Background::from_chunk(chunk, color_type_for(chunk, ihdr)?)?.to_string())
+        }),
+        transparency::TRNS => Some(|chunk, ihdr| {
+            Ok(Transparency::from_chunk(chunk, color_type_for(chunk, ihdr)?)?.to_string())
+        }),
+        exif::EXIF => Some(|chunk, _| Ok(Exif::from_chunk(chunk)?.summary()?.to_string())),
+        apng::ACTL => Some(|chunk, _| Ok(AnimationControl::from_chunk(chunk)?.to_string())),
+        apng::FCTL => Some(|chunk, _| Ok(FrameControl::from_chunk(chunk)?.to_string())),
+        apng::FDAT => Some(|chunk, _| Ok(format!(
+            "#{}: {} bytes of frame data",
+            apng::frame_data_sequence_number(chunk.data())?,
+            chunk.data().len() - 4
+        ))),
+        phys::PHYS => Some(|chunk, _| Ok(Phys::from_chunk(chunk)?.to_string())),
+        timestamp::TIME => Some(|chunk, _| Ok(Timestamp::from_chunk(chunk)?.to_string())),
+        _ => None
+    }
+}
+
+fn color_type_for(chunk: &Chunk, ihdr: Option<&Ihdr>) -> Result<ColorType> {
+    ihdr.map(|ihdr| ihdr.color_type)
+        .ok_or_else(|| anyhow!("{} can't be read without a valid IHDR", chunk.chunk_type()))
+}
+
+#[derive(Serialize)]
+struct ListedPngView {
+    header: [u8;8],
+    chunks: Vec<ListedChunkView>
+}
+
+#[derive(Serialize)]
+struct ListedChunkView {
+    #[serde(flatten)]
+    chunk: ChunkView,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<String>
+}
+
+#[derive(Serialize)]
+struct ContainerView {
+    media_type: &'static str,
+    blocks: Vec<BlockView>
+}
+
+#[derive(Serialize)]
+struct BlockView {
+    index: usize,
+    chunk_type: String,
+    length: usize
+}
+
+pub fn list(ListArgs {
+    file_path
+}: ListArgs, format: Format) -> Result<()> {
+    let mut png = match read_lazy_carrier(file_path.as_path())? {
+        Carrier::Png(png) => png,
+        Carrier::Other(container) => return list_blocks(container.as_ref(), format)
+    };
+    let PngView { header, chunks } = PngView::from_lazy(&png);
+
+    // Only chunks with details are read, so listing stays cheap for large images
+    let ihdr = match png.chunk_headers().first() {
+        Some(header) if header.chunk_type().to_string() == ihdr::IHDR => Ihdr::from_chunk(&png.load_chunk(0)?).ok(),
+        _ => None
+    };
+    let mut listed = Vec::with_capacity(chunks.len());
+    for view in chunks {
+        let details = match describer(png.chunk_headers()[view.index].chunk_type()) {
+            Some(describe) => Some(
+                describe(&png.load_chunk(view.index)?, ihdr.as_ref()).unwrap_or_else(|error| format!("<{}>", error))
+            ),
+            None => None
+        };
+        listed.push(ListedChunkView { chunk: view, details });
+    }
+
+    if format == Format::Json {
+        return print_json(&ListedPngView { header, chunks: listed });
+    }
+
+    anstream::println!(
+        "{}{:>5}  {:<4}  {:>10}  {:<10}  {:>10}  {:<8}  {:<6}  {:<12}  DETAILS{:#}",
+        style::HEADER, "INDEX", "TYPE", "LENGTH", "CRC", "OFFSET", "CRITICAL", "PUBLIC", "SAFE-TO-COPY", style::HEADER
+    );
+
+    for ListedChunkView { chunk, details } in &listed {
+        let chunk_style = style::chunk_type(&chunk.chunk_type);
+        let row = format!(
+            "{:>5}  {}{:<4}{:#}  {:>10}  {}{:#010x}{:#}  {:>10}  {:<8}  {:<6}  {:<12}",
+            chunk.index,
+            chunk_style,
+            chunk.chunk_type,
+            chunk_style,
+            chunk.length,
+            style::DIM,
+            chunk.crc,
+            style::DIM,
+            chunk.offset,
+            yes_no(chunk.critical),
+            yes_no(chunk.public),
+            yes_no(chunk.safe_to_copy)
+        );
+        match details {
+            Some(details) => anstream::println!("{}  {}", row, escape_controls(details)),
+            None => anstream::println!("{}", row.trim_end())
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the payload blocks of a carrier other than a PNG, which has no
+/// CRCs or offsets to show.
+fn list_blocks(container: &dyn Container, format: Format) -> Result<()> {
+    let blocks: Vec<BlockView> = container
+        .blocks()?
+        .into_iter()
+        .enumerate()
+        .map(|(index, (chunk_type, length))| BlockView { index, chunk_type: chunk_type.to_string(), length })
+        .collect();
+    if format == Format::Json {
+        return print_json(&ContainerView { media_type: container.media_type(), blocks });
     }
 
+    anstream::println!("{}{:>5}  {:<4}  {:>10}{:#}", style::HEADER, "INDEX", "TYPE", "LENGTH", style::HEADER);
+    for block in &blocks {
+        let chunk_style = style::chunk_type(&block.chunk_type);
+        anstream::println!("{:>5}  {}{:<4}{:#}  {:>10}", block.index, chunk_style, block.chunk_type, chunk_style, block.length);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Serialize)]
+struct CheckView<'a> {
+    file: String,
+    valid: bool,
+    problems: Vec<ProblemView<'a>>
+}
+
+pub fn check(CheckArgs {
+    file_path
+}: CheckArgs, format: Format) -> Result<()> {
+    let bytes = match remote::is_url(file_path.as_path()) {
+        true => remote::fetch(file_path.as_path())?,
+        false => read_image(file_path.as_path())?
+    };
+    let problems = validate(&bytes);
+    // A data URI is the whole image, far too long to repeat on every line
+    let name = match is_data_uri(file_path.as_path()) {
+        true => "<data URI>".to_string(),
+        false => file_path.display().to_string()
+    };
+
+    match format {
+        Format::Text if problems.is_empty() => anstream::println!("{}: {}OK{:#}", name, style::OK, style::OK),
+        Format::Text => {
+            for problem in &problems {
+                match problem.severity {
+                    Severity::Error => anstream::println!("{}: {}{}{:#}", name, style::ERROR, problem, style::ERROR),
+                    Severity::Warning => anstream::println!("{}: {}warning:{:#} {}", name, style::WARNING, style::WARNING, problem)
+                }
+            }
+        },
+        Format::Json => print_json(&CheckView {
+            file: name.clone(),
+            valid: !problems.iter().any(Problem::is_error),
+            problems: problems.iter().map(ProblemView::new).collect()
+        })?
+    }
+
+    // Warnings such as trailing data are worth reporting but don't make the file invalid
+    let errors = problems.iter().filter(|problem| problem.is_error()).count();
+    if errors == 0 {
+        return Ok(());
+    }
+
+    Err(anyhow!("Found {} problem(s) in {}", errors, name))
+}
+
+pub fn scrub(ScrubArgs {
+    file_path,
+    keep,
+    edit
+}: ScrubArgs) -> Result<()> {
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    let removed = png.remove_chunks_where(|chunk| {
+        let chunk_type = chunk.chunk_type();
+        let is_scrubbable = !chunk_type.is_critical() || !chunk_type.is_public();
+        is_scrubbable && !keep.contains(&chunk_type.to_string())
+    });
+
+    report_removed(&removed, edit.dry_run);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+fn report_removed(removed: &[Chunk], dry_run: bool) {
+    let removed_types: Vec<String> = removed
+        .iter()
+        .map(describe_chunk)
+        .collect();
+    if dry_run {
+        println!("Would remove {} chunk(s): {}", removed.len(), removed_types.join(", "));
+    } else {
+        info!("Removed {} chunk(s): {}", removed.len(), removed_types.join(", "));
+    }
+}
+
+pub fn hexdump(HexdumpArgs {
+    file_path,
+    chunk_type
+}: HexdumpArgs) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+
+    let chunk = match png.chunk_by_type(chunk_type.as_str()) {
+        Some(chunk) => chunk,
+        None => return Err(anyhow!("No such chunk_type found"))
+    };
+
+    print!("{}", format_hexdump(chunk.data()));
+    Ok(())
+}
+
+pub fn fix_crc(FixCrcArgs {
+    file_path,
+    edit
+}: FixCrcArgs) -> Result<()> {
+    let bytes = read_input(file_path.as_path())?;
+    let Repair { mut png, fixes, skipped } = fix_crcs(&bytes)?;
+
+    for chunk in &skipped {
+        warn!(
+            "{} chunk at offset {} with the invalid type {:?} ({} bytes of data)",
+            if edit.dry_run { "Would drop" } else { "Dropped" },
+            chunk.offset,
+            String::from_utf8_lossy(&chunk.chunk_type),
+            chunk.length
+        );
+    }
+    // The output is written all the same, so scripts can count on it
+    if fixes.is_empty() {
+        info!("All {} chunk CRC(s) are already correct", png.chunks().len());
+    }
+
+    let verb = if edit.dry_run { "Would fix" } else { "Fixed" };
+    for fix in &fixes {
+        info!(
+            "{} CRC of {} at offset {}: {:#010x} -> {:#010x}",
+            verb,
+            fix.chunk_type,
+            fix.offset,
+            fix.stored,
+            fix.computed
+        );
+    }
+
+    save_png(file_path, &mut png, bytes.len(), edit)
+}
+
+pub fn sign(SignArgs {
+    file_path,
+    key,
+    chunk,
+    edit
+}: SignArgs) -> Result<()> {
+    let key = signature::read_signing_key(key.as_path())?;
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    let signed = match chunk {
+        Some(chunk) => Signed::Chunk(ChunkType::parse(chunk.as_str(), true)?),
+        None => Signed::Image
+    };
+    signature::sign(&mut png, &key, signed)?;
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+pub fn verify(VerifyArgs {
+    file_path,
+    public_key
+}: VerifyArgs, format: Format) -> Result<()> {
+    let key = signature::read_verifying_key(public_key.as_path())?;
+    let png = read_png(file_path.as_path())?;
+    let checks = signature::verify(&png, &key)?;
+
+    let views: Vec<SignatureView> = checks
+        .iter()
+        .map(|check| SignatureView {
+            signed: match &check.signed {
+                Signed::Image => "image".to_string(),
+                Signed::Chunk(chunk_type) => format!("chunk {}", chunk_type)
+            },
+            valid: check.valid
+        })
+        .collect();
+
+    match format {
+        Format::Text => {
+            for view in &views {
+                let status = if view.valid { "valid" } else { "INVALID" };
+                println!("{}: {} signature {}", file_path.display(), view.signed, status);
+            }
+        },
+        Format::Json => print_json(&views)?
+    }
+
+    let invalid = checks.iter().filter(|check| !check.valid).count();
+    if invalid > 0 {
+        return Err(anyhow!("{} of {} signature(s) in {} did not verify", invalid, checks.len(), file_path.display()));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SignatureView {
+    signed: String,
+    valid: bool
+}
+
+pub fn keygen(KeygenArgs {
+    prefix,
+    force
+}: KeygenArgs) -> Result<()> {
+    // Appended rather than set with `with_extension`, which would replace
+    // the part after a dot in the prefix, as in `alice.work`
+    let with_suffix = |suffix: &str| {
+        let mut path = prefix.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    };
+    let private_path = with_suffix(".key");
+    let public_path = with_suffix(".pub");
+    for path in [&private_path, &public_path] {
+        if path.exists() && !force {
+            return Err(anyhow!("{} already exists; pass --force to overwrite it", path.display()));
+        }
+    }
+
+    let (private_key, public_key) = crypto::generate_keypair();
+    let encoded_private = Zeroizing::new(format!("{}\n", STANDARD.encode(*private_key)));
+    let encoded_public = STANDARD.encode(public_key);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&private_path)?;
+    // The mode only applies to new files, so one being overwritten with
+    // --force is restricted before the key goes into it
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(encoded_private.as_bytes())?;
+    fs::write(&public_path, format!("{}\n", encoded_public))?;
+
+    info!("Wrote {} and {}", private_path.display(), public_path.display());
+    println!("{}", encoded_public);
+    Ok(())
+}
+
+pub fn text_set(TextSetArgs {
+    file_path,
+    keyword,
+    text,
+    compress,
+    language,
+    translated_keyword,
+    edit
+}: TextSetArgs) -> Result<()> {
+    let international = language.is_some() || translated_keyword.is_some() || !text.chars().all(|c| c <= '\u{ff}');
+    let entry = match international {
+        true => Text::international(
+            &keyword,
+            &text,
+            language.as_deref().unwrap_or_default(),
+            translated_keyword.as_deref().unwrap_or_default()
+        )?,
+        false => Text::new(&keyword, &text)?
+    };
+    let entry = Text { compressed: compress, ..entry };
+    let chunk = entry.to_chunk()?;
+
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    // Entries for the same keyword in other languages are kept
+    let replaced = png.remove_chunks_where(|chunk| {
+        text::is_text(chunk.chunk_type())
+            && Text::from_chunk(chunk).is_ok_and(|existing| {
+                existing.keyword == entry.keyword && existing.language_tag().eq_ignore_ascii_case(entry.language_tag())
+            })
+    });
+    if edit.dry_run {
+        if !replaced.is_empty() {
+            println!("Would replace {} chunk(s) with keyword {:?}", replaced.len(), keyword);
+        }
+        println!("Would add chunk {}", describe_chunk(&chunk));
+    }
+    png.append_chunk(chunk);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+#[derive(Serialize)]
+struct TextView<'a> {
+    index: usize,
+    chunk_type: String,
+    keyword: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language_tag: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translated_keyword: Option<&'a str>,
+    text: &'a str
+}
+
+pub fn text_get(TextGetArgs {
+    file_path,
+    keyword,
+    language
+}: TextGetArgs, format: Format) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+
+    let mut entries = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        if !text::is_text(chunk.chunk_type()) {
+            continue;
+        }
+        match Text::from_chunk(chunk) {
+            Ok(entry) => entries.push((index, chunk.chunk_type(), entry)),
+            Err(error) => warn!("{}: skipped chunk {}: {}", file_path.display(), index, error)
+        }
+    }
+    if let Some(language) = &language {
+        entries.retain(|(_, _, entry)| entry.language_tag().eq_ignore_ascii_case(language));
+    }
+    if let Some(keyword) = &keyword {
+        entries.retain(|(_, _, entry)| entry.keyword == *keyword);
+        if entries.is_empty() {
+            return Err(anyhow!("No text with keyword {:?} found", keyword));
+        }
+    }
+
+    if format == Format::Json {
+        let views: Vec<TextView> = entries
+            .iter()
+            .map(|(index, chunk_type, entry)| TextView {
+                index: *index,
+                chunk_type: chunk_type.to_string(),
+                keyword: &entry.keyword,
+                language_tag: entry.international.as_ref().map(|international| international.language_tag.as_str()),
+                translated_keyword: entry.international.as_ref().map(|international| international.translated_keyword.as_str()),
+                text: &entry.text
+            })
+            .collect();
+        return print_json(&views);
+    }
+
+    for (_, _, entry) in &entries {
+        match keyword {
+            Some(_) => println!("{}", entry.text),
+            None => println!("{}", entry)
+        }
+    }
+    Ok(())
+}
+
+pub fn touch(TouchArgs {
+    file_path,
+    time,
+    edit
+}: TouchArgs) -> Result<()> {
+    let chunk = time.unwrap_or_else(Timestamp::now).to_chunk();
+
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    // There may only be one tIME chunk
+    let replaced = png.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == timestamp::TIME);
+    if edit.dry_run {
+        for chunk in &replaced {
+            println!("Would replace modification time {}", chunk_text(chunk, None).unwrap_or_else(|error| format!("<{}>", error)));
+        }
+        println!("Would add chunk {}", describe_chunk(&chunk));
+    }
+    png.append_chunk(chunk);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+pub fn set_dpi(SetDpiArgs {
+    file_path,
+    dpi,
+    edit
+}: SetDpiArgs) -> Result<()> {
+    let chunk = Phys::from_dpi(dpi)?.to_chunk();
+
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    // There may only be one pHYs chunk
+    let replaced = png.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == phys::PHYS);
+    if edit.dry_run {
+        for chunk in &replaced {
+            println!("Would replace resolution {}", chunk_text(chunk, None).unwrap_or_else(|error| format!("<{}>", error)));
+        }
+        println!("Would add chunk {}", describe_chunk(&chunk));
+    }
+    png.append_chunk(chunk);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+pub fn icc_extract(IccExtractArgs {
+    file_path,
+    output
+}: IccExtractArgs) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+
+    let chunk = png
+        .chunk_by_type(icc::ICCP)
+        .ok_or_else(|| anyhow!("{} has no ICC profile", file_path.display()))?;
+    write_output(output.as_path(), &IccProfile::from_chunk(chunk)?.profile)
+}
+
+pub fn icc_embed(IccEmbedArgs {
+    file_path,
+    profile,
+    name,
+    edit
+}: IccEmbedArgs) -> Result<()> {
+    // Profile file names such as "Display P3.icc" usually make a fitting name
+    let name = name.unwrap_or_else(|| {
+        profile
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .filter(|stem| text::validate_keyword(stem).is_ok())
+            .unwrap_or_else(|| "ICC profile".to_string())
+    });
+    let icc = IccProfile::new(&name, read_input(profile.as_path())?)?;
+    if icc.header().is_none() {
+        return Err(anyhow!("{} is not an ICC profile", profile.display()));
+    }
+    let chunk = icc.to_chunk()?;
+
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    if png.chunk_by_type(color::SRGB).is_some() {
+        warn!(
+            "{}: the image also has an sRGB chunk, which decoders may prefer over the profile",
+            file_path.display()
+        );
+    }
+    // There may only be one iCCP chunk
+    let replaced = png.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == icc::ICCP);
+    if edit.dry_run {
+        for chunk in &replaced {
+            println!("Would replace profile {}", chunk_text(chunk, None).unwrap_or_else(|error| format!("<{}>", error)));
+        }
+        println!("Would add chunk {}", describe_chunk(&chunk));
+    }
+    png.append_chunk(chunk);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+pub fn exif_export(ExifExportArgs {
+    file_path,
+    output
+}: ExifExportArgs) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+
+    let chunk = png
+        .chunk_by_type(exif::EXIF)
+        .ok_or_else(|| anyhow!("{} has no EXIF data", file_path.display()))?;
+    write_output(output.as_path(), chunk.data())
+}
+
+pub fn exif_import(ExifImportArgs {
+    file_path,
+    exif,
+    edit
+}: ExifImportArgs) -> Result<()> {
+    let chunk = Exif::new(read_input(exif.as_path())?)?.to_chunk()?;
+
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    // There may only be one eXIf chunk
+    let replaced = png.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == exif::EXIF);
+    if edit.dry_run {
+        for chunk in &replaced {
+            println!("Would replace EXIF data {}", chunk_text(chunk, None).unwrap_or_else(|error| format!("<{}>", error)));
+        }
+        println!("Would add chunk {}", describe_chunk(&chunk));
+    }
+    png.append_chunk(chunk);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+#[derive(Serialize)]
+struct PaletteEntryView {
+    index: usize,
+    red: u8,
+    green: u8,
+    blue: u8,
+    hex: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alpha: Option<u8>
+}
+
+pub fn palette(PaletteArgs {
+    file_path,
+    swatches
+}: PaletteArgs, format: Format) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+
+    let chunk = png
+        .chunk_by_type(palette::PLTE)
+        .ok_or_else(|| anyhow!("{} has no palette", file_path.display()))?;
+    let palette = Palette::from_chunk(chunk)?;
+    let ihdr = png.ihdr()?;
+    // A palette the bit depth can't fully index is still worth seeing
+    if let Err(error) = palette.validate(&ihdr) {
+        warn!("{}: {}", file_path.display(), error);
+    }
+    // tRNS gives indexed images the alpha of the first entries; the rest are opaque
+    let alpha = match png.chunk_by_type(transparency::TRNS).map(|chunk| Transparency::from_chunk(chunk, ihdr.color_type)) {
+        Some(Ok(Transparency::PaletteAlpha(alpha))) => Some(alpha),
+        _ => None
+    };
+
+    let entries: Vec<PaletteEntryView> = palette.entries
+        .iter()
+        .enumerate()
+        .map(|(index, &[red, green, blue])| PaletteEntryView {
+            index,
+            red,
+            green,
+            blue,
+            hex: format!("#{:02x}{:02x}{:02x}", red, green, blue),
+            alpha: alpha.as_ref().map(|alpha| alpha.get(index).copied().unwrap_or(u8::MAX))
+        })
+        .collect();
+
+    if format == Format::Json {
+        return print_json(&entries);
+    }
+
+    for entry in &entries {
+        let swatch = match swatches {
+            true => format!("\x1b[48;2;{};{};{}m    \x1b[0m  ", entry.red, entry.green, entry.blue),
+            false => String::new()
+        };
+        let alpha = entry.alpha.map_or(String::new(), |alpha| format!("  alpha {:>3}", alpha));
+        println!(
+            "{:>3}  {}{}  {:>3} {:>3} {:>3}{}",
+            entry.index, swatch, entry.hex, entry.red, entry.green, entry.blue, alpha
+        );
+    }
+    Ok(())
+}
+
+pub fn lsb_encode(LsbEncodeArgs {
+    file_path,
+    message,
+    message_file,
+    base64,
+    bits,
+    compress,
+    compression_method,
+    encrypt,
+    passphrase,
+    edit
+}: LsbEncodeArgs) -> Result<()> {
+    let data = read_message(&file_path, message, message_file, base64)?;
+    let mut options = Options::default();
+    // Compress before encrypting, as ciphertext doesn't compress
+    let data = match compress.then_some(compression_method) {
+        Some(compression) => {
+            let method = match compression {
+                Compression::Deflate => Method::Deflate,
+                Compression::Zstd => Method::Zstd
+            };
+            let compressed = compress::compress(&data, method)?;
+            options.compressed = compressed.len() < data.len();
+            if options.compressed { compressed } else { data }
+        },
+        None => data
+    };
+    options.encrypted = encrypt;
+    let data = match encrypt {
+        true => {
+            let plaintext = Zeroizing::new(data);
+            let passphrase = match passphrase {
+                Some(passphrase) => Zeroizing::new(passphrase),
+                None => prompt_passphrase(true)?
+            };
+            crypto::encrypt(&plaintext, passphrase.as_bytes())?
+        },
+        false => data
+    };
+    let data = payload::wrap(options, &data);
+
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+
+    let mut image = ImageData::from_png(&png)?;
+    lsb::embed(&mut image, &data, bits)?;
+    // Keep the encoder's chunk size, so only the pixels tell the files apart
+    let max_len = png
+        .chunks_by_type(image_data::IDAT)
+        .iter()
+        .map(|chunk| chunk.data().len())
+        .max()
+        .unwrap_or(Chunk::MAX_LENGTH as usize);
+    let idat = image.to_chunks(max_len)?;
+    if edit.dry_run {
+        println!("Would hide {} bytes in the low {} bit(s) of the pixels, as {} IDAT chunk(s)", data.len(), bits, idat.len());
+    }
+    png.replace_idat(idat);
+
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+pub fn lsb_decode(LsbDecodeArgs {
+    file_path,
+    bits,
+    passphrase,
+    output
+}: LsbDecodeArgs) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+    let data = lsb::extract(&ImageData::from_png(&png)?, bits)?;
+
+    let mut keys = DecodeKeys {
+        passphrase: passphrase.map(Zeroizing::new),
+        key_file: None,
+        private_key: None,
+        age_identity: None,
+        hmac_key: None
+    };
+    let chunk_type = ChunkType::parse(image_data::IDAT, false)?;
+    let message = open_payload(&chunk_type, &data, &mut keys, usize::MAX)?;
+
+    if let Some(output) = output {
+        return write_output(output.as_path(), &message);
+    }
+    match std::str::from_utf8(&message) {
+        Ok(text) => println!("{}", text),
+        Err(_) if !io::stdout().is_terminal() => io::stdout().write_all(&message)?,
+        Err(_) => return Err(anyhow!(
+            "The hidden message is binary data ({} bytes), which won't be printed here; pass --output <FILE>",
+            message.len()
+        ))
+    }
+    Ok(())
+}
+
+/// Bytes of file size a chunk of `len` payload bytes adds, including the
+/// payload header and any split into parts.
+fn chunk_overhead(len: u64) -> u64 {
+    let wrapped = len + payload::HEADER_LEN as u64;
+    if wrapped <= Chunk::MAX_LENGTH as u64 {
+        return CHUNK_FRAME_LEN + wrapped;
+    }
+    let parts = wrapped.div_ceil(Chunk::MAX_LENGTH as u64 - PART_HEADER_LEN as u64);
+    CHUNK_FRAME_LEN + MANIFEST_LEN as u64 + parts * (CHUNK_FRAME_LEN + PART_HEADER_LEN as u64) + wrapped
+}
+
+/// Length, type and CRC around each chunk's data.
+const CHUNK_FRAME_LEN: u64 = 12;
+
+/// How many bytes the file grows by when `len` bytes are hidden in the low
+/// `bits` bits of its pixels. Encrypted payloads look random, so random
+/// bytes stand in for the payload.
+fn lsb_overhead(png: &Png, image: &ImageData, len: usize, bits: u8) -> Result<i64> {
+    let filler: Vec<u8> = (0..len.div_ceil(32) as u64)
+        .flat_map(|block| Sha256::digest(block.to_be_bytes()))
+        .take(len)
+        .collect();
+    let mut image = image.clone();
+    lsb::embed(&mut image, &filler, bits)?;
+
+    let idat = png.chunks_by_type(image_data::IDAT);
+    let max_len = idat.iter().map(|chunk| chunk.data().len()).max().unwrap_or(Chunk::MAX_LENGTH as usize);
+    let original_len: u64 = idat.iter().map(|chunk| CHUNK_FRAME_LEN + chunk.data().len() as u64).sum();
+    let encoded_len: u64 = image.to_chunks(max_len)?.iter().map(|chunk| CHUNK_FRAME_LEN + chunk.data().len() as u64).sum();
+    Ok(encoded_len as i64 - original_len as i64)
+}
+
+#[derive(Serialize)]
+struct CapacityView {
+    strategy: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bits: Option<u8>,
+    /// None for chunks, which split payloads of any size.
+    capacity: Option<u64>,
+    /// For `size` bytes, or for filling the pixels when no size is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overhead: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unsupported: Option<String>
+}
+
+pub fn capacity(CapacityArgs {
+    file_path,
+    size
+}: CapacityArgs, format: Format) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+
+    let mut views = vec![CapacityView {
+        strategy: "chunk",
+        bits: None,
+        capacity: None,
+        overhead: size.map(|size| chunk_overhead(size) as i64),
+        unsupported: None
+    }];
+    let image = ImageData::from_png(&png);
+    for bits in [1, 2] {
+        let capacity = match &image {
+            Ok(image) => lsb::capacity(&image.ihdr, bits).map(|capacity| (image, capacity)).map_err(|error| error.to_string()),
+            Err(error) => Err(error.to_string())
+        };
+        let view = match capacity {
+            Ok((image, capacity)) => {
+                let len = size.unwrap_or(capacity as u64);
+                let overhead = match len <= capacity as u64 {
+                    true => Some(lsb_overhead(&png, image, len as usize, bits)?),
+                    false => None
+                };
+                CapacityView { strategy: "lsb", bits: Some(bits), capacity: Some(capacity as u64), overhead, unsupported: None }
+            },
+            Err(unsupported) => CapacityView {
+                strategy: "lsb",
+                bits: Some(bits),
+                capacity: Some(0),
+                overhead: None,
+                unsupported: Some(unsupported)
+            }
+        };
+        views.push(view);
+    }
+
+    if format == Format::Json {
+        return print_json(&views);
+    }
+
+    let original_size = png.byte_len() as f64;
+    for view in &views {
+        let strategy = match view.bits {
+            Some(bits) => format!("{} ({} bit{})", view.strategy, bits, if bits == 1 { "" } else { "s" }),
+            None => view.strategy.to_string()
+        };
+        let capacity = match (&view.unsupported, view.capacity) {
+            (Some(unsupported), _) => {
+                println!("{:<14}  unsupported: {}", strategy, unsupported);
+                continue;
+            },
+            (None, Some(capacity)) => format!("{} bytes", capacity),
+            (None, None) => "no limit".to_string()
+        };
+        let overhead = match (view.overhead, size) {
+            (Some(overhead), _) => format!(
+                "{:+} bytes ({:+.1}%){}",
+                overhead,
+                overhead as f64 / original_size * 100.0,
+                if size.is_none() { " when full" } else { "" }
+            ),
+            (None, Some(size)) if view.capacity.is_some() => format!("{} bytes don't fit", size),
+            (None, _) => format!("+{} bytes plus the payload", chunk_overhead(0))
+        };
+        println!("{:<14}  {:<16}  {}", strategy, capacity, overhead);
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct FindingView {
+    offset: usize,
+    indicator: &'static str,
+    description: String,
+    score: u32
+}
+
+#[derive(Serialize)]
+struct DetectView {
+    file: String,
+    score: u32,
+    level: &'static str,
+    findings: Vec<FindingView>
+}
+
+/// A rough label for a report's score, for sorting files into piles.
+fn detect_level(score: u32) -> &'static str {
+    match score {
+        0 => "clean",
+        1..40 => "low",
+        40..70 => "medium",
+        _ => "high"
+    }
+}
+
+pub fn detect(DetectArgs {
+    file_path,
+    min_score
+}: DetectArgs, recursive: bool, format: Format) -> Result<()> {
+    let file_paths = expand_paths(file_path.as_path(), recursive)?;
+
+    let mut reports: Vec<(PathBuf, Report)> = Vec::new();
+    let mut failed = 0;
+    for file_path in file_paths {
+        match read_png(file_path.as_path()) {
+            Ok(png) => reports.push((file_path, detect::analyze(&png))),
+            Err(error) => {
+                error!("{}: {}", file_path.display(), error);
+                failed += 1;
+            }
+        }
+    }
+    // The most suspicious files first
+    reports.retain(|(_, report)| report.score() >= min_score);
+    reports.sort_by_key(|(_, report)| std::cmp::Reverse(report.score()));
+
+    let views: Vec<DetectView> = reports
+        .iter()
+        .map(|(file_path, report)| DetectView {
+            file: file_path.display().to_string(),
+            score: report.score(),
+            level: detect_level(report.score()),
+            findings: report.findings
+                .iter()
+                .map(|finding| FindingView {
+                    offset: finding.offset,
+                    indicator: match finding.indicator {
+                        Indicator::UnknownChunk { .. } => "unknown-chunk",
+                        Indicator::HighEntropy { .. } => "high-entropy",
+                        Indicator::AfterIend { .. } => "after-iend",
+                        Indicator::LsbDistribution { .. } => "lsb-distribution",
+                        Indicator::LsbPayload { .. } => "lsb-payload"
+                    },
+                    description: finding.indicator.to_string(),
+                    score: finding.indicator.score()
+                })
+                .collect()
+        })
+        .collect();
+
+    match format {
+        Format::Text => {
+            for view in &views {
+                println!("{:>3}  {:<6}  {}", view.score, view.level, view.file);
+                for finding in &view.findings {
+                    println!("             offset {}: {} (+{})", finding.offset, finding.description, finding.score);
+                }
+            }
+        },
+        Format::Json => print_json(&views)?
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} file(s) could not be read", failed));
+    }
+    Ok(())
+}
+
+pub fn completions(CompletionsArgs {
+    shell
+}: CompletionsArgs) -> Result<()> {
+    let known: Vec<String> = ChunkType::standard_types()
+        .map(|chunk_type| chunk_type.to_string())
+        .chain([signature::SIGNATURE_CHUNK.to_string()])
+        .collect();
+    let mut command = suggest_chunk_types(Cli::command(), &known);
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+    Ok(())
+}
+
+/// Offers `known` for every chunk type argument of `command` and its
+/// subcommands. Only the completion script sees these, so any other type
+/// still parses. `encode` is left out, as it wants a private type of the
+/// user's own.
+fn suggest_chunk_types(command: Command, known: &[String]) -> Command {
+    let command = command.mut_args(|arg| match arg.get_id().as_str() {
+        "chunk_type" | "chunk" => arg.value_parser(PossibleValuesParser::new(known.iter().cloned())),
+        _ => arg
+    });
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .filter(|name| name != "encode")
+        .collect();
+    names.iter().fold(command, |command, name| {
+        command.mut_subcommand(name, |subcommand| suggest_chunk_types(subcommand, known))
+    })
+}
+
+pub fn man(ManArgs {
+    command,
+    dir
+}: ManArgs) -> Result<()> {
+    // `help` has no page of its own; every page already describes --help
+    let mut cli = Cli::command().disable_help_subcommand(true);
+    cli.build();
+
+    if let Some(dir) = dir {
+        fs::create_dir_all(&dir)?;
+        write_man_pages(cli, &dir)?;
+        info!("Wrote the man pages to {}", dir.display());
+        return Ok(());
+    }
+
+    let mut page = cli;
+    for name in &command {
+        page = page
+            .find_subcommand(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("{} has no {} command", page.get_display_name().unwrap_or(page.get_name()), name))?;
+    }
+    man_page(page).render(&mut io::stdout())?;
+    Ok(())
+}
+
+/// Subcommands have no version of their own, so every page names pngme's.
+fn man_page(command: Command) -> clap_mangen::Man {
+    clap_mangen::Man::new(command).source(format!("pngme {}", env!("CARGO_PKG_VERSION")))
+}
+
+fn write_man_pages(command: Command, dir: &Path) -> Result<()> {
+    for subcommand in command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set()) {
+        write_man_pages(subcommand.clone(), dir)?;
+    }
+    man_page(command).generate_to(dir)?;
+    Ok(())
+}
+
+pub fn tui(TuiArgs {
+    file_path,
+    edit
+}: TuiArgs) -> Result<()> {
+    let png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+    let ihdr = png.ihdr().ok();
+    let describe = |chunk: &Chunk| chunk_text(chunk, ihdr.as_ref()).unwrap_or_else(|error| format!("<{}>", error));
+
+    match crate::tui::browse(&file_path.display().to_string(), png, &describe)? {
+        Some(mut png) => save_png(file_path, &mut png, original_size, edit),
+        None => Ok(())
+    }
+}
+
+/// A changed IDAT can differ on every line, so only this many lines of
+/// each chunk are shown.
+const MAX_DIFF_LINES: usize = 16;
+
+#[derive(Serialize)]
+struct DiffView {
+    old: String,
+    new: String,
+    changes: Vec<ChangeView>,
+    trailing_data_changed: bool
+}
+
+#[derive(Serialize)]
+struct ChangeView {
+    change: &'static str,
+    old: Option<ChunkView>,
+    new: Option<ChunkView>
+}
+
+pub fn diff(DiffArgs {
+    old: old_path,
+    new: new_path,
+    all,
+    data
+}: DiffArgs, format: Format) -> Result<()> {
+    let old = read_png(old_path.as_path())?;
+    let new = read_png(new_path.as_path())?;
+    let changes: Vec<Change> = pngme::diff::diff(old.chunks(), new.chunks())
+        .into_iter()
+        .filter(|change| all || !matches!(change, Change::Unchanged { .. }))
+        .collect();
+    let trailing_data_changed = old.trailing_data() != new.trailing_data();
+
+    if format == Format::Json {
+        // Each chunk appears in at most one change, so its view is moved there
+        let views = |png: &Png| PngView::new(png).chunks.into_iter().map(Some).collect::<Vec<_>>();
+        let (mut old_views, mut new_views) = (views(&old), views(&new));
+        let changes = changes
+            .iter()
+            .map(|change| {
+                let (name, old, new) = match *change {
+                    Change::Unchanged { old, new } => ("unchanged", Some(old), Some(new)),
+                    Change::Changed { old, new } => ("changed", Some(old), Some(new)),
+                    Change::Added { new } => ("added", None, Some(new)),
+                    Change::Removed { old } => ("removed", Some(old), None)
+                };
+                ChangeView {
+                    change: name,
+                    old: old.and_then(|index| old_views[index].take()),
+                    new: new.and_then(|index| new_views[index].take())
+                }
+            })
+            .collect();
+        return print_json(&DiffView {
+            old: old_path.display().to_string(),
+            new: new_path.display().to_string(),
+            changes,
+            trailing_data_changed
+        });
+    }
+
+    let (old_chunks, new_chunks) = (old.chunks(), new.chunks());
+    let describe = |chunk: &Chunk, index: usize| format!("{} (index {}, {} bytes, CRC {:#010x})", chunk.chunk_type(), index, chunk.length(), chunk.crc());
+    for change in &changes {
+        match *change {
+            Change::Unchanged { new, .. } => anstream::println!("{}  {}{:#}", style::DIM, describe(&new_chunks[new], new), style::DIM),
+            Change::Added { new } => anstream::println!("{}+ {}{:#}", style::ADDED, describe(&new_chunks[new], new), style::ADDED),
+            Change::Removed { old } => anstream::println!("{}- {}{:#}", style::REMOVED, describe(&old_chunks[old], old), style::REMOVED),
+            Change::Changed { old, new } => {
+                let (old_chunk, new_chunk) = (&old_chunks[old], &new_chunks[new]);
+                let index = match old == new {
+                    true => old.to_string(),
+                    false => format!("{} -> {}", old, new)
+                };
+                anstream::println!(
+                    "{}~ {} (index {}, {} -> {} bytes, CRC {:#010x} -> {:#010x}){:#}",
+                    style::CHANGED,
+                    new_chunk.chunk_type(),
+                    index,
+                    old_chunk.length(),
+                    new_chunk.length(),
+                    old_chunk.crc(),
+                    new_chunk.crc(),
+                    style::CHANGED
+                );
+                if data {
+                    print_data_diff(old_chunk.data(), new_chunk.data());
+                }
+            }
+        }
+    }
+    if trailing_data_changed {
+        anstream::println!(
+            "{}~ data after IEND ({} -> {} bytes){:#}",
+            style::CHANGED,
+            old.trailing_data().len(),
+            new.trailing_data().len(),
+            style::CHANGED
+        );
+    }
+
+    let count = |kind: fn(&Change) -> bool| changes.iter().filter(|change| kind(change)).count();
+    println!(
+        "{} added, {} removed, {} changed{}",
+        count(|change| matches!(change, Change::Added { .. })),
+        count(|change| matches!(change, Change::Removed { .. })),
+        count(|change| matches!(change, Change::Changed { .. })),
+        match all {
+            true => format!(", {} unchanged", count(|change| matches!(change, Change::Unchanged { .. }))),
+            false => String::new()
+        }
+    );
+    Ok(())
+}
+
+/// Prints the lines of the two chunks' hex dumps that differ, old above new.
+fn print_data_diff(old: &[u8], new: &[u8]) {
+    let (old_dump, new_dump) = (format_hexdump(old), format_hexdump(new));
+    let (mut old_lines, mut new_lines) = (old_dump.lines(), new_dump.lines());
+    let mut shown = 0;
+    loop {
+        let (old_line, new_line) = (old_lines.next(), new_lines.next());
+        if old_line.is_none() && new_line.is_none() {
+            break;
+        }
+        if old_line == new_line {
+            continue;
+        }
+        if shown == MAX_DIFF_LINES {
+            anstream::println!("{}    … more lines differ{:#}", style::DIM, style::DIM);
+            break;
+        }
+        if let Some(line) = old_line {
+            anstream::println!("{}    - {}{:#}", style::REMOVED, line, style::REMOVED);
+        }
+        if let Some(line) = new_line {
+            anstream::println!("{}    + {}{:#}", style::ADDED, line, style::ADDED);
+        }
+        shown += 1;
+    }
+}
+
+pub fn copy_chunks(CopyChunksArgs {
+    source: source_path,
+    destination: file_path,
+    types,
+    force,
+    edit
+}: CopyChunksArgs) -> Result<()> {
+    let source = read_png(source_path.as_path())?;
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+    let types = types.iter().map(|chunk_type| ChunkType::parse(chunk_type, true)).collect::<Result<Vec<_>, _>>()?;
+
+    for chunk_type in &types {
+        if source.chunk_by_type(&chunk_type.to_string()).is_none() {
+            warn!("{} has no {} chunk", source_path.display(), chunk_type);
+        }
+    }
+    let report = copy::copy_chunks(&source, &mut png, &types, force);
+    // Images usually have several IDAT chunks, which need only one warning
+    let skipped = report.skipped.iter().enumerate().filter(|(index, skipped)| !report.skipped[..*index].contains(skipped));
+    for (_, (chunk_type, reason)) in skipped {
+        match reason {
+            Skipped::Critical => warn!("Skipped {}: critical chunks can't be copied between images", chunk_type),
+            Skipped::UnsafeToCopy => warn!(
+                "Skipped {}: it is unsafe to copy into an image with other critical chunks; pass --force to copy it anyway",
+                chunk_type
+            )
+        }
+    }
+    if report.copied.is_empty() {
+        return Err(anyhow!("Nothing to copy from {}", source_path.display()));
+    }
+
+    let copied: Vec<String> = report.copied.iter().map(ChunkType::to_string).collect();
+    info!("Copied {} chunk(s): {}", copied.len(), copied.join(", "));
+    if !report.replaced.is_empty() {
+        let replaced: Vec<String> = report.replaced.iter().map(ChunkType::to_string).collect();
+        info!("Replaced the existing {}", replaced.join(", "));
+    }
+    save_png(file_path, &mut png, original_size, edit)
+}
+
+pub fn merge(MergeArgs {
+    file_paths,
+    on_conflict,
+    edit
+}: MergeArgs) -> Result<()> {
+    let (file_path, sources) = file_paths.split_first().ok_or_else(|| anyhow!("No images to merge"))?;
+    let mut png = read_png(file_path.as_path())?;
+    let original_size = png.byte_len();
+    let conflict = match on_conflict {
+        OnConflict::Fail => Conflict::Fail,
+        OnConflict::KeepBoth => Conflict::KeepBoth,
+        OnConflict::KeepFirst => Conflict::KeepFirst,
+        OnConflict::KeepLast => Conflict::KeepLast
+    };
+
+    for source_path in sources {
+        let source = read_png(source_path.as_path())?;
+        let report = pngme::merge::merge(&mut png, &source, conflict).map_err(|error| {
+            anyhow!("{}: {}. Pass --on-conflict to keep both, the first or the last", source_path.display(), error)
+        })?;
+
+        let added: Vec<String> = report.added.iter().map(ChunkType::to_string).collect();
+        let duplicates = match report.duplicates {
+            0 => String::new(),
+            duplicates => format!(" ({} already there)", duplicates)
+        };
+        match added.is_empty() {
+            true => info!("{}: nothing new to merge{}", source_path.display(), duplicates),
+            false => info!("{}: merged {} chunk(s): {}{}", source_path.display(), added.len(), added.join(", "), duplicates)
+        }
+        for chunk_type in &report.conflicts {
+            let kept = match conflict {
+                Conflict::KeepBoth | Conflict::Fail => "both",
+                Conflict::KeepFirst => "the first",
+                Conflict::KeepLast => "the last"
+            };
+            info!("{}: {} collided with an earlier image; kept {}", source_path.display(), chunk_type, kept);
+        }
+    }
+    save_png(file_path.clone(), &mut png, original_size, edit)
+}
+
+#[derive(Serialize)]
+struct StatsView {
+    files: usize,
+    bytes: u64,
+    types: Vec<TypeStatsView>,
+    ancillary_bytes: u64,
+    payload_chunks: usize,
+    payload_bytes: u64,
+    trailing_bytes: u64,
+    largest: Vec<LargestChunkView>
+}
+
+#[derive(Serialize)]
+struct TypeStatsView {
+    chunk_type: String,
+    count: usize,
+    bytes: u64
+}
+
+#[derive(Serialize)]
+struct LargestChunkView {
+    file: String,
+    index: usize,
+    chunk_type: String,
+    length: u32
+}
+
+pub fn stats(StatsArgs {
+    file_path,
+    top
+}: StatsArgs, recursive: bool, format: Format) -> Result<()> {
+    // A directory can only mean the images in it
+    let file_paths = expand_paths(file_path.as_path(), recursive || file_path.is_dir())?;
+
+    let mut stats = Stats::default();
+    let mut largest: Vec<LargestChunkView> = Vec::new();
+    let mut failed = 0;
+    let mut progress = Progress::new("Reading", Unit::Files, file_paths.len() as u64);
+    for file_path in file_paths {
+        let png = match read_png(file_path.as_path()) {
+            Ok(png) => png,
+            Err(error) => {
+                progress.clear();
+                error!("{}: {}", file_path.display(), error);
+                failed += 1;
+                progress.inc(1);
+                continue;
+            }
+        };
+        stats.add(&Stats::of(&png));
+        largest.extend(png.chunks().iter().enumerate().map(|(index, chunk)| LargestChunkView {
+            file: file_path.display().to_string(),
+            index,
+            chunk_type: chunk.chunk_type().to_string(),
+            length: chunk.length()
+        }));
+        // Only the top few are kept, so a large directory doesn't pile up every chunk
+        largest.sort_by_key(|chunk| std::cmp::Reverse(chunk.length));
+        largest.truncate(top);
+        progress.inc(1);
+    }
+    progress.clear();
+
+    let view = StatsView {
+        files: stats.images,
+        bytes: stats.bytes,
+        types: stats.types
+            .iter()
+            .map(|(chunk_type, &TypeStats { count, bytes })| TypeStatsView { chunk_type: chunk_type.clone(), count, bytes })
+            .collect(),
+        ancillary_bytes: stats.ancillary_bytes,
+        payload_chunks: stats.payload.count,
+        payload_bytes: stats.payload.bytes,
+        trailing_bytes: stats.trailing_bytes,
+        largest
+    };
+    match format {
+        Format::Text => print_stats(&view),
+        Format::Json => print_json(&view)?
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} file(s) could not be read", failed));
+    }
+    Ok(())
+}
+
+fn print_stats(view: &StatsView) {
+    // Shares of the total size, which is never 0 for a file that was read
+    let share = |bytes: u64| bytes as f64 * 100.0 / view.bytes.max(1) as f64;
+
+    println!("{} file(s), {} bytes", view.files, view.bytes);
+    anstream::println!("\n{}{:<4}  {:>8}  {:>12}{:#}", style::HEADER, "TYPE", "COUNT", "BYTES", style::HEADER);
+    for chunk_type in &view.types {
+        let chunk_style = style::chunk_type(&chunk_type.chunk_type);
+        anstream::println!(
+            "{}{:<4}{:#}  {:>8}  {:>12}",
+            chunk_style,
+            chunk_type.chunk_type,
+            chunk_style,
+            chunk_type.count,
+            chunk_type.bytes
+        );
+    }
+
+    println!();
+    println!("Ancillary chunks: {} bytes ({:.1}%)", view.ancillary_bytes, share(view.ancillary_bytes));
+    println!("Payload chunks:   {} bytes in {} chunk(s) ({:.1}%)", view.payload_bytes, view.payload_chunks, share(view.payload_bytes));
+    println!("After IEND:       {} bytes ({:.1}%)", view.trailing_bytes, share(view.trailing_bytes));
+
+    if view.largest.is_empty() {
+        return;
+    }
+    anstream::println!("\n{}{:>12}  {:<4}  FILE{:#}", style::HEADER, "LENGTH", "TYPE", style::HEADER);
+    for chunk in &view.largest {
+        let chunk_style = style::chunk_type(&chunk.chunk_type);
+        anstream::println!(
+            "{:>12}  {}{:<4}{:#}  {} (index {})",
+            chunk.length,
+            chunk_style,
+            chunk.chunk_type,
+            chunk_style,
+            chunk.file,
+            chunk.index
+        );
+    }
+}
+
+#[derive(Serialize)]
+struct ScannedView {
+    file: String,
+    chunks: Vec<TypeStatsView>,
+    trailing_bytes: usize
+}
+
+pub fn scan(ScanArgs {
+    dir,
+    all_files
+}: ScanArgs, format: Format) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!("{} is not a directory", dir.display()));
+    }
+    let file_paths = match all_files {
+        true => walk_files(dir.as_path())?,
+        false => expand_paths(dir.as_path(), true)?
+    };
+
+    let mut found = Vec::new();
+    let mut scanned = 0;
+    let mut failed = 0;
+    let mut progress = Progress::new("Scanning", Unit::Files, file_paths.len() as u64);
+    for file_path in file_paths {
+        progress.inc(1);
+        // Only the chunk headers are read; the data is skipped over
+        let png = match LazyPng::open(file_path.as_path()) {
+            Ok(png) => png,
+            // Files picked by their contents that turn out not to be PNGs are
+            // what --all-files expects to find
+            Err(PngError::InvalidSignature | PngError::TruncatedSignature { .. }) if all_files => continue,
+            Err(error) => {
+                progress.clear();
+                error!("{}: {}", file_path.display(), error);
+                failed += 1;
+                continue;
+            }
+        };
+        scanned += 1;
+
+        let mut chunks: Vec<TypeStatsView> = Vec::new();
+        for header in png.chunk_headers().iter().filter(|header| !header.chunk_type().is_standard()) {
+            let chunk_type = header.chunk_type().to_string();
+            match chunks.iter_mut().find(|chunk| chunk.chunk_type == chunk_type) {
+                Some(chunk) => {
+                    chunk.count += 1;
+                    chunk.bytes += header.length() as u64;
+                },
+                None => chunks.push(TypeStatsView { chunk_type, count: 1, bytes: header.length() as u64 })
+            }
+        }
+        if chunks.is_empty() && png.trailing_len() == 0 {
+            continue;
+        }
+        let view = ScannedView { file: file_path.display().to_string(), chunks, trailing_bytes: png.trailing_len() };
+        if format == Format::Text {
+            progress.clear();
+            print_scanned(&view);
+        }
+        found.push(view);
+    }
+    progress.clear();
+
+    if format == Format::Json {
+        print_json(&found)?;
+    }
+    info!("Scanned {} PNG(s): {} with chunks outside the specification or data after IEND", scanned, found.len());
+    if failed > 0 {
+        return Err(anyhow!("{} file(s) could not be read", failed));
+    }
+    Ok(())
+}
+
+fn print_scanned(view: &ScannedView) {
+    let mut line = view.file.clone();
+    for chunk in &view.chunks {
+        let chunk_style = style::chunk_type(&chunk.chunk_type);
+        line.push_str(&format!("  {}{}{:#} ({} bytes", chunk_style, chunk.chunk_type, chunk_style, chunk.bytes));
+        if chunk.count > 1 {
+            line.push_str(&format!(" in {} chunks", chunk.count));
+        }
+        line.push(')');
+    }
+    if view.trailing_bytes > 0 {
+        line.push_str(&format!("  {}{} bytes after IEND{:#}", style::WARNING, view.trailing_bytes, style::WARNING));
+    }
+    anstream::println!("{}", line);
+}
+
+pub fn extract(ExtractArgs {
+    file_path,
+    dir,
+    force
+}: ExtractArgs) -> Result<()> {
+    let mut png = match read_lazy_carrier(file_path.as_path())? {
+        Carrier::Png(png) => png,
+        Carrier::Other(container) => return Err(anyhow!("extract only works on PNGs, not {}", container.media_type()))
+    };
+    // Standard input and data URIs have no name of their own
+    let name = match is_stdio(file_path.as_path()) || is_data_uri(file_path.as_path()) {
+        true => "image".into(),
+        false => file_path.file_stem().map_or("image".into(), |stem| stem.to_string_lossy())
+    };
+
+    let payloads: Vec<usize> = png
+        .chunk_headers()
+        .iter()
+        .enumerate()
+        .filter(|(_, header)| pngme::merge::is_payload(header.chunk_type()))
+        .map(|(index, _)| index)
+        .collect();
+    if payloads.is_empty() {
+        return Err(anyhow!("{} has no payload chunks", file_path.display()));
+    }
+
+    let outputs: Vec<(usize, PathBuf)> = payloads
+        .into_iter()
+        .map(|index| (index, dir.join(format!("{}.{}.{}.bin", name, png.chunk_headers()[index].chunk_type(), index))))
+        .collect();
+    // Checked up front, so a clash doesn't leave half the chunks written
+    if let Some((_, output)) = outputs.iter().find(|(_, output)| output.exists() && !force) {
+        return Err(anyhow!("{} already exists; pass --force to replace it", output.display()));
+    }
+
+    fs::create_dir_all(&dir)?;
+    for (index, output) in outputs {
+        write_output(&output, &png.read_data(index)?)?;
+        info!("Wrote {} to {}", png.chunk_headers()[index].chunk_type(), output.display());
+    }
+    Ok(())
+}
+
+pub fn compose(ComposeArgs {
+    spec: spec_path,
+    mut edit
+}: ComposeArgs) -> Result<()> {
+    let spec = crate::compose::load(spec_path.as_path())?;
+    let mut png = read_png(spec.base.as_path())?;
+    let original_size = png.byte_len();
+
+    for (number, chunk_spec) in spec.chunks.iter().enumerate() {
+        compose_chunk(chunk_spec)
+            .and_then(|chunk| {
+                if edit.dry_run {
+                    println!("Would add chunk {}", describe_chunk(&chunk));
+                }
+                place_chunk(&mut png, chunk, &chunk_spec.position)
+            })
+            .map_err(|error| anyhow!("{}: chunk {} ({}): {}", spec_path.display(), number + 1, chunk_spec.chunk_type, error))?;
+    }
+    warn_ordering(spec.base.as_path(), &png);
+
+    // The command line wins over the spec
+    if edit.output.is_none() && edit.output_dir.is_none() {
+        edit.output = spec.output;
+    }
+    if !edit.dry_run {
+        info!("Added {} chunk(s) from {}", spec.chunks.len(), spec_path.display());
+    }
+    save_png(spec.base, &mut png, original_size, edit)
+}
+
+fn compose_chunk(chunk_spec: &ChunkSpec) -> Result<Chunk> {
+    let chunk_type = ChunkType::parse(chunk_spec.chunk_type.as_str(), chunk_spec.allow_reserved)?;
+    check_writable(&chunk_type, chunk_spec.force || chunk_spec.raw && !chunk_type.is_critical())?;
+    let data = chunk_spec.data()?;
+    let data = match chunk_spec.raw {
+        true => data,
+        false => payload::wrap(Options::default(), &data)
+    };
+    Ok(Chunk::try_new(chunk_type, data)?)
+}
+
+fn place_chunk(png: &mut Png, chunk: Chunk, position: &Position) -> Result<()> {
+    let before = |chunk_type: &str| png.chunks().iter().position(|chunk| chunk.chunk_type().to_string() == chunk_type);
+    match position {
+        Position::Auto => png.append_chunk(chunk),
+        Position::Start => png.insert_chunk_at(before(ihdr::IHDR).map_or(0, |ihdr| ihdr + 1), chunk)?,
+        Position::End => png.insert_before_iend(chunk),
+        Position::Before(chunk_type) => match before(chunk_type) {
+            Some(index) => png.insert_chunk_at(index, chunk)?,
+            None => return Err(PngError::ChunkNotFound { chunk_type: chunk_type.clone() }.into())
+        },
+        Position::After(chunk_type) => png.insert_after_type(chunk_type, chunk)?,
+        Position::Index(index) => png.insert_chunk_at(*index, chunk)?
+    }
+    Ok(())
+}
+
+pub fn watch(WatchArgs {
+    dir,
+    chunk,
+    encode: message,
+    allow_reserved,
+    force
+}: WatchArgs, recursive: bool, format: Format) -> Result<()> {
+    let chunk_type = ChunkType::parse(chunk.as_str(), allow_reserved || message.is_none())?;
+    if message.is_some() {
+        check_writable(&chunk_type, force)?;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    watcher.watch(dir.as_path(), mode)?;
+    info!("Watching {} for PNG files, press Ctrl-C to stop", dir.display());
+
+    // Writers usually emit several events per file, so only react once per modification time.
+    let mut seen: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    for event in receiver {
+        let event = event?;
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            continue;
+        }
+
+        for file_path in event.paths {
+            let metadata = match fs::metadata(&file_path) {
+                Ok(metadata) if is_png_path(&file_path) && metadata.len() > 0 => metadata,
+                _ => continue
+            };
+            let modified = match metadata.modified() {
+                Ok(modified) => modified,
+                Err(error) => {
+                    warn!("{}: {}", file_path.display(), error);
+                    continue;
+                }
+            };
+            if seen.get(&file_path) == Some(&modified) {
+                continue;
+            }
+
+            let result = match &message {
+                Some(message) => watch_encode(&file_path, &chunk_type, message),
+                None => {
+                    if format == Format::Text {
+                        println!("==> {} <==", file_path.display());
+                    }
+                    decode(DecodeArgs {
+                        file_path: file_path.clone(),
+                        chunk_type: Some(chunk.clone()),
+                        chunk_key: None,
+                        scatter_key: None,
+                        output: None,
+                        clipboard: false,
+                        qr: None,
+                        all: false,
+                        index: None,
+                        base64: false,
+                        passphrase: None,
+                        key_file: None,
+                        private_key: None,
+                        age_identity: None,
+                        hmac_key: None
+                    }, format)
+                }
+            };
+            if let Err(error) = result {
+                error!("{}: {}", file_path.display(), error);
+            }
+
+            if let Ok(modified) = fs::metadata(&file_path).and_then(|metadata| metadata.modified()) {
+                seen.insert(file_path, modified);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn watch_encode(file_path: &Path, chunk_type: &ChunkType, message: &str) -> Result<()> {
+    let mut png = read_png(file_path)?;
+    if png.chunk_by_type(&chunk_type.to_string()).is_some() {
+        return Ok(());
+    }
+
+    png.append_chunk(Chunk::try_new(chunk_type.clone(), payload::wrap(Options::default(), message.as_bytes()))?);
+    write_png(file_path, &png)?;
+    println!("{}: added chunk {}", file_path.display(), chunk_type);
+    Ok(())
+}
+
+pub fn check_writable(chunk_type: &ChunkType, force: bool) -> Result<()> {
+    if force {
+        return Ok(());
+    }
+
+    if chunk_type.is_critical() {
+        return Err(anyhow!(
+            "Refusing to write chunk type {}: critical chunks (uppercase first letter) must be understood by every decoder, \
+             so adding one will likely break the image. Use a type like {} instead, or pass --force",
+            chunk_type,
+            suggested_type(chunk_type)
+        ));
+    }
+    if chunk_type.is_standard() {
+        return Err(anyhow!(
+            "Refusing to write chunk type {}: it is a registered PNG chunk and decoders will try to interpret the message. \
+             Use a private type like {} instead, or pass --force",
+            chunk_type,
+            suggested_type(chunk_type)
+        ));
+    }
+    Ok(())
+}
+
+// Lowercase first two letters make a chunk ancillary and private, which decoders skip
+fn suggested_type(chunk_type: &ChunkType) -> String {
+    let mut bytes = chunk_type.bytes();
+    bytes[0] = bytes[0].to_ascii_lowercase();
+    bytes[1] = bytes[1].to_ascii_lowercase();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Keeps each listed chunk on one line.
+fn escape_controls(text: &str) -> String {
+    text.chars()
+        .map(|c| match c.is_control() {
+            true => c.escape_debug().to_string(),
+            false => c.to_string()
+        })
+        .collect()
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// Finds the manifest and parts in `chunks` the way `decode` does and
+    /// joins them.
+    fn join(chunks: Vec<Chunk>) -> Result<Vec<u8>> {
+        let mut png = LazyPng::from_reader(Cursor::new(Png::from_chunks(chunks).as_bytes()))?;
+        let mut manifest = None;
+        let mut parts = Vec::new();
+        for position in 0..png.chunk_headers().len() {
+            let prefix = png.read_data_prefix(position, PART_HEADER_LEN)?;
+            match prefix.starts_with(&PART_MAGIC) {
+                true => parts.push((position, prefix)),
+                false => manifest = Some(png.read_data(position)?)
+            }
+        }
+        join_parts(&mut png, &manifest.unwrap(), &parts)
+    }
+
+    fn copy(chunk: &Chunk) -> Chunk {
+        Chunk::new(chunk.chunk_type().clone(), chunk.data().to_vec())
+    }
+
+    fn split(data: &[u8]) -> Vec<Chunk> {
+        split_payload(&ChunkType::from_str("ruSt").unwrap(), data, PART_HEADER_LEN + 10).unwrap()
+    }
+
+    #[test]
+    fn test_split_round_trip() {
+        let data: Vec<u8> = (0..95u8).collect();
+        let chunks = split(&data);
+
+        // A manifest, then 10 parts of up to 10 bytes
+        assert_eq!(chunks.len(), 11);
+        assert_eq!(chunks[0].data().len(), MANIFEST_LEN);
+        assert!(chunks[1..].iter().all(|chunk| chunk.data().starts_with(&PART_MAGIC)));
+        assert_eq!(join(chunks).unwrap(), data);
+    }
+
+    #[test]
+    fn test_split_reordered_parts() {
+        let data: Vec<u8> = (0..95u8).collect();
+        let mut chunks = split(&data);
+        chunks[1..].reverse();
+        assert_eq!(join(chunks).unwrap(), data);
+    }
+
+    #[test]
+    fn test_split_missing_part() {
+        let mut chunks = split(&[7; 95]);
+        chunks.remove(5);
+        assert!(join(chunks).unwrap_err().to_string().contains("Found 9 chunks for the 10 parts"));
+    }
+
+    #[test]
+    fn test_split_duplicate_part() {
+        let mut chunks = split(&[7; 95]);
+        chunks.push(copy(&chunks[3]));
+        assert!(join(chunks).unwrap_err().to_string().contains("Found 11 chunks for the 10 parts"));
+
+        // A duplicate standing in for a missing part doesn't count either
+        let mut chunks = split(&[7; 95]);
+        chunks[4] = copy(&chunks[3]);
+        assert!(join(chunks).unwrap_err().to_string().contains("Found 10 chunks for the 10 parts"));
+    }
+
+    #[test]
+    fn test_split_digest_mismatch() {
+        let mut chunks = split(&[7; 95]);
+        let mut data = chunks[2].data().to_vec();
+        data[PART_HEADER_LEN] ^= 1;
+        chunks[2] = Chunk::new(chunks[2].chunk_type().clone(), data);
+        assert!(join(chunks).unwrap_err().to_string().contains("don't match its manifest"));
+    }
+}
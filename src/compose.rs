@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use serde::Deserialize;
+use anyhow::Result;
+
+/// A PNG to build: the base image and the chunks to add to it, in order.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spec {
+    pub base: PathBuf,
+    pub output: Option<PathBuf>,
+    #[serde(default, rename = "chunk", alias = "chunks")]
+    pub chunks: Vec<ChunkSpec>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChunkSpec {
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub text: Option<String>,
+    pub file: Option<PathBuf>,
+    pub base64: Option<String>,
+    #[serde(default)]
+    pub position: Position,
+    /// Write the data as it is, without the pngme header `encode` adds. Raw
+    /// chunks may have registered ancillary types, such as tEXt.
+    #[serde(default)]
+    pub raw: bool,
+    /// Allow a type whose reserved bit is set, as `encode --allow-reserved`
+    /// does.
+    #[serde(default)]
+    pub allow_reserved: bool,
+    /// Allow critical and registered chunk types, as `encode --force` does.
+    #[serde(default)]
+    pub force: bool
+}
+
+/// Where a chunk goes, written in the spec as `"auto"`, `"start"`, `"end"`,
+/// `"before:TYPE"`, `"after:TYPE"` or an index.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawPosition")]
+pub enum Position {
+    /// The first place the ordering rules allow, as `encode` picks.
+    #[default]
+    Auto,
+    /// Right after IHDR.
+    Start,
+    /// Right before IEND.
+    End,
+    /// Before the first chunk of a type.
+    Before(String),
+    /// After the last chunk of a type.
+    After(String),
+    Index(usize)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPosition {
+    Index(usize),
+    Named(String)
+}
+
+impl TryFrom<RawPosition> for Position {
+    type Error = String;
+
+    fn try_from(position: RawPosition) -> Result<Position, String> {
+        let name = match position {
+            RawPosition::Index(index) => return Ok(Position::Index(index)),
+            RawPosition::Named(name) => name
+        };
+        match name.split_once(':') {
+            Some(("before", chunk_type)) => Ok(Position::Before(chunk_type.to_string())),
+            Some(("after", chunk_type)) => Ok(Position::After(chunk_type.to_string())),
+            _ => match name.as_str() {
+                "auto" => Ok(Position::Auto),
+                "start" => Ok(Position::Start),
+                "end" => Ok(Position::End),
+                _ => Err(format!(
+                    "unknown position {:?}; expected auto, start, end, before:TYPE, after:TYPE or an index",
+                    name
+                ))
+            }
+        }
+    }
+}
+
+impl ChunkSpec {
+    /// The chunk's data, from whichever of `text`, `file` and `base64` is set.
+    pub fn data(&self) -> Result<Vec<u8>> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        match (&self.text, &self.file, &self.base64) {
+            (Some(text), None, None) => Ok(text.as_bytes().to_vec()),
+            (None, Some(file), None) => fs::read(file).map_err(|error| anyhow!("Could not read {}: {}", file.display(), error)),
+            (None, None, Some(encoded)) => STANDARD
+                .decode(encoded.trim())
+                .map_err(|error| anyhow!("base64 is not valid: {}", error)),
+            _ => Err(anyhow!("needs exactly one of text, file and base64"))
+        }
+    }
+}
+
+/// Reads a spec from a JSON file, or TOML for any other extension. Paths in
+/// it are relative to the spec's own directory, so a build script works
+/// from anywhere.
+pub fn load(spec_path: &Path) -> Result<Spec> {
+    let contents = fs::read_to_string(spec_path)
+        .map_err(|error| anyhow!("Could not read {}: {}", spec_path.display(), error))?;
+    let is_json = spec_path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let mut spec: Spec = match is_json {
+        true => serde_json::from_str(&contents).map_err(|error| anyhow!("{}: {}", spec_path.display(), error))?,
+        false => toml::from_str(&contents).map_err(|error| anyhow!("{}: {}", spec_path.display(), error))?
+    };
+
+    let dir = spec_path.parent().unwrap_or(Path::new(""));
+    spec.base = dir.join(&spec.base);
+    spec.output = spec.output.map(|output| dir.join(output));
+    for chunk in &mut spec.chunks {
+        chunk.file = chunk.file.take().map(|file| dir.join(file));
+    }
+    Ok(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("pngme-compose-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn position(value: &str) -> Result<Position, String> {
+        let chunk: ChunkSpec = toml::from_str(&format!("type = \"ruSt\"\ntext = \"\"\nposition = {}", value))
+            .map_err(|error| error.message().to_string())?;
+        Ok(chunk.position)
+    }
+
+    #[test]
+    fn test_load_toml() {
+        let dir = testing_dir("toml");
+        fs::write(dir.join("build.toml"), r#"
+            base = "images/base.png"
+            output = "out.png"
+
+            [[chunk]]
+            type = "ruSt"
+            text = "hello"
+
+            [[chunk]]
+            type = "teSt"
+            file = "data/payload.bin"
+            position = "end"
+            allow_reserved = true
+        "#).unwrap();
+
+        let spec = load(&dir.join("build.toml")).unwrap();
+        assert_eq!(spec.base, dir.join("images/base.png"));
+        assert_eq!(spec.output, Some(dir.join("out.png")));
+        assert_eq!(spec.chunks.len(), 2);
+        assert_eq!(spec.chunks[0].data().unwrap(), b"hello");
+        assert_eq!(spec.chunks[0].position, Position::Auto);
+        assert!(!spec.chunks[0].allow_reserved && !spec.chunks[0].force);
+        assert_eq!(spec.chunks[1].file, Some(dir.join("data/payload.bin")));
+        assert_eq!(spec.chunks[1].position, Position::End);
+        assert!(spec.chunks[1].allow_reserved && !spec.chunks[1].force);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_json() {
+        let dir = testing_dir("json");
+        fs::write(dir.join("build.JSON"), r#"{
+            "base": "/images/base.png",
+            "chunks": [{ "type": "ruSt", "base64": "aGk=", "position": 3, "raw": true }]
+        }"#).unwrap();
+
+        let spec = load(&dir.join("build.JSON")).unwrap();
+        // Absolute paths are kept as they are
+        assert_eq!(spec.base, PathBuf::from("/images/base.png"));
+        assert_eq!(spec.output, None);
+        assert_eq!(spec.chunks[0].data().unwrap(), b"hi");
+        assert_eq!(spec.chunks[0].position, Position::Index(3));
+        assert!(spec.chunks[0].raw);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let dir = testing_dir("errors");
+        assert!(load(&dir.join("missing.toml")).err().unwrap().to_string().starts_with("Could not read"));
+
+        fs::write(dir.join("unknown.toml"), "base = \"base.png\"\nextra = 1").unwrap();
+        assert!(load(&dir.join("unknown.toml")).err().unwrap().to_string().contains("unknown field `extra`"));
+
+        fs::write(dir.join("both.toml"), "base = \"base.png\"\n[[chunk]]\ntype = \"ruSt\"\ntext = \"a\"\nbase64 = \"YQ==\"").unwrap();
+        let spec = load(&dir.join("both.toml")).unwrap();
+        assert!(spec.chunks[0].data().unwrap_err().to_string().contains("exactly one of"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_positions() {
+        assert_eq!(position("\"auto\""), Ok(Position::Auto));
+        assert_eq!(position("\"start\""), Ok(Position::Start));
+        assert_eq!(position("\"end\""), Ok(Position::End));
+        assert_eq!(position("\"before:IDAT\""), Ok(Position::Before("IDAT".to_string())));
+        assert_eq!(position("\"after:tEXt\""), Ok(Position::After("tEXt".to_string())));
+        assert_eq!(position("0"), Ok(Position::Index(0)));
+        assert!(position("\"middle\"").unwrap_err().contains("unknown position \"middle\""));
+        assert!(position("\"inside:IDAT\"").unwrap_err().contains("unknown position"));
+        assert!(position("-1").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+use crate::error::CompressError;
+
+/// Marks a chunk payload as compressed by pngme.
+pub const MAGIC: [u8;4] = *b"PMEz";
+/// Magic, method byte and the decompressed length as a big-endian u32.
+const HEADER_LEN: usize = 9;
+/// Deflate can't expand data more than about 1032 times, so no more than
+/// that is reserved up front, whatever length the header claims.
+const MAX_RATIO: usize = 1032;
+
+/// How a payload was compressed, stored after the magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// zlib-wrapped deflate.
+    Deflate,
+    /// A zstd frame, slower to write but smaller for large binary payloads.
+    Zstd
+}
+
+impl Method {
+    /// The compression levels the method accepts.
+    pub fn levels(self) -> RangeInclusive<i32> {
+        match self {
+            Method::Deflate => 0..=9,
+            Method::Zstd => zstd::compression_level_range()
+        }
+    }
+
+    fn default_level(self) -> i32 {
+        match self {
+            Method::Deflate => 6,
+            Method::Zstd => zstd::DEFAULT_COMPRESSION_LEVEL
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Method::Deflate => 1,
+            Method::Zstd => 2
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Method, CompressError> {
+        match byte {
+            1 => Ok(Method::Deflate),
+            2 => Ok(Method::Zstd),
+            method => Err(CompressError::UnknownMethod { method })
+        }
+    }
+}
+
+/// Whether a chunk payload starts with the pngme compression header.
+pub fn is_compressed(payload: &[u8]) -> bool {
+    payload.starts_with(&MAGIC)
+}
+
+/// Compresses `data` behind a header recording the method and the original
+/// length, which `decompress` checks the output against.
+pub fn compress(data: &[u8], method: Method) -> Result<Vec<u8>, CompressError> {
+    compress_with_level(data, method, method.default_level())
+}
+
+/// Like `compress`, at a level from `Method::levels`. The level isn't needed
+/// to decompress, so it isn't stored.
+pub fn compress_with_level(data: &[u8], method: Method, level: i32) -> Result<Vec<u8>, CompressError> {
+    if !method.levels().contains(&level) {
+        return Err(CompressError::InvalidLevel { method, level });
+    }
+    let len = u32::try_from(data.len()).map_err(|_| CompressError::TooLarge { len: data.len() })?;
+    let mut payload = [&MAGIC[..], &[method.to_byte()], &len.to_be_bytes()].concat();
+
+    match method {
+        Method::Deflate => {
+            let mut encoder = ZlibEncoder::new(payload, Compression::new(level as u32));
+            encoder.write_all(data)?;
+            payload = encoder.finish()?;
+        },
+        Method::Zstd => zstd::stream::copy_encode(data, &mut payload, level)?
+    }
+    Ok(payload)
+}
+
+/// Reverses `compress`. Output beyond the recorded length is an error rather
+/// than being read to the end, so a crafted payload can't expand without bound.
+pub fn decompress(payload: &[u8]) -> Result<Vec<u8>, CompressError> {
+    decompress_with_limit(payload, usize::MAX)
+}
+
+/// Like `decompress`, but refuses payloads that say they hold more than
+/// `max_len` bytes before inflating any of them.
+pub fn decompress_with_limit(payload: &[u8], max_len: usize) -> Result<Vec<u8>, CompressError> {
+    if !is_compressed(payload) {
+        return Err(CompressError::NotCompressed);
+    }
+    if payload.len() < HEADER_LEN {
+        return Err(CompressError::Truncated { len: payload.len() });
+    }
+    let method = Method::from_byte(payload[MAGIC.len()])?;
+    let expected = u32::from_be_bytes(payload[MAGIC.len() + 1..HEADER_LEN].try_into().unwrap()) as usize;
+    if expected > max_len {
+        return Err(CompressError::LimitExceeded { len: expected, limit: max_len });
+    }
+
+    let compressed = &payload[HEADER_LEN..];
+    // The length comes from the payload, so it only bounds the output
+    let mut data = Vec::with_capacity(expected.min(compressed.len().saturating_mul(MAX_RATIO)));
+    let limit = expected as u64 + 1;
+    match method {
+        Method::Deflate => ZlibDecoder::new(compressed).take(limit).read_to_end(&mut data)?,
+        Method::Zstd => zstd::Decoder::new(compressed)?.take(limit).read_to_end(&mut data)?
+    };
+    if data.len() != expected {
+        return Err(CompressError::LengthMismatch { expected, actual: data.len() });
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deflate_round_trip() {
+        let data = "This is where your secret message will be! ".repeat(100);
+        let payload = compress(data.as_bytes(), Method::Deflate).unwrap();
+
+        assert!(is_compressed(&payload));
+        assert!(payload.len() < data.len() / 10);
+        assert_eq!(decompress(&payload).unwrap(), data.as_bytes());
+    }
+
+    #[test]
+    fn test_zstd_round_trip() {
+        let data: Vec<u8> = (0..10_000u32).flat_map(|n| (n % 251).to_be_bytes()).collect();
+        for level in [1, 19] {
+            let payload = compress_with_level(&data, Method::Zstd, level).unwrap();
+            assert_eq!(payload[4], 2);
+            assert!(payload.len() < data.len() / 10);
+            assert_eq!(decompress(&payload).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_invalid_level() {
+        assert!(matches!(
+            compress_with_level(b"hello", Method::Deflate, 10),
+            Err(CompressError::InvalidLevel { method: Method::Deflate, level: 10 })
+        ));
+        assert!(compress_with_level(b"hello", Method::Zstd, 23).is_err());
+    }
+
+    #[test]
+    fn test_decompress_checks_length() {
+        let mut payload = compress(b"hello hello hello", Method::Deflate).unwrap();
+        payload[8] -= 1;
+        assert!(matches!(
+            decompress(&payload),
+            Err(CompressError::LengthMismatch { expected: 16, actual: 17 })
+        ));
+    }
+
+    #[test]
+    fn test_decompress_huge_claimed_length() {
+        let mut payload = compress(b"hello", Method::Zstd).unwrap();
+        payload[5..9].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(
+            decompress(&payload),
+            Err(CompressError::LengthMismatch { expected, actual: 5 }) if expected == u32::MAX as usize
+        ));
+    }
+
+    #[test]
+    fn test_decompress_with_limit() {
+        let payload = compress(&[0; 1000], Method::Deflate).unwrap();
+        assert_eq!(decompress_with_limit(&payload, 1000).unwrap().len(), 1000);
+        assert!(matches!(
+            decompress_with_limit(&payload, 999),
+            Err(CompressError::LimitExceeded { len: 1000, limit: 999 })
+        ));
+    }
+
+    #[test]
+    fn test_decompress_rejects_bad_headers() {
+        assert!(matches!(decompress(b"hello"), Err(CompressError::NotCompressed)));
+        assert!(matches!(decompress(b"PMEz\x01\x00"), Err(CompressError::Truncated { len: 6 })));
+        assert!(matches!(
+            decompress(b"PMEz\x07\x00\x00\x00\x00"),
+            Err(CompressError::UnknownMethod { method: 7 })
+        ));
+    }
+}
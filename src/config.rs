@@ -0,0 +1,405 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use anyhow::anyhow;
+use clap::builder::Resettable;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command, CommandFactory, FromArgMatches};
+use toml::{Table, Value};
+use crate::args::{Cli, Commands};
+use anyhow::Result;
+
+/// Keys that apply to every subcommand taking them, when set at the top of
+/// the file.
+const SHARED_KEYS: [&str;2] = ["chunk_type", "output_dir"];
+
+/// Environment variables, and the option each sets a default for in every
+/// subcommand that has it. They win over the config file, so scripts can
+/// pass secrets without putting them on the command line.
+const ENVIRONMENT: [(&str, &str);6] = [
+    ("PNGME_PASSPHRASE", "passphrase"),
+    ("PNGME_HIDDEN_PASSPHRASE", "hidden_passphrase"),
+    ("PNGME_KEY_FILE", "key_file"),
+    ("PNGME_HMAC_KEY", "hmac_key"),
+    ("PNGME_CHUNK_TYPE", "chunk_type"),
+    ("PNGME_FORMAT", "format")
+];
+
+/// Options whose defaults are left out of --help, as they are secrets.
+const SECRETS: [&str;4] = ["passphrase", "hidden_passphrase", "hmac_key", "chunk_key"];
+
+/// Secrets that stand in for each other. Giving one on the command line
+/// drops the others' defaults, which would otherwise be used in its place.
+const ALTERNATIVES: [&str;2] = ["passphrase", "key_file"];
+
+/// Parses the command line, with defaults from the config file and the
+/// environment. The file holds top-level options such as `format`, the
+/// shared `chunk_type` and `output_dir`, a table per subcommand, e.g.
+/// `[encode]`, whose keys are its long option names, and `[profile.NAME]`
+/// tables of encode options that `encode --profile NAME` uses over
+/// everything else. Flags on the command line override them all.
+pub fn parse() -> Result<Cli> {
+    let args: Vec<OsString> = env::args_os().collect();
+    let table = read_config(&args)?.unwrap_or_default();
+    let environment: Vec<(&str, String)> = ENVIRONMENT
+        .iter()
+        .filter_map(|(variable, key)| env::var(variable).ok().filter(|value| !value.is_empty()).map(|value| (*key, value)))
+        .collect();
+    parse_with(args, table, &environment).map_err(|error| match error.downcast::<clap::Error>() {
+        Ok(error) => error.exit(),
+        Err(error) => error
+    })
+}
+
+/// `parse` with the config file and the environment already read.
+fn parse_with(args: Vec<OsString>, mut table: Table, environment: &[(&str, String)]) -> Result<Cli> {
+    let mut command = Cli::command();
+
+    let profiles = table.remove("profile");
+    let profile = match option_arg(&args, "--profile") {
+        Some(name) => Some(find_profile(profiles, &name)?),
+        None => None
+    };
+    command = apply(command, &table, true)?;
+
+    for (key, value) in environment {
+        command = apply_shared(command, key, std::slice::from_ref(value));
+    }
+
+    if let Some(profile) = &profile {
+        let mut encode = Table::new();
+        encode.insert("encode".to_string(), Value::Table(profile.clone()));
+        command = apply(command, &encode, true)?;
+    }
+
+    // encode's chunk type is positional before the message, so it's filled
+    // in after parsing instead. Each source overrides those before it.
+    let from_environment = environment.iter().find(|(key, _)| *key == "chunk_type").map(|(_, value)| value.clone());
+    let default_chunk_type = [
+        chunk_type_in(Some(&table))?,
+        chunk_type_in(table.get("encode").and_then(Value::as_table))?,
+        from_environment,
+        chunk_type_in(profile.as_ref())?
+    ].into_iter().flatten().last();
+    if default_chunk_type.is_some() {
+        command = command.mut_subcommand("encode", |encode| {
+            encode
+                .mut_arg("chunk_type", |arg| arg.required_unless_present(Resettable::Reset))
+                .mut_arg("message", |arg| arg.required_unless_present(Resettable::Reset))
+        });
+    }
+
+    let matches = command.try_get_matches_from(args)?;
+    let mut cli = Cli::from_arg_matches(&matches)?;
+    match (&mut cli.command, matches.subcommand()) {
+        (Commands::Encode(args), Some((_, matches))) => {
+            args.default_chunk_type = default_chunk_type;
+            if dropped(matches, "passphrase") {
+                args.passphrase = None;
+            }
+            if dropped(matches, "key_file") {
+                args.key_file = None;
+            }
+            // Only a hidden message needs the hidden passphrase
+            if defaulted(matches, "hidden_passphrase") && args.hidden_message.is_none() && args.hidden_message_file.is_none() {
+                args.hidden_passphrase = None;
+            }
+        },
+        (Commands::Decode(args), Some((_, matches))) => {
+            if dropped(matches, "passphrase") {
+                args.passphrase = None;
+            }
+            if dropped(matches, "key_file") {
+                args.key_file = None;
+            }
+        },
+        _ => ()
+    }
+    Ok(cli)
+}
+
+fn defaulted(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::DefaultValue)
+}
+
+/// Whether `id` only has a default, and another of the `ALTERNATIVES` was
+/// given on the command line in its place.
+fn dropped(matches: &ArgMatches, id: &str) -> bool {
+    defaulted(matches, id) && ALTERNATIVES
+        .iter()
+        .any(|other| *other != id && matches.value_source(other) == Some(ValueSource::CommandLine))
+}
+
+fn chunk_type_in(table: Option<&Table>) -> Result<Option<String>> {
+    Ok(match table.and_then(|table| table.get("chunk_type")) {
+        Some(value) => values("chunk_type", value)?.into_iter().next(),
+        None => None
+    })
+}
+
+/// The config file named by --config or PNGME_CONFIG, which must exist, or
+/// else the one in the user's config directory, if there is one.
+fn read_config(args: &[OsString]) -> Result<Option<Table>> {
+    let named = option_arg(args, "--config").or_else(|| env::var("PNGME_CONFIG").ok().filter(|path| !path.is_empty()));
+    let (path, explicit) = match named {
+        Some(path) => (PathBuf::from(path), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(None)
+        }
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound && !explicit => return Ok(None),
+        Err(error) => return Err(anyhow!("Could not read {}: {}", path.display(), error))
+    };
+    text.parse::<Table>().map(Some).map_err(|error| anyhow!("{} is not valid TOML: {}", path.display(), error))
+}
+
+/// --config and --profile have to be found before clap parses anything, as
+/// the defaults they pick change how the rest is parsed.
+fn option_arg(args: &[OsString], name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    let mut args = args.iter().skip(1).take_while(|arg| *arg != "--");
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next().map(|value| value.to_string_lossy().into_owned());
+        }
+        if let Some(value) = arg.to_str().and_then(|arg| arg.strip_prefix(prefix.as_str())) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// The `[profile.NAME]` table of encode options.
+fn find_profile(profiles: Option<Value>, name: &str) -> Result<Table> {
+    match profiles {
+        Some(Value::Table(mut profiles)) => match profiles.remove(name) {
+            Some(Value::Table(profile)) => Ok(profile),
+            _ => Err(anyhow!("The config file has no [profile.{}] table", name))
+        },
+        _ => Err(anyhow!("The config file has no [profile.{}] table", name))
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("pngme").join("config.toml"))
+}
+
+/// Sets the values in `table` as defaults of `command`'s options, and those
+/// in nested tables on its subcommands.
+fn apply(mut command: Command, table: &Table, top_level: bool) -> Result<Command> {
+    for (key, value) in table {
+        if let Value::Table(table) = value {
+            let subcommand = command
+                .find_subcommand(key)
+                .cloned()
+                .ok_or_else(|| anyhow!("{} has no {} command", command.get_name(), key))?;
+            let subcommand = apply(subcommand, table, false)?;
+            command = command.mut_subcommand(key, |_| subcommand);
+            continue;
+        }
+
+        let values = values(key, value)?;
+        if top_level && SHARED_KEYS.contains(&key.as_str()) {
+            command = apply_shared(command, key, &values);
+            continue;
+        }
+        let id = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key || arg.get_long() == Some(&key.replace('_', "-")))
+            .map(|arg| arg.get_id().clone())
+            .ok_or_else(|| anyhow!("{} has no --{} option", command.get_name(), key.replace('_', "-")))?;
+        // encode's chunk type is left to `parse`
+        if command.get_name() == "encode" && id == "chunk_type" {
+            continue;
+        }
+        command = command.mut_arg(id, |arg| set_default(arg, &values));
+    }
+    Ok(command)
+}
+
+/// Sets `values` as the default of every `key` option, in every subcommand.
+/// encode's chunk type is left to `parse`.
+fn apply_shared(mut command: Command, key: &str, values: &[String]) -> Command {
+    if command.get_arguments().any(|arg| arg.get_id() == key) {
+        command = command.mut_arg(key, |arg| set_default(arg, values));
+    }
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .filter(|name| key != "chunk_type" || name != "encode")
+        .collect();
+    for name in names {
+        command = command.mut_subcommand(name, |subcommand| apply_shared(subcommand, key, values));
+    }
+    command
+}
+
+fn set_default(arg: Arg, values: &[String]) -> Arg {
+    let secret = SECRETS.contains(&arg.get_id().as_str());
+    let arg = arg.default_values(values.to_vec()).required(false).required_unless_present(Resettable::Reset);
+    match secret {
+        true => arg.hide_default_value(true),
+        false => arg
+    }
+}
+
+/// The values of an option as they would be written on the command line.
+fn values(key: &str, value: &Value) -> Result<Vec<String>> {
+    match value {
+        Value::Array(values) => values.iter().map(|value| plain_value(key, value)).collect(),
+        value => Ok(vec![plain_value(key, value)?])
+    }
+}
+
+fn plain_value(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Array(_) | Value::Table(_) => Err(anyhow!("{} can only hold plain values or a list of them", key)),
+        value => Ok(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::{Compression, DecodeArgs, EncodeArgs, Format};
+
+    const CONFIG: &str = r#"
+        chunk_type = "toPs"
+        format = "json"
+
+        [encode]
+        hmac_key = "config"
+        decoys = 1
+
+        [profile.work]
+        hmac_key = "profile"
+        chunk_type = "prOf"
+        decoys = 2
+    "#;
+
+    fn cli(args: &str, config: &str, environment: &[(&'static str, &str)]) -> Cli {
+        let args = std::iter::once("pngme").chain(args.split_whitespace()).map(OsString::from).collect();
+        let environment: Vec<(&str, String)> = environment.iter().map(|(key, value)| (*key, value.to_string())).collect();
+        parse_with(args, config.parse().unwrap(), &environment).unwrap()
+    }
+
+    fn encode(args: &str, config: &str, environment: &[(&'static str, &str)]) -> Box<EncodeArgs> {
+        match cli(&format!("encode image.png {}", args), config, environment).command {
+            Commands::Encode(args) => args,
+            _ => unreachable!()
+        }
+    }
+
+    fn decode(args: &str, environment: &[(&'static str, &str)]) -> DecodeArgs {
+        match cli(&format!("decode image.png ruSt {}", args), "", environment).command {
+            Commands::Decode(args) => args,
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_config_file() {
+        let args = encode("message", CONFIG, &[]);
+        assert_eq!(args.hmac_key.as_deref(), Some("config"));
+        assert_eq!(args.decoys, 1);
+        assert_eq!(args.default_chunk_type.as_deref(), Some("toPs"));
+        assert!(cli("list image.png", CONFIG, &[]).format == Format::Json);
+        assert!(cli("list image.png", "", &[]).format == Format::Text);
+    }
+
+    #[test]
+    fn test_flags_take_no_value() {
+        let args = match cli("encode --compress --envelope image.png ruSt message", "", &[]).command {
+            Commands::Encode(args) => args,
+            _ => unreachable!()
+        };
+        assert_eq!(args.file_path, PathBuf::from("image.png"));
+        assert!(args.compress && args.compression_method == Compression::Deflate);
+        assert!(args.envelope && args.content_type.is_none());
+
+        let config = "[encode]\ncompress = true\ncompression_method = \"zstd\"";
+        assert!(encode("ruSt message", config, &[]).compression_method == Compression::Zstd);
+        let args = encode("ruSt message --compression-method deflate", config, &[]);
+        assert!(args.compress && args.compression_method == Compression::Deflate);
+    }
+
+    #[test]
+    fn test_subcommand_table_over_top_level() {
+        let config = "chunk_type = \"toPs\"\n[encode]\nchunk_type = \"enCo\"";
+        assert_eq!(encode("message", config, &[]).default_chunk_type.as_deref(), Some("enCo"));
+    }
+
+    #[test]
+    fn test_environment_over_config_file() {
+        let environment = [("hmac_key", "environment"), ("chunk_type", "enVi"), ("format", "text")];
+        let args = encode("message", CONFIG, &environment);
+        assert_eq!(args.hmac_key.as_deref(), Some("environment"));
+        assert_eq!(args.default_chunk_type.as_deref(), Some("enVi"));
+        assert!(cli("list image.png", CONFIG, &environment).format == Format::Text);
+    }
+
+    #[test]
+    fn test_profile_over_environment_and_config_file() {
+        let args = encode("--profile work message", CONFIG, &[("hmac_key", "environment"), ("chunk_type", "enVi")]);
+        assert_eq!(args.hmac_key.as_deref(), Some("profile"));
+        assert_eq!(args.decoys, 2);
+        assert_eq!(args.default_chunk_type.as_deref(), Some("prOf"));
+    }
+
+    #[test]
+    fn test_flags_over_everything() {
+        let environment = [("hmac_key", "environment"), ("format", "json")];
+        let args = encode("--profile work flAg message --hmac-key flag --decoys 3", CONFIG, &environment);
+        assert_eq!(args.hmac_key.as_deref(), Some("flag"));
+        assert_eq!(args.decoys, 3);
+        assert_eq!(args.chunk_type.as_deref(), Some("flAg"));
+        assert_eq!(args.message.as_deref(), Some("message"));
+        assert!(cli("list image.png --format text", CONFIG, &environment).format == Format::Text);
+    }
+
+    #[test]
+    fn test_unknown_profile_and_option() {
+        let args = ["pngme", "encode", "image.png", "--profile", "home", "message"].map(OsString::from).to_vec();
+        assert!(parse_with(args.clone(), CONFIG.parse().unwrap(), &[]).is_err());
+        assert!(parse_with(args[..3].to_vec(), "[encode]\nno_such_option = 1".parse().unwrap(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_flag_drops_key_file_default() {
+        let environment = [("key_file", "key"), ("passphrase", "environment")];
+        let args = encode("ruSt message --encrypt --passphrase flag", "", &environment);
+        assert_eq!(args.passphrase.as_deref(), Some("flag"));
+        assert_eq!(args.key_file, None);
+
+        let args = encode("ruSt message --encrypt --key-file flag", "", &environment);
+        assert_eq!(args.passphrase, None);
+        assert_eq!(args.key_file, Some(PathBuf::from("flag")));
+
+        let args = decode("--passphrase flag", &environment);
+        assert_eq!(args.passphrase.as_deref(), Some("flag"));
+        assert_eq!(args.key_file, None);
+
+        // Without a flag, both defaults stay
+        let args = decode("", &environment);
+        assert_eq!(args.passphrase.as_deref(), Some("environment"));
+        assert_eq!(args.key_file, Some(PathBuf::from("key")));
+    }
+
+    #[test]
+    fn test_hidden_passphrase_default_needs_hidden_message() {
+        let environment = [("hidden_passphrase", "hidden")];
+        assert_eq!(encode("ruSt message", "", &environment).hidden_passphrase, None);
+
+        let args = encode("ruSt message --encrypt --deniable --hidden-message other", "", &environment);
+        assert_eq!(args.hidden_passphrase.as_deref(), Some("hidden"));
+    }
+}
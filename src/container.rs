@@ -0,0 +1,259 @@
+use log::debug;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::{ContainerError, PngError};
+use crate::gif::{self, Gif};
+use crate::jpeg::{self, Jpeg};
+use crate::png::Png;
+use crate::tiff::{self, Tiff};
+
+/// An image format that holds opaque metadata blocks tagged with chunk
+/// types. A PNG's blocks are its chunks; other formats keep pngme payloads
+/// in whatever blocks they set aside for applications, and only those are
+/// listed.
+pub trait Container {
+    /// The MIME type of the format, such as "image/png".
+    fn media_type(&self) -> &'static str;
+
+    /// The type and data length of each block: in file order for a PNG, and
+    /// grouped by chunk type for formats that split payloads over blocks.
+    fn blocks(&self) -> Result<Vec<(ChunkType, usize)>, ContainerError>;
+
+    /// The data of each block of type `chunk_type`, in order.
+    fn block_data(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, ContainerError>;
+
+    /// Adds a block of type `chunk_type` holding `data`.
+    fn add_block(&mut self, chunk_type: &ChunkType, data: &[u8]) -> Result<(), ContainerError>;
+
+    /// Removes the `index`th block of type `chunk_type` and returns its data.
+    fn remove_block(&mut self, chunk_type: &str, index: usize) -> Result<Vec<u8>, ContainerError>;
+
+    /// Puts blocks added from now on in comments rather than application
+    /// blocks, in formats that have both.
+    fn prefer_comments(&mut self) {}
+
+    /// The whole file.
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+/// The MIME type of the format whose signature `bytes` start with, if pngme
+/// knows it.
+pub fn media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&Png::STANDARD_HEADER) {
+        Some("image/png")
+    } else if jpeg::is_jpeg(bytes) {
+        Some("image/jpeg")
+    } else if gif::is_gif(bytes) {
+        Some("image/gif")
+    } else if tiff::is_tiff(bytes) {
+        Some("image/tiff")
+    } else {
+        None
+    }
+}
+
+/// Parses `bytes` as whichever format their signature belongs to.
+pub fn open(bytes: &[u8]) -> Result<Box<dyn Container>, ContainerError> {
+    let media_type = media_type(bytes);
+    debug!("Reading {} bytes as {}", bytes.len(), media_type.unwrap_or("an unknown format"));
+    match media_type {
+        Some("image/png") => Ok(Box::new(Png::try_from(bytes)?)),
+        Some("image/jpeg") => Ok(Box::new(Jpeg::try_from(bytes)?)),
+        Some("image/gif") => Ok(Box::new(Gif::try_from(bytes)?)),
+        Some("image/tiff") => Ok(Box::new(Tiff::try_from(bytes)?)),
+        _ => Err(ContainerError::UnknownFormat)
+    }
+}
+
+fn not_found(chunk_type: &str, index: usize) -> ContainerError {
+    ContainerError::BlockNotFound { chunk_type: chunk_type.to_string(), index }
+}
+
+/// The blocks of a format that stores whole payloads, grouped by type.
+fn payload_blocks<E>(
+    chunk_types: Vec<ChunkType>,
+    payloads: impl Fn(&str) -> Result<Vec<Vec<u8>>, E>
+) -> Result<Vec<(ChunkType, usize)>, ContainerError>
+where
+    ContainerError: From<E>
+{
+    let mut blocks = Vec::new();
+    for chunk_type in chunk_types {
+        for data in payloads(&chunk_type.to_string())? {
+            blocks.push((chunk_type.clone(), data.len()));
+        }
+    }
+    Ok(blocks)
+}
+
+impl Container for Png {
+    fn media_type(&self) -> &'static str {
+        "image/png"
+    }
+
+    fn blocks(&self) -> Result<Vec<(ChunkType, usize)>, ContainerError> {
+        Ok(self.chunks().iter().map(|chunk| (chunk.chunk_type().clone(), chunk.data().len())).collect())
+    }
+
+    fn block_data(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, ContainerError> {
+        Ok(self.chunks_by_type(chunk_type).into_iter().map(|chunk| chunk.data().to_vec()).collect())
+    }
+
+    fn add_block(&mut self, chunk_type: &ChunkType, data: &[u8]) -> Result<(), ContainerError> {
+        self.append_chunk(Chunk::try_new(chunk_type.clone(), data.to_vec())?);
+        Ok(())
+    }
+
+    fn remove_block(&mut self, chunk_type: &str, index: usize) -> Result<Vec<u8>, ContainerError> {
+        match self.remove_nth_chunk(chunk_type, index) {
+            Ok(chunk) => Ok(chunk.data().to_vec()),
+            Err(PngError::ChunkNotFound { .. } | PngError::IndexOutOfRange { .. }) => Err(not_found(chunk_type, index)),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes()
+    }
+}
+
+impl Container for Jpeg {
+    fn media_type(&self) -> &'static str {
+        "image/jpeg"
+    }
+
+    fn blocks(&self) -> Result<Vec<(ChunkType, usize)>, ContainerError> {
+        payload_blocks(self.payload_types(), |chunk_type| self.payloads(chunk_type))
+    }
+
+    fn block_data(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, ContainerError> {
+        Ok(self.payloads(chunk_type)?)
+    }
+
+    fn add_block(&mut self, chunk_type: &ChunkType, data: &[u8]) -> Result<(), ContainerError> {
+        Ok(self.add_payload(chunk_type, data, self.payload_marker())?)
+    }
+
+    fn remove_block(&mut self, chunk_type: &str, index: usize) -> Result<Vec<u8>, ContainerError> {
+        self.remove_payload(chunk_type, index)?.ok_or_else(|| not_found(chunk_type, index))
+    }
+
+    fn prefer_comments(&mut self) {
+        self.set_payload_marker(jpeg::COM);
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes()
+    }
+}
+
+impl Container for Gif {
+    fn media_type(&self) -> &'static str {
+        "image/gif"
+    }
+
+    fn blocks(&self) -> Result<Vec<(ChunkType, usize)>, ContainerError> {
+        payload_blocks(self.payload_types()?, |chunk_type| self.payloads(chunk_type))
+    }
+
+    fn block_data(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, ContainerError> {
+        Ok(self.payloads(chunk_type)?)
+    }
+
+    fn add_block(&mut self, chunk_type: &ChunkType, data: &[u8]) -> Result<(), ContainerError> {
+        self.add_payload(chunk_type, data);
+        Ok(())
+    }
+
+    fn remove_block(&mut self, chunk_type: &str, index: usize) -> Result<Vec<u8>, ContainerError> {
+        self.remove_payload(chunk_type, index)?.ok_or_else(|| not_found(chunk_type, index))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes()
+    }
+}
+
+impl Container for Tiff {
+    fn media_type(&self) -> &'static str {
+        "image/tiff"
+    }
+
+    fn blocks(&self) -> Result<Vec<(ChunkType, usize)>, ContainerError> {
+        payload_blocks(self.payload_types()?, |chunk_type| self.payloads(chunk_type))
+    }
+
+    fn block_data(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, ContainerError> {
+        Ok(self.payloads(chunk_type)?)
+    }
+
+    fn add_block(&mut self, chunk_type: &ChunkType, data: &[u8]) -> Result<(), ContainerError> {
+        Ok(self.add_payload(chunk_type, data)?)
+    }
+
+    fn remove_block(&mut self, chunk_type: &str, index: usize) -> Result<Vec<u8>, ContainerError> {
+        self.remove_payload(chunk_type, index)?.ok_or_else(|| not_found(chunk_type, index))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn png() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![])
+        ]).as_bytes()
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, 0xDB, 0x00, 0x04, 0x01, 0x02];
+        bytes.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x03, 0x00, 0x12, 0xFF, 0xD9]);
+        bytes
+    }
+
+    fn gif() -> Vec<u8> {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend_from_slice(&[1, 0, 1, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x44, 0x01, 0, 0x3B]);
+        bytes
+    }
+
+    fn tiff() -> Vec<u8> {
+        let mut bytes = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        bytes.extend_from_slice(&[0x00, 0x01, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_container_formats() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        for (bytes, media_type) in [(png(), "image/png"), (jpeg(), "image/jpeg"), (gif(), "image/gif"), (tiff(), "image/tiff")] {
+            let mut container = open(&bytes).unwrap();
+            assert_eq!(super::media_type(&bytes), Some(media_type));
+            assert_eq!(container.media_type(), media_type);
+            container.add_block(&chunk_type, b"first").unwrap();
+            container.add_block(&chunk_type, b"second").unwrap();
+
+            let mut reopened = open(&container.to_bytes()).unwrap();
+            assert!(reopened.blocks().unwrap().contains(&(chunk_type.clone(), 6)));
+            assert_eq!(reopened.block_data("ruSt").unwrap(), vec![b"first".to_vec(), b"second".to_vec()]);
+            assert_eq!(reopened.remove_block("ruSt", 0).unwrap(), b"first");
+            assert_eq!(reopened.block_data("ruSt").unwrap(), vec![b"second".to_vec()]);
+            assert!(matches!(reopened.remove_block("ruSt", 1), Err(ContainerError::BlockNotFound { index: 1, .. })));
+        }
+    }
+
+    #[test]
+    fn test_container_unknown_format() {
+        assert_eq!(media_type(b"BM\x00\x00"), None);
+        assert!(matches!(open(b"BM\x00\x00"), Err(ContainerError::UnknownFormat)));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    check,
+    png::Png
+};
+
+/// Why a chunk asked for was left behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skipped {
+    /// Critical chunks make up the image itself, so another image's would
+    /// replace or corrupt it.
+    Critical,
+    /// The chunk depends on the critical chunks of its image, which the
+    /// destination doesn't share (fourth letter uppercase).
+    UnsafeToCopy
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CopyReport {
+    pub copied: Vec<ChunkType>,
+    /// Chunks of the destination that may appear only once and were
+    /// replaced by the copied one.
+    pub replaced: Vec<ChunkType>,
+    pub skipped: Vec<(ChunkType, Skipped)>
+}
+
+/// Copies the chunks of `source` whose types are in `types` into
+/// `destination`, in their order, each at the first place the ordering
+/// rules allow. Chunks that are unsafe to copy are only copied if both
+/// images have the same critical chunks, or with `force`.
+pub fn copy_chunks(source: &Png, destination: &mut Png, types: &[ChunkType], force: bool) -> CopyReport {
+    let critical = |png: &Png| png
+        .chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type().is_critical())
+        .map(|chunk| (chunk.chunk_type().bytes(), chunk.data().to_vec()))
+        .collect::<Vec<_>>();
+    let same_image = critical(source) == critical(destination);
+
+    let mut report = CopyReport::default();
+    for chunk in source.chunks().iter().filter(|chunk| types.contains(chunk.chunk_type())) {
+        let chunk_type = chunk.chunk_type();
+        if chunk_type.is_critical() {
+            report.skipped.push((chunk_type.clone(), Skipped::Critical));
+            continue;
+        }
+        if !chunk_type.is_safe_to_copy() && !same_image && !force {
+            report.skipped.push((chunk_type.clone(), Skipped::UnsafeToCopy));
+            continue;
+        }
+
+        if check::SINGLE.contains(&&chunk_type.bytes()) {
+            report.replaced.extend(destination
+                .remove_chunks_where(|existing| existing.chunk_type() == chunk_type)
+                .into_iter()
+                .map(|existing| existing.chunk_type().clone()));
+        }
+        destination.append_chunk(Chunk::new(chunk_type.clone(), chunk.data().to_vec()));
+        report.copied.push(chunk_type.clone());
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn types(png: &Png) -> Vec<String> {
+        png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect()
+    }
+
+    fn testing_png(pixels: &[u8], ancillary: Vec<Chunk>) -> Png {
+        let mut chunks = vec![chunk("IHDR", b"header")];
+        chunks.extend(ancillary);
+        chunks.push(chunk("IDAT", pixels));
+        chunks.push(chunk("IEND", b""));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_copy_chunks() {
+        let source = testing_png(b"source", vec![
+            chunk("pHYs", b"new density"),
+            chunk("tEXt", b"Title\0one"),
+            chunk("tEXt", b"Title\0two"),
+            chunk("ruSt", b"secret")
+        ]);
+        let mut destination = testing_png(b"destination", vec![chunk("pHYs", b"old density")]);
+        let wanted = [ChunkType::from_str("pHYs").unwrap(), ChunkType::from_str("tEXt").unwrap()];
+
+        let report = copy_chunks(&source, &mut destination, &wanted, false);
+        assert_eq!(report.copied.len(), 3);
+        assert_eq!(report.replaced, vec![ChunkType::from_str("pHYs").unwrap()]);
+        assert!(report.skipped.is_empty());
+        assert_eq!(types(&destination), vec!["IHDR", "pHYs", "IDAT", "tEXt", "tEXt", "IEND"]);
+        assert_eq!(destination.chunks()[1].data(), b"new density");
+        assert_eq!(destination.chunks()[3].data(), b"Title\0one");
+    }
+
+    #[test]
+    fn test_copy_chunks_skips_critical_and_unsafe() {
+        let source = testing_png(b"source", vec![chunk("ruST", b"depends on pixels")]);
+        let wanted = [ChunkType::from_str("IDAT").unwrap(), ChunkType::from_str("ruST").unwrap()];
+
+        let mut destination = testing_png(b"destination", Vec::new());
+        let report = copy_chunks(&source, &mut destination, &wanted, false);
+        assert!(report.copied.is_empty());
+        assert_eq!(report.skipped, vec![
+            (ChunkType::from_str("ruST").unwrap(), Skipped::UnsafeToCopy),
+            (ChunkType::from_str("IDAT").unwrap(), Skipped::Critical)
+        ]);
+        assert_eq!(types(&destination), vec!["IHDR", "IDAT", "IEND"]);
+
+        let report = copy_chunks(&source, &mut destination, &wanted, true);
+        assert_eq!(report.copied, vec![ChunkType::from_str("ruST").unwrap()]);
+    }
+
+    #[test]
+    fn test_copy_chunks_unsafe_between_copies_of_one_image() {
+        let source = testing_png(b"pixels", vec![chunk("ruST", b"depends on pixels")]);
+        let mut destination = testing_png(b"pixels", Vec::new());
+
+        let report = copy_chunks(&source, &mut destination, &[ChunkType::from_str("ruST").unwrap()], false);
+        assert_eq!(report.copied.len(), 1);
+        assert_eq!(types(&destination), vec!["IHDR", "IDAT", "ruST", "IEND"]);
+    }
+}
@@ -0,0 +1,757 @@
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng, Payload, rand_core::RngCore}
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::{chunk_type::ChunkType, error::CryptoError};
+
+/// Marks a chunk payload as encrypted by pngme.
+pub const MAGIC: [u8;4] = *b"PMEn";
+/// Format byte following the magic, saying where the key came from. The
+/// legacy passphrase format used fixed Argon2id parameters, the current one
+/// stores them in the header.
+pub const LEGACY_PASSPHRASE_FORMAT: u8 = 1;
+pub const KEY_FORMAT: u8 = 2;
+pub const PASSPHRASE_FORMAT: u8 = 3;
+pub const RECIPIENT_FORMAT: u8 = 4;
+pub const DENIABLE_FORMAT: u8 = 5;
+pub const KEY_LEN: usize = 32;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const PARAMS_LEN: usize = 9;
+const GCM_TAG_LEN: usize = 16;
+/// The smallest padded message in a deniable slot, length prefix included.
+const MIN_SLOT_LEN: usize = 256;
+
+/// Argon2id cost parameters, stored in each passphrase-encrypted payload so
+/// it can be decrypted regardless of what the defaults are at the time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u8
+}
+
+impl KdfParams {
+    /// The parameters behind `LEGACY_PASSPHRASE_FORMAT` payloads.
+    pub const LEGACY: KdfParams = KdfParams {
+        memory_kib: 19 * 1024,
+        iterations: 2,
+        parallelism: 1
+    };
+    /// Refuse to derive keys with more memory than this, so a crafted header
+    /// can't make decode allocate without bound.
+    pub const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+    /// Nor with more passes than this, so it can't make decode run for hours.
+    pub const MAX_ITERATIONS: u32 = 16;
+
+    fn to_bytes(self) -> [u8;PARAMS_LEN] {
+        let mut bytes = [0; PARAMS_LEN];
+        bytes[0..4].copy_from_slice(&self.memory_kib.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.iterations.to_be_bytes());
+        bytes[8] = self.parallelism;
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> KdfParams {
+        KdfParams {
+            memory_kib: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+            iterations: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            parallelism: bytes[8]
+        }
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> KdfParams {
+        KdfParams {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1
+        }
+    }
+}
+
+/// Marks a chunk payload as carrying an HMAC-SHA256 tag.
+pub const HMAC_MAGIC: [u8;4] = *b"PMEh";
+const TAG_LEN: usize = 32;
+
+/// Every binary age file starts with this version line.
+const AGE_MAGIC: &[u8] = b"age-encryption.org/v1\n";
+
+/// Whether a chunk payload starts with the pngme encryption header.
+pub fn is_encrypted(payload: &[u8]) -> bool {
+    payload.starts_with(&MAGIC)
+}
+
+/// Whether an encrypted payload was written with `encrypt_with_key` rather
+/// than a passphrase.
+pub fn is_key_encrypted(payload: &[u8]) -> bool {
+    is_encrypted(payload) && payload.get(MAGIC.len()) == Some(&KEY_FORMAT)
+}
+
+/// Whether an encrypted payload was written with `encrypt_to_recipient`.
+pub fn is_recipient_encrypted(payload: &[u8]) -> bool {
+    is_encrypted(payload) && payload.get(MAGIC.len()) == Some(&RECIPIENT_FORMAT)
+}
+
+/// Encrypts `plaintext` with AES-256-GCM under a key derived from
+/// `passphrase` with the default Argon2id parameters.
+pub fn encrypt(plaintext: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    encrypt_with_params(plaintext, passphrase, KdfParams::default())
+}
+
+/// Like `encrypt`, with explicit Argon2id parameters. The payload is laid out
+/// as magic, format, parameters, salt and nonce followed by the ciphertext,
+/// and the header is authenticated along with it.
+pub fn encrypt_with_params(plaintext: &[u8], passphrase: &[u8], params: KdfParams) -> Result<Vec<u8>, CryptoError> {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt, params)?;
+
+    let header = [&MAGIC[..], &[PASSPHRASE_FORMAT], &params.to_bytes(), &salt].concat();
+    seal(header, &key, plaintext)
+}
+
+/// The Argon2id parameters a passphrase-encrypted payload was written with.
+pub fn kdf_params(payload: &[u8]) -> Result<KdfParams, CryptoError> {
+    match format(payload)? {
+        LEGACY_PASSPHRASE_FORMAT => Ok(KdfParams::LEGACY),
+        PASSPHRASE_FORMAT | DENIABLE_FORMAT if payload.len() >= MAGIC.len() + 1 + PARAMS_LEN => {
+            Ok(KdfParams::from_bytes(&payload[MAGIC.len() + 1..]))
+        },
+        PASSPHRASE_FORMAT | DENIABLE_FORMAT => Err(CryptoError::Truncated { len: payload.len() }),
+        KEY_FORMAT => Err(CryptoError::KeyRequired),
+        RECIPIENT_FORMAT => Err(CryptoError::PrivateKeyRequired),
+        version => Err(CryptoError::UnsupportedVersion { version })
+    }
+}
+
+/// Encrypts `plaintext` with AES-256-GCM under a raw key, skipping the key
+/// derivation. The payload has the same layout as `encrypt` minus the salt.
+pub fn encrypt_with_key(plaintext: &[u8], key: &[u8;KEY_LEN]) -> Result<Vec<u8>, CryptoError> {
+    let header = [&MAGIC[..], &[KEY_FORMAT]].concat();
+    seal(header, key, plaintext)
+}
+
+pub fn decrypt(payload: &[u8], passphrase: &[u8]) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    let params = kdf_params(payload)?;
+    let salt_offset = match payload[MAGIC.len()] {
+        LEGACY_PASSPHRASE_FORMAT => MAGIC.len() + 1,
+        DENIABLE_FORMAT => return decrypt_deniable(payload, passphrase, params),
+        _ => MAGIC.len() + 1 + PARAMS_LEN
+    };
+    let header_len = salt_offset + SALT_LEN + NONCE_LEN;
+    if payload.len() < header_len {
+        return Err(CryptoError::Truncated { len: payload.len() });
+    }
+
+    let salt = &payload[salt_offset..salt_offset + SALT_LEN];
+    open(payload, header_len, &*derive_key(passphrase, salt, params)?)
+}
+
+pub fn decrypt_with_key(payload: &[u8], key: &[u8;KEY_LEN]) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    match format(payload)? {
+        KEY_FORMAT => open(payload, MAGIC.len() + 1 + NONCE_LEN, key),
+        LEGACY_PASSPHRASE_FORMAT | PASSPHRASE_FORMAT | DENIABLE_FORMAT => Err(CryptoError::PassphraseRequired),
+        RECIPIENT_FORMAT => Err(CryptoError::PrivateKeyRequired),
+        version => Err(CryptoError::UnsupportedVersion { version })
+    }
+}
+
+/// Encrypts `plaintext` under `passphrase` into one of two slots, and the
+/// `hidden` message under its own passphrase into the other. Each passphrase
+/// opens only its own slot with `decrypt`. Without a hidden message the other
+/// slot is filled with random bytes, which look the same as ciphertext, so
+/// nothing in the payload shows whether a second message exists. The slots
+/// are in random order and both messages are padded to the same power of two,
+/// which gives away no more than the size of the longer one.
+pub fn encrypt_deniable(
+    plaintext: &[u8],
+    passphrase: &[u8],
+    hidden: Option<(&[u8], &[u8])>,
+    params: KdfParams
+) -> Result<Vec<u8>, CryptoError> {
+    if hidden.is_some_and(|(_, hidden_passphrase)| hidden_passphrase == passphrase) {
+        return Err(CryptoError::SamePassphrase);
+    }
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let header = [&MAGIC[..], &[DENIABLE_FORMAT], &params.to_bytes(), &salt].concat();
+
+    let longest = hidden.map_or(0, |(hidden, _)| hidden.len()).max(plaintext.len());
+    let padded_len = (4 + longest).next_power_of_two().max(MIN_SLOT_LEN);
+    let mut slots = vec![seal_slot(&header, &*derive_key(passphrase, &salt, params)?, plaintext, padded_len)?];
+    slots.push(match hidden {
+        Some((hidden, hidden_passphrase)) => {
+            seal_slot(&header, &*derive_key(hidden_passphrase, &salt, params)?, hidden, padded_len)?
+        },
+        None => {
+            let mut filler = vec![0; NONCE_LEN + padded_len + GCM_TAG_LEN];
+            OsRng.fill_bytes(&mut filler);
+            filler
+        }
+    });
+    if OsRng.next_u32() & 1 == 1 {
+        slots.swap(0, 1);
+    }
+    Ok([header, slots.concat()].concat())
+}
+
+/// Opens whichever slot of an `encrypt_deniable` payload `passphrase` belongs
+/// to. Failing both is the same error as a wrong passphrase anywhere else.
+fn decrypt_deniable(payload: &[u8], passphrase: &[u8], params: KdfParams) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    let header_len = MAGIC.len() + 1 + PARAMS_LEN + SALT_LEN;
+    let slots_len = payload.len().saturating_sub(header_len);
+    if slots_len < 2 * (NONCE_LEN + 4 + GCM_TAG_LEN) || !slots_len.is_multiple_of(2) {
+        return Err(CryptoError::Truncated { len: payload.len() });
+    }
+
+    let (header, slots) = payload.split_at(header_len);
+    let key = derive_key(passphrase, &header[header_len - SALT_LEN..], params)?;
+    let padded = slots
+        .chunks_exact(slots_len / 2)
+        .find_map(|slot| open_slot(header, slot, &key))
+        .ok_or(CryptoError::DecryptionFailed)?;
+    let len = u32::from_be_bytes(padded[..4].try_into().unwrap()) as usize;
+    match padded.get(4..4 + len) {
+        Some(plaintext) => Ok(Zeroizing::new(plaintext.to_vec())),
+        None => Err(CryptoError::DecryptionFailed)
+    }
+}
+
+/// A nonce followed by the length-prefixed message, zero-padded to
+/// `padded_len` and encrypted with the payload header as associated data.
+fn seal_slot(header: &[u8], key: &[u8;KEY_LEN], message: &[u8], padded_len: usize) -> Result<Vec<u8>, CryptoError> {
+    let mut padded = Zeroizing::new(vec![0; padded_len]);
+    padded[..4].copy_from_slice(&(message.len() as u32).to_be_bytes());
+    padded[4..4 + message.len()].copy_from_slice(message);
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .encrypt(&nonce, Payload { msg: &padded[..], aad: header })
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    Ok([&nonce[..], &ciphertext].concat())
+}
+
+fn open_slot(header: &[u8], slot: &[u8], key: &[u8;KEY_LEN]) -> Option<Zeroizing<Vec<u8>>> {
+    let (nonce, ciphertext) = slot.split_at(NONCE_LEN);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .ok()
+        .map(Zeroizing::new)
+}
+
+/// How many leading bytes of a payload from `encrypt` or its siblings are
+/// the same for every message encrypted the same way: the magic, the format
+/// and any Argon2id parameters. What follows looks random. 0 for anything
+/// else.
+pub fn fixed_header_len(payload: &[u8]) -> usize {
+    match format(payload) {
+        Ok(PASSPHRASE_FORMAT | DENIABLE_FORMAT) => (MAGIC.len() + 1 + PARAMS_LEN).min(payload.len()),
+        Ok(_) => MAGIC.len() + 1,
+        Err(_) => 0
+    }
+}
+
+/// Generates an X25519 key pair for `encrypt_to_recipient`, returned as
+/// (private key, public key).
+pub fn generate_keypair() -> (Zeroizing<[u8;KEY_LEN]>, [u8;KEY_LEN]) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    (Zeroizing::new(secret.to_bytes()), public.to_bytes())
+}
+
+/// Parses a base64 X25519 public key, as written by `generate_keypair` users
+/// such as `pngme keygen`.
+pub fn parse_public_key(recipient: &str) -> Result<[u8;KEY_LEN], CryptoError> {
+    let invalid_recipient = |reason: &str| CryptoError::InvalidRecipient {
+        recipient: recipient.to_string(),
+        reason: reason.to_string()
+    };
+    STANDARD
+        .decode(recipient.trim())
+        .map_err(|_| invalid_recipient("not valid base64"))?
+        .try_into()
+        .map_err(|_| invalid_recipient("an X25519 public key is 32 bytes"))
+}
+
+/// Encrypts `plaintext` so only the holder of the private key matching
+/// `public_key` can read it. A fresh ephemeral key is agreed with the
+/// recipient's key, and the payload carries its public half after the format
+/// byte in place of a salt.
+pub fn encrypt_to_recipient(plaintext: &[u8], public_key: &[u8;KEY_LEN]) -> Result<Vec<u8>, CryptoError> {
+    let recipient = PublicKey::from(*public_key);
+    let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient);
+
+    let key = recipient_key(shared.as_bytes(), ephemeral_public.as_bytes(), recipient.as_bytes())?;
+    let header = [&MAGIC[..], &[RECIPIENT_FORMAT], ephemeral_public.as_bytes()].concat();
+    seal(header, &key, plaintext)
+}
+
+pub fn decrypt_with_private_key(payload: &[u8], private_key: &[u8;KEY_LEN]) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    match format(payload)? {
+        RECIPIENT_FORMAT => {
+            let header_len = MAGIC.len() + 1 + KEY_LEN + NONCE_LEN;
+            if payload.len() < header_len {
+                return Err(CryptoError::Truncated { len: payload.len() });
+            }
+            let ephemeral_public: [u8;KEY_LEN] = payload[MAGIC.len() + 1..MAGIC.len() + 1 + KEY_LEN].try_into().unwrap();
+
+            let secret = StaticSecret::from(*private_key);
+            let shared = secret.diffie_hellman(&PublicKey::from(ephemeral_public));
+            let key = recipient_key(shared.as_bytes(), &ephemeral_public, PublicKey::from(&secret).as_bytes())?;
+            open(payload, header_len, &key)
+        },
+        LEGACY_PASSPHRASE_FORMAT | PASSPHRASE_FORMAT | DENIABLE_FORMAT => Err(CryptoError::PassphraseRequired),
+        KEY_FORMAT => Err(CryptoError::KeyRequired),
+        version => Err(CryptoError::UnsupportedVersion { version })
+    }
+}
+
+/// Turns an X25519 shared secret into an AES key, binding both public keys in
+/// so the key is specific to this exchange.
+fn recipient_key(shared: &[u8;KEY_LEN], ephemeral: &[u8;KEY_LEN], recipient: &[u8;KEY_LEN]) -> Result<Zeroizing<[u8;KEY_LEN]>, CryptoError> {
+    // A peer key of low order yields an all-zero secret, which would make the key predictable
+    if shared.iter().all(|&byte| byte == 0) {
+        return Err(CryptoError::InvalidRecipient {
+            recipient: STANDARD.encode(recipient),
+            reason: "the key agreement produced no shared secret".to_string()
+        });
+    }
+
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    Hkdf::<Sha256>::new(Some(&[&ephemeral[..], &recipient[..]].concat()), shared)
+        .expand(b"pngme x25519", key.as_mut())
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    Ok(key)
+}
+
+/// Reads a 32-byte key from a file holding either the raw bytes or their
+/// base64 encoding, e.g. from `openssl rand -base64 32`.
+pub fn read_key_file(path: &Path) -> Result<Zeroizing<[u8;KEY_LEN]>, CryptoError> {
+    let invalid_key = |reason: String| CryptoError::InvalidKeyFile {
+        path: path.display().to_string(),
+        reason
+    };
+    let contents = Zeroizing::new(std::fs::read(path).map_err(|error| invalid_key(error.to_string()))?);
+
+    let decoded = match contents.len() {
+        KEY_LEN => contents,
+        _ => Zeroizing::new(STANDARD
+            .decode(contents.trim_ascii())
+            .map_err(|_| invalid_key(format!("expected {} raw bytes or their base64 encoding", KEY_LEN)))?)
+    };
+    if decoded.len() != KEY_LEN {
+        return Err(invalid_key(format!("the key is {} bytes but must be {}", decoded.len(), KEY_LEN)));
+    }
+
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    key.copy_from_slice(&decoded);
+    Ok(key)
+}
+
+fn format(payload: &[u8]) -> Result<u8, CryptoError> {
+    if !is_encrypted(payload) {
+        return Err(CryptoError::NotEncrypted);
+    }
+    payload
+        .get(MAGIC.len())
+        .copied()
+        .ok_or(CryptoError::Truncated { len: payload.len() })
+}
+
+fn seal(mut header: Vec<u8>, key: &[u8;KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    header.extend_from_slice(&nonce);
+
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .encrypt(&nonce, Payload { msg: plaintext, aad: &header })
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    header.extend_from_slice(&ciphertext);
+    Ok(header)
+}
+
+fn open(payload: &[u8], header_len: usize, key: &[u8;KEY_LEN]) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    if payload.len() < header_len {
+        return Err(CryptoError::Truncated { len: payload.len() });
+    }
+
+    let (header, ciphertext) = payload.split_at(header_len);
+    let nonce = Nonce::from_slice(&header[header_len - NONCE_LEN..]);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .decrypt(nonce, Payload { msg: ciphertext, aad: header })
+        .map(Zeroizing::new)
+        .map_err(|_| CryptoError::DecryptionFailed)
+}
+
+/// Whether a chunk payload is an age-encrypted file.
+pub fn is_age(payload: &[u8]) -> bool {
+    payload.starts_with(AGE_MAGIC)
+}
+
+/// Encrypts `plaintext` to one or more X25519 age recipients (`age1...`), so
+/// the payload can be extracted and decrypted with any age implementation.
+pub fn encrypt_to_age(plaintext: &[u8], recipients: &[String]) -> Result<Vec<u8>, CryptoError> {
+    let recipients = recipients
+        .iter()
+        .map(|recipient| age::x25519::Recipient::from_str(recipient).map_err(|reason| {
+            CryptoError::InvalidRecipient { recipient: recipient.clone(), reason: reason.to_string() }
+        }))
+        .collect::<Result<Vec<_>, CryptoError>>()?;
+
+    let encryptor = age::Encryptor::with_recipients(recipients.iter().map(|recipient| recipient as _))?;
+    let mut payload = Vec::with_capacity(plaintext.len());
+    let mut writer = encryptor.wrap_output(&mut payload).map_err(age::EncryptError::from)?;
+    writer.write_all(plaintext).map_err(age::EncryptError::from)?;
+    writer.finish().map_err(age::EncryptError::from)?;
+    Ok(payload)
+}
+
+/// Decrypts an age payload with the identities in an age identity file, such
+/// as one written by `age-keygen`.
+pub fn decrypt_age(payload: &[u8], identity_file: &Path) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    let invalid_identity = |reason: String| CryptoError::InvalidIdentity {
+        path: identity_file.display().to_string(),
+        reason
+    };
+    let identities = age::IdentityFile::from_file(identity_file.display().to_string())
+        .map_err(|error| invalid_identity(error.to_string()))?
+        .into_identities()
+        .map_err(|error| invalid_identity(error.to_string()))?;
+
+    let decryptor = age::Decryptor::new_buffered(payload)?;
+    let mut reader = decryptor.decrypt(identities.iter().map(|identity| identity.as_ref()))?;
+    let mut plaintext = Zeroizing::new(Vec::new());
+    reader.read_to_end(&mut plaintext).map_err(age::DecryptError::from)?;
+    Ok(plaintext)
+}
+
+/// Whether a chunk payload starts with an HMAC tag.
+pub fn is_authenticated(payload: &[u8]) -> bool {
+    payload.starts_with(&HMAC_MAGIC)
+}
+
+/// Prefixes `data` with an HMAC-SHA256 tag over the chunk type and data, so
+/// tampering with either can be detected by anyone holding `key`.
+pub fn authenticate(chunk_type: &ChunkType, data: &[u8], key: &[u8]) -> Vec<u8> {
+    let tag = hmac(chunk_type, data, key).finalize().into_bytes();
+
+    let mut payload = Vec::with_capacity(HMAC_MAGIC.len() + TAG_LEN + data.len());
+    payload.extend_from_slice(&HMAC_MAGIC);
+    payload.extend_from_slice(&tag);
+    payload.extend_from_slice(data);
+    payload
+}
+
+/// Checks the tag written by `authenticate` and returns the data behind it.
+pub fn verify_tag(chunk_type: &ChunkType, payload: &[u8], key: &[u8]) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    if !is_authenticated(payload) {
+        return Err(CryptoError::NotAuthenticated);
+    }
+    if payload.len() < HMAC_MAGIC.len() + TAG_LEN {
+        return Err(CryptoError::Truncated { len: payload.len() });
+    }
+
+    let (tag, data) = payload[HMAC_MAGIC.len()..].split_at(TAG_LEN);
+    hmac(chunk_type, data, key)
+        .verify_slice(tag)
+        .map_err(|_| CryptoError::TagMismatch)?;
+    Ok(Zeroizing::new(data.to_vec()))
+}
+
+fn hmac(chunk_type: &ChunkType, data: &[u8], key: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&chunk_type.bytes());
+    mac.update(data);
+    mac
+}
+
+/// Derives a private ancillary chunk type from `key`, so only those who
+/// know it can tell which chunk to decode. The first two letters are
+/// lowercase and the third uppercase, as valid private ancillary types need;
+/// the case of the fourth comes from the key too.
+pub fn derive_chunk_type(key: &[u8]) -> ChunkType {
+    let mut bytes = [0; 5];
+    Hkdf::<Sha256>::new(None, key)
+        .expand(b"pngme chunk type", &mut bytes)
+        .expect("5 bytes is a valid HKDF-SHA256 output length");
+    let letter = |byte: u8| b'a' + byte % 26;
+    let fourth = match bytes[4] & 1 {
+        0 => letter(bytes[3]),
+        _ => letter(bytes[3]).to_ascii_uppercase()
+    };
+    let value = [letter(bytes[0]), letter(bytes[1]), letter(bytes[2]).to_ascii_uppercase(), fourth];
+    ChunkType::try_from(value).expect("derived chunk types are alphabetic")
+}
+
+/// Derives the AES key from a passphrase with Argon2id. The key is wiped
+/// when dropped, like every other secret in this module.
+fn derive_key(passphrase: &[u8], salt: &[u8], params: KdfParams) -> Result<Zeroizing<[u8;KEY_LEN]>, CryptoError> {
+    if params.memory_kib > KdfParams::MAX_MEMORY_KIB {
+        return Err(CryptoError::KeyDerivation(format!(
+            "{} KiB of memory exceeds the limit of {} KiB",
+            params.memory_kib,
+            KdfParams::MAX_MEMORY_KIB
+        )));
+    }
+    if params.iterations > KdfParams::MAX_ITERATIONS {
+        return Err(CryptoError::KeyDerivation(format!(
+            "{} iterations exceed the limit of {}",
+            params.iterations,
+            KdfParams::MAX_ITERATIONS
+        )));
+    }
+    let argon2_params = Params::new(params.memory_kib, params.iterations, params.parallelism as u32, Some(KEY_LEN))
+        .map_err(|error| CryptoError::KeyDerivation(error.to_string()))?;
+
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+        .hash_password_into(passphrase, salt, key.as_mut())
+        .map_err(|error| CryptoError::KeyDerivation(error.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+
+    #[test]
+    fn test_round_trip() {
+        let payload = encrypt(b"meet me at midnight", b"hunter2").unwrap();
+
+        assert!(is_encrypted(&payload));
+        assert!(!is_key_encrypted(&payload));
+        assert_eq!(payload[4], PASSPHRASE_FORMAT);
+        assert_eq!(kdf_params(&payload).unwrap(), KdfParams::default());
+        assert_eq!(payload.len(), 4 + 1 + PARAMS_LEN + SALT_LEN + NONCE_LEN + 19 + 16);
+        assert_eq!(*decrypt(&payload, b"hunter2").unwrap(), b"meet me at midnight");
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let payload = encrypt(b"secret", b"hunter2").unwrap();
+        assert!(matches!(decrypt(&payload, b"hunter3"), Err(CryptoError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn test_tampered_header() {
+        let mut payload = encrypt(b"secret", b"hunter2").unwrap();
+        // Flipping a salt bit changes the key, and the header is authenticated anyway
+        payload[8] ^= 1;
+        assert!(matches!(decrypt(&payload, b"hunter2"), Err(CryptoError::DecryptionFailed)));
+
+        payload[4] = 9;
+        assert!(matches!(decrypt(&payload, b"hunter2"), Err(CryptoError::UnsupportedVersion { version: 9 })));
+    }
+
+    #[test]
+    fn test_not_encrypted() {
+        assert!(!is_encrypted(b"plain text"));
+        assert!(matches!(decrypt(b"plain text", b"hunter2"), Err(CryptoError::NotEncrypted)));
+        assert!(matches!(decrypt(b"PMEn\x01", b"hunter2"), Err(CryptoError::Truncated { len: 5 })));
+    }
+
+    #[test]
+    fn test_age_round_trip() {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let identity_file = std::env::temp_dir().join(format!("pngme-age-{}.txt", std::process::id()));
+        std::fs::write(&identity_file, identity.to_string().expose_secret()).unwrap();
+
+        let payload = encrypt_to_age(b"for your eyes only", &[recipient]).unwrap();
+        assert!(is_age(&payload));
+        assert!(!is_encrypted(&payload));
+        assert_eq!(*decrypt_age(&payload, &identity_file).unwrap(), b"for your eyes only");
+
+        let other = age::x25519::Identity::generate();
+        std::fs::write(&identity_file, other.to_string().expose_secret()).unwrap();
+        assert!(matches!(decrypt_age(&payload, &identity_file), Err(CryptoError::AgeDecrypt(_))));
+
+        std::fs::remove_file(identity_file).unwrap();
+    }
+
+    #[test]
+    fn test_age_invalid_recipient() {
+        assert!(matches!(
+            encrypt_to_age(b"secret", &["age1nope".to_string()]),
+            Err(CryptoError::InvalidRecipient { .. })
+        ));
+    }
+
+    #[test]
+    fn test_hmac_round_trip() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let payload = authenticate(&chunk_type, b"signed, sealed", b"shared key");
+
+        assert!(is_authenticated(&payload));
+        assert_eq!(payload.len(), 4 + 32 + 14);
+        assert_eq!(*verify_tag(&chunk_type, &payload, b"shared key").unwrap(), b"signed, sealed");
+    }
+
+    #[test]
+    fn test_hmac_detects_tampering() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut payload = authenticate(&chunk_type, b"signed, sealed", b"shared key");
+
+        assert!(matches!(verify_tag(&chunk_type, &payload, b"wrong key"), Err(CryptoError::TagMismatch)));
+        let moved = ChunkType::from_str("teSt").unwrap();
+        assert!(matches!(verify_tag(&moved, &payload, b"shared key"), Err(CryptoError::TagMismatch)));
+
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        assert!(matches!(verify_tag(&chunk_type, &payload, b"shared key"), Err(CryptoError::TagMismatch)));
+    }
+
+    #[test]
+    fn test_derive_chunk_type() {
+        let chunk_type = derive_chunk_type(b"hunter2");
+
+        assert_eq!(chunk_type, derive_chunk_type(b"hunter2"));
+        assert_ne!(chunk_type, derive_chunk_type(b"hunter3"));
+        assert!(chunk_type.is_valid());
+        assert!(!chunk_type.is_critical());
+        assert!(!chunk_type.is_public());
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        let key = [7; KEY_LEN];
+        let payload = encrypt_with_key(b"no kdf here", &key).unwrap();
+
+        assert!(is_key_encrypted(&payload));
+        assert_eq!(payload.len(), 4 + 1 + NONCE_LEN + 11 + 16);
+        assert_eq!(*decrypt_with_key(&payload, &key).unwrap(), b"no kdf here");
+        assert!(matches!(decrypt_with_key(&payload, &[8; KEY_LEN]), Err(CryptoError::DecryptionFailed)));
+        assert!(matches!(decrypt(&payload, b"hunter2"), Err(CryptoError::KeyRequired)));
+    }
+
+    #[test]
+    fn test_read_key_file() {
+        let path = std::env::temp_dir().join(format!("pngme-key-{}", std::process::id()));
+
+        std::fs::write(&path, [3; KEY_LEN]).unwrap();
+        assert_eq!(*read_key_file(&path).unwrap(), [3; KEY_LEN]);
+
+        std::fs::write(&path, format!("{}\n", STANDARD.encode([4; KEY_LEN]))).unwrap();
+        assert_eq!(*read_key_file(&path).unwrap(), [4; KEY_LEN]);
+
+        std::fs::write(&path, STANDARD.encode([4; 16])).unwrap();
+        assert!(matches!(read_key_file(&path), Err(CryptoError::InvalidKeyFile { .. })));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn cheap_params() -> KdfParams {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1
+        }
+    }
+
+    #[test]
+    fn test_params_are_stored_in_the_header() {
+        let payload = encrypt_with_params(b"tuned", b"hunter2", cheap_params()).unwrap();
+
+        assert_eq!(kdf_params(&payload).unwrap(), cheap_params());
+        assert_eq!(*decrypt(&payload, b"hunter2").unwrap(), b"tuned");
+    }
+
+    #[test]
+    fn test_legacy_format_still_decrypts() {
+        let salt = [5; SALT_LEN];
+        let key = derive_key(b"hunter2", &salt, KdfParams::LEGACY).unwrap();
+        let header = [&MAGIC[..], &[LEGACY_PASSPHRASE_FORMAT], &salt].concat();
+        let payload = seal(header, &key, b"from long ago").unwrap();
+
+        assert_eq!(kdf_params(&payload).unwrap(), KdfParams::LEGACY);
+        assert_eq!(*decrypt(&payload, b"hunter2").unwrap(), b"from long ago");
+    }
+
+    #[test]
+    fn test_unreasonable_params_are_refused() {
+        let mut payload = encrypt_with_params(b"tuned", b"hunter2", cheap_params()).unwrap();
+        payload[5..9].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(decrypt(&payload, b"hunter2"), Err(CryptoError::KeyDerivation(_))));
+        payload[5..9].copy_from_slice(&(2 * 1024 * 1024u32).to_be_bytes());
+        assert!(matches!(decrypt(&payload, b"hunter2"), Err(CryptoError::KeyDerivation(_))));
+
+        // Refused up front rather than run for ages
+        let mut payload = encrypt_with_params(b"tuned", b"hunter2", cheap_params()).unwrap();
+        payload[9..13].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(decrypt(&payload, b"hunter2"), Err(CryptoError::KeyDerivation(_))));
+
+        let params = KdfParams { parallelism: 0, ..cheap_params() };
+        assert!(matches!(encrypt_with_params(b"tuned", b"hunter2", params), Err(CryptoError::KeyDerivation(_))));
+    }
+
+    #[test]
+    fn test_deniable_round_trip() {
+        let payload = encrypt_deniable(b"grocery list", b"hunter2", Some((b"the real plans", b"swordfish")), cheap_params()).unwrap();
+
+        assert_eq!(payload[4], DENIABLE_FORMAT);
+        assert_eq!(kdf_params(&payload).unwrap(), cheap_params());
+        assert_eq!(payload.len(), 4 + 1 + PARAMS_LEN + SALT_LEN + 2 * (NONCE_LEN + MIN_SLOT_LEN + GCM_TAG_LEN));
+        assert_eq!(*decrypt(&payload, b"hunter2").unwrap(), b"grocery list");
+        assert_eq!(*decrypt(&payload, b"swordfish").unwrap(), b"the real plans");
+        assert!(matches!(decrypt(&payload, b"hunter3"), Err(CryptoError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn test_deniable_without_hidden_message() {
+        let hidden = vec![7; 300];
+        let with_hidden = encrypt_deniable(b"grocery list", b"hunter2", Some((&hidden, b"swordfish")), cheap_params()).unwrap();
+        let without = encrypt_deniable(&[8; 300], b"hunter2", None, cheap_params()).unwrap();
+
+        // Both slots are padded to 512 bytes either way
+        assert_eq!(with_hidden.len(), without.len());
+        assert_eq!(*decrypt(&without, b"hunter2").unwrap(), [8; 300]);
+        assert!(matches!(decrypt(&without, b"swordfish"), Err(CryptoError::DecryptionFailed)));
+        assert!(matches!(
+            encrypt_deniable(b"a", b"hunter2", Some((b"b", b"hunter2")), cheap_params()),
+            Err(CryptoError::SamePassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_recipient_round_trip() {
+        let (private_key, public_key) = generate_keypair();
+        let recipient = STANDARD.encode(public_key);
+        let payload = encrypt_to_recipient(b"only for you", &parse_public_key(&recipient).unwrap()).unwrap();
+
+        assert!(is_recipient_encrypted(&payload));
+        assert_eq!(payload.len(), 4 + 1 + KEY_LEN + NONCE_LEN + 12 + 16);
+        assert_eq!(*decrypt_with_private_key(&payload, &private_key).unwrap(), b"only for you");
+
+        let (other_key, _) = generate_keypair();
+        assert!(matches!(decrypt_with_private_key(&payload, &other_key), Err(CryptoError::DecryptionFailed)));
+        assert!(matches!(decrypt(&payload, b"hunter2"), Err(CryptoError::PrivateKeyRequired)));
+    }
+
+    #[test]
+    fn test_parse_public_key() {
+        assert!(matches!(parse_public_key("not base64!"), Err(CryptoError::InvalidRecipient { .. })));
+        assert!(matches!(parse_public_key(&STANDARD.encode([1; 16])), Err(CryptoError::InvalidRecipient { .. })));
+        assert_eq!(parse_public_key(&format!("{}\n", STANDARD.encode([1; 32]))).unwrap(), [1; 32]);
+    }
+
+    #[test]
+    fn test_low_order_recipient_is_refused() {
+        assert!(matches!(encrypt_to_recipient(b"secret", &[0; KEY_LEN]), Err(CryptoError::InvalidRecipient { .. })));
+    }
+}
@@ -0,0 +1,92 @@
+use aes_gcm::aead::{OsRng, rand_core::RngCore};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::{crypto, payload};
+
+/// The length of the leading bytes every payload written with the same
+/// options shares, which decoys copy so they don't stand out by lacking them.
+fn shared_prefix_len(data: &[u8]) -> usize {
+    match payload::is_pngme(data) && data.len() >= payload::HEADER_LEN {
+        true => payload::HEADER_LEN + crypto::fixed_header_len(&data[payload::HEADER_LEN..]),
+        false => crypto::fixed_header_len(data)
+    }
+}
+
+/// A random number below `bound`, which must not be 0.
+fn random_below(bound: usize) -> usize {
+    (OsRng.next_u64() % bound as u64) as usize
+}
+
+/// `count` chunks of random data meant to be mistaken for the one carrying
+/// `data`. Each is between half and twice as long as `data`, up to `max_len`
+/// and `Chunk::MAX_LENGTH`, and starts with the same header bytes. Their types
+/// are random private ancillary ones, shaped like those from
+/// `crypto::derive_chunk_type` and different from every type in `taken`.
+pub fn decoys(data: &[u8], count: usize, max_len: usize, taken: &[ChunkType]) -> Vec<Chunk> {
+    let prefix = &data[..shared_prefix_len(data)];
+    let mut types: Vec<ChunkType> = taken.to_vec();
+    let mut chunks = Vec::with_capacity(count);
+    while chunks.len() < count {
+        let mut key = [0; 16];
+        OsRng.fill_bytes(&mut key);
+        let chunk_type = crypto::derive_chunk_type(&key);
+        if types.contains(&chunk_type) {
+            continue;
+        }
+        types.push(chunk_type.clone());
+
+        let min_len = (data.len() / 2).max(prefix.len() + 1);
+        let len = (min_len + random_below(data.len() * 2 - min_len + 1)).min(max_len.min(Chunk::MAX_LENGTH as usize)).max(prefix.len());
+        let mut decoy = prefix.to_vec();
+        decoy.resize(len, 0);
+        OsRng.fill_bytes(&mut decoy[prefix.len()..]);
+        chunks.push(Chunk::new(chunk_type, decoy));
+    }
+    chunks
+}
+
+/// Puts the chunks in a random order, so the real ones don't come first.
+pub fn shuffle(chunks: &mut [Chunk]) {
+    for index in (1..chunks.len()).rev() {
+        chunks.swap(index, random_below(index + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_decoys() {
+        let options = payload::Options { encrypted: true, ..Default::default() };
+        let data = payload::wrap(options, &crypto::encrypt_with_key(&[7; 200], &[1; 32]).unwrap());
+        let taken = [ChunkType::from_str("ruSt").unwrap()];
+        let chunks = decoys(&data, 20, 300, &taken);
+
+        assert_eq!(chunks.len(), 20);
+        for chunk in &chunks {
+            assert!((data.len() / 2..=300).contains(&chunk.data().len()));
+            assert_eq!(chunk.data()[..11], data[..11]);
+            assert!(!chunk.chunk_type().is_public() && !chunk.chunk_type().is_critical());
+            assert!(!taken.contains(chunk.chunk_type()));
+        }
+        let mut types: Vec<String> = chunks.iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        types.sort();
+        types.dedup();
+        assert_eq!(types.len(), 20);
+    }
+
+    #[test]
+    fn test_shuffle_keeps_chunks() {
+        let mut chunks: Vec<Chunk> = (0..10)
+            .map(|index| Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![index]))
+            .collect();
+        shuffle(&mut chunks);
+
+        let mut data: Vec<u8> = chunks.iter().map(|chunk| chunk.data()[0]).collect();
+        data.sort();
+        assert_eq!(data, (0..10).collect::<Vec<u8>>());
+    }
+}
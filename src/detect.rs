@@ -0,0 +1,234 @@
+use std::fmt;
+
+use crate::image_data::{self, ImageData};
+use crate::lsb;
+use crate::png::Png;
+
+/// Chunks whose data is compressed by design, so high entropy says nothing.
+const COMPRESSED: [&[u8;4]; 3] = [b"iCCP", b"iTXt", b"zTXt"];
+/// Shorter data can't reach a telling entropy.
+const MIN_ENTROPY_LEN: usize = 256;
+/// Bits per byte above which data looks compressed or encrypted. Text stays
+/// well below 6.
+const HIGH_ENTROPY: f64 = 7.0;
+/// Embedding probability from which the LSB statistics are reported.
+const LSB_PROBABILITY: f64 = 0.9;
+/// The most a report scores.
+pub const MAX_SCORE: u32 = 100;
+
+/// Something about a PNG that suggests it carries hidden data.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Indicator {
+    /// A chunk type that isn't in the PNG specification or its extensions.
+    UnknownChunk { chunk_type: String, private: bool },
+    /// An ancillary chunk whose data looks compressed or encrypted, in bits
+    /// per byte.
+    HighEntropy { chunk_type: String, entropy: f64 },
+    /// Chunks or bytes after IEND, which viewers ignore.
+    AfterIend { len: usize },
+    /// Pixel values whose lowest bits are distributed the way embedding
+    /// leaves them.
+    LsbDistribution { probability: f64 },
+    /// A payload written by `lsb::embed`.
+    LsbPayload { bits: u8, len: usize }
+}
+
+impl Indicator {
+    /// How strongly the indicator points to hidden data, out of `MAX_SCORE`.
+    pub fn score(&self) -> u32 {
+        match self {
+            Indicator::UnknownChunk { private: true, .. } => 25,
+            Indicator::UnknownChunk { private: false, .. } => 15,
+            Indicator::HighEntropy { .. } => 30,
+            Indicator::AfterIend { .. } => 40,
+            Indicator::LsbDistribution { probability } => (probability * 50.0).round() as u32,
+            Indicator::LsbPayload { .. } => MAX_SCORE
+        }
+    }
+}
+
+/// Shown as e.g. `unknown private chunk ruSt`.
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Indicator::UnknownChunk { chunk_type, private } => {
+                write!(f, "unknown {} chunk {}", if *private { "private" } else { "public" }, chunk_type)
+            },
+            Indicator::HighEntropy { chunk_type, entropy } => {
+                write!(f, "{} data looks compressed or encrypted ({:.2} bits per byte)", chunk_type, entropy)
+            },
+            Indicator::AfterIend { len } => write!(f, "{} bytes after IEND", len),
+            Indicator::LsbDistribution { probability } => write!(
+                f,
+                "lowest pixel bits are evened out the way embedding leaves them (probability {:.2})",
+                probability
+            ),
+            Indicator::LsbPayload { bits, len } => {
+                write!(f, "pngme LSB payload of {} bytes in the lowest {} bit(s)", len, bits)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// Where in the file the indicator was found.
+    pub offset: usize,
+    pub indicator: Indicator
+}
+
+/// The indicators found in one PNG, by offset.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>
+}
+
+impl Report {
+    /// The scores of the findings added up, capped at `MAX_SCORE`.
+    pub fn score(&self) -> u32 {
+        self.findings
+            .iter()
+            .map(|finding| finding.indicator.score())
+            .sum::<u32>()
+            .min(MAX_SCORE)
+    }
+}
+
+/// Shannon entropy of `data`, in bits per byte.
+pub fn entropy(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let probability = count as f64 / data.len() as f64;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// Looks for the signs of hidden data: unknown chunks, ancillary data that
+/// looks encrypted, data after IEND and tampered pixel bits.
+pub fn analyze(png: &Png) -> Report {
+    let mut findings = Vec::new();
+    let mut iend_end = None;
+    let mut idat_offset = None;
+
+    for (offset, chunk) in png.chunks_with_offsets() {
+        let chunk_type = chunk.chunk_type();
+        let bytes = chunk_type.bytes();
+        if iend_end.is_none() && &bytes == b"IEND" {
+            iend_end = Some(offset + chunk.byte_len());
+        }
+        if idat_offset.is_none() && bytes == *image_data::IDAT.as_bytes() {
+            idat_offset = Some(offset);
+        }
+
+        if !chunk_type.is_standard() {
+            let indicator = Indicator::UnknownChunk { chunk_type: chunk_type.to_string(), private: !chunk_type.is_public() };
+            findings.push(Finding { offset, indicator });
+        }
+        if !chunk_type.is_critical() && !COMPRESSED.contains(&&bytes) && chunk.data().len() >= MIN_ENTROPY_LEN {
+            let entropy = entropy(chunk.data());
+            if entropy >= HIGH_ENTROPY {
+                findings.push(Finding { offset, indicator: Indicator::HighEntropy { chunk_type: chunk_type.to_string(), entropy } });
+            }
+        }
+    }
+
+    if let Some(iend_end) = iend_end.filter(|&iend_end| iend_end < png.byte_len()) {
+        findings.push(Finding { offset: iend_end, indicator: Indicator::AfterIend { len: png.byte_len() - iend_end } });
+    }
+
+    // Palette and low bit depth images can't be read this way, and aren't
+    // written by `lsb::embed` either
+    if let (Some(offset), Ok(image)) = (idat_offset, ImageData::from_png(png)) {
+        let payload = [1, 2].into_iter().find_map(|bits| lsb::extract(&image, bits).ok().map(|payload| (bits, payload)));
+        if let Some((bits, payload)) = payload {
+            findings.push(Finding { offset, indicator: Indicator::LsbPayload { bits, len: payload.len() } });
+        } else if let Ok(Some(probability)) = lsb::embedding_probability(&image) {
+            if probability >= LSB_PROBABILITY {
+                findings.push(Finding { offset, indicator: Indicator::LsbDistribution { probability } });
+            }
+        }
+    }
+
+    findings.sort_by_key(|finding| finding.offset);
+    Report { findings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::ihdr::{ColorType, Ihdr};
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    /// An 8x8 gray gradient.
+    fn png() -> Png {
+        let image = ImageData {
+            ihdr: Ihdr::new(8, 8, 8, ColorType::Grayscale).unwrap(),
+            filters: vec![0; 8],
+            pixels: (0..64).map(|byte| byte * 4).collect()
+        };
+        let mut chunks = vec![image.ihdr.to_chunk()];
+        chunks.extend(image.to_chunks(1 << 16).unwrap());
+        chunks.push(chunk("IEND", vec![]));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(b"aaaa"), 0.0);
+        assert_eq!(entropy(b"abab"), 1.0);
+        assert_eq!(entropy(&(0..=255).collect::<Vec<u8>>()), 8.0);
+    }
+
+    #[test]
+    fn test_clean_png() {
+        let report = analyze(&png());
+
+        assert!(report.findings.is_empty());
+        assert_eq!(report.score(), 0);
+    }
+
+    #[test]
+    fn test_suspicious_chunks() {
+        let mut png = png();
+        let random: Vec<u8> = (0..1024u32).map(|index| (index.wrapping_mul(2654435761) >> 13) as u8).collect();
+        png.append_chunk(chunk("prVt", random));
+        png.append_chunk(chunk("tEXt", b"Comment\0a perfectly ordinary comment".to_vec()));
+        png.set_trailing_data(vec![0; 10]);
+        let report = analyze(&png);
+
+        let indicators: Vec<&Indicator> = report.findings.iter().map(|finding| &finding.indicator).collect();
+        assert_eq!(indicators.len(), 3);
+        assert!(matches!(indicators[0], Indicator::UnknownChunk { private: true, .. }));
+        assert!(matches!(indicators[1], Indicator::HighEntropy { entropy, .. } if *entropy > 7.5));
+        assert_eq!(*indicators[2], Indicator::AfterIend { len: 10 });
+        assert_eq!(report.score(), 95);
+        assert_eq!(indicators[0].to_string(), "unknown private chunk prVt");
+    }
+
+    #[test]
+    fn test_lsb_payload() {
+        let png = png();
+        let mut image = ImageData::from_png(&png).unwrap();
+        lsb::embed(&mut image, b"hi", 2).unwrap();
+        let mut png = png;
+        png.replace_idat(image.to_chunks(1 << 16).unwrap());
+        let report = analyze(&png);
+
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].indicator, Indicator::LsbPayload { bits: 2, len: 2 });
+        assert_eq!(report.score(), MAX_SCORE);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::chunk::Chunk;
+
+/// Above this many cells the longest-common-subsequence table isn't built,
+/// and the chunks in between are only paired up by type.
+const MAX_TABLE: usize = 1 << 22;
+
+/// How a chunk of one image relates to the other, by index into each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Unchanged { old: usize, new: usize },
+    /// The same chunk type in the same place, with other data.
+    Changed { old: usize, new: usize },
+    Added { new: usize },
+    Removed { old: usize }
+}
+
+fn same(old: &Chunk, new: &Chunk) -> bool {
+    old.chunk_type() == new.chunk_type() && old.data() == new.data()
+}
+
+/// Lines up the chunks of two images, in the order of the new one. Chunks
+/// that are the same in both are matched first, as a diff matches lines;
+/// chunks of the same type left between two matches count as changed, and
+/// whatever remains as added or removed.
+pub fn diff(old: &[Chunk], new: &[Chunk]) -> Vec<Change> {
+    // Edits are usually local, so the common start and end are set aside
+    let prefix = old.iter().zip(new).take_while(|(old, new)| same(old, new)).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| same(old, new))
+        .count();
+
+    let mut changes: Vec<Change> = (0..prefix).map(|index| Change::Unchanged { old: index, new: index }).collect();
+    let old_middle = prefix..old.len() - suffix;
+    let new_middle = prefix..new.len() - suffix;
+
+    let mut matches = match old_middle.len() * new_middle.len() {
+        cells if cells <= MAX_TABLE => common(&old[old_middle.clone()], &new[new_middle.clone()]),
+        _ => Vec::new()
+    };
+    matches.iter_mut().for_each(|(old, new)| {
+        *old += prefix;
+        *new += prefix;
+    });
+    // A match at the end closes the last gap
+    matches.push((old_middle.end, new_middle.end));
+
+    let (mut old_start, mut new_start) = (prefix, prefix);
+    for (old_end, new_end) in matches {
+        pair_up(old, new, old_start..old_end, new_start..new_end, &mut changes);
+        if old_end < old_middle.end {
+            changes.push(Change::Unchanged { old: old_end, new: new_end });
+        }
+        (old_start, new_start) = (old_end + 1, new_end + 1);
+    }
+
+    let skipped = old.len() - suffix;
+    changes.extend((0..suffix).map(|index| Change::Unchanged { old: skipped + index, new: new.len() - suffix + index }));
+    changes
+}
+
+/// The index pairs of a longest common subsequence of `old` and `new`.
+fn common(old: &[Chunk], new: &[Chunk]) -> Vec<(usize, usize)> {
+    let width = new.len() + 1;
+    // lengths[i * width + j] is the length for old[i..] and new[j..]
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = match same(&old[i], &new[j]) {
+                true => lengths[(i + 1) * width + j + 1] + 1,
+                false => lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if same(&old[i], &new[j]) {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+/// Pairs the chunks between two matches by type, in order, and records the
+/// rest as removed and added.
+fn pair_up(
+    old: &[Chunk],
+    new: &[Chunk],
+    old_range: std::ops::Range<usize>,
+    new_range: std::ops::Range<usize>,
+    changes: &mut Vec<Change>
+) {
+    let mut unpaired: Vec<usize> = old_range.collect();
+    let mut gap = Vec::new();
+    for new_index in new_range {
+        let paired = unpaired.iter().position(|&old_index| old[old_index].chunk_type() == new[new_index].chunk_type());
+        match paired {
+            Some(position) => gap.push(Change::Changed { old: unpaired.remove(position), new: new_index }),
+            None => gap.push(Change::Added { new: new_index })
+        }
+    }
+    // Removed chunks go where they were, before whatever came after them
+    changes.extend(unpaired.into_iter().map(|old| Change::Removed { old }));
+    changes.extend(gap);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = vec![
+            chunk("IHDR", b"header"),
+            chunk("tEXt", b"Title\0old"),
+            chunk("ruSt", b"secret"),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b"")
+        ];
+        let new = vec![
+            chunk("IHDR", b"header"),
+            chunk("tEXt", b"Title\0new"),
+            chunk("IDAT", b"pixels"),
+            chunk("teSt", b"added"),
+            chunk("IEND", b"")
+        ];
+
+        assert_eq!(diff(&old, &new), vec![
+            Change::Unchanged { old: 0, new: 0 },
+            Change::Removed { old: 2 },
+            Change::Changed { old: 1, new: 1 },
+            Change::Unchanged { old: 3, new: 2 },
+            Change::Added { new: 3 },
+            Change::Unchanged { old: 4, new: 4 }
+        ]);
+    }
+
+    #[test]
+    fn test_diff_identical_and_empty() {
+        let chunks = vec![chunk("IHDR", b"header"), chunk("IEND", b"")];
+        assert_eq!(diff(&chunks, &chunks), vec![
+            Change::Unchanged { old: 0, new: 0 },
+            Change::Unchanged { old: 1, new: 1 }
+        ]);
+        assert_eq!(diff(&[], &chunks), vec![Change::Added { new: 0 }, Change::Added { new: 1 }]);
+        assert_eq!(diff(&chunks, &[]), vec![Change::Removed { old: 0 }, Change::Removed { old: 1 }]);
+    }
+}
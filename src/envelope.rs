@@ -0,0 +1,147 @@
+use crate::error::EnvelopeError;
+
+/// Marks a chunk payload as wrapped in a pngme envelope.
+pub const MAGIC: [u8;4] = *b"PMEe";
+/// Content types are stored behind a one-byte length.
+pub const MAX_CONTENT_TYPE_LEN: usize = u8::MAX as usize;
+
+/// A payload together with its MIME type, so whoever decodes it knows what
+/// to do with it. Laid out as the magic, the content type behind a one-byte
+/// length, the data length as a big-endian u32 and the data.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    pub content_type: &'a str,
+    pub data: &'a [u8]
+}
+
+impl<'a> Envelope<'a> {
+    pub fn new(content_type: &'a str, data: &'a [u8]) -> Result<Envelope<'a>, EnvelopeError> {
+        if content_type.is_empty()
+            || content_type.len() > MAX_CONTENT_TYPE_LEN
+            || !content_type.bytes().all(|byte| byte.is_ascii_graphic() || byte == b' ') {
+            return Err(EnvelopeError::InvalidContentType { content_type: content_type.to_string() });
+        }
+        if u32::try_from(data.len()).is_err() {
+            return Err(EnvelopeError::TooLarge { len: data.len() });
+        }
+        Ok(Envelope { content_type, data })
+    }
+
+    /// Reads an envelope, checking the recorded length against the data
+    /// that is actually there.
+    pub fn parse(payload: &'a [u8]) -> Result<Envelope<'a>, EnvelopeError> {
+        if !is_enveloped(payload) {
+            return Err(EnvelopeError::NotEnveloped);
+        }
+        let truncated = || EnvelopeError::Truncated { len: payload.len() };
+
+        let rest = &payload[MAGIC.len()..];
+        let (&type_len, rest) = rest.split_first().ok_or_else(truncated)?;
+        let (content_type, rest) = rest.split_at_checked(type_len as usize).ok_or_else(truncated)?;
+        let (len, data) = rest.split_at_checked(4).ok_or_else(truncated)?;
+
+        let expected = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+        if data.len() != expected {
+            return Err(EnvelopeError::LengthMismatch { expected, actual: data.len() });
+        }
+        let content_type = std::str::from_utf8(content_type).map_err(|_| EnvelopeError::InvalidContentType {
+            content_type: String::from_utf8_lossy(content_type).into_owned()
+        })?;
+        Envelope::new(content_type, data)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            &MAGIC[..],
+            &[self.content_type.len() as u8],
+            self.content_type.as_bytes(),
+            &(self.data.len() as u32).to_be_bytes(),
+            self.data
+        ].concat()
+    }
+
+    /// Whether the data can be shown as text: `text/*` and the usual
+    /// structured text formats.
+    pub fn is_text(&self) -> bool {
+        let essence = self.content_type.split(';').next().unwrap_or_default().trim();
+        essence.starts_with("text/")
+            || essence.ends_with("+json")
+            || essence.ends_with("+xml")
+            || ["application/json", "application/xml", "application/javascript"].contains(&essence)
+    }
+}
+
+/// Whether a chunk payload starts with the envelope magic.
+pub fn is_enveloped(payload: &[u8]) -> bool {
+    payload.starts_with(&MAGIC)
+}
+
+/// Guesses a content type from the data's leading bytes, falling back to
+/// plain text for UTF-8 and `application/octet-stream` for anything else.
+pub fn guess_content_type(data: &[u8]) -> &'static str {
+    const SIGNATURES: [(&[u8], &str);7] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"age-encryption.org/v1\n", "application/age")
+    ];
+    match SIGNATURES.iter().find(|(signature, _)| data.starts_with(signature)) {
+        Some((_, content_type)) => content_type,
+        None if std::str::from_utf8(data).is_ok() => "text/plain; charset=utf-8",
+        None => "application/octet-stream"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let envelope = Envelope::new("image/png", b"\x89PNG").unwrap();
+        let bytes = envelope.to_bytes();
+
+        assert!(is_enveloped(&bytes));
+        assert_eq!(&bytes[..14], b"PMEe\x09image/png");
+        assert_eq!(Envelope::parse(&bytes).unwrap(), envelope);
+        assert!(!envelope.is_text());
+    }
+
+    #[test]
+    fn test_envelope_length_is_checked() {
+        let mut bytes = Envelope::new("text/plain", b"hello").unwrap().to_bytes();
+        bytes.pop();
+        assert!(matches!(
+            Envelope::parse(&bytes),
+            Err(EnvelopeError::LengthMismatch { expected: 5, actual: 4 })
+        ));
+        assert!(matches!(Envelope::parse(b"PMEe\x09image"), Err(EnvelopeError::Truncated { len: 10 })));
+        assert!(matches!(Envelope::parse(b"hello"), Err(EnvelopeError::NotEnveloped)));
+    }
+
+    #[test]
+    fn test_invalid_content_type() {
+        assert!(Envelope::new("", b"").is_err());
+        assert!(Envelope::new("text/plain\n", b"").is_err());
+        assert!(Envelope::new(&"a".repeat(256), b"").is_err());
+    }
+
+    #[test]
+    fn test_is_text() {
+        let text = |content_type| Envelope::new(content_type, b"").unwrap().is_text();
+        assert!(text("text/plain; charset=utf-8"));
+        assert!(text("application/json"));
+        assert!(text("image/svg+xml"));
+        assert!(!text("application/octet-stream"));
+    }
+
+    #[test]
+    fn test_guess_content_type() {
+        assert_eq!(guess_content_type(b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(guess_content_type("héllo".as_bytes()), "text/plain; charset=utf-8");
+        assert_eq!(guess_content_type(&[0, 159, 146, 150]), "application/octet-stream");
+    }
+}
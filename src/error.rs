@@ -0,0 +1,638 @@
+use std::{io, string::FromUtf8Error};
+
+use crate::check::Problem;
+use crate::compress::Method;
+use crate::ihdr::ColorType;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ChunkTypeError {
+    #[error("Chunk type {chunk_type:?} must be exactly 4 bytes long but is {len}")]
+    WrongLength { chunk_type: String, len: usize },
+
+    #[error("The bytes {bytes:?} are not between 65-90 or 97-122, i.e. ASCII")]
+    NotAlphabetic { bytes: [u8;4] },
+
+    #[error("Chunk type {chunk_type} has the reserved bit set (lowercase third letter)")]
+    ReservedBitSet { chunk_type: String }
+}
+
+#[derive(Debug, Error)]
+pub enum ChunkError {
+    #[error("Truncated chunk: only {remaining} bytes remain but a chunk needs at least 12")]
+    Truncated { remaining: usize },
+
+    #[error("Declared chunk length {length} exceeds the {remaining} bytes remaining")]
+    LengthExceedsInput { length: usize, remaining: usize },
+
+    #[error("Chunk length {length} exceeds the PNG maximum of {}", crate::chunk::Chunk::MAX_LENGTH)]
+    LengthTooLarge { length: usize },
+
+    #[error("The chunk is followed by {trailing} unexpected bytes")]
+    TrailingBytes { trailing: usize },
+
+    #[error(transparent)]
+    InvalidType(#[from] ChunkTypeError),
+
+    #[error("The crc checksum is invalid: expected {expected:#010x} but found {found:#010x}")]
+    InvalidCrc { expected: u32, found: u32 },
+
+    #[error("The chunk data is not valid UTF-8")]
+    NotUtf8(#[from] FromUtf8Error)
+}
+
+#[derive(Debug, Error)]
+pub enum CompressError {
+    #[error("The payload is not compressed")]
+    NotCompressed,
+
+    #[error("The compressed payload is truncated ({len} bytes)")]
+    Truncated { len: usize },
+
+    #[error("Unknown compression method {method}")]
+    UnknownMethod { method: u8 },
+
+    #[error("Level {level} is out of range for {method:?} compression")]
+    InvalidLevel { method: Method, level: i32 },
+
+    #[error("The payload is too large to compress ({len} bytes)")]
+    TooLarge { len: usize },
+
+    #[error("The payload decompressed to {actual} bytes but its header says {expected}")]
+    LengthMismatch { expected: usize, actual: usize },
+
+    #[error("The payload would decompress to {len} bytes, more than the limit of {limit}")]
+    LimitExceeded { len: usize, limit: usize },
+
+    #[error(transparent)]
+    Io(#[from] io::Error)
+}
+
+#[derive(Debug, Error)]
+pub enum EnvelopeError {
+    #[error("The payload is not in an envelope")]
+    NotEnveloped,
+
+    #[error("The envelope is truncated ({len} bytes)")]
+    Truncated { len: usize },
+
+    #[error("Invalid content type {content_type:?}")]
+    InvalidContentType { content_type: String },
+
+    #[error("The payload is too large for an envelope ({len} bytes)")]
+    TooLarge { len: usize },
+
+    #[error("The envelope holds {actual} bytes but its header says {expected}")]
+    LengthMismatch { expected: usize, actual: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum PayloadError {
+    #[error("The payload has no pngme header")]
+    NotPngme,
+
+    #[error("The pngme header is truncated ({len} bytes)")]
+    Truncated { len: usize },
+
+    #[error("Unsupported pngme header version {version}; it was written by a newer pngme")]
+    UnsupportedVersion { version: u8 },
+
+    #[error("The pngme header has unknown options {bits:#04x}")]
+    UnknownOptions { bits: u8 }
+}
+
+#[derive(Debug, Error)]
+pub enum ContainerError {
+    #[error("The file isn't a PNG, JPEG, GIF or TIFF")]
+    UnknownFormat,
+
+    #[error("There is no block of type {chunk_type} at index {index}")]
+    BlockNotFound { chunk_type: String, index: usize },
+
+    #[error(transparent)]
+    Png(#[from] PngError),
+
+    #[error(transparent)]
+    Chunk(#[from] ChunkError),
+
+    #[error(transparent)]
+    Jpeg(#[from] JpegError),
+
+    #[error(transparent)]
+    Gif(#[from] GifError),
+
+    #[error(transparent)]
+    Tiff(#[from] TiffError)
+}
+
+#[derive(Debug, Error)]
+pub enum CryptoError {
+    #[error("The payload is not encrypted")]
+    NotEncrypted,
+
+    #[error("The encrypted payload is only {len} bytes, too short for its header")]
+    Truncated { len: usize },
+
+    #[error("Unsupported encryption format version {version}")]
+    UnsupportedVersion { version: u8 },
+
+    #[error("The payload was encrypted with a key file, not a passphrase")]
+    KeyRequired,
+
+    #[error("The payload was encrypted with a passphrase, not a key file")]
+    PassphraseRequired,
+
+    #[error("The payload was encrypted to a recipient; its private key is needed")]
+    PrivateKeyRequired,
+
+    #[error("Could not read key from {path}: {reason}")]
+    InvalidKeyFile { path: String, reason: String },
+
+    #[error("Key derivation failed: {0}")]
+    KeyDerivation(String),
+
+    #[error("Encryption failed")]
+    EncryptionFailed,
+
+    #[error("The hidden message needs a passphrase of its own")]
+    SamePassphrase,
+
+    #[error("Decryption failed: wrong passphrase or key, or tampered data")]
+    DecryptionFailed,
+
+    #[error("The payload has no HMAC tag")]
+    NotAuthenticated,
+
+    #[error("HMAC mismatch: the payload was tampered with or the key is wrong")]
+    TagMismatch,
+
+    #[error("Invalid recipient {recipient:?}: {reason}")]
+    InvalidRecipient { recipient: String, reason: String },
+
+    #[error("Could not read age identities from {path}: {reason}")]
+    InvalidIdentity { path: String, reason: String },
+
+    #[error(transparent)]
+    AgeEncrypt(#[from] age::EncryptError),
+
+    #[error(transparent)]
+    AgeDecrypt(#[from] age::DecryptError)
+}
+
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    #[error("The PNG has no signature chunk")]
+    Unsigned,
+
+    #[error("The signature chunk is malformed ({len} bytes)")]
+    Malformed { len: usize },
+
+    #[error("The signature chunk has unknown scope {scope}")]
+    UnknownScope { scope: u8 },
+
+    #[error("The signed chunk {chunk_type} is missing")]
+    MissingChunk { chunk_type: String },
+
+    #[error("Could not read key from {path}: {reason}")]
+    InvalidKey { path: String, reason: String },
+
+    #[error(transparent)]
+    InvalidChunkType(#[from] ChunkTypeError)
+}
+
+#[derive(Debug, Error)]
+pub enum TextError {
+    #[error("{chunk_type} is not a text chunk")]
+    NotText { chunk_type: String },
+
+    #[error("Invalid keyword {keyword:?}: {reason}")]
+    InvalidKeyword { keyword: String, reason: &'static str },
+
+    #[error("The text chunk has no null byte after its keyword")]
+    MissingSeparator,
+
+    #[error("Text may not contain null bytes")]
+    NullInText,
+
+    #[error("{character:?} can't be written as Latin-1")]
+    NotLatin1 { character: char },
+
+    #[error("The compressed text chunk has no compression method")]
+    MissingCompressionMethod,
+
+    #[error("Unknown text compression method {method}; only 0 (deflate) is defined")]
+    UnknownCompressionMethod { method: u8 },
+
+    #[error("The compressed text inflates to more than {limit} bytes")]
+    TooLong { limit: usize },
+
+    #[error("Invalid iTXt compression flag {flag}; it must be 0 or 1")]
+    InvalidCompressionFlag { flag: u8 },
+
+    #[error("Invalid language tag {language_tag:?}: expected words of 1 to 8 ASCII letters or digits separated by hyphens")]
+    InvalidLanguageTag { language_tag: String },
+
+    #[error("The international text is not valid UTF-8")]
+    NotUtf8(#[from] FromUtf8Error),
+
+    #[error(transparent)]
+    Chunk(#[from] ChunkError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error)
+}
+
+#[derive(Debug, Error)]
+pub enum TimestampError {
+    #[error("{chunk_type} is not a tIME chunk")]
+    NotTime { chunk_type: String },
+
+    #[error("A tIME chunk holds 7 bytes but this one has {len}")]
+    WrongLength { len: usize },
+
+    #[error("The {field} {value} is out of range")]
+    OutOfRange { field: &'static str, value: u8 },
+
+    #[error("The year {year} is out of range for tIME")]
+    YearOutOfRange { year: i64 },
+
+    #[error("Invalid timestamp {timestamp:?}: expected RFC 3339, e.g. 2023-07-21T09:30:00Z")]
+    Invalid { timestamp: String }
+}
+
+#[derive(Debug, Error)]
+pub enum IhdrError {
+    #[error("{chunk_type} is not an IHDR chunk")]
+    NotIhdr { chunk_type: String },
+
+    #[error("The image has no IHDR chunk")]
+    Missing,
+
+    #[error("IHDR must be 13 bytes long but is {len}")]
+    WrongLength { len: usize },
+
+    #[error("The image {dimension} {value} is outside 1 to 2^31 - 1")]
+    InvalidDimension { dimension: &'static str, value: u32 },
+
+    #[error("Unknown color type {color_type}")]
+    UnknownColorType { color_type: u8 },
+
+    #[error("Bit depth {bit_depth} isn't allowed for {color_type} images")]
+    InvalidBitDepth { bit_depth: u8, color_type: ColorType },
+
+    #[error("Unknown compression method {method}")]
+    UnknownCompressionMethod { method: u8 },
+
+    #[error("Unknown filter method {method}")]
+    UnknownFilterMethod { method: u8 },
+
+    #[error("Unknown interlace method {method}")]
+    UnknownInterlaceMethod { method: u8 }
+}
+
+#[derive(Debug, Error)]
+pub enum PhysError {
+    #[error("{chunk_type} is not a pHYs chunk")]
+    NotPhys { chunk_type: String },
+
+    #[error("A pHYs chunk holds 9 bytes but this one has {len}")]
+    WrongLength { len: usize },
+
+    #[error("Unknown pHYs unit {unit}")]
+    UnknownUnit { unit: u8 },
+
+    #[error("{dpi} DPI can't be stored in pHYs")]
+    InvalidDpi { dpi: f64 }
+}
+
+#[derive(Debug, Error)]
+pub enum ColorError {
+    #[error("{chunk_type} is not a {expected} chunk")]
+    WrongType { chunk_type: String, expected: String },
+
+    #[error("A {chunk_type} chunk holds {expected} bytes but this one has {len}")]
+    WrongLength { chunk_type: &'static str, expected: usize, len: usize },
+
+    #[error("A gamma of 0 is invalid")]
+    ZeroGamma,
+
+    #[error("Unknown sRGB rendering intent {intent}")]
+    UnknownRenderingIntent { intent: u8 }
+}
+
+#[derive(Debug, Error)]
+pub enum PaletteError {
+    #[error("{chunk_type} is not a PLTE chunk")]
+    NotPlte { chunk_type: String },
+
+    #[error("PLTE must hold 1 to 256 RGB entries of 3 bytes each but is {len} bytes long")]
+    WrongLength { len: usize },
+
+    #[error("PLTE isn't allowed in {color_type} images")]
+    NotAllowed { color_type: ColorType },
+
+    #[error("PLTE has {entries} entries but a bit depth of {bit_depth} can only index {}", 1u32 << bit_depth)]
+    TooManyEntries { entries: usize, bit_depth: u8 },
+
+    #[error("{chunk_type} is not a hIST chunk")]
+    NotHist { chunk_type: String },
+
+    #[error("hIST holds 2 bytes per palette entry but is {len} bytes long")]
+    OddHistogramLength { len: usize },
+
+    #[error("hIST has {entries} entries but the palette has {palette_entries}")]
+    HistogramMismatch { entries: usize, palette_entries: usize },
+
+    #[error("{chunk_type} is not an sPLT chunk")]
+    NotSplt { chunk_type: String },
+
+    #[error("Invalid suggested palette name: {0}")]
+    InvalidName(#[from] TextError),
+
+    #[error("The sPLT chunk has no null byte after its palette name")]
+    MissingSeparator,
+
+    #[error("The sPLT chunk has no sample depth")]
+    MissingSampleDepth,
+
+    #[error("Unknown sPLT sample depth {depth}; only 8 and 16 are allowed")]
+    UnknownSampleDepth { depth: u8 },
+
+    #[error("sPLT entries are {entry_len} bytes each but {len} bytes of entries are left")]
+    SuggestedEntriesLength { len: usize, entry_len: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum SignificantBitsError {
+    #[error("{chunk_type} is not an sBIT chunk")]
+    NotSbit { chunk_type: String },
+
+    #[error("sBIT holds {expected} bytes for {color_type} images but this one has {len}")]
+    WrongLength { color_type: ColorType, expected: usize, len: usize },
+
+    #[error("{bits} significant bits is outside 1 to the sample depth {max}")]
+    OutOfRange { bits: u8, max: u8 }
+}
+
+#[derive(Debug, Error)]
+pub enum BackgroundError {
+    #[error("{chunk_type} is not a bKGD chunk")]
+    NotBkgd { chunk_type: String },
+
+    #[error("bKGD holds {expected} bytes for {color_type} images but this one has {len}")]
+    WrongLength { color_type: ColorType, expected: usize, len: usize },
+
+    #[error("The background sample {sample} doesn't fit the bit depth {bit_depth}")]
+    SampleOutOfRange { sample: u16, bit_depth: u8 },
+
+    #[error("The background palette entry {index} is outside the {palette_entries}-entry palette")]
+    IndexOutOfRange { index: u8, palette_entries: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum TransparencyError {
+    #[error("{chunk_type} is not a tRNS chunk")]
+    NotTrns { chunk_type: String },
+
+    #[error("tRNS isn't allowed in {color_type} images, which have an alpha channel")]
+    NotAllowed { color_type: ColorType },
+
+    #[error("tRNS holds {expected} bytes for {color_type} images but this one has {len}")]
+    WrongLength { color_type: ColorType, expected: usize, len: usize },
+
+    #[error("The transparent sample {sample} doesn't fit the bit depth {bit_depth}")]
+    SampleOutOfRange { sample: u16, bit_depth: u8 },
+
+    #[error("tRNS has {entries} alpha values but the palette only {palette_entries} entries")]
+    TooManyEntries { entries: usize, palette_entries: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum ImageDataError {
+    #[error("The image has no IDAT chunks")]
+    Missing,
+
+    #[error("Interlaced images aren't supported")]
+    Interlaced,
+
+    #[error("The image data inflates to {len} bytes but the image needs {expected}")]
+    WrongSize { expected: u64, len: usize },
+
+    #[error("A {width}x{height} image is too large to decode")]
+    TooLarge { width: u32, height: u32 },
+
+    #[error("Unknown filter type {filter} in row {row}")]
+    UnknownFilter { filter: u8, row: usize },
+
+    #[error(transparent)]
+    Ihdr(#[from] IhdrError),
+
+    #[error(transparent)]
+    Chunk(#[from] ChunkError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error)
+}
+
+#[derive(Debug, Error)]
+pub enum LsbError {
+    #[error("Only 8- and 16-bit grayscale and color images can carry LSB payloads, not {bit_depth}-bit {color_type}")]
+    UnsupportedImage { color_type: ColorType, bit_depth: u8 },
+
+    #[error("{bits} bits per sample isn't supported; use 1 or 2")]
+    InvalidBits { bits: u8 },
+
+    #[error("The payload is {len} bytes but the image can only carry {capacity}")]
+    TooLarge { len: usize, capacity: usize },
+
+    #[error("No LSB payload was found in the image data")]
+    NotFound,
+
+    #[error(transparent)]
+    ImageData(#[from] ImageDataError)
+}
+
+#[derive(Debug, Error)]
+pub enum ApngError {
+    #[error("{chunk_type} is not an {expected} chunk")]
+    WrongType { chunk_type: String, expected: String },
+
+    #[error("A {chunk_type} chunk holds {expected} bytes but this one has {len}")]
+    WrongLength { chunk_type: &'static str, expected: usize, len: usize },
+
+    #[error("acTL declares an animation with no frames")]
+    NoFrames,
+
+    #[error("Frame #{sequence_number} has a width or height of 0")]
+    EmptyFrame { sequence_number: u32 },
+
+    #[error("Unknown fcTL dispose operation {op}")]
+    UnknownDisposeOp { op: u8 },
+
+    #[error("Unknown fcTL blend operation {op}")]
+    UnknownBlendOp { op: u8 }
+}
+
+#[derive(Debug, Error)]
+pub enum GifError {
+    #[error("The file doesn't start with a GIF87a or GIF89a signature")]
+    InvalidSignature,
+
+    #[error("The GIF ends in the middle of the block at offset {offset}")]
+    Truncated { offset: usize },
+
+    #[error("Expected a GIF extension, image or trailer at offset {offset}, found {found:#04x}")]
+    UnknownBlock { offset: usize, found: u8 },
+
+    #[error("A GIF application extension holding a pngme payload is too short for its chunk type")]
+    BrokenPayload
+}
+
+#[derive(Debug, Error)]
+pub enum IccError {
+    #[error("{chunk_type} is not an iCCP chunk")]
+    NotIccp { chunk_type: String },
+
+    #[error("Invalid profile name: {0}")]
+    InvalidName(#[from] TextError),
+
+    #[error("The iCCP chunk has no null byte after its profile name")]
+    MissingSeparator,
+
+    #[error("The iCCP chunk has no compression method")]
+    MissingCompressionMethod,
+
+    #[error("Unknown iCCP compression method {method}; only 0 (deflate) is defined")]
+    UnknownCompressionMethod { method: u8 },
+
+    #[error(transparent)]
+    Chunk(#[from] ChunkError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error)
+}
+
+#[derive(Debug, Error)]
+pub enum ExifError {
+    #[error("{chunk_type} is not an eXIf chunk")]
+    NotExif { chunk_type: String },
+
+    #[error("EXIF data must start with a TIFF header, II*\\0 or MM\\0*")]
+    InvalidHeader,
+
+    #[error("The EXIF data ends early; nothing can be read at offset {offset}")]
+    Truncated { offset: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum JpegError {
+    #[error("The file doesn't start with a JPEG SOI marker")]
+    InvalidSignature,
+
+    #[error("Expected a JPEG marker at offset {offset}, found {found:#04x}")]
+    ExpectedMarker { offset: usize, found: u8 },
+
+    #[error("The JPEG segment at offset {offset} has a length of {len}, less than its own length field")]
+    InvalidLength { offset: usize, len: usize },
+
+    #[error("The JPEG ends in the middle of the segment at offset {offset}")]
+    Truncated { offset: usize },
+
+    #[error("A {len}-byte payload needs more JPEG segments than fit in one image")]
+    PayloadTooLarge { len: usize },
+
+    #[error("The JPEG segments holding the {chunk_type} payload are incomplete or out of order")]
+    BrokenPayload { chunk_type: String }
+}
+
+#[derive(Debug, Error)]
+pub enum TiffError {
+    #[error("The file doesn't start with a TIFF header, II*\\0 or MM\\0*")]
+    InvalidHeader,
+
+    #[error("The TIFF ends in the middle of the data at offset {offset}")]
+    Truncated { offset: usize },
+
+    #[error("The TIFF has no private tags left for another payload")]
+    TooManyPayloads,
+
+    #[error("TIFF offsets are 32 bits, so the file can't grow past 4 GiB")]
+    TooLarge,
+
+    #[error("TIFF tag {tag} is too short to hold a pngme payload")]
+    BrokenPayload { tag: u16 }
+}
+
+#[derive(Debug, Error)]
+pub enum QrError {
+    #[error("{len} bytes is too much for a QR code, which holds at most 2953")]
+    TooLong { len: usize },
+
+    #[error(transparent)]
+    Ihdr(#[from] IhdrError),
+
+    #[error(transparent)]
+    ImageData(#[from] ImageDataError),
+
+    #[error(transparent)]
+    Png(#[from] PngError)
+}
+
+#[derive(Debug, Error)]
+pub enum ScatterError {
+    #[error("The image already has a {chunk_type} chunk, which this scatter key would reuse; pick another key")]
+    TypeTaken { chunk_type: String },
+
+    #[error("Piece {index} of the scattered payload ({chunk_type}) is missing")]
+    Missing { index: usize, chunk_type: String },
+
+    #[error("Piece {index} of the scattered payload is {len} bytes, expected {expected}")]
+    WrongLength { index: usize, len: usize, expected: usize },
+
+    #[error("The payload needs {pieces} pieces but at most {max} can be scattered; use bigger pieces")]
+    TooManyPieces { pieces: usize, max: usize }
+}
+
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("Both images have {chunk_type} chunks with different data")]
+    Conflict { chunk_type: String }
+}
+
+#[derive(Debug, Error)]
+pub enum PngError {
+    #[error("The input ended after {read} bytes, before the end of the PNG signature")]
+    TruncatedSignature { read: usize },
+
+    #[error("The given header doesn't match the PNG standard header")]
+    InvalidSignature,
+
+    #[error("Truncated chunk at offset {offset}: expected {expected} more bytes but the input ended after {found}")]
+    TruncatedChunk { offset: usize, expected: u64, found: u64 },
+
+    #[error("Invalid chunk at offset {offset}: {source}")]
+    InvalidChunk { offset: usize, source: ChunkError },
+
+    #[error("No chunk of type {chunk_type} could be found")]
+    ChunkNotFound { chunk_type: String },
+
+    #[error("Chunk index {index} is out of range for {len} chunk(s)")]
+    IndexOutOfRange { index: usize, len: usize },
+
+    #[error("The chunks don't form a valid PNG: {}", join_problems(.problems))]
+    InvalidStructure { problems: Vec<Problem> },
+
+    #[error(transparent)]
+    InvalidChunkType(#[from] ChunkTypeError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error)
+}
+
+fn join_problems(problems: &[Problem]) -> String {
+    problems
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>()
+        .join("; ")
+}
@@ -0,0 +1,253 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::{ChunkError, ExifError};
+
+/// The chunk type of EXIF metadata.
+pub const EXIF: &str = "eXIf";
+
+/// JPEG APP1 segments put this before the TIFF data; eXIf doesn't.
+const APP1_PREFIX: &[u8] = b"Exif\0\0";
+const LITTLE_ENDIAN: &[u8;4] = b"II*\0";
+const BIG_ENDIAN: &[u8;4] = b"MM\0*";
+
+const MAKE: u16 = 0x010f;
+const MODEL: u16 = 0x0110;
+const ORIENTATION: u16 = 0x0112;
+const DATE_TIME: u16 = 0x0132;
+const EXIF_IFD: u16 = 0x8769;
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+
+/// EXIF metadata as stored in an eXIf chunk: a TIFF header and the image
+/// file directories after it, kept as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exif {
+    pub data: Vec<u8>
+}
+
+/// The commonly wanted tags from an EXIF blob.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// When the photo was taken, or else when the file was last changed, in
+    /// EXIF's `YYYY:MM:DD HH:MM:SS` form.
+    pub date_time: Option<String>,
+    /// 1 to 8, how the image has to be rotated or mirrored to display upright.
+    pub orientation: Option<u16>
+}
+
+impl Exif {
+    /// Takes EXIF data as eXIf stores it, or as it appears in a JPEG APP1
+    /// segment behind `Exif\0\0`.
+    pub fn new(data: Vec<u8>) -> Result<Exif, ExifError> {
+        let data = match data.strip_prefix(APP1_PREFIX) {
+            Some(tiff) => tiff.to_vec(),
+            None => data
+        };
+        if !data.starts_with(LITTLE_ENDIAN) && !data.starts_with(BIG_ENDIAN) {
+            return Err(ExifError::InvalidHeader);
+        }
+        Ok(Exif { data })
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Exif, ExifError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != EXIF {
+            return Err(ExifError::NotExif { chunk_type });
+        }
+        Exif::new(chunk.data().to_vec())
+    }
+
+    pub fn to_chunk(&self) -> Result<Chunk, ChunkError> {
+        Chunk::try_new(ChunkType::from_str(EXIF).unwrap(), self.data.clone())
+    }
+
+    /// Reads make, model, date and orientation from the first image file
+    /// directory and the EXIF directory it points to.
+    pub fn summary(&self) -> Result<Summary, ExifError> {
+        let tiff = Tiff { data: &self.data, big_endian: self.data.starts_with(BIG_ENDIAN) };
+        let mut summary = Summary::default();
+        let mut modified = None;
+
+        for entry in tiff.entries(tiff.u32(4)? as usize)? {
+            match (entry.tag, entry.kind) {
+                (MAKE, TYPE_ASCII) => summary.make = Some(tiff.ascii(&entry)?),
+                (MODEL, TYPE_ASCII) => summary.model = Some(tiff.ascii(&entry)?),
+                (DATE_TIME, TYPE_ASCII) => modified = Some(tiff.ascii(&entry)?),
+                (ORIENTATION, TYPE_SHORT) => summary.orientation = Some(tiff.u16(entry.value_offset)?),
+                (EXIF_IFD, TYPE_LONG) => {
+                    let exif_ifd = tiff.u32(entry.value_offset)? as usize;
+                    summary.date_time = tiff
+                        .entries(exif_ifd)?
+                        .iter()
+                        .find(|entry| entry.tag == DATE_TIME_ORIGINAL && entry.kind == TYPE_ASCII)
+                        .map(|entry| tiff.ascii(entry))
+                        .transpose()?;
+                },
+                _ => {}
+            }
+        }
+        summary.date_time = summary.date_time.or(modified);
+        Ok(summary)
+    }
+}
+
+/// Shown as e.g. `Canon EOS 5D, taken 2023:07:21 09:30:05, rotated 90° clockwise`.
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let camera = match (&self.make, &self.model) {
+            (Some(make), Some(model)) if model.starts_with(make.as_str()) => Some(model.clone()),
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.clone().or(model.clone())
+        };
+        let parts: Vec<String> = [
+            camera,
+            self.date_time.as_ref().map(|date_time| format!("taken {}", date_time)),
+            self.orientation.map(|orientation| describe_orientation(orientation).to_string())
+        ].into_iter().flatten().collect();
+
+        match parts.is_empty() {
+            true => f.write_str("no camera, date or orientation tags"),
+            false => f.write_str(&parts.join(", "))
+        }
+    }
+}
+
+fn describe_orientation(orientation: u16) -> &'static str {
+    match orientation {
+        1 => "upright",
+        2 => "mirrored horizontally",
+        3 => "rotated 180°",
+        4 => "mirrored vertically",
+        5 => "mirrored horizontally and rotated 90° counterclockwise",
+        6 => "rotated 90° clockwise",
+        7 => "mirrored horizontally and rotated 90° clockwise",
+        8 => "rotated 90° counterclockwise",
+        _ => "unknown orientation"
+    }
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool
+}
+
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    /// Where the entry's 4-byte value or offset field is.
+    value_offset: usize
+}
+
+impl Tiff<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8;N], ExifError> {
+        self.data
+            .get(offset..offset.saturating_add(N))
+            .map(|bytes| bytes.try_into().unwrap())
+            .ok_or(ExifError::Truncated { offset })
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ExifError> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ExifError> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn entries(&self, ifd_offset: usize) -> Result<Vec<Entry>, ExifError> {
+        let count = self.u16(ifd_offset)? as usize;
+        (0..count)
+            .map(|index| {
+                let offset = ifd_offset + 2 + index * 12;
+                Ok(Entry {
+                    tag: self.u16(offset)?,
+                    kind: self.u16(offset + 2)?,
+                    count: self.u32(offset + 4)?,
+                    value_offset: offset + 8
+                })
+            })
+            .collect()
+    }
+
+    /// ASCII values of up to 4 bytes are stored in the entry itself.
+    fn ascii(&self, entry: &Entry) -> Result<String, ExifError> {
+        let len = entry.count as usize;
+        let offset = match len <= 4 {
+            true => entry.value_offset,
+            false => self.u32(entry.value_offset)? as usize
+        };
+        let bytes = self.data
+            .get(offset..offset.saturating_add(len))
+            .ok_or(ExifError::Truncated { offset })?;
+        let text = bytes.split(|&byte| byte == 0).next().unwrap_or_default();
+        Ok(String::from_utf8_lossy(text).trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little-endian TIFF with Make, Model, Orientation and an EXIF
+    /// directory holding DateTimeOriginal.
+    fn exif_data() -> Vec<u8> {
+        let mut data = b"II*\0\x08\0\0\0".to_vec();
+        // IFD0 at 8 with four entries, then the next-IFD offset
+        data.extend_from_slice(&[4, 0]);
+        data.extend_from_slice(&[0x0f, 0x01, 2, 0, 6, 0, 0, 0, 62, 0, 0, 0]);
+        data.extend_from_slice(&[0x10, 0x01, 2, 0, 4, 0, 0, 0, b'E', b'O', b'S', 0]);
+        data.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        data.extend_from_slice(&[0x69, 0x87, 4, 0, 1, 0, 0, 0, 68, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        // Make at 62, the EXIF IFD at 68 and its date at 86
+        data.extend_from_slice(b"Canon\0");
+        data.extend_from_slice(&[1, 0]);
+        data.extend_from_slice(&[0x03, 0x90, 2, 0, 20, 0, 0, 0, 86, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"2023:07:21 09:30:05\0");
+        data
+    }
+
+    #[test]
+    fn test_exif_summary() {
+        let exif = Exif::new(exif_data()).unwrap();
+        let summary = exif.summary().unwrap();
+
+        assert_eq!(summary.make.as_deref(), Some("Canon"));
+        assert_eq!(summary.model.as_deref(), Some("EOS"));
+        assert_eq!(summary.date_time.as_deref(), Some("2023:07:21 09:30:05"));
+        assert_eq!(summary.orientation, Some(6));
+        assert_eq!(summary.to_string(), "Canon EOS, taken 2023:07:21 09:30:05, rotated 90° clockwise");
+    }
+
+    #[test]
+    fn test_exif_chunk_round_trip() {
+        let exif = Exif::new([APP1_PREFIX, &exif_data()].concat()).unwrap();
+        let chunk = exif.to_chunk().unwrap();
+
+        assert_eq!(chunk.chunk_type().to_string(), "eXIf");
+        assert_eq!(chunk.data(), exif_data());
+        assert_eq!(Exif::from_chunk(&chunk).unwrap(), exif);
+    }
+
+    #[test]
+    fn test_invalid_exif() {
+        assert!(matches!(Exif::new(b"GIF89a".to_vec()), Err(ExifError::InvalidHeader)));
+
+        let exif = Exif::new(b"MM\0*\0\0\0\x08\0".to_vec()).unwrap();
+        assert!(matches!(exif.summary(), Err(ExifError::Truncated { offset: 8 })));
+        let empty = Exif::new(b"MM\0*\0\0\0\x08\0\0".to_vec()).unwrap();
+        assert_eq!(empty.summary().unwrap().to_string(), "no camera, date or orientation tags");
+    }
+}
@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngError;
+use crate::png::Png;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A parsed PNG, owned by the caller until it is passed to `pngme_free`.
+pub struct PngmePng(Png);
+
+/// Records `error` for `pngme_last_error` and returns the failure code.
+fn fail(error: impl ToString) -> c_int {
+    let message = CString::new(error.to_string()).unwrap_or_else(|_| c"error message contained a NUL byte".into());
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    -1
+}
+
+/// Reads a chunk type from a C string, recording why when it isn't one.
+unsafe fn chunk_type(chunk_type: *const c_char) -> Option<ChunkType> {
+    if chunk_type.is_null() {
+        fail("The chunk type is NULL");
+        return None;
+    }
+    let parsed = CStr::from_ptr(chunk_type)
+        .to_str()
+        .map_err(|error| error.to_string())
+        .and_then(|chunk_type| ChunkType::from_str(chunk_type).map_err(|error| error.to_string()));
+    parsed.map_err(fail).ok()
+}
+
+/// The message of the last error on this thread, or NULL if nothing has
+/// failed yet. The string stays valid until the next failing call on the
+/// same thread.
+#[no_mangle]
+pub extern "C" fn pngme_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Parses the `len` bytes at `data` as a PNG. Returns NULL on failure.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_parse(data: *const u8, len: usize) -> *mut PngmePng {
+    if data.is_null() {
+        fail("The PNG data is NULL");
+        return ptr::null_mut();
+    }
+    match Png::try_from(std::slice::from_raw_parts(data, len)) {
+        Ok(png) => Box::into_raw(Box::new(PngmePng(png))),
+        Err(error) => {
+            fail(error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a PNG from `pngme_parse`. NULL is ignored.
+///
+/// # Safety
+///
+/// `png` must come from `pngme_parse` and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_free(png: *mut PngmePng) {
+    if !png.is_null() {
+        drop(Box::from_raw(png));
+    }
+}
+
+/// The number of chunks in the PNG, IHDR and IEND included.
+///
+/// # Safety
+///
+/// `png` must be a live PNG from `pngme_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_chunk_count(png: *const PngmePng) -> usize {
+    (*png).0.chunks().len()
+}
+
+/// Points `data` and `len` at the data of the first chunk of type
+/// `chunk_type`. The data belongs to the PNG and stays valid until it is
+/// changed or freed. Returns 0 on success and -1 if there is no such chunk.
+///
+/// # Safety
+///
+/// `png` must be a live PNG from `pngme_parse`, `chunk_type` a NUL-terminated
+/// string, and `data` and `len` writable.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_get_chunk(
+    png: *const PngmePng,
+    chunk_type: *const c_char,
+    data: *mut *const u8,
+    len: *mut usize
+) -> c_int {
+    let Some(chunk_type) = self::chunk_type(chunk_type) else {
+        return -1;
+    };
+    match (*png).0.chunk_by_type(&chunk_type.to_string()) {
+        Some(chunk) => {
+            *data = chunk.data().as_ptr();
+            *len = chunk.data().len();
+            0
+        },
+        None => fail(PngError::ChunkNotFound { chunk_type: chunk_type.to_string() })
+    }
+}
+
+/// Adds a chunk of type `chunk_type` holding a copy of the `len` bytes at
+/// `data`, before IEND. Returns 0 on success and -1 on failure.
+///
+/// # Safety
+///
+/// `png` must be a live PNG from `pngme_parse`, `chunk_type` a NUL-terminated
+/// string, and `data` point to `len` readable bytes (or be NULL if `len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn pngme_add_chunk(
+    png: *mut PngmePng,
+    chunk_type: *const c_char,
+    data: *const u8,
+    len: usize
+) -> c_int {
+    let Some(chunk_type) = self::chunk_type(chunk_type) else {
+        return -1;
+    };
+    let data = match (data.is_null(), len) {
+        (true, 0) => Vec::new(),
+        (true, _) => return fail("The chunk data is NULL"),
+        (false, _) => std::slice::from_raw_parts(data, len).to_vec()
+    };
+    match Chunk::try_new(chunk_type, data) {
+        Ok(chunk) => {
+            (*png).0.append_chunk(chunk);
+            0
+        },
+        Err(error) => fail(error)
+    }
+}
+
+/// Writes the PNG out to a new buffer, returned through `data` and `len`.
+/// The buffer must be freed with `pngme_bytes_free`. Returns 0.
+///
+/// # Safety
+///
+/// `png` must be a live PNG from `pngme_parse`, and `data` and `len`
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_serialize(png: *const PngmePng, data: *mut *mut u8, len: *mut usize) -> c_int {
+    let bytes = (*png).0.as_bytes().into_boxed_slice();
+    *len = bytes.len();
+    *data = Box::into_raw(bytes) as *mut u8;
+    0
+}
+
+/// Frees a buffer from `pngme_serialize`. NULL is ignored.
+///
+/// # Safety
+///
+/// `data` and `len` must be exactly as `pngme_serialize` returned them, and
+/// not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_bytes_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![])
+        ]).as_bytes()
+    }
+
+    #[test]
+    fn test_header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/pngme.h"));
+        assert!(
+            generated == include_str!("../include/pngme.h"),
+            "include/pngme.h is out of date; rebuild with PNGME_UPDATE_HEADER=1"
+        );
+    }
+
+    #[test]
+    fn test_ffi_round_trip() {
+        unsafe {
+            let bytes = png();
+            let png = pngme_parse(bytes.as_ptr(), bytes.len());
+            assert!(!png.is_null());
+            assert_eq!(pngme_add_chunk(png, c"ruSt".as_ptr(), b"hello".as_ptr(), 5), 0);
+            assert_eq!(pngme_chunk_count(png), 3);
+
+            let (mut data, mut len) = (ptr::null_mut(), 0);
+            assert_eq!(pngme_serialize(png, &mut data, &mut len), 0);
+            let reparsed = pngme_parse(data, len);
+            pngme_bytes_free(data, len);
+            pngme_free(png);
+
+            let (mut chunk, mut chunk_len) = (ptr::null(), 0);
+            assert_eq!(pngme_get_chunk(reparsed, c"ruSt".as_ptr(), &mut chunk, &mut chunk_len), 0);
+            assert_eq!(std::slice::from_raw_parts(chunk, chunk_len), b"hello");
+            pngme_free(reparsed);
+        }
+    }
+
+    #[test]
+    fn test_ffi_errors() {
+        unsafe {
+            assert!(pngme_parse(b"not a png".as_ptr(), 9).is_null());
+            assert_eq!(CStr::from_ptr(pngme_last_error()).to_str().unwrap(), "The given header doesn't match the PNG standard header");
+
+            let bytes = png();
+            let png = pngme_parse(bytes.as_ptr(), bytes.len());
+            let (mut data, mut len) = (ptr::null(), 0);
+            assert_eq!(pngme_get_chunk(png, c"ruSt".as_ptr(), &mut data, &mut len), -1);
+            assert_eq!(pngme_add_chunk(png, c"ru1t".as_ptr(), ptr::null(), 0), -1);
+            assert!(!CStr::from_ptr(pngme_last_error()).to_bytes().is_empty());
+            pngme_free(png);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use log::trace;
+
+use crate::chunk_type::ChunkType;
+use crate::error::GifError;
+
+/// Both GIF versions start with "GIF8", then "7a" or "9a".
+pub const SIGNATURE_LEN: usize = 6;
+
+/// Introduces an extension block.
+const EXTENSION: u8 = 0x21;
+/// Introduces an image descriptor.
+const IMAGE: u8 = 0x2C;
+/// Ends the stream.
+const TRAILER: u8 = 0x3B;
+/// The extension label of an application extension.
+const APPLICATION: u8 = 0xFF;
+/// The extension label of a comment extension.
+const COMMENT: u8 = 0xFE;
+
+/// The application identifier and authentication code of extensions holding
+/// a pngme payload.
+const PAYLOAD_APPLICATION: [u8;11] = *b"PNGMEPAY1.0";
+/// Data sub-blocks have a one-byte length.
+const MAX_SUB_BLOCK_LEN: usize = u8::MAX as usize;
+
+/// Whether `bytes` look like a GIF rather than a PNG.
+pub fn is_gif(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a")
+}
+
+/// One extension or image in the stream, kept byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// The extension label, or None for an image.
+    pub label: Option<u8>,
+    /// The whole block as it is in the file, introducer included.
+    pub bytes: Vec<u8>
+}
+
+impl Block {
+    /// The data of the block's sub-blocks, joined, if it is an application
+    /// extension for `application`.
+    fn application_data(&self, application: &[u8;11]) -> Option<Vec<u8>> {
+        let header = self.bytes.get(2..14)?;
+        if self.label != Some(APPLICATION) || header[0] != 11 || header[1..] != application[..] {
+            return None;
+        }
+        Some(sub_block_data(&self.bytes[14..]))
+    }
+}
+
+/// A payload and the block it is in.
+struct Stored {
+    position: usize,
+    chunk_type: [u8;4],
+    data: Vec<u8>
+}
+
+/// Joins well-formed data sub-blocks, up to their terminator.
+fn sub_block_data(mut bytes: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    while let Some((&len, rest)) = bytes.split_first() {
+        if len == 0 {
+            break;
+        }
+        data.extend_from_slice(&rest[..len as usize]);
+        bytes = &rest[len as usize..];
+    }
+    data
+}
+
+/// The length of the sub-blocks at `offset`, terminator included.
+fn sub_blocks_len(bytes: &[u8], offset: usize) -> Result<usize, GifError> {
+    let mut position = offset;
+    loop {
+        let len = *bytes.get(position).ok_or(GifError::Truncated { offset })? as usize;
+        position += 1 + len;
+        if len == 0 {
+            return Ok(position - offset);
+        }
+    }
+}
+
+/// A GIF split into its header, which holds the screen descriptor and global
+/// color table, and its extension and image blocks, which pngme can add to.
+/// Whatever follows the trailer is kept as it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gif {
+    header: Vec<u8>,
+    blocks: Vec<Block>,
+    trailing: Vec<u8>
+}
+
+impl Gif {
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Adds `data` under `chunk_type` in an application extension. It goes
+    /// after the leading extensions, so a NETSCAPE2.0 looping extension stays
+    /// first. A GIF87a becomes a GIF89a, the first version with extensions.
+    pub fn add_payload(&mut self, chunk_type: &ChunkType, data: &[u8]) {
+        let data = [&chunk_type.bytes()[..], data].concat();
+        let mut bytes = vec![EXTENSION, APPLICATION, PAYLOAD_APPLICATION.len() as u8];
+        bytes.extend_from_slice(&PAYLOAD_APPLICATION);
+        for sub_block in data.chunks(MAX_SUB_BLOCK_LEN) {
+            bytes.push(sub_block.len() as u8);
+            bytes.extend_from_slice(sub_block);
+        }
+        bytes.push(0);
+
+        let position = self
+            .blocks
+            .iter()
+            .position(|block| !matches!(block.label, Some(APPLICATION | COMMENT)))
+            .unwrap_or(self.blocks.len());
+        self.blocks.insert(position, Block { label: Some(APPLICATION), bytes });
+        self.header[3..SIGNATURE_LEN].copy_from_slice(b"89a");
+    }
+
+    /// The payloads stored under `chunk_type`, in the order they appear.
+    pub fn payloads(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, GifError> {
+        Ok(self.stored()?.into_iter().filter(|stored| stored.chunk_type == chunk_type.as_bytes()).map(|stored| stored.data).collect())
+    }
+
+    /// The chunk types payloads are stored under, in the order they first
+    /// appear.
+    pub fn payload_types(&self) -> Result<Vec<ChunkType>, GifError> {
+        let mut chunk_types: Vec<ChunkType> = Vec::new();
+        for stored in self.stored()? {
+            if let Ok(chunk_type) = ChunkType::try_from(stored.chunk_type) {
+                if !chunk_types.contains(&chunk_type) {
+                    chunk_types.push(chunk_type);
+                }
+            }
+        }
+        Ok(chunk_types)
+    }
+
+    /// Removes the `index`th payload stored under `chunk_type` and returns
+    /// it, or None if there are fewer payloads.
+    pub fn remove_payload(&mut self, chunk_type: &str, index: usize) -> Result<Option<Vec<u8>>, GifError> {
+        let found = self.stored()?.into_iter().filter(|stored| stored.chunk_type == chunk_type.as_bytes()).nth(index);
+        Ok(found.map(|stored| {
+            self.blocks.remove(stored.position);
+            stored.data
+        }))
+    }
+
+    /// Every payload, with the position of its block.
+    fn stored(&self) -> Result<Vec<Stored>, GifError> {
+        let mut stored = Vec::new();
+        for (position, block) in self.blocks.iter().enumerate() {
+            let Some(data) = block.application_data(&PAYLOAD_APPLICATION) else {
+                continue;
+            };
+            if data.len() < 4 {
+                return Err(GifError::BrokenPayload);
+            }
+            stored.push(Stored { position, chunk_type: [data[0], data[1], data[2], data[3]], data: data[4..].to_vec() });
+        }
+        Ok(stored)
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.clone();
+        for block in &self.blocks {
+            bytes.extend_from_slice(&block.bytes);
+        }
+        bytes.push(TRAILER);
+        bytes.extend_from_slice(&self.trailing);
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for Gif {
+    type Error = GifError;
+
+    fn try_from(bytes: &[u8]) -> Result<Gif, GifError> {
+        if !is_gif(bytes) {
+            return Err(GifError::InvalidSignature);
+        }
+
+        // The logical screen descriptor, then the global color table if its
+        // flag is set
+        let flags = *bytes.get(10).ok_or(GifError::Truncated { offset: SIGNATURE_LEN })?;
+        let mut offset = 13 + color_table_len(flags);
+        if bytes.len() < offset {
+            return Err(GifError::Truncated { offset: SIGNATURE_LEN });
+        }
+        let header = bytes[..offset].to_vec();
+
+        let mut blocks = Vec::new();
+        loop {
+            let start = offset;
+            let len = match bytes.get(offset) {
+                Some(&TRAILER) => return Ok(Gif { header, blocks, trailing: bytes[offset + 1..].to_vec() }),
+                Some(&EXTENSION) => 2 + sub_blocks_len(bytes, offset + 2)?,
+                Some(&IMAGE) => {
+                    // The descriptor, an optional local color table and the
+                    // LZW minimum code size, then the image data
+                    let flags = *bytes.get(offset + 9).ok_or(GifError::Truncated { offset })?;
+                    let data = offset + 11 + color_table_len(flags);
+                    data - offset + sub_blocks_len(bytes, data)?
+                },
+                Some(&found) => return Err(GifError::UnknownBlock { offset, found }),
+                None => return Err(GifError::Truncated { offset })
+            };
+            let block = bytes.get(start..start + len).ok_or(GifError::Truncated { offset })?;
+            trace!("block {:#04X} at offset {}, {} bytes", block[0], start, len);
+            let label = match block[0] {
+                EXTENSION => Some(block[1]),
+                _ => None
+            };
+            blocks.push(Block { label, bytes: block.to_vec() });
+            offset += len;
+        }
+    }
+}
+
+/// The size of the color table described by a screen or image descriptor's
+/// packed fields.
+fn color_table_len(flags: u8) -> usize {
+    match flags & 0x80 {
+        0 => 0,
+        _ => 3 << ((flags & 0x07) + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// A 1x1 GIF87a with a two-color global table and one image.
+    fn gif() -> Vec<u8> {
+        let mut bytes = b"GIF87a".to_vec();
+        bytes.extend_from_slice(&[1, 0, 1, 0, 0x80, 0, 0]);
+        bytes.extend_from_slice(&[0, 0, 0, 255, 255, 255]);
+        bytes.extend_from_slice(&[IMAGE, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x44, 0x01, 0]);
+        bytes.push(TRAILER);
+        bytes
+    }
+
+    #[test]
+    fn test_gif_round_trip() {
+        let bytes = gif();
+        assert!(is_gif(&bytes));
+        let parsed = Gif::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.blocks().len(), 1);
+        assert_eq!(parsed.blocks()[0].label, None);
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_gif_payloads() {
+        let mut parsed = Gif::try_from(gif().as_slice()).unwrap();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let large: Vec<u8> = (0..1000u32).map(|byte| byte as u8).collect();
+        parsed.add_payload(&chunk_type, b"hello");
+        parsed.add_payload(&ChunkType::from_str("teSt").unwrap(), b"other");
+        parsed.add_payload(&chunk_type, &large);
+
+        let bytes = parsed.as_bytes();
+        assert!(bytes.starts_with(b"GIF89a"));
+        let reparsed = Gif::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(reparsed.blocks().len(), 4);
+        assert_eq!(reparsed.blocks()[3].label, None);
+        assert_eq!(reparsed.payloads("ruSt").unwrap(), vec![b"hello".to_vec(), large]);
+        assert_eq!(reparsed.payloads("teSt").unwrap(), vec![b"other".to_vec()]);
+        assert!(reparsed.payloads("soMe").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_gif_errors() {
+        assert!(matches!(Gif::try_from(&b"\x89PNG"[..]), Err(GifError::InvalidSignature)));
+        assert!(matches!(Gif::try_from(&gif()[..20]), Err(GifError::Truncated { .. })));
+
+        let mut bytes = gif();
+        bytes[19] = 0x42;
+        assert!(matches!(Gif::try_from(bytes.as_slice()), Err(GifError::UnknownBlock { offset: 19, found: 0x42 })));
+    }
+}
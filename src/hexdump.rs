@@ -0,0 +1,59 @@
+use std::fmt::Write;
+
+const BYTES_PER_LINE: usize = 16;
+
+pub fn format_hexdump(data: &[u8]) -> String {
+    let mut dump = String::new();
+
+    for (line, bytes) in data.chunks(BYTES_PER_LINE).enumerate() {
+        write!(dump, "{:08x} ", line * BYTES_PER_LINE).unwrap();
+
+        for column in 0..BYTES_PER_LINE {
+            if column % 8 == 0 {
+                dump.push(' ');
+            }
+            match bytes.get(column) {
+                Some(byte) => write!(dump, "{:02x} ", byte).unwrap(),
+                None => dump.push_str("   ")
+            }
+        }
+
+        let ascii: String = bytes
+            .iter()
+            .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+            .collect();
+        writeln!(dump, " |{}|", ascii).unwrap();
+    }
+
+    writeln!(dump, "{:08x}", data.len()).unwrap();
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump_full_line() {
+        let data = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+        let expected = "\
+00000000  89 50 4e 47 0d 0a 1a 0a  00 00 00 0d 49 48 44 52  |.PNG........IHDR|
+00000010
+";
+        assert_eq!(format_hexdump(data), expected);
+    }
+
+    #[test]
+    fn test_hexdump_partial_line() {
+        let expected = "\
+00000000  48 69 20 74 68 65 72 65  21                       |Hi there!|
+00000009
+";
+        assert_eq!(format_hexdump(b"Hi there!"), expected);
+    }
+
+    #[test]
+    fn test_hexdump_empty() {
+        assert_eq!(format_hexdump(&[]), "00000000\n");
+    }
+}
@@ -0,0 +1,160 @@
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::IccError;
+use crate::text;
+
+/// The chunk type of an embedded ICC profile.
+pub const ICCP: &str = "iCCP";
+/// Every ICC profile starts with a 128-byte header.
+pub const HEADER_LEN: usize = 128;
+
+/// Compression method 0, zlib-wrapped deflate, is the only one iCCP allows.
+const DEFLATE: u8 = 0;
+/// The profile file signature at offset 36 of the header.
+const SIGNATURE: &[u8;4] = b"acsp";
+
+/// An ICC profile from an iCCP chunk, laid out as the profile name, which
+/// follows the same rules as text keywords, a null separator, the compression
+/// method and the zlib-compressed profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IccProfile {
+    pub name: String,
+    pub profile: Vec<u8>
+}
+
+/// The fields of an ICC profile header that say what the profile is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IccHeader {
+    /// The profile length the header declares.
+    pub size: u32,
+    pub major_version: u8,
+    pub minor_version: u8,
+    /// e.g. `mntr` for displays or `prtr` for printers.
+    pub device_class: String,
+    /// e.g. `RGB ` or `GRAY`.
+    pub color_space: String
+}
+
+impl IccProfile {
+    pub fn new(name: &str, profile: Vec<u8>) -> Result<IccProfile, IccError> {
+        text::validate_keyword(name)?;
+        Ok(IccProfile { name: name.to_string(), profile })
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<IccProfile, IccError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != ICCP {
+            return Err(IccError::NotIccp { chunk_type });
+        }
+        let data = chunk.data();
+        let separator = data.iter().position(|&byte| byte == 0).ok_or(IccError::MissingSeparator)?;
+        let name = text::from_latin1(&data[..separator]);
+        text::validate_keyword(&name)?;
+
+        let (&method, compressed) = data[separator + 1..].split_first().ok_or(IccError::MissingCompressionMethod)?;
+        if method != DEFLATE {
+            return Err(IccError::UnknownCompressionMethod { method });
+        }
+        let mut profile = Vec::new();
+        ZlibDecoder::new(compressed).read_to_end(&mut profile)?;
+        Ok(IccProfile { name, profile })
+    }
+
+    pub fn to_chunk(&self) -> Result<Chunk, IccError> {
+        let name = text::to_latin1(&self.name)?;
+        let mut encoder = ZlibEncoder::new([&name[..], &[0, DEFLATE]].concat(), Compression::best());
+        encoder.write_all(&self.profile)?;
+        Ok(Chunk::try_new(ChunkType::from_str(ICCP).unwrap(), encoder.finish()?)?)
+    }
+
+    /// Reads the profile's header, or `None` if the data isn't an ICC profile.
+    pub fn header(&self) -> Option<IccHeader> {
+        let header = self.profile.get(..HEADER_LEN)?;
+        if &header[36..40] != SIGNATURE {
+            return None;
+        }
+        let tag = |range: std::ops::Range<usize>| String::from_utf8_lossy(&header[range]).into_owned();
+        Some(IccHeader {
+            size: u32::from_be_bytes(header[0..4].try_into().unwrap()),
+            major_version: header[8],
+            minor_version: header[9] >> 4,
+            device_class: tag(12..16),
+            color_space: tag(16..20)
+        })
+    }
+}
+
+/// Shown as e.g. `"sRGB IEC61966-2.1": ICC 2.1 mntr RGB profile, 3144 bytes`.
+impl fmt::Display for IccProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.header() {
+            Some(header) => write!(
+                f,
+                "{:?}: ICC {}.{} {} {} profile, {} bytes",
+                self.name,
+                header.major_version,
+                header.minor_version,
+                header.device_class.trim_end(),
+                header.color_space.trim_end(),
+                self.profile.len()
+            ),
+            None => write!(f, "{:?}: {} bytes, not an ICC profile", self.name, self.profile.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> Vec<u8> {
+        let mut profile = vec![0; 200];
+        profile[..4].copy_from_slice(&200u32.to_be_bytes());
+        profile[8] = 4;
+        profile[9] = 0x30;
+        profile[12..20].copy_from_slice(b"mntrRGB ");
+        profile[36..40].copy_from_slice(b"acsp");
+        profile
+    }
+
+    #[test]
+    fn test_icc_profile_round_trip() {
+        let icc = IccProfile::new("Display P3", profile()).unwrap();
+        let chunk = icc.to_chunk().unwrap();
+
+        assert_eq!(chunk.chunk_type().to_string(), "iCCP");
+        assert!(chunk.data().starts_with(b"Display P3\0\0"));
+        assert!(chunk.data().len() < 100);
+        assert_eq!(IccProfile::from_chunk(&chunk).unwrap(), icc);
+    }
+
+    #[test]
+    fn test_icc_header() {
+        let icc = IccProfile::new("Display P3", profile()).unwrap();
+        let header = icc.header().unwrap();
+
+        assert_eq!((header.size, header.major_version, header.minor_version), (200, 4, 3));
+        assert_eq!((header.device_class.as_str(), header.color_space.as_str()), ("mntr", "RGB "));
+        assert_eq!(icc.to_string(), "\"Display P3\": ICC 4.3 mntr RGB profile, 200 bytes");
+        assert_eq!(IccProfile::new("Junk", vec![1; 200]).unwrap().header(), None);
+    }
+
+    #[test]
+    fn test_invalid_iccp() {
+        let chunk = |data: &[u8]| Chunk::new(ChunkType::from_str("iCCP").unwrap(), data.to_vec());
+
+        assert!(matches!(IccProfile::from_chunk(&chunk(b"name")), Err(IccError::MissingSeparator)));
+        assert!(matches!(IccProfile::from_chunk(&chunk(b"name\0")), Err(IccError::MissingCompressionMethod)));
+        assert!(matches!(
+            IccProfile::from_chunk(&chunk(b"name\0\x01")),
+            Err(IccError::UnknownCompressionMethod { method: 1 })
+        ));
+        assert!(matches!(IccProfile::from_chunk(&chunk(b" name\0\0")), Err(IccError::InvalidName(_))));
+        assert!(IccProfile::new("", profile()).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::IhdrError;
+
+/// The chunk type of the image header.
+pub const IHDR: &str = "IHDR";
+/// Width and height as big-endian u32s, then five single-byte fields.
+pub const DATA_LEN: usize = 13;
+/// Widths and heights are limited to 2^31 - 1, like chunk lengths.
+pub const MAX_DIMENSION: u32 = (1 << 31) - 1;
+
+/// How pixels are stored, which decides the bit depths allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    Grayscale,
+    Rgb,
+    /// Palette indices into PLTE.
+    Indexed,
+    GrayscaleAlpha,
+    Rgba
+}
+
+impl ColorType {
+    pub fn from_byte(byte: u8) -> Result<ColorType, IhdrError> {
+        match byte {
+            0 => Ok(ColorType::Grayscale),
+            2 => Ok(ColorType::Rgb),
+            3 => Ok(ColorType::Indexed),
+            4 => Ok(ColorType::GrayscaleAlpha),
+            6 => Ok(ColorType::Rgba),
+            color_type => Err(IhdrError::UnknownColorType { color_type })
+        }
+    }
+
+    pub fn to_byte(self) -> u8 {
+        match self {
+            ColorType::Grayscale => 0,
+            ColorType::Rgb => 2,
+            ColorType::Indexed => 3,
+            ColorType::GrayscaleAlpha => 4,
+            ColorType::Rgba => 6
+        }
+    }
+
+    pub fn bit_depths(self) -> &'static [u8] {
+        match self {
+            ColorType::Grayscale => &[1, 2, 4, 8, 16],
+            ColorType::Indexed => &[1, 2, 4, 8],
+            ColorType::Rgb | ColorType::GrayscaleAlpha | ColorType::Rgba => &[8, 16]
+        }
+    }
+
+    /// Samples per pixel.
+    pub fn channels(self) -> u8 {
+        match self {
+            ColorType::Grayscale | ColorType::Indexed => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4
+        }
+    }
+}
+
+impl fmt::Display for ColorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColorType::Grayscale => "grayscale",
+            ColorType::Rgb => "RGB",
+            ColorType::Indexed => "indexed",
+            ColorType::GrayscaleAlpha => "grayscale + alpha",
+            ColorType::Rgba => "RGBA"
+        })
+    }
+}
+
+/// The image properties from IHDR. Compression and filter method 0 are the
+/// only ones defined, so they aren't stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ihdr {
+    pub width: u32,
+    pub height: u32,
+    /// Bits per sample, or per palette index.
+    pub bit_depth: u8,
+    pub color_type: ColorType,
+    /// Adam7 interlacing.
+    pub interlaced: bool
+}
+
+impl Ihdr {
+    pub fn new(width: u32, height: u32, bit_depth: u8, color_type: ColorType) -> Result<Ihdr, IhdrError> {
+        for (dimension, value) in [("width", width), ("height", height)] {
+            if value == 0 || value > MAX_DIMENSION {
+                return Err(IhdrError::InvalidDimension { dimension, value });
+            }
+        }
+        if !color_type.bit_depths().contains(&bit_depth) {
+            return Err(IhdrError::InvalidBitDepth { bit_depth, color_type });
+        }
+        Ok(Ihdr { width, height, bit_depth, color_type, interlaced: false })
+    }
+
+    /// Reads IHDR's data, checking every field against the specification.
+    pub fn parse(data: &[u8]) -> Result<Ihdr, IhdrError> {
+        let data: &[u8;DATA_LEN] = data.try_into().map_err(|_| IhdrError::WrongLength { len: data.len() })?;
+        let [compression_method, filter_method, interlace_method] = [data[10], data[11], data[12]];
+        if compression_method != 0 {
+            return Err(IhdrError::UnknownCompressionMethod { method: compression_method });
+        }
+        if filter_method != 0 {
+            return Err(IhdrError::UnknownFilterMethod { method: filter_method });
+        }
+        let interlaced = match interlace_method {
+            0 => false,
+            1 => true,
+            method => return Err(IhdrError::UnknownInterlaceMethod { method })
+        };
+        let width = u32::from_be_bytes(data[0..4].try_into().unwrap());
+        let height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+        let ihdr = Ihdr::new(width, height, data[8], ColorType::from_byte(data[9])?)?;
+        Ok(Ihdr { interlaced, ..ihdr })
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Ihdr, IhdrError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != IHDR {
+            return Err(IhdrError::NotIhdr { chunk_type });
+        }
+        Ihdr::parse(chunk.data())
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [
+            &self.width.to_be_bytes()[..],
+            &self.height.to_be_bytes(),
+            &[self.bit_depth, self.color_type.to_byte(), 0, 0, self.interlaced as u8]
+        ].concat();
+        Chunk::new(ChunkType::from_str(IHDR).unwrap(), data)
+    }
+
+    pub fn bits_per_pixel(&self) -> u8 {
+        self.bit_depth * self.color_type.channels()
+    }
+
+    /// The largest sample value the bit depth can hold.
+    pub fn max_sample(&self) -> u16 {
+        ((1u32 << self.bit_depth) - 1) as u16
+    }
+}
+
+/// Shown as e.g. `640x480, 8-bit RGBA, Adam7 interlaced`.
+impl fmt::Display for Ihdr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}, {}-bit {}", self.width, self.height, self.bit_depth, self.color_type)?;
+        if self.interlaced {
+            write!(f, ", Adam7 interlaced")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ihdr_round_trip() {
+        let ihdr = Ihdr { interlaced: true, ..Ihdr::new(640, 480, 8, ColorType::Rgba).unwrap() };
+        let chunk = ihdr.to_chunk();
+
+        assert_eq!(chunk.chunk_type().to_string(), "IHDR");
+        assert_eq!(chunk.data(), [0, 0, 2, 128, 0, 0, 1, 224, 8, 6, 0, 0, 1]);
+        assert_eq!(Ihdr::from_chunk(&chunk).unwrap(), ihdr);
+        assert_eq!(ihdr.bits_per_pixel(), 32);
+        assert_eq!(ihdr.max_sample(), 255);
+        assert_eq!(ihdr.to_string(), "640x480, 8-bit RGBA, Adam7 interlaced");
+    }
+
+    #[test]
+    fn test_bit_depth_must_suit_color_type() {
+        assert!(Ihdr::new(1, 1, 16, ColorType::Grayscale).is_ok());
+        assert!(Ihdr::new(1, 1, 4, ColorType::Indexed).is_ok());
+        assert!(matches!(
+            Ihdr::new(1, 1, 16, ColorType::Indexed),
+            Err(IhdrError::InvalidBitDepth { bit_depth: 16, color_type: ColorType::Indexed })
+        ));
+        assert!(Ihdr::new(1, 1, 4, ColorType::Rgb).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_fields() {
+        let parse = |patch: &[(usize, u8)]| {
+            let mut data = [0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0];
+            for &(index, value) in patch {
+                data[index] = value;
+            }
+            Ihdr::parse(&data)
+        };
+
+        assert!(parse(&[]).is_ok());
+        assert!(matches!(parse(&[(3, 0)]), Err(IhdrError::InvalidDimension { dimension: "width", value: 0 })));
+        assert!(matches!(parse(&[(4, 0x80)]), Err(IhdrError::InvalidDimension { dimension: "height", .. })));
+        assert!(matches!(parse(&[(9, 1)]), Err(IhdrError::UnknownColorType { color_type: 1 })));
+        assert!(matches!(parse(&[(10, 1)]), Err(IhdrError::UnknownCompressionMethod { method: 1 })));
+        assert!(matches!(parse(&[(11, 1)]), Err(IhdrError::UnknownFilterMethod { method: 1 })));
+        assert!(matches!(parse(&[(12, 2)]), Err(IhdrError::UnknownInterlaceMethod { method: 2 })));
+        assert!(matches!(Ihdr::parse(&[0; 12]), Err(IhdrError::WrongLength { len: 12 })));
+    }
+}
@@ -0,0 +1,235 @@
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::ImageDataError;
+use crate::ihdr::Ihdr;
+use crate::png::Png;
+
+/// The chunk type of the compressed image data.
+pub const IDAT: &str = "IDAT";
+
+const FILTER_NONE: u8 = 0;
+const FILTER_SUB: u8 = 1;
+const FILTER_UP: u8 = 2;
+const FILTER_AVERAGE: u8 = 3;
+const FILTER_PAETH: u8 = 4;
+
+/// The pixels of a non-interlaced image, inflated from IDAT and unfiltered,
+/// along with the filter type of each row so `encode` can filter them the
+/// same way again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageData {
+    pub ihdr: Ihdr,
+    /// One filter type per row.
+    pub filters: Vec<u8>,
+    /// The rows one after another, `stride` bytes each, without filter bytes.
+    pub pixels: Vec<u8>
+}
+
+impl ImageData {
+    /// Inflates and unfilters the concatenated data of the image's IDAT chunks.
+    pub fn from_png(png: &Png) -> Result<ImageData, ImageDataError> {
+        let ihdr = png.ihdr()?;
+        let compressed: Vec<u8> = png
+            .chunks_by_type(IDAT)
+            .iter()
+            .flat_map(|chunk| chunk.data())
+            .copied()
+            .collect();
+        if compressed.is_empty() {
+            return Err(ImageDataError::Missing);
+        }
+        ImageData::decode(ihdr, &compressed)
+    }
+
+    pub fn decode(ihdr: Ihdr, compressed: &[u8]) -> Result<ImageData, ImageDataError> {
+        if ihdr.interlaced {
+            return Err(ImageDataError::Interlaced);
+        }
+        // A row and its filter byte for every line, which for the largest
+        // dimensions IHDR allows doesn't even fit in a u64
+        let expected = (ihdr.width as u64 * ihdr.bits_per_pixel() as u64)
+            .div_ceil(8)
+            .checked_add(1)
+            .and_then(|row| row.checked_mul(ihdr.height as u64))
+            .filter(|&expected| expected < usize::MAX as u64)
+            .ok_or(ImageDataError::TooLarge { width: ihdr.width, height: ihdr.height })?;
+        let stride = stride(&ihdr);
+
+        // Reading one byte past the expected size tells a too-long stream apart
+        let mut filtered = Vec::new();
+        ZlibDecoder::new(compressed).take(expected + 1).read_to_end(&mut filtered)?;
+        if filtered.len() as u64 != expected {
+            return Err(ImageDataError::WrongSize { expected, len: filtered.len() });
+        }
+
+        let bytes_per_pixel = bytes_per_pixel(&ihdr);
+        let mut filters = Vec::with_capacity(ihdr.height as usize);
+        // Both are no larger than the data just inflated
+        let mut pixels = vec![0; filtered.len() - ihdr.height as usize];
+        for (row, line) in filtered.chunks_exact(stride + 1).enumerate() {
+            let (previous, current) = pixels.split_at_mut(row * stride);
+            let previous = previous.get(previous.len().saturating_sub(stride)..).filter(|_| row > 0);
+            let current = &mut current[..stride];
+            unfilter(line[0], &line[1..], previous, current, bytes_per_pixel)
+                .ok_or(ImageDataError::UnknownFilter { filter: line[0], row })?;
+            filters.push(line[0]);
+        }
+        Ok(ImageData { ihdr, filters, pixels })
+    }
+
+    /// Filters every row with the filter type it had and deflates the result.
+    pub fn encode(&self) -> Result<Vec<u8>, ImageDataError> {
+        let stride = self.stride();
+        let bytes_per_pixel = bytes_per_pixel(&self.ihdr);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        let mut line = vec![0; stride];
+        for (row, current) in self.pixels.chunks_exact(stride).enumerate() {
+            let previous = row.checked_sub(1).map(|previous| &self.pixels[previous * stride..row * stride]);
+            filter(self.filters[row], current, previous, &mut line, bytes_per_pixel);
+            encoder.write_all(&[self.filters[row]])?;
+            encoder.write_all(&line)?;
+        }
+        Ok(encoder.finish()?)
+    }
+
+    /// `encode`s the image into IDAT chunks of at most `max_len` bytes each.
+    pub fn to_chunks(&self, max_len: usize) -> Result<Vec<Chunk>, ImageDataError> {
+        self.encode()?
+            .chunks(max_len.max(1))
+            .map(|data| Ok(Chunk::try_new(ChunkType::from_str(IDAT).unwrap(), data.to_vec())?))
+            .collect()
+    }
+
+    /// Bytes per row, without the filter byte.
+    pub fn stride(&self) -> usize {
+        stride(&self.ihdr)
+    }
+}
+
+fn stride(ihdr: &Ihdr) -> usize {
+    (ihdr.width as usize * ihdr.bits_per_pixel() as usize).div_ceil(8)
+}
+
+/// The distance filters look back, a whole pixel or at least one byte.
+fn bytes_per_pixel(ihdr: &Ihdr) -> usize {
+    (ihdr.bits_per_pixel() as usize).div_ceil(8)
+}
+
+fn paeth(left: u8, above: u8, upper_left: u8) -> u8 {
+    let estimate = left as i16 + above as i16 - upper_left as i16;
+    let distance = |value: u8| (estimate - value as i16).abs();
+    if distance(left) <= distance(above) && distance(left) <= distance(upper_left) {
+        left
+    } else if distance(above) <= distance(upper_left) {
+        above
+    } else {
+        upper_left
+    }
+}
+
+/// The filter's prediction for byte `index` of a row, from the bytes
+/// already reconstructed. The row before the first counts as all zeros.
+fn predict(filter_type: u8, current: &[u8], previous: Option<&[u8]>, index: usize, bytes_per_pixel: usize) -> Option<u8> {
+    let left = index.checked_sub(bytes_per_pixel).map_or(0, |left| current[left]);
+    let above = previous.map_or(0, |previous| previous[index]);
+    let upper_left = match (previous, index.checked_sub(bytes_per_pixel)) {
+        (Some(previous), Some(left)) => previous[left],
+        _ => 0
+    };
+    match filter_type {
+        FILTER_NONE => Some(0),
+        FILTER_SUB => Some(left),
+        FILTER_UP => Some(above),
+        FILTER_AVERAGE => Some(((left as u16 + above as u16) / 2) as u8),
+        FILTER_PAETH => Some(paeth(left, above, upper_left)),
+        _ => None
+    }
+}
+
+fn unfilter(filter_type: u8, line: &[u8], previous: Option<&[u8]>, current: &mut [u8], bytes_per_pixel: usize) -> Option<()> {
+    for index in 0..line.len() {
+        let prediction = predict(filter_type, current, previous, index, bytes_per_pixel)?;
+        current[index] = line[index].wrapping_add(prediction);
+    }
+    Some(())
+}
+
+fn filter(filter_type: u8, current: &[u8], previous: Option<&[u8]>, line: &mut [u8], bytes_per_pixel: usize) {
+    for index in 0..current.len() {
+        // Filter types were checked when the rows were unfiltered
+        let prediction = predict(filter_type, current, previous, index, bytes_per_pixel).unwrap_or(0);
+        line[index] = current[index].wrapping_sub(prediction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ihdr::ColorType;
+
+    /// A 3x5 RGB image whose rows use each filter type in turn.
+    fn image() -> ImageData {
+        ImageData {
+            ihdr: Ihdr::new(3, 5, 8, ColorType::Rgb).unwrap(),
+            filters: vec![FILTER_NONE, FILTER_SUB, FILTER_UP, FILTER_AVERAGE, FILTER_PAETH],
+            pixels: (0..45).map(|byte| (byte * 37 % 251) as u8).collect()
+        }
+    }
+
+    #[test]
+    fn test_image_data_round_trip() {
+        let image = image();
+        let compressed = image.encode().unwrap();
+
+        assert_eq!(ImageData::decode(image.ihdr, &compressed).unwrap(), image);
+        assert_eq!(image.stride(), 9);
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut line = [0; 4];
+        filter(FILTER_SUB, &[10, 20, 30, 40], None, &mut line, 2);
+        assert_eq!(line, [10, 20, 20, 20]);
+        filter(FILTER_AVERAGE, &[10, 20, 30, 40], Some(&[4, 6, 8, 10]), &mut line, 2);
+        assert_eq!(line, [8, 17, 21, 25]);
+        assert_eq!(paeth(10, 20, 15), 15);
+        assert_eq!(paeth(10, 20, 5), 20);
+    }
+
+    #[test]
+    fn test_huge_dimensions() {
+        let compressed = image().encode().unwrap();
+        let huge = Ihdr::new(0x7fff_ffff, 0x7fff_ffff, 16, ColorType::Rgba).unwrap();
+        assert!(matches!(ImageData::decode(huge, &compressed), Err(ImageDataError::TooLarge { .. })));
+        let large = Ihdr::new(0x7fff_ffff, 0x7fff, 16, ColorType::Rgba).unwrap();
+        assert!(matches!(ImageData::decode(large, &compressed), Err(ImageDataError::WrongSize { .. })));
+    }
+
+    #[test]
+    fn test_invalid_image_data() {
+        let image = image();
+        let mut interlaced = image.ihdr;
+        interlaced.interlaced = true;
+        assert!(matches!(ImageData::decode(interlaced, &image.encode().unwrap()), Err(ImageDataError::Interlaced)));
+
+        let short = ImageData {
+            ihdr: Ihdr::new(3, 4, 8, ColorType::Rgb).unwrap(),
+            filters: image.filters[..4].to_vec(),
+            pixels: image.pixels[..36].to_vec()
+        };
+        assert!(matches!(
+            ImageData::decode(image.ihdr, &short.encode().unwrap()),
+            Err(ImageDataError::WrongSize { expected: 50, len: 40 })
+        ));
+
+        let unknown = ImageData { filters: vec![5; 5], ..image.clone() };
+        assert!(matches!(
+            ImageData::decode(image.ihdr, &unknown.encode().unwrap()),
+            Err(ImageDataError::UnknownFilter { filter: 5, row: 0 })
+        ));
+    }
+}
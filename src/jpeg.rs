@@ -0,0 +1,296 @@
+use std::ops::Range;
+
+use log::trace;
+
+use crate::chunk_type::ChunkType;
+use crate::error::JpegError;
+
+/// Every JPEG starts with an SOI marker followed by the next marker's 0xFF.
+pub const SIGNATURE: [u8;3] = [0xFF, 0xD8, 0xFF];
+
+/// Start of image.
+const SOI: u8 = 0xD8;
+/// Start of scan; the entropy-coded data follows, so parsing stops here.
+const SOS: u8 = 0xDA;
+/// End of image.
+const EOI: u8 = 0xD9;
+/// A comment.
+pub const COM: u8 = 0xFE;
+/// The last application segment, which no common format claims.
+pub const APP15: u8 = 0xEF;
+
+/// Marks a segment holding part of a pngme payload.
+const PAYLOAD_MAGIC: [u8;4] = *b"PMEj";
+/// Magic, chunk type, part number and part count.
+const PAYLOAD_HEADER_LEN: usize = 12;
+/// A segment's length field counts itself and is 16 bits.
+const MAX_SEGMENT_DATA: usize = u16::MAX as usize - 2;
+const MAX_PART_LEN: usize = MAX_SEGMENT_DATA - PAYLOAD_HEADER_LEN;
+
+/// Whether `bytes` look like a JPEG rather than a PNG.
+pub fn is_jpeg(bytes: &[u8]) -> bool {
+    bytes.starts_with(&SIGNATURE)
+}
+
+/// One marker segment before the image data, without its 0xFF, marker and
+/// length bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub marker: u8,
+    pub data: Vec<u8>
+}
+
+impl Segment {
+    /// Markers that stand alone, without a length or data.
+    fn is_standalone(marker: u8) -> bool {
+        matches!(marker, 0x01 | 0xD0..=0xD7 | SOI | EOI)
+    }
+}
+
+/// A JPEG split into the segments before its first scan, which pngme can
+/// add to, and everything from the scan on, which is kept byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jpeg {
+    segments: Vec<Segment>,
+    scan: Vec<u8>,
+    payload_marker: u8
+}
+
+impl Jpeg {
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The marker payloads go into when added through `Container`, `APP15`
+    /// unless changed.
+    pub fn payload_marker(&self) -> u8 {
+        self.payload_marker
+    }
+
+    pub fn set_payload_marker(&mut self, marker: u8) {
+        self.payload_marker = marker;
+    }
+
+    /// Adds `data` under `chunk_type` in segments of type `marker`, `COM` or
+    /// `APP15`, split into parts as a segment holds at most 64 KiB. They go
+    /// after the leading application and comment segments, so JFIF and EXIF
+    /// headers stay first.
+    pub fn add_payload(&mut self, chunk_type: &ChunkType, data: &[u8], marker: u8) -> Result<(), JpegError> {
+        let parts: Vec<&[u8]> = match data.is_empty() {
+            true => vec![data],
+            false => data.chunks(MAX_PART_LEN).collect()
+        };
+        let count = u16::try_from(parts.len()).map_err(|_| JpegError::PayloadTooLarge { len: data.len() })?;
+
+        let position = self
+            .segments
+            .iter()
+            .position(|segment| !matches!(segment.marker, 0xE0..=0xEF | COM))
+            .unwrap_or(self.segments.len());
+        let segments = parts.into_iter().enumerate().map(|(part, data)| Segment {
+            marker,
+            data: [&PAYLOAD_MAGIC[..], &chunk_type.bytes(), &(part as u16).to_be_bytes(), &count.to_be_bytes(), data].concat()
+        });
+        self.segments.splice(position..position, segments);
+        Ok(())
+    }
+
+    /// The payloads stored under `chunk_type`, in the order they appear.
+    pub fn payloads(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, JpegError> {
+        Ok(self.stored(chunk_type)?.into_iter().map(|stored| stored.data).collect())
+    }
+
+    /// The chunk types payloads are stored under, in the order they first
+    /// appear.
+    pub fn payload_types(&self) -> Vec<ChunkType> {
+        let mut chunk_types: Vec<ChunkType> = Vec::new();
+        for segment in &self.segments {
+            let Some(chunk_type) = payload_type(segment) else {
+                continue;
+            };
+            if !chunk_types.contains(&chunk_type) {
+                chunk_types.push(chunk_type);
+            }
+        }
+        chunk_types
+    }
+
+    /// Removes the `index`th payload stored under `chunk_type` and returns
+    /// it, or None if there are fewer payloads.
+    pub fn remove_payload(&mut self, chunk_type: &str, index: usize) -> Result<Option<Vec<u8>>, JpegError> {
+        let Some(stored) = self.stored(chunk_type)?.into_iter().nth(index) else {
+            return Ok(None);
+        };
+        // The parts are contiguous, or they wouldn't have been gathered
+        self.segments.drain(stored.segments);
+        Ok(Some(stored.data))
+    }
+
+    /// The payloads stored under `chunk_type`, with the segments they take
+    /// up.
+    fn stored(&self, chunk_type: &str) -> Result<Vec<Stored>, JpegError> {
+        let broken = || JpegError::BrokenPayload { chunk_type: chunk_type.to_string() };
+        let mut payloads = Vec::new();
+        // The payload being gathered, with where it starts and the parts it
+        // still lacks
+        let mut current: Option<(usize, Vec<u8>, u16, u16)> = None;
+
+        for (position, segment) in self.segments.iter().enumerate() {
+            if payload_type(segment).is_none_or(|found| found.bytes() != *chunk_type.as_bytes()) {
+                continue;
+            }
+
+            let data = &segment.data;
+            let part = u16::from_be_bytes([data[8], data[9]]);
+            let count = u16::from_be_bytes([data[10], data[11]]);
+            let (start, mut payload, next, expected) = match current.take() {
+                None if part == 0 && count > 0 => (position, Vec::new(), 0, count),
+                Some((start, payload, next, expected)) if part == next && count == expected && position == start + next as usize => {
+                    (start, payload, next, expected)
+                },
+                _ => return Err(broken())
+            };
+            payload.extend_from_slice(&data[PAYLOAD_HEADER_LEN..]);
+            match next + 1 == expected {
+                true => payloads.push(Stored { segments: start..position + 1, data: payload }),
+                false => current = Some((start, payload, next + 1, expected))
+            }
+        }
+
+        match current {
+            Some(_) => Err(broken()),
+            None => Ok(payloads)
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0xFF, SOI];
+        for segment in &self.segments {
+            bytes.extend_from_slice(&[0xFF, segment.marker]);
+            if !Segment::is_standalone(segment.marker) {
+                bytes.extend_from_slice(&(segment.data.len() as u16 + 2).to_be_bytes());
+                bytes.extend_from_slice(&segment.data);
+            }
+        }
+        bytes.extend_from_slice(&self.scan);
+        bytes
+    }
+}
+
+/// A payload gathered from its parts.
+struct Stored {
+    segments: Range<usize>,
+    data: Vec<u8>
+}
+
+/// The chunk type of the payload part in `segment`, if it holds one.
+fn payload_type(segment: &Segment) -> Option<ChunkType> {
+    let data = &segment.data;
+    if !matches!(segment.marker, APP15 | COM) || data.len() < PAYLOAD_HEADER_LEN || !data.starts_with(&PAYLOAD_MAGIC) {
+        return None;
+    }
+    ChunkType::try_from([data[4], data[5], data[6], data[7]]).ok()
+}
+
+impl TryFrom<&[u8]> for Jpeg {
+    type Error = JpegError;
+
+    fn try_from(bytes: &[u8]) -> Result<Jpeg, JpegError> {
+        if !bytes.starts_with(&[0xFF, SOI]) {
+            return Err(JpegError::InvalidSignature);
+        }
+
+        let mut segments = Vec::new();
+        let mut offset = 2;
+        loop {
+            let start = offset;
+            if bytes.get(offset) != Some(&0xFF) {
+                return match bytes.get(offset) {
+                    Some(&found) => Err(JpegError::ExpectedMarker { offset, found }),
+                    None => Err(JpegError::Truncated { offset })
+                };
+            }
+            // Any number of 0xFF bytes may pad the space before a marker
+            while bytes.get(offset) == Some(&0xFF) {
+                offset += 1;
+            }
+            let marker = *bytes.get(offset).ok_or(JpegError::Truncated { offset })?;
+            offset += 1;
+
+            if marker == SOS {
+                return Ok(Jpeg { segments, scan: bytes[start..].to_vec(), payload_marker: APP15 });
+            }
+            if Segment::is_standalone(marker) {
+                segments.push(Segment { marker, data: Vec::new() });
+                if marker == EOI {
+                    return Ok(Jpeg { segments, scan: bytes[offset..].to_vec(), payload_marker: APP15 });
+                }
+                continue;
+            }
+
+            let len = match bytes.get(offset..offset + 2) {
+                Some(len) => u16::from_be_bytes([len[0], len[1]]) as usize,
+                None => return Err(JpegError::Truncated { offset })
+            };
+            if len < 2 {
+                return Err(JpegError::InvalidLength { offset, len });
+            }
+            let data = bytes.get(offset + 2..offset + len).ok_or(JpegError::Truncated { offset })?;
+            trace!("segment {:#04X} at offset {}, {} bytes of data", marker, start, len - 2);
+            segments.push(Segment { marker, data: data.to_vec() });
+            offset += len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// SOI, a JFIF APP0, a DQT, then a scan of made-up entropy-coded data.
+    fn jpeg() -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        bytes.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x07, b'J', b'F', b'I', b'F', 0x00]);
+        bytes.extend_from_slice(&[0xFF, 0xDB, 0x00, 0x04, 0x01, 0x02]);
+        bytes.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x03, 0x00, 0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD9]);
+        bytes
+    }
+
+    #[test]
+    fn test_jpeg_round_trip() {
+        let bytes = jpeg();
+        assert!(is_jpeg(&bytes));
+        let parsed = Jpeg::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.segments().len(), 2);
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_jpeg_payloads() {
+        let mut parsed = Jpeg::try_from(jpeg().as_slice()).unwrap();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let large: Vec<u8> = (0..150_000u32).map(|byte| byte as u8).collect();
+        parsed.add_payload(&chunk_type, b"hello", APP15).unwrap();
+        parsed.add_payload(&chunk_type, &large, COM).unwrap();
+
+        // JFIF stays first and the payloads go before DQT
+        let markers: Vec<u8> = parsed.segments().iter().map(|segment| segment.marker).collect();
+        assert_eq!(markers, [0xE0, APP15, COM, COM, COM, 0xDB]);
+
+        let reparsed = Jpeg::try_from(parsed.as_bytes().as_slice()).unwrap();
+        assert_eq!(reparsed.payloads("ruSt").unwrap(), vec![b"hello".to_vec(), large]);
+        assert!(reparsed.payloads("teSt").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_jpeg_errors() {
+        assert!(matches!(Jpeg::try_from(&b"\x89PNG"[..]), Err(JpegError::InvalidSignature)));
+        assert!(matches!(Jpeg::try_from(&jpeg()[..12]), Err(JpegError::Truncated { .. })));
+
+        let mut parsed = Jpeg::try_from(jpeg().as_slice()).unwrap();
+        parsed.add_payload(&ChunkType::from_str("ruSt").unwrap(), &[0; 100_000], APP15).unwrap();
+        parsed.segments.remove(1);
+        assert!(matches!(parsed.payloads("ruSt"), Err(JpegError::BrokenPayload { .. })));
+    }
+}
@@ -0,0 +1,330 @@
+use std::{fs::File, path::Path};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use log::{debug, trace};
+
+use crate::{
+    Result,
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::{ChunkError, PngError},
+    png::{read_fully, Png}
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHeader {
+    offset: usize,
+    length: u32,
+    chunk_type: ChunkType,
+    crc: u32
+}
+
+impl ChunkHeader {
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    pub fn byte_len(&self) -> usize {
+        12 + self.length as usize
+    }
+
+    fn data_offset(&self) -> u64 {
+        self.offset as u64 + 8
+    }
+}
+
+/// A PNG whose chunk headers are read up front while chunk data stays in the
+/// source until it's asked for.
+pub struct LazyPng<R> {
+    reader: R,
+    header: [u8;8],
+    chunks: Vec<ChunkHeader>,
+    trailing_len: usize
+}
+
+impl LazyPng<BufReader<File>> {
+    pub fn open(file_path: &Path) -> Result<LazyPng<BufReader<File>>> {
+        LazyPng::from_reader(BufReader::new(File::open(file_path)?))
+    }
+}
+
+impl<R: Read + Seek> LazyPng<R> {
+    pub fn from_reader(mut reader: R) -> Result<LazyPng<R>> {
+        let mut header = [0; 8];
+        let header_read = read_fully(&mut reader, &mut header)?;
+        if header_read < header.len() {
+            return Err(PngError::TruncatedSignature { read: header_read });
+        }
+
+        if header != Png::STANDARD_HEADER {
+            return Err(PngError::InvalidSignature);
+        }
+
+        let position = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(position))?;
+
+        let mut chunks = Vec::new();
+        let mut offset = header.len();
+        let mut seen_iend = false;
+        let mut trailing_len = 0;
+
+        loop {
+            match read_header(&mut reader, offset, end) {
+                Ok(Some(chunk)) => {
+                    trace!("{} chunk at offset {}, {} bytes of data", chunk.chunk_type, offset, chunk.length);
+                    seen_iend |= chunk.chunk_type.bytes() == *b"IEND";
+                    offset += chunk.byte_len();
+                    chunks.push(chunk);
+                }
+                Ok(None) => break,
+                // Whatever follows IEND and doesn't parse as a chunk is data appended to the file
+                Err(error) if seen_iend && !matches!(error, PngError::Io(_)) => {
+                    trailing_len = end as usize - offset;
+                    debug!("{} bytes of trailing data at offset {}", trailing_len, offset);
+                    break;
+                }
+                Err(error) => return Err(error)
+            }
+        }
+
+        Ok(LazyPng {
+            reader,
+            header,
+            chunks,
+            trailing_len
+        })
+    }
+
+    pub fn header(&self) -> &[u8;8] {
+        &self.header
+    }
+
+    pub fn chunk_headers(&self) -> &[ChunkHeader] {
+        &self.chunks
+    }
+
+    pub fn position_by_type(&self, chunk_type: &str) -> Option<usize> {
+        self.chunks
+            .iter()
+            .position(|chunk| chunk.chunk_type.to_string() == chunk_type)
+    }
+
+    pub fn read_data(&mut self, index: usize) -> Result<Vec<u8>> {
+        self.read_data_prefix(index, usize::MAX)
+    }
+
+    /// Reads at most the first `len` bytes of a chunk's data, e.g. to look
+    /// at a payload header without loading the rest. The CRC isn't checked.
+    pub fn read_data_prefix(&mut self, index: usize, len: usize) -> Result<Vec<u8>> {
+        let chunk = self.chunks
+            .get(index)
+            .ok_or(PngError::IndexOutOfRange { index, len: self.chunks.len() })?;
+
+        let mut data = vec![0; len.min(chunk.length as usize)];
+        self.reader.seek(SeekFrom::Start(chunk.data_offset()))?;
+        self.reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    pub fn load_chunk(&mut self, index: usize) -> Result<Chunk> {
+        let data = self.read_data(index)?;
+        let chunk = &self.chunks[index];
+
+        let expected = Chunk::checksum(&chunk.chunk_type.bytes(), &data);
+        if expected != chunk.crc {
+            return Err(PngError::InvalidChunk {
+                offset: chunk.offset,
+                source: ChunkError::InvalidCrc { expected, found: chunk.crc }
+            });
+        }
+        Ok(Chunk::from_parts(chunk.chunk_type.clone(), data.into_boxed_slice(), chunk.crc))
+    }
+
+    /// Number of bytes after IEND that don't form a chunk.
+    pub fn trailing_len(&self) -> usize {
+        self.trailing_len
+    }
+
+    pub fn into_png(mut self) -> Result<Png> {
+        let chunks = (0..self.chunks.len())
+            .map(|index| self.load_chunk(index))
+            .collect::<Result<Vec<Chunk>>>()?;
+
+        let mut trailing = vec![0; self.trailing_len];
+        self.reader.seek(SeekFrom::End(-(self.trailing_len as i64)))?;
+        self.reader.read_exact(&mut trailing)?;
+
+        let mut png = Png::from_chunks(chunks);
+        png.set_trailing_data(trailing);
+        Ok(png)
+    }
+}
+
+fn read_header<R: Read + Seek>(reader: &mut R, offset: usize, end: u64) -> Result<Option<ChunkHeader>> {
+    let mut prefix = [0; 8];
+    match read_fully(reader, &mut prefix)? {
+        0 => return Ok(None),
+        8 => (),
+        read => return Err(PngError::TruncatedChunk {
+            offset,
+            expected: 8,
+            found: read as u64
+        })
+    }
+
+    let length = u32::from_be_bytes(prefix[0..4].try_into().unwrap());
+    if length > Chunk::MAX_LENGTH {
+        return Err(PngError::InvalidChunk {
+            offset,
+            source: ChunkError::LengthTooLarge { length: length as usize }
+        });
+    }
+    let chunk_type_bytes: [u8;4] = prefix[4..8].try_into().unwrap();
+    let chunk_type = ChunkType::try_from(chunk_type_bytes)
+        .map_err(|error| PngError::InvalidChunk { offset, source: error.into() })?;
+
+    // Seeking past the end succeeds, so check the declared length against the source size first
+    let remaining = end.saturating_sub(reader.stream_position()?);
+    if remaining < 4 {
+        return Err(PngError::TruncatedChunk {
+            offset,
+            expected: length as u64 + 4,
+            found: remaining
+        });
+    }
+    if length as u64 > remaining - 4 {
+        return Err(PngError::InvalidChunk {
+            offset,
+            source: ChunkError::LengthExceedsInput {
+                length: length as usize,
+                remaining: (remaining - 4) as usize
+            }
+        });
+    }
+
+    reader.seek(SeekFrom::Current(length as i64))?;
+    let mut crc_bytes = [0; 4];
+    read_fully(reader, &mut crc_bytes)?;
+
+    Ok(Some(ChunkHeader {
+        offset,
+        length,
+        chunk_type,
+        crc: u32::from_be_bytes(crc_bytes)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        let chunks = ["FrSt", "miDl", "LASt"]
+            .iter()
+            .map(|chunk_type| Chunk::new(
+                ChunkType::from_str(chunk_type).unwrap(),
+                chunk_type.as_bytes().repeat(3)
+            ))
+            .collect();
+        Png::from_chunks(chunks).as_bytes()
+    }
+
+    #[test]
+    fn test_lazy_headers() {
+        let png = LazyPng::from_reader(Cursor::new(testing_bytes())).unwrap();
+        let chunks = png.chunk_headers();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1].chunk_type().to_string(), "miDl");
+        assert_eq!(chunks[1].length(), 12);
+        assert_eq!(chunks.iter().map(ChunkHeader::offset).collect::<Vec<_>>(), vec![8, 32, 56]);
+        assert_eq!(png.position_by_type("LASt"), Some(2));
+    }
+
+    #[test]
+    fn test_lazy_load_chunk() {
+        let mut png = LazyPng::from_reader(Cursor::new(testing_bytes())).unwrap();
+        let chunk = png.load_chunk(1).unwrap();
+
+        assert_eq!(chunk.data(), b"miDlmiDlmiDl");
+        assert_eq!(chunk.crc(), png.chunk_headers()[1].crc());
+        assert!(png.load_chunk(3).is_err());
+    }
+
+    #[test]
+    fn test_lazy_read_data_prefix() {
+        let mut png = LazyPng::from_reader(Cursor::new(testing_bytes())).unwrap();
+
+        assert_eq!(png.read_data_prefix(1, 4).unwrap(), b"miDl");
+        assert_eq!(png.read_data_prefix(1, 100).unwrap(), b"miDlmiDlmiDl");
+        assert!(png.read_data_prefix(3, 4).is_err());
+    }
+
+    #[test]
+    fn test_lazy_bad_crc_is_found_on_load() {
+        let mut bytes = testing_bytes();
+        bytes[40] ^= 0xff;
+        let mut png = LazyPng::from_reader(Cursor::new(bytes)).unwrap();
+
+        assert!(png.load_chunk(0).is_ok());
+        assert!(png.load_chunk(1).is_err());
+    }
+
+    #[test]
+    fn test_lazy_truncated() {
+        let bytes = testing_bytes();
+        assert!(LazyPng::from_reader(Cursor::new(&bytes[..bytes.len() - 2])).is_err());
+        assert!(LazyPng::from_reader(Cursor::new(&bytes[..12])).is_err());
+    }
+
+    #[test]
+    fn test_lazy_declared_length() {
+        let mut bytes = testing_bytes();
+        bytes[57] = 0x01;
+        assert!(matches!(
+            LazyPng::from_reader(Cursor::new(bytes.clone())),
+            Err(PngError::InvalidChunk { offset: 56, source: ChunkError::LengthExceedsInput { length: 65548, remaining: 12 } })
+        ));
+
+        bytes[56] = 0xff;
+        assert!(matches!(
+            LazyPng::from_reader(Cursor::new(bytes)),
+            Err(PngError::InvalidChunk { offset: 56, source: ChunkError::LengthTooLarge { .. } })
+        ));
+    }
+
+    #[test]
+    fn test_lazy_into_png() {
+        let bytes = testing_bytes();
+        let png = LazyPng::from_reader(Cursor::new(bytes.clone())).unwrap().into_png().unwrap();
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_lazy_trailing_data() {
+        let mut bytes = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new())
+        ]).as_bytes();
+        bytes.extend_from_slice(b"appended");
+
+        let png = LazyPng::from_reader(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(png.chunk_headers().len(), 1);
+        assert_eq!(png.trailing_len(), 8);
+        assert_eq!(png.into_png().unwrap().as_bytes(), bytes);
+    }
+}
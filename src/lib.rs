@@ -0,0 +1,60 @@
+pub mod apng;
+pub mod background;
+pub mod builder;
+pub mod check;
+pub mod chunk;
+pub mod chunk_ref;
+pub mod chunk_type;
+pub mod color;
+pub mod compress;
+pub mod container;
+pub mod copy;
+pub mod crypto;
+pub mod decoy;
+pub mod detect;
+pub mod diff;
+pub mod envelope;
+pub mod error;
+pub mod exif;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod gif;
+pub mod hexdump;
+pub mod icc;
+pub mod ihdr;
+pub mod image_data;
+pub mod jpeg;
+pub mod lazy;
+pub mod lsb;
+pub mod merge;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod palette;
+pub mod payload;
+pub mod phys;
+pub mod png;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod qr;
+pub mod repair;
+pub mod scatter;
+pub mod signature;
+pub mod significant_bits;
+pub mod stats;
+pub mod stream;
+pub mod text;
+pub mod tiff;
+pub mod timestamp;
+pub mod transparency;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use builder::PngBuilder;
+pub use chunk::Chunk;
+pub use chunk_ref::ChunkRef;
+pub use chunk_type::ChunkType;
+pub use error::{ApngError, BackgroundError, ChunkError, ChunkTypeError, ColorError, CompressError, ContainerError, CryptoError, EnvelopeError, ExifError, GifError, IccError, IhdrError, ImageDataError, JpegError, LsbError, MergeError, PaletteError, PayloadError, PhysError, PngError, QrError, ScatterError, SignatureError, SignificantBitsError, TextError, TiffError, TimestampError, TransparencyError};
+pub use lazy::LazyPng;
+pub use png::Png;
+
+pub type Result<T, E = PngError> = std::result::Result<T, E>;
@@ -0,0 +1,57 @@
+use std::io::Write;
+use std::time::Instant;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use crate::style;
+
+/// Writes log records to standard error. Status messages and errors are
+/// printed as they are, warnings with a "warning:" prefix, and debugging
+/// output with the time since startup, so slow steps stand out.
+struct Logger {
+    started: Instant
+}
+
+impl Log for Logger {
+    // Debugging output from dependencies would drown out pngme's own
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && (metadata.level() <= Level::Warn || metadata.target().starts_with("pngme"))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut stderr = anstream::stderr().lock();
+        // There's nowhere left to report a failure to write to stderr
+        let _ = match record.level() {
+            Level::Error | Level::Info => writeln!(stderr, "{}", record.args()),
+            Level::Warn => writeln!(stderr, "{}warning:{:#} {}", style::WARNING, style::WARNING, record.args()),
+            level => writeln!(
+                stderr,
+                "{}[{:>8.3}s {} {}]{:#} {}",
+                style::DIM,
+                self.started.elapsed().as_secs_f64(),
+                level.as_str().to_lowercase(),
+                record.target(),
+                style::DIM,
+                record.args()
+            )
+        };
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger: `--quiet` leaves only errors, each `--verbose`
+/// adds a level of detail past the usual status messages and warnings.
+pub fn init(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace
+    };
+    let logger = Logger { started: Instant::now() };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
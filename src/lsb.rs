@@ -0,0 +1,232 @@
+use crate::error::LsbError;
+use crate::ihdr::{ColorType, Ihdr};
+use crate::image_data::ImageData;
+
+/// Marks the start of a payload hidden in the image data.
+pub const MAGIC: [u8;4] = *b"PMEl";
+/// Magic and the payload length.
+pub const HEADER_LEN: usize = 8;
+
+/// Checks the image and bit count can carry a payload: the samples have to
+/// be whole bytes, and palette indices don't survive having bits flipped.
+fn check(ihdr: &Ihdr, bits: u8) -> Result<(), LsbError> {
+    if ihdr.color_type == ColorType::Indexed || ihdr.bit_depth < 8 {
+        return Err(LsbError::UnsupportedImage { color_type: ihdr.color_type, bit_depth: ihdr.bit_depth });
+    }
+    match bits {
+        1 | 2 => Ok(()),
+        bits => Err(LsbError::InvalidBits { bits })
+    }
+}
+
+/// Channels that carry payload bits. Alpha is left alone, since changing it
+/// shows up in fully transparent areas.
+fn color_channels(color_type: ColorType) -> usize {
+    match color_type {
+        ColorType::GrayscaleAlpha => 1,
+        ColorType::Rgba => 3,
+        color_type => color_type.channels() as usize
+    }
+}
+
+/// The positions of the bytes that carry payload bits, in order: the color
+/// samples of each pixel, or the low byte of them in 16-bit images.
+fn carriers(ihdr: &Ihdr) -> impl Iterator<Item = usize> {
+    let sample_len = ihdr.bit_depth as usize / 8;
+    let channels = ihdr.color_type.channels() as usize;
+    let color_channels = color_channels(ihdr.color_type);
+    (0..ihdr.width as usize * ihdr.height as usize).flat_map(move |pixel| {
+        (0..color_channels).map(move |channel| (pixel * channels + channel) * sample_len + sample_len - 1)
+    })
+}
+
+/// How many payload bytes the image can carry in the lowest `bits` bits of
+/// its color samples, after the header.
+pub fn capacity(ihdr: &Ihdr, bits: u8) -> Result<usize, LsbError> {
+    check(ihdr, bits)?;
+    let samples = ihdr.width as usize * ihdr.height as usize * color_channels(ihdr.color_type);
+    Ok((samples * bits as usize / 8).saturating_sub(HEADER_LEN))
+}
+
+/// Hides `payload` behind a header in the lowest `bits` bits of the image's
+/// color samples, most significant bits first.
+pub fn embed(image: &mut ImageData, payload: &[u8], bits: u8) -> Result<(), LsbError> {
+    let capacity = capacity(&image.ihdr, bits)?;
+    if payload.len() > capacity {
+        return Err(LsbError::TooLarge { len: payload.len(), capacity });
+    }
+    let data = [&MAGIC[..], &(payload.len() as u32).to_be_bytes(), payload].concat();
+    let mask = (1 << bits) - 1;
+    let groups = data
+        .iter()
+        .flat_map(|&byte| (0..8 / bits).rev().map(move |group| (byte >> (group * bits)) & mask));
+    for (index, group) in carriers(&image.ihdr).zip(groups) {
+        image.pixels[index] = image.pixels[index] & !mask | group;
+    }
+    Ok(())
+}
+
+/// Reads back a payload hidden by `embed` with the same number of bits.
+pub fn extract(image: &ImageData, bits: u8) -> Result<Vec<u8>, LsbError> {
+    let capacity = capacity(&image.ihdr, bits)?;
+    let mask = (1 << bits) - 1;
+    let mut groups = carriers(&image.ihdr).map(|index| image.pixels[index] & mask);
+    let mut read = |len: usize| -> Vec<u8> {
+        (0..len)
+            .map(|_| groups.by_ref().take((8 / bits) as usize).fold(0, |byte, group| byte << bits | group))
+            .collect()
+    };
+
+    let header = read(HEADER_LEN);
+    let len = u32::from_be_bytes(header[4..].try_into().unwrap()) as usize;
+    if header[..4] != MAGIC || len > capacity {
+        return Err(LsbError::NotFound);
+    }
+    Ok(read(len))
+}
+
+/// The probability that the color samples carry a payload in their lowest
+/// bit, from the chi-square attack on pairs of values that differ only in
+/// that bit. Embedding evens out the counts within each pair, which natural
+/// images rarely do. Images with a flat histogram have even pairs anyway, so
+/// the pairs straddling them, which embedding leaves alone, have to be
+/// uneven for it to count. None when there are too few samples to tell.
+pub fn embedding_probability(image: &ImageData) -> Result<Option<f64>, LsbError> {
+    check(&image.ihdr, 1)?;
+    let samples: Vec<u8> = carriers(&image.ihdr).map(|index| image.pixels[index]).collect();
+    // Payloads fill the image from the start, so the first part alone gives
+    // away one that is much smaller than the capacity
+    Ok([samples.len() / 10, samples.len()]
+        .into_iter()
+        .filter_map(|len| {
+            let counts = histogram(&samples[..len]);
+            let paired = pair_probability(&counts)?;
+            let straddling = pair_probability(&counts[1..]).unwrap_or(0.0);
+            Some(paired * (1.0 - straddling))
+        })
+        .reduce(f64::max))
+}
+
+fn histogram(samples: &[u8]) -> [u64; 256] {
+    let mut counts = [0; 256];
+    for &sample in samples {
+        counts[sample as usize] += 1;
+    }
+    counts
+}
+
+/// The probability that the counts, taken two at a time, are as even as
+/// chance makes them.
+fn pair_probability(counts: &[u64]) -> Option<f64> {
+    let mut chi_square = 0.0;
+    let mut pairs = 0;
+    for pair in counts.chunks_exact(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        // The statistic is unreliable for rare values
+        if expected < 5.0 {
+            continue;
+        }
+        chi_square += (pair[0] as f64 - expected).powi(2) / expected;
+        pairs += 1;
+    }
+    match pairs {
+        0 | 1 => None,
+        pairs => Some(chi_square_survival(chi_square, pairs - 1))
+    }
+}
+
+/// The probability of a chi-square statistic at least `x` with `dof`
+/// degrees of freedom, by the Wilson-Hilferty approximation.
+fn chi_square_survival(x: f64, dof: u32) -> f64 {
+    let k = dof as f64;
+    let variance = 2.0 / (9.0 * k);
+    let z = ((x / k).cbrt() - (1.0 - variance)) / variance.sqrt();
+    erfc(z / std::f64::consts::SQRT_2) / 2.0
+}
+
+/// The complementary error function, after Abramowitz and Stegun 7.1.26.
+fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    polynomial * (-x * x).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(color_type: ColorType, bit_depth: u8) -> ImageData {
+        let ihdr = Ihdr::new(6, 4, bit_depth, color_type).unwrap();
+        let len = 6 * 4 * color_type.channels() as usize * bit_depth as usize / 8;
+        ImageData {
+            ihdr,
+            filters: vec![0; 4],
+            pixels: (0..len).map(|byte| (byte * 37 % 251) as u8).collect()
+        }
+    }
+
+    #[test]
+    fn test_lsb_round_trip() {
+        for (color_type, bit_depth, bits) in [(ColorType::Rgb, 8, 1), (ColorType::Rgba, 16, 2), (ColorType::Grayscale, 8, 2)] {
+            let mut image = image(color_type, bit_depth);
+            let original = image.pixels.clone();
+            let payload = vec![0xa5; capacity(&image.ihdr, bits).unwrap()];
+            embed(&mut image, &payload, bits).unwrap();
+
+            assert_eq!(extract(&image, bits).unwrap(), payload, "{} {}", color_type, bit_depth);
+            let changed = image.pixels.iter().zip(&original).map(|(new, old)| new ^ old);
+            assert!(changed.clone().all(|difference| difference < 1 << bits));
+            // Neither alpha nor the high bytes of 16-bit samples are touched
+            if color_type == ColorType::Rgba {
+                assert!(changed.enumerate().all(|(index, difference)| index % 2 == 1 && index % 8 != 7 || difference == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        // 24 pixels of 3 samples at 1 bit, less the header
+        assert_eq!(capacity(&image(ColorType::Rgb, 8).ihdr, 1).unwrap(), 1);
+        assert_eq!(capacity(&image(ColorType::Rgba, 16).ihdr, 2).unwrap(), 10);
+        assert_eq!(capacity(&Ihdr::new(1, 1, 8, ColorType::Rgb).unwrap(), 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_invalid_lsb() {
+        let indexed = Ihdr::new(1, 1, 8, ColorType::Indexed).unwrap();
+        assert!(matches!(capacity(&indexed, 1), Err(LsbError::UnsupportedImage { bit_depth: 8, .. })));
+        assert!(matches!(capacity(&image(ColorType::Rgb, 8).ihdr, 3), Err(LsbError::InvalidBits { bits: 3 })));
+
+        let mut image = image(ColorType::Rgb, 8);
+        assert!(matches!(extract(&image, 1), Err(LsbError::NotFound)));
+        assert!(matches!(embed(&mut image, &[0; 2], 1), Err(LsbError::TooLarge { len: 2, capacity: 1 })));
+    }
+
+    #[test]
+    fn test_embedding_probability() {
+        let mut state = 1u32;
+        let mut random = || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 24) as u8
+        };
+        // Squares modulo 85 turn up unevenly, giving the lumpy histogram of a
+        // photo rather than a flat or smooth one
+        let ihdr = Ihdr::new(64, 64, 8, ColorType::Rgb).unwrap();
+        let pixels = (0..64 * 64 * 3).map(|_| ((random() as u16 % 85).pow(2) % 85 * 3) as u8).collect();
+        let mut image = ImageData { ihdr, filters: vec![0; 64], pixels };
+        assert!(embedding_probability(&image).unwrap().unwrap() < 0.1);
+
+        let payload: Vec<u8> = (0..capacity(&ihdr, 1).unwrap()).map(|_| random()).collect();
+        embed(&mut image, &payload, 1).unwrap();
+        assert!(embedding_probability(&image).unwrap().unwrap() > 0.9);
+
+        // A flat histogram has even pairs without any payload
+        let flat = ImageData { pixels: (0..64 * 64 * 3).map(|byte| byte as u8).collect(), ..image.clone() };
+        assert!(embedding_probability(&flat).unwrap().unwrap() < 0.1);
+        assert_eq!(embedding_probability(&self::image(ColorType::Grayscale, 8)).unwrap(), None);
+        assert!((erfc(0.5) - 0.4795).abs() < 1e-4);
+    }
+}
@@ -1,30 +1,194 @@
-use args::{Cli, Commands};
+use args::{
+    Commands,
+    EncodeArgs,
+    DecodeArgs,
+    RemoveArgs,
+    PrintArgs,
+    ListArgs,
+    CheckArgs,
+    ScrubArgs,
+    HexdumpArgs,
+    FixCrcArgs,
+    SignArgs,
+    VerifyArgs,
+    TextArgs,
+    TextCommand,
+    TextSetArgs,
+    TextGetArgs,
+    TouchArgs,
+    SetDpiArgs,
+    IccArgs,
+    IccCommand,
+    IccExtractArgs,
+    IccEmbedArgs,
+    ExifArgs,
+    ExifCommand,
+    ExifExportArgs,
+    ExifImportArgs,
+    PaletteArgs,
+    LsbArgs,
+    LsbCommand,
+    LsbEncodeArgs,
+    LsbDecodeArgs,
+    CapacityArgs,
+    ExtractArgs
+};
+use batch::Batch;
 use commands::{
     encode,
     decode,
     remove,
-    print
+    print,
+    list,
+    check,
+    scrub,
+    hexdump,
+    fix_crc,
+    sign,
+    verify,
+    keygen,
+    text_set,
+    text_get,
+    touch,
+    set_dpi,
+    icc_extract,
+    icc_embed,
+    exif_export,
+    exif_import,
+    palette,
+    lsb_encode,
+    lsb_decode,
+    capacity,
+    detect,
+    completions,
+    man,
+    tui,
+    diff,
+    copy_chunks,
+    merge,
+    stats,
+    scan,
+    extract,
+    compose,
+    watch
 };
-use clap::Parser;
+use std::time::Instant;
+use log::debug;
+
+use anyhow::Result;
 
 mod args;
-mod chunk;
-mod chunk_type;
+mod batch;
 mod commands;
-mod png;
-
-// pub type Error = Box<dyn std::error::Error>;
-// pub type Result<T> = std::result::Result<T, Error>;
-pub type Error = anyhow::Error;
-pub type Result<T> = std::result::Result<T, Error>;
+mod compose;
+mod config;
+mod logger;
+mod progress;
+mod remote;
+mod s3;
+mod serve;
+mod style;
+mod tui;
+mod view;
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let cli = config::parse()?;
+    style::init(cli.no_color);
+    logger::init(cli.verbose, cli.quiet);
+    let started = Instant::now();
+    let format = cli.format;
+    let batch = Batch {
+        recursive: cli.recursive,
+        format
+    };
 
-    match cli.command {
-        Commands::Encode(args) => encode(args),
-        Commands::Decode(args) => decode(args),
-        Commands::Remove(args) => remove(args),
-        Commands::Print(args) => print(args),
-    }
+    let result = match cli.command {
+        Commands::Encode(args) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            encode(EncodeArgs { file_path, ..(*args).clone() })
+        }),
+        Commands::Decode(args) => batch.run(&args.file_path, args.output.is_some(), |file_path| {
+            decode(DecodeArgs { file_path, ..args.clone() }, format)
+        }),
+        Commands::Remove(args) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            remove(RemoveArgs { file_path, ..args.clone() })
+        }),
+        Commands::Print(args) => batch.run(&args.file_path, false, |file_path| {
+            print(PrintArgs { file_path }, format)
+        }),
+        Commands::List(args) => batch.run(&args.file_path, false, |file_path| {
+            list(ListArgs { file_path }, format)
+        }),
+        Commands::Check(args) => batch.run(&args.file_path, false, |file_path| {
+            check(CheckArgs { file_path }, format)
+        }),
+        Commands::Scrub(args) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            scrub(ScrubArgs { file_path, ..args.clone() })
+        }),
+        Commands::Hexdump(args) => batch.run(&args.file_path, false, |file_path| {
+            hexdump(HexdumpArgs { file_path, ..args.clone() })
+        }),
+        Commands::FixCrc(args) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            fix_crc(FixCrcArgs { file_path, ..args.clone() })
+        }),
+        Commands::Sign(args) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            sign(SignArgs { file_path, ..args.clone() })
+        }),
+        Commands::Verify(args) => batch.run(&args.file_path, false, |file_path| {
+            verify(VerifyArgs { file_path, ..args.clone() }, format)
+        }),
+        Commands::Text(TextArgs { command: TextCommand::Set(args) }) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            text_set(TextSetArgs { file_path, ..args.clone() })
+        }),
+        Commands::Text(TextArgs { command: TextCommand::Get(args) }) => batch.run(&args.file_path, false, |file_path| {
+            text_get(TextGetArgs { file_path, ..args.clone() }, format)
+        }),
+        Commands::Touch(args) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            touch(TouchArgs { file_path, ..args.clone() })
+        }),
+        Commands::SetDpi(args) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            set_dpi(SetDpiArgs { file_path, ..args.clone() })
+        }),
+        Commands::Icc(IccArgs { command: IccCommand::Extract(args) }) => batch.run(&args.file_path, true, |file_path| {
+            icc_extract(IccExtractArgs { file_path, ..args.clone() })
+        }),
+        Commands::Icc(IccArgs { command: IccCommand::Embed(args) }) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            icc_embed(IccEmbedArgs { file_path, ..args.clone() })
+        }),
+        Commands::Exif(ExifArgs { command: ExifCommand::Export(args) }) => batch.run(&args.file_path, true, |file_path| {
+            exif_export(ExifExportArgs { file_path, ..args.clone() })
+        }),
+        Commands::Exif(ExifArgs { command: ExifCommand::Import(args) }) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            exif_import(ExifImportArgs { file_path, ..args.clone() })
+        }),
+        Commands::Palette(args) => batch.run(&args.file_path, false, |file_path| {
+            palette(PaletteArgs { file_path, ..args.clone() }, format)
+        }),
+        Commands::Lsb(LsbArgs { command: LsbCommand::Encode(args) }) => batch.run(&args.file_path, args.edit.output.is_some(), |file_path| {
+            lsb_encode(LsbEncodeArgs { file_path, ..args.clone() })
+        }),
+        Commands::Lsb(LsbArgs { command: LsbCommand::Decode(args) }) => batch.run(&args.file_path, args.output.is_some(), |file_path| {
+            lsb_decode(LsbDecodeArgs { file_path, ..args.clone() })
+        }),
+        Commands::Capacity(args) => batch.run(&args.file_path, false, |file_path| {
+            capacity(CapacityArgs { file_path, ..args.clone() }, format)
+        }),
+        Commands::Extract(args) => batch.run(&args.file_path, false, |file_path| {
+            extract(ExtractArgs { file_path, ..args.clone() })
+        }),
+        Commands::Keygen(args) => keygen(args),
+        Commands::Detect(args) => detect(args, cli.recursive, format),
+        Commands::Serve(args) => serve::serve(args),
+        Commands::Completions(args) => completions(args),
+        Commands::Man(args) => man(args),
+        Commands::Tui(args) => tui(args),
+        Commands::Diff(args) => diff(args, format),
+        Commands::CopyChunks(args) => copy_chunks(args),
+        Commands::Merge(args) => merge(args),
+        Commands::Stats(args) => stats(args, cli.recursive, format),
+        Commands::Scan(args) => scan(args, format),
+        Commands::Compose(args) => compose(args),
+        Commands::Watch(args) => watch(args, cli.recursive, format),
+    };
+    debug!("Finished in {:.3?}", started.elapsed());
+    result
 }
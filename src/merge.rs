@@ -0,0 +1,158 @@
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::MergeError,
+    png::Png
+};
+
+/// What to do when both images have payload chunks of one type, with
+/// different data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    Fail,
+    /// Keep the chunks of both, to be told apart with `decode --all`.
+    KeepBoth,
+    KeepFirst,
+    KeepLast
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// One entry per chunk added.
+    pub added: Vec<ChunkType>,
+    /// Chunks left out because the destination already had them, data and all.
+    pub duplicates: usize,
+    /// The types both images had, resolved as the `Conflict` said.
+    pub conflicts: Vec<ChunkType>
+}
+
+/// Whether chunks of a type can carry a payload: the types pngme writes
+/// without `--force`, which are neither critical nor registered.
+pub fn is_payload(chunk_type: &ChunkType) -> bool {
+    !chunk_type.is_critical() && !chunk_type.is_standard()
+}
+
+/// Adds the payload chunks of `source` to `destination`. Chunks of a type
+/// the destination already has are handled as `conflict` says, unless they
+/// are all already there; on `Conflict::Fail` nothing is added.
+pub fn merge(destination: &mut Png, source: &Png, conflict: Conflict) -> Result<MergeReport, MergeError> {
+    let mut types: Vec<&ChunkType> = Vec::new();
+    for chunk in source.chunks().iter().filter(|chunk| is_payload(chunk.chunk_type())) {
+        if !types.contains(&chunk.chunk_type()) {
+            types.push(chunk.chunk_type());
+        }
+    }
+    let of_type = |png: &Png, chunk_type: &ChunkType| -> Vec<(ChunkType, Vec<u8>)> {
+        png.chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == chunk_type)
+            .map(|chunk| (chunk.chunk_type().clone(), chunk.data().to_vec()))
+            .collect()
+    };
+
+    // Each type is settled before any chunk is added, so a failure leaves
+    // the destination as it was
+    let mut plan = Vec::with_capacity(types.len());
+    let mut report = MergeReport::default();
+    for chunk_type in types {
+        let existing = of_type(destination, chunk_type);
+        let incoming = of_type(source, chunk_type);
+        let new: Vec<_> = incoming.iter().filter(|chunk| !existing.contains(chunk)).cloned().collect();
+        report.duplicates += incoming.len() - new.len();
+        if new.is_empty() {
+            continue;
+        }
+        if existing.is_empty() {
+            plan.push((chunk_type, false, new));
+            continue;
+        }
+
+        report.conflicts.push(chunk_type.clone());
+        match conflict {
+            Conflict::Fail => return Err(MergeError::Conflict { chunk_type: chunk_type.to_string() }),
+            Conflict::KeepBoth => plan.push((chunk_type, false, new)),
+            Conflict::KeepFirst => {},
+            Conflict::KeepLast => plan.push((chunk_type, true, incoming))
+        }
+    }
+
+    for (chunk_type, replace, chunks) in plan {
+        if replace {
+            destination.remove_chunks_where(|chunk| chunk.chunk_type() == chunk_type);
+        }
+        for (chunk_type, data) in chunks {
+            report.added.push(chunk_type.clone());
+            destination.append_chunk(Chunk::new(chunk_type, data));
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn testing_png(payloads: Vec<Chunk>) -> Png {
+        let mut chunks = vec![chunk("IHDR", b"header"), chunk("IDAT", b"pixels")];
+        chunks.extend(payloads);
+        chunks.push(chunk("IEND", b""));
+        Png::from_chunks(chunks)
+    }
+
+    fn payloads(png: &Png) -> Vec<(String, Vec<u8>)> {
+        png.chunks()
+            .iter()
+            .filter(|chunk| is_payload(chunk.chunk_type()))
+            .map(|chunk| (chunk.chunk_type().to_string(), chunk.data().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut destination = testing_png(vec![chunk("ruSt", b"first")]);
+        let source = testing_png(vec![chunk("ruSt", b"first"), chunk("noTe", b"second"), chunk("tEXt", b"Title\0x")]);
+
+        let report = merge(&mut destination, &source, Conflict::Fail).unwrap();
+        assert_eq!(report.added, vec![ChunkType::from_str("noTe").unwrap()]);
+        assert_eq!(report.duplicates, 1);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(payloads(&destination), vec![
+            ("ruSt".to_string(), b"first".to_vec()),
+            ("noTe".to_string(), b"second".to_vec())
+        ]);
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let original = testing_png(vec![chunk("ruSt", b"first")]);
+        let source = testing_png(vec![chunk("ruSt", b"second"), chunk("noTe", b"note")]);
+
+        let mut destination = testing_png(vec![chunk("ruSt", b"first")]);
+        assert!(merge(&mut destination, &source, Conflict::Fail).is_err());
+        assert_eq!(payloads(&destination), payloads(&original));
+
+        let mut destination = testing_png(vec![chunk("ruSt", b"first")]);
+        merge(&mut destination, &source, Conflict::KeepBoth).unwrap();
+        assert_eq!(payloads(&destination).len(), 3);
+
+        let mut destination = testing_png(vec![chunk("ruSt", b"first")]);
+        let report = merge(&mut destination, &source, Conflict::KeepFirst).unwrap();
+        assert_eq!(report.conflicts, vec![ChunkType::from_str("ruSt").unwrap()]);
+        assert_eq!(payloads(&destination), vec![
+            ("ruSt".to_string(), b"first".to_vec()),
+            ("noTe".to_string(), b"note".to_vec())
+        ]);
+
+        let mut destination = testing_png(vec![chunk("ruSt", b"first")]);
+        merge(&mut destination, &source, Conflict::KeepLast).unwrap();
+        assert_eq!(payloads(&destination), vec![
+            ("ruSt".to_string(), b"second".to_vec()),
+            ("noTe".to_string(), b"note".to_vec())
+        ]);
+    }
+}
@@ -0,0 +1,82 @@
+use std::{fs::File, path::Path};
+
+use crate::{
+    Result,
+    chunk_ref::{ChunkRef, ChunkRefs},
+    error::PngError,
+    png::Png
+};
+use memmap2::Mmap;
+
+/// A PNG file mapped into memory, so chunks can be read straight out of the
+/// page cache without copying the file first.
+pub struct MappedPng {
+    map: Mmap
+}
+
+impl MappedPng {
+    pub fn open(file_path: &Path) -> Result<MappedPng> {
+        let file = File::open(file_path)?;
+        // SAFETY: the map is read-only and we never hand out references that
+        // outlive it. Another process truncating the file underneath us can
+        // still fault, which is the usual caveat of any mmap.
+        let map = unsafe { Mmap::map(&file)? };
+
+        if map.len() < Png::STANDARD_HEADER.len() || map[..8] != Png::STANDARD_HEADER {
+            return Err(PngError::InvalidSignature);
+        }
+
+        Ok(MappedPng {
+            map
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    pub fn chunks(&self) -> ChunkRefs<'_> {
+        ChunkRefs::new(&self.map[Png::STANDARD_HEADER.len()..], Png::STANDARD_HEADER.len())
+    }
+
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Result<Option<ChunkRef<'_>>> {
+        for parsed in self.chunks() {
+            let (_, chunk) = parsed?;
+            if chunk.chunk_type_bytes() == chunk_type.as_bytes() {
+                return Ok(Some(chunk));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn to_png(&self) -> Result<Png> {
+        Png::try_from(self.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+    use std::str::FromStr;
+    use crate::{chunk::Chunk, chunk_type::ChunkType};
+
+    #[test]
+    fn test_mapped_chunk_by_type() {
+        let file_path = env::temp_dir().join(format!("pngme-mmap-{}.png", std::process::id()));
+        let chunks = vec![
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), b"first".to_vec()),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec())
+        ];
+        fs::write(&file_path, Png::from_chunks(chunks).as_bytes()).unwrap();
+
+        let png = MappedPng::open(&file_path).unwrap();
+        let chunk = png.chunk_by_type("ruSt").unwrap().unwrap();
+        assert_eq!(chunk.data(), b"hidden");
+        assert!(png.chunk_by_type("miSs").unwrap().is_none());
+        assert_eq!(png.to_png().unwrap().chunks().len(), 2);
+
+        drop(png);
+        fs::remove_file(file_path).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PaletteError;
+use crate::ihdr::{ColorType, Ihdr};
+use crate::text;
+
+/// The chunk type of the palette.
+pub const PLTE: &str = "PLTE";
+/// A palette has at most 256 entries, the most an 8-bit index can address.
+pub const MAX_ENTRIES: usize = 256;
+/// The chunk type of how often each palette entry is used.
+pub const HIST: &str = "hIST";
+/// The chunk type of a suggested palette.
+pub const SPLT: &str = "sPLT";
+
+/// The colors of a PLTE chunk, as red, green and blue bytes. Indexed images
+/// refer to them by position; other color images may suggest them for
+/// quantizing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub entries: Vec<[u8;3]>
+}
+
+impl Palette {
+    pub fn parse(data: &[u8]) -> Result<Palette, PaletteError> {
+        if data.is_empty() || !data.len().is_multiple_of(3) || data.len() / 3 > MAX_ENTRIES {
+            return Err(PaletteError::WrongLength { len: data.len() });
+        }
+        let entries = data
+            .chunks_exact(3)
+            .map(|entry| [entry[0], entry[1], entry[2]])
+            .collect();
+        Ok(Palette { entries })
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Palette, PaletteError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != PLTE {
+            return Err(PaletteError::NotPlte { chunk_type });
+        }
+        Palette::parse(chunk.data())
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::from_str(PLTE).unwrap(), self.entries.concat())
+    }
+
+    /// Checks the image may have a palette, and for indexed images that it
+    /// has no more entries than the bit depth can index.
+    pub fn validate(&self, ihdr: &Ihdr) -> Result<(), PaletteError> {
+        match ihdr.color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => {
+                Err(PaletteError::NotAllowed { color_type: ihdr.color_type })
+            },
+            ColorType::Indexed if self.entries.len() > 1 << ihdr.bit_depth => Err(PaletteError::TooManyEntries {
+                entries: self.entries.len(),
+                bit_depth: ihdr.bit_depth
+            }),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Shown as e.g. `16 colors`.
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.entries.len() {
+            1 => f.write_str("1 color"),
+            entries => write!(f, "{} colors", entries)
+        }
+    }
+}
+
+/// Approximate usage frequencies of the palette entries, from hIST, with one
+/// entry per PLTE entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram(pub Vec<u16>);
+
+impl Histogram {
+    pub fn parse(data: &[u8]) -> Result<Histogram, PaletteError> {
+        if !data.len().is_multiple_of(2) {
+            return Err(PaletteError::OddHistogramLength { len: data.len() });
+        }
+        Ok(Histogram(data.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect()))
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Histogram, PaletteError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != HIST {
+            return Err(PaletteError::NotHist { chunk_type });
+        }
+        Histogram::parse(chunk.data())
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = self.0.iter().flat_map(|frequency| frequency.to_be_bytes()).collect();
+        Chunk::new(ChunkType::from_str(HIST).unwrap(), data)
+    }
+
+    /// Checks there is exactly one frequency per palette entry.
+    pub fn validate(&self, palette_entries: usize) -> Result<(), PaletteError> {
+        match self.0.len() == palette_entries {
+            true => Ok(()),
+            false => Err(PaletteError::HistogramMismatch { entries: self.0.len(), palette_entries })
+        }
+    }
+}
+
+/// Shown as e.g. `usage of 16 palette entries, 2 unused`.
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "usage of {} palette entries, {} unused",
+            self.0.len(),
+            self.0.iter().filter(|&&frequency| frequency == 0).count()
+        )
+    }
+}
+
+/// One color of a suggested palette. With a sample depth of 8 the samples are
+/// 0 to 255.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuggestedEntry {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+    /// Proportional to how often the color appears in the image.
+    pub frequency: u16
+}
+
+/// A palette for viewers that can show fewer colors than the image has, from
+/// sPLT, laid out as a name following the rules of text keywords, a null
+/// separator, the sample depth and the entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestedPalette {
+    pub name: String,
+    /// 8 or 16.
+    pub sample_depth: u8,
+    pub entries: Vec<SuggestedEntry>
+}
+
+impl SuggestedPalette {
+    pub fn parse(data: &[u8]) -> Result<SuggestedPalette, PaletteError> {
+        let separator = data.iter().position(|&byte| byte == 0).ok_or(PaletteError::MissingSeparator)?;
+        let name = text::from_latin1(&data[..separator]);
+        text::validate_keyword(&name)?;
+
+        let (&sample_depth, entries) = data[separator + 1..].split_first().ok_or(PaletteError::MissingSampleDepth)?;
+        let entry_len = match sample_depth {
+            8 => 6,
+            16 => 10,
+            depth => return Err(PaletteError::UnknownSampleDepth { depth })
+        };
+        if !entries.len().is_multiple_of(entry_len) {
+            return Err(PaletteError::SuggestedEntriesLength { len: entries.len(), entry_len });
+        }
+        let read = |entry: &[u8], index: usize| match sample_depth {
+            8 => entry[index] as u16,
+            _ => u16::from_be_bytes([entry[index * 2], entry[index * 2 + 1]])
+        };
+        let entries = entries
+            .chunks_exact(entry_len)
+            .map(|entry| SuggestedEntry {
+                red: read(entry, 0),
+                green: read(entry, 1),
+                blue: read(entry, 2),
+                alpha: read(entry, 3),
+                frequency: u16::from_be_bytes([entry[entry_len - 2], entry[entry_len - 1]])
+            })
+            .collect();
+        Ok(SuggestedPalette { name, sample_depth, entries })
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<SuggestedPalette, PaletteError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != SPLT {
+            return Err(PaletteError::NotSplt { chunk_type });
+        }
+        SuggestedPalette::parse(chunk.data())
+    }
+
+    pub fn to_chunk(&self) -> Result<Chunk, PaletteError> {
+        let mut data = text::to_latin1(&self.name)?;
+        data.extend_from_slice(&[0, self.sample_depth]);
+        for entry in &self.entries {
+            for sample in [entry.red, entry.green, entry.blue, entry.alpha] {
+                match self.sample_depth {
+                    8 => data.push(sample as u8),
+                    _ => data.extend_from_slice(&sample.to_be_bytes())
+                }
+            }
+            data.extend_from_slice(&entry.frequency.to_be_bytes());
+        }
+        Ok(Chunk::new(ChunkType::from_str(SPLT).unwrap(), data))
+    }
+}
+
+/// Shown as e.g. `"web safe": 216 colors, 8-bit`.
+impl fmt::Display for SuggestedPalette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {} colors, {}-bit", self.name, self.entries.len(), self.sample_depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_round_trip() {
+        let palette = Palette { entries: vec![[255, 0, 0], [0, 128, 255]] };
+        let chunk = palette.to_chunk();
+
+        assert_eq!(chunk.chunk_type().to_string(), "PLTE");
+        assert_eq!(chunk.data(), [255, 0, 0, 0, 128, 255]);
+        assert_eq!(Palette::from_chunk(&chunk).unwrap(), palette);
+        assert_eq!(palette.to_string(), "2 colors");
+    }
+
+    #[test]
+    fn test_invalid_palette() {
+        for len in [0, 4, 257 * 3] {
+            assert!(matches!(Palette::parse(&vec![0; len]), Err(PaletteError::WrongLength { .. })), "{}", len);
+        }
+
+        let palette = Palette::parse(&[0; 5 * 3]).unwrap();
+        let indexed = |bit_depth| Ihdr::new(1, 1, bit_depth, ColorType::Indexed).unwrap();
+        assert!(palette.validate(&indexed(4)).is_ok());
+        assert!(matches!(
+            palette.validate(&indexed(2)),
+            Err(PaletteError::TooManyEntries { entries: 5, bit_depth: 2 })
+        ));
+        assert!(palette.validate(&Ihdr::new(1, 1, 8, ColorType::Rgb).unwrap()).is_ok());
+        assert!(matches!(
+            palette.validate(&Ihdr::new(1, 1, 8, ColorType::Grayscale).unwrap()),
+            Err(PaletteError::NotAllowed { color_type: ColorType::Grayscale })
+        ));
+    }
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram(vec![3, 0, 258]);
+        let chunk = histogram.to_chunk();
+
+        assert_eq!(chunk.data(), [0, 3, 0, 0, 1, 2]);
+        assert_eq!(Histogram::from_chunk(&chunk).unwrap(), histogram);
+        assert_eq!(histogram.to_string(), "usage of 3 palette entries, 1 unused");
+        assert!(histogram.validate(3).is_ok());
+        assert!(matches!(histogram.validate(4), Err(PaletteError::HistogramMismatch { entries: 3, palette_entries: 4 })));
+        assert!(matches!(Histogram::parse(&[0; 3]), Err(PaletteError::OddHistogramLength { len: 3 })));
+    }
+
+    #[test]
+    fn test_suggested_palette_round_trip() {
+        let entry = SuggestedEntry { red: 1000, green: 0, blue: 65535, alpha: 65535, frequency: 7 };
+        let palette = SuggestedPalette { name: "deep".to_string(), sample_depth: 16, entries: vec![entry; 2] };
+        let chunk = palette.to_chunk().unwrap();
+
+        assert_eq!(chunk.data().len(), 5 + 1 + 2 * 10);
+        assert_eq!(SuggestedPalette::from_chunk(&chunk).unwrap(), palette);
+        assert_eq!(palette.to_string(), "\"deep\": 2 colors, 16-bit");
+
+        let shallow = SuggestedPalette::parse(b"web\0\x08\xff\x00\x00\xff\x00\x01").unwrap();
+        assert_eq!(shallow.entries, [SuggestedEntry { red: 255, green: 0, blue: 0, alpha: 255, frequency: 1 }]);
+    }
+
+    #[test]
+    fn test_invalid_suggested_palette() {
+        assert!(matches!(SuggestedPalette::parse(b"web"), Err(PaletteError::MissingSeparator)));
+        assert!(matches!(SuggestedPalette::parse(b"web\0"), Err(PaletteError::MissingSampleDepth)));
+        assert!(matches!(SuggestedPalette::parse(b"web\0\x04"), Err(PaletteError::UnknownSampleDepth { depth: 4 })));
+        assert!(matches!(
+            SuggestedPalette::parse(b"web\0\x08\0\0\0"),
+            Err(PaletteError::SuggestedEntriesLength { len: 3, entry_len: 6 })
+        ));
+        assert!(matches!(SuggestedPalette::parse(b"\0\x08"), Err(PaletteError::InvalidName(_))));
+    }
+}
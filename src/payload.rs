@@ -0,0 +1,105 @@
+use crate::error::PayloadError;
+
+/// Marks a chunk payload as written by pngme.
+pub const MAGIC: [u8;4] = *b"PMEv";
+/// The header version written by this release.
+pub const VERSION: u8 = 1;
+/// Magic, version and the options byte.
+pub const HEADER_LEN: usize = 6;
+
+const AUTHENTICATED: u8 = 1;
+const ENCRYPTED: u8 = 1 << 1;
+const COMPRESSED: u8 = 1 << 2;
+const ENVELOPED: u8 = 1 << 3;
+
+/// Which layers `encode` wrapped the message in, outermost first. The
+/// layers carry their own markers as well; the header lets a reader tell
+/// what to expect without peeling them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    /// An HMAC tag, see `crypto::authenticate`.
+    pub authenticated: bool,
+    /// Any kind of encryption, pngme's own or age.
+    pub encrypted: bool,
+    /// See `compress::compress`.
+    pub compressed: bool,
+    /// A content type envelope, see `envelope::Envelope`.
+    pub enveloped: bool
+}
+
+impl Options {
+    fn to_byte(self) -> u8 {
+        [
+            (self.authenticated, AUTHENTICATED),
+            (self.encrypted, ENCRYPTED),
+            (self.compressed, COMPRESSED),
+            (self.enveloped, ENVELOPED)
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |byte, (_, bit)| byte | bit)
+    }
+
+    fn from_byte(byte: u8) -> Result<Options, PayloadError> {
+        let unknown = byte & !(AUTHENTICATED | ENCRYPTED | COMPRESSED | ENVELOPED);
+        if unknown != 0 {
+            return Err(PayloadError::UnknownOptions { bits: unknown });
+        }
+        Ok(Options {
+            authenticated: byte & AUTHENTICATED != 0,
+            encrypted: byte & ENCRYPTED != 0,
+            compressed: byte & COMPRESSED != 0,
+            enveloped: byte & ENVELOPED != 0
+        })
+    }
+}
+
+/// Whether a chunk payload starts with the pngme header.
+pub fn is_pngme(payload: &[u8]) -> bool {
+    payload.starts_with(&MAGIC)
+}
+
+/// Prefixes `data` with the current header version and `options`.
+pub fn wrap(options: Options, data: &[u8]) -> Vec<u8> {
+    [&MAGIC[..], &[VERSION, options.to_byte()], data].concat()
+}
+
+/// Splits a payload into the options from its header and the data after it.
+/// Headers from a newer version are refused rather than guessed at.
+pub fn parse(payload: &[u8]) -> Result<(Options, &[u8]), PayloadError> {
+    if !is_pngme(payload) {
+        return Err(PayloadError::NotPngme);
+    }
+    if payload.len() < HEADER_LEN {
+        return Err(PayloadError::Truncated { len: payload.len() });
+    }
+    let version = payload[MAGIC.len()];
+    if version != VERSION {
+        return Err(PayloadError::UnsupportedVersion { version });
+    }
+    Ok((Options::from_byte(payload[MAGIC.len() + 1])?, &payload[HEADER_LEN..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_and_parse() {
+        let options = Options { encrypted: true, compressed: true, ..Options::default() };
+        let payload = wrap(options, b"data");
+
+        assert_eq!(payload, b"PMEv\x01\x06data");
+        assert!(is_pngme(&payload));
+        assert_eq!(parse(&payload).unwrap(), (options, &b"data"[..]));
+        assert_eq!(parse(&wrap(Options::default(), b"")).unwrap(), (Options::default(), &b""[..]));
+    }
+
+    #[test]
+    fn test_parse_refuses_unknown_headers() {
+        assert!(matches!(parse(b"hello"), Err(PayloadError::NotPngme)));
+        assert!(matches!(parse(b"PMEv\x01"), Err(PayloadError::Truncated { len: 5 })));
+        assert!(matches!(parse(b"PMEv\x02\x00"), Err(PayloadError::UnsupportedVersion { version: 2 })));
+        assert!(matches!(parse(b"PMEv\x01\x30"), Err(PayloadError::UnknownOptions { bits: 0x30 })));
+    }
+}
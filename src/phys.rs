@@ -0,0 +1,142 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PhysError;
+
+/// The chunk type of the physical pixel dimensions.
+pub const PHYS: &str = "pHYs";
+/// Pixels per unit on each axis as big-endian u32s, then the unit byte.
+pub const DATA_LEN: usize = 9;
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// What the pixels-per-unit values of pHYs count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Only the ratio of the two axes, the pixel aspect ratio, is known.
+    Unknown,
+    Meter
+}
+
+/// The intended pixel size or aspect ratio, as stored in a pHYs chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Phys {
+    pub x_pixels_per_unit: u32,
+    pub y_pixels_per_unit: u32,
+    pub unit: Unit
+}
+
+impl Phys {
+    /// Square pixels at `dpi` dots per inch, rounded to whole pixels per meter.
+    pub fn from_dpi(dpi: f64) -> Result<Phys, PhysError> {
+        let pixels_per_meter = (dpi / METERS_PER_INCH).round();
+        if !pixels_per_meter.is_finite() || pixels_per_meter < 1.0 || pixels_per_meter > u32::MAX as f64 {
+            return Err(PhysError::InvalidDpi { dpi });
+        }
+        Ok(Phys {
+            x_pixels_per_unit: pixels_per_meter as u32,
+            y_pixels_per_unit: pixels_per_meter as u32,
+            unit: Unit::Meter
+        })
+    }
+
+    /// Dots per inch on each axis, when the unit is known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        match self.unit {
+            Unit::Meter => Some((
+                self.x_pixels_per_unit as f64 * METERS_PER_INCH,
+                self.y_pixels_per_unit as f64 * METERS_PER_INCH
+            )),
+            Unit::Unknown => None
+        }
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Phys, PhysError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != PHYS {
+            return Err(PhysError::NotPhys { chunk_type });
+        }
+        let data: &[u8;DATA_LEN] = chunk
+            .data()
+            .try_into()
+            .map_err(|_| PhysError::WrongLength { len: chunk.data().len() })?;
+        let unit = match data[8] {
+            0 => Unit::Unknown,
+            1 => Unit::Meter,
+            unit => return Err(PhysError::UnknownUnit { unit })
+        };
+        Ok(Phys {
+            x_pixels_per_unit: u32::from_be_bytes(data[0..4].try_into().unwrap()),
+            y_pixels_per_unit: u32::from_be_bytes(data[4..8].try_into().unwrap()),
+            unit
+        })
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [
+            &self.x_pixels_per_unit.to_be_bytes()[..],
+            &self.y_pixels_per_unit.to_be_bytes(),
+            &[(self.unit == Unit::Meter) as u8]
+        ].concat();
+        Chunk::new(ChunkType::from_str(PHYS).unwrap(), data)
+    }
+}
+
+/// Shown as e.g. `300 DPI (11811 pixels per meter)`, or as the aspect ratio
+/// when the unit is unknown.
+impl fmt::Display for Phys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let round = |dpi: f64| (dpi * 100.0).round() / 100.0;
+        match self.dpi() {
+            Some((x, _)) if self.x_pixels_per_unit == self.y_pixels_per_unit => {
+                write!(f, "{} DPI ({} pixels per meter)", round(x), self.x_pixels_per_unit)
+            },
+            Some((x, y)) => write!(
+                f,
+                "{} x {} DPI ({} x {} pixels per meter)",
+                round(x), round(y), self.x_pixels_per_unit, self.y_pixels_per_unit
+            ),
+            None => write!(f, "pixel aspect ratio {}:{}", self.x_pixels_per_unit, self.y_pixels_per_unit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phys_round_trip() {
+        let phys = Phys::from_dpi(300.0).unwrap();
+        let chunk = phys.to_chunk();
+
+        assert_eq!(phys.x_pixels_per_unit, 11811);
+        assert_eq!(chunk.chunk_type().to_string(), "pHYs");
+        assert_eq!(chunk.data(), [0, 0, 0x2e, 0x23, 0, 0, 0x2e, 0x23, 1]);
+        assert_eq!(Phys::from_chunk(&chunk).unwrap(), phys);
+        assert_eq!(phys.to_string(), "300 DPI (11811 pixels per meter)");
+    }
+
+    #[test]
+    fn test_display() {
+        let phys = |x, y, unit| Phys { x_pixels_per_unit: x, y_pixels_per_unit: y, unit };
+
+        assert_eq!(phys(2835, 2835, Unit::Meter).to_string(), "72.01 DPI (2835 pixels per meter)");
+        assert_eq!(phys(3780, 7559, Unit::Meter).to_string(), "96.01 x 192 DPI (3780 x 7559 pixels per meter)");
+        assert_eq!(phys(2, 1, Unit::Unknown).to_string(), "pixel aspect ratio 2:1");
+        assert_eq!(phys(2, 1, Unit::Unknown).dpi(), None);
+    }
+
+    #[test]
+    fn test_invalid_phys() {
+        let chunk = |data: &[u8]| Chunk::new(ChunkType::from_str("pHYs").unwrap(), data.to_vec());
+
+        assert!(matches!(Phys::from_chunk(&chunk(&[0; 8])), Err(PhysError::WrongLength { len: 8 })));
+        assert!(matches!(Phys::from_chunk(&chunk(&[0, 0, 0, 1, 0, 0, 0, 1, 2])), Err(PhysError::UnknownUnit { unit: 2 })));
+        for dpi in [0.0, -72.0, f64::NAN, f64::INFINITY, 1e12] {
+            assert!(Phys::from_dpi(dpi).is_err(), "{}", dpi);
+        }
+    }
+}
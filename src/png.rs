@@ -1,29 +1,146 @@
-use std::{fmt, fs, path::Path};
+use std::{fmt, fs::File, path::Path};
+use std::io::{self, BufReader, Read, Write};
 
-use crate::{Error, Result, chunk::Chunk};
-use anyhow::anyhow;
+use log::{debug, trace};
 
+use crate::{
+    Result,
+    check::{self, Problem},
+    chunk::Chunk,
+    chunk_ref::ChunkRef,
+    error::{ChunkError, IhdrError, PngError},
+    ihdr::{self, Ihdr}
+};
+
+#[derive(Debug)]
 pub struct Png {
     header: [u8;8],
-    chunks: Vec<Chunk>
+    chunks: Vec<Chunk>,
+    trailing: Vec<u8>
 }
 
 impl Png {
-    const STANDARD_HEADER: [u8;8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    pub const STANDARD_HEADER: [u8;8] = [137, 80, 78, 71, 13, 10, 26, 10];
 
     pub fn try_from_path(file_path: &Path) -> Result<Png> {
-        Png::try_from(fs::read(file_path)?.as_slice())
+        Png::from_reader(BufReader::new(File::open(file_path)?))
     }
 
-    fn from_chunks(chunks: Vec<Chunk>) -> Png {
+    /// Parses a PNG from `reader`. Each chunk's data is copied into an owned
+    /// `Chunk`; `ChunkRef` or `MappedPng` read chunks without copying them.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Png> {
+        let mut header = [0; 8];
+        let header_read = read_fully(&mut reader, &mut header)?;
+        if header_read < header.len() {
+            return Err(PngError::TruncatedSignature { read: header_read });
+        }
+
+        if header != Png::STANDARD_HEADER {
+            return Err(PngError::InvalidSignature);
+        }
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut offset = header.len();
+        let mut chunk_bytes = Vec::new();
+        let mut seen_iend = false;
+
+        loop {
+            match read_chunk(&mut reader, offset, &mut chunk_bytes) {
+                Ok(Some(chunk)) => {
+                    trace!("{} chunk at offset {}, {} bytes of data", chunk.chunk_type(), offset, chunk.length());
+                    seen_iend |= chunk.chunk_type().bytes() == *b"IEND";
+                    offset += chunk.byte_len();
+                    chunks.push(chunk);
+                },
+                Ok(None) => break,
+                // Whatever follows IEND and doesn't parse as a chunk is data appended to the file
+                Err(error) if seen_iend && !matches!(error, PngError::Io(_)) => {
+                    reader.read_to_end(&mut chunk_bytes)?;
+                    debug!("{} bytes of trailing data at offset {}", chunk_bytes.len(), offset);
+                    return Ok(Png {
+                        header,
+                        chunks,
+                        trailing: chunk_bytes
+                    });
+                },
+                Err(error) => return Err(error)
+            }
+        }
+
+        Ok(Png {
+            header,
+            chunks,
+            trailing: Vec::new()
+        })
+    }
+
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
         Png {
             header: Png::STANDARD_HEADER,
-            chunks
+            chunks,
+            trailing: Vec::new()
         }
     }
 
+    pub fn into_chunks(self) -> Vec<Chunk> {
+        self.chunks
+    }
+
+    /// Adds a chunk at the first position the ordering rules allow for its
+    /// type, which for most ancillary chunks is just before IEND.
     pub fn append_chunk(&mut self, chunk: Chunk) {
-        self.chunks.push(chunk);
+        let index = self.insertion_index(&chunk.chunk_type().bytes());
+        self.chunks.insert(index, chunk);
+    }
+
+    fn insertion_index(&self, chunk_type: &[u8;4]) -> usize {
+        let first_of = |types: &[&[u8;4]]| self.chunks
+            .iter()
+            .position(|chunk| types.contains(&&chunk.chunk_type().bytes()));
+
+        let index = match chunk_type {
+            b"IHDR" => Some(0),
+            b"IEND" => None,
+            b"IDAT" => self.chunks
+                .iter()
+                .rposition(|chunk| chunk.chunk_type().bytes() == *b"IDAT")
+                .map(|last| last + 1)
+                .or_else(|| first_of(&[b"IEND"])),
+            chunk_type => first_of(insert_before(chunk_type))
+        };
+        index.unwrap_or(self.chunks.len())
+    }
+
+    pub fn ordering_problems(&self) -> Vec<Problem> {
+        check::validate_ordering(self)
+    }
+
+    pub fn insert_chunk_at(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        if index > self.chunks.len() {
+            return Err(PngError::IndexOutOfRange {
+                index,
+                len: self.chunks.len()
+            });
+        }
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    pub fn insert_before_iend(&mut self, chunk: Chunk) {
+        match self.chunks.iter().position(|chunk| chunk.chunk_type().to_string() == "IEND") {
+            Some(index) => self.chunks.insert(index, chunk),
+            None => self.chunks.push(chunk)
+        }
+    }
+
+    pub fn insert_after_type(&mut self, chunk_type: &str, chunk: Chunk) -> Result<()> {
+        match self.chunks.iter().rposition(|chunk| chunk.chunk_type().to_string() == chunk_type) {
+            Some(index) => {
+                self.chunks.insert(index + 1, chunk);
+                Ok(())
+            },
+            None => Err(PngError::ChunkNotFound { chunk_type: chunk_type.to_string() })
+        }
     }
 
     pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
@@ -33,16 +150,59 @@ impl Png {
                     let removed = self.chunks.remove(index);
                     Ok(removed)
                 },
-                None => Err(anyhow!("No chunk matching the chunk_type could be found"))
+                None => Err(PngError::ChunkNotFound { chunk_type: chunk_type.to_string() })
             }
     }
 
+    pub fn remove_nth_chunk(&mut self, chunk_type: &str, n: usize) -> Result<Chunk> {
+        let positions: Vec<usize> = self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.chunk_type().to_string() == chunk_type)
+            .map(|(position, _)| position)
+            .collect();
+
+        match positions.get(n) {
+            Some(&position) => Ok(self.chunks.remove(position)),
+            None if positions.is_empty() => Err(PngError::ChunkNotFound { chunk_type: chunk_type.to_string() }),
+            None => Err(PngError::IndexOutOfRange { index: n, len: positions.len() })
+        }
+    }
+
+    pub fn remove_chunks_where<F>(&mut self, mut predicate: F) -> Vec<Chunk>
+    where
+        F: FnMut(&Chunk) -> bool
+    {
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| predicate(chunk));
+        self.chunks = kept;
+        removed
+    }
+
+    /// Replaces the IDAT chunks with `idat`, where the first of them was.
+    pub fn replace_idat(&mut self, idat: Vec<Chunk>) {
+        let is_idat = |chunk: &Chunk| chunk.chunk_type().bytes() == *b"IDAT";
+        let index = self.chunks.iter().position(is_idat);
+        self.chunks.retain(|chunk| !is_idat(chunk));
+        let index = index.unwrap_or_else(|| self.insertion_index(b"IDAT"));
+        self.chunks.splice(index..index, idat);
+    }
+
     pub fn header(&self) -> &[u8;8] {
         &self.header
     }
 
     pub fn chunks(&self) -> &[Chunk] {
-        &self.chunks.as_slice()
+        self.chunks.as_slice()
+    }
+
+    /// The image properties from the first chunk, which must be IHDR.
+    pub fn ihdr(&self) -> Result<Ihdr, IhdrError> {
+        match self.chunks.first() {
+            Some(chunk) if chunk.chunk_type().to_string() == ihdr::IHDR => Ihdr::from_chunk(chunk),
+            _ => Err(IhdrError::Missing)
+        }
     }
 
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
@@ -51,6 +211,59 @@ impl Png {
             .find(|&chunk| chunk.chunk_type().to_string() == chunk_type)
     }
 
+    pub fn chunks_with_offsets(&self) -> impl Iterator<Item = (usize, &Chunk)> {
+        self.chunks
+            .iter()
+            .scan(self.header.len(), |offset, chunk| {
+                let chunk_offset = *offset;
+                *offset += chunk.byte_len();
+                Some((chunk_offset, chunk))
+            })
+    }
+
+    pub fn chunk_offset(&self, index: usize) -> Option<usize> {
+        self.chunks_with_offsets()
+            .nth(index)
+            .map(|(offset, _)| offset)
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.header.len() + self.chunks
+            .iter()
+            .map(Chunk::byte_len)
+            .sum::<usize>() + self.trailing.len()
+    }
+
+    /// Bytes found after IEND that don't form a chunk, such as an appended archive.
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    pub fn set_trailing_data(&mut self, trailing: Vec<u8>) {
+        self.trailing = trailing;
+    }
+
+    pub fn strip_trailing_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.trailing)
+    }
+
+    pub fn chunks_by_type(&self, chunk_type: &str) -> Vec<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type().to_string() == chunk_type)
+            .collect()
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.header)?;
+        for chunk in &self.chunks {
+            chunk.write_to(&mut writer)?;
+        }
+        writer.write_all(&self.trailing)?;
+        writer.flush()?;
+        Ok(())
+    }
+
     pub fn as_bytes(&self) -> Vec<u8> {
         self.header
             .into_iter()
@@ -59,63 +272,122 @@ impl Png {
                     .iter()
                     .flat_map(|chunk| chunk.as_bytes())
             )
+            .chain(self.trailing.iter().copied())
             .collect()
     }
 }
 
+/// The chunk types a chunk of `chunk_type` has to come before; it goes in
+/// front of the first of them. IHDR and IDAT, which go first and after the
+/// other IDATs, are left to the caller.
+pub(crate) fn insert_before(chunk_type: &[u8;4]) -> &'static [&'static [u8;4]] {
+    match chunk_type {
+        chunk_type if check::BEFORE_PLTE.contains(&chunk_type) => &[b"PLTE", b"IDAT", b"IEND"],
+        chunk_type if chunk_type == b"PLTE"
+            || check::AFTER_PLTE.contains(&chunk_type)
+            || check::BEFORE_IDAT.contains(&chunk_type) => &[b"IDAT", b"IEND"],
+        _ => &[b"IEND"]
+    }
+}
+
+/// Reads the next chunk, leaving every byte it consumed in `chunk_bytes`.
+fn read_chunk<R: Read>(reader: &mut R, offset: usize, chunk_bytes: &mut Vec<u8>) -> Result<Option<Chunk>> {
+    chunk_bytes.clear();
+
+    let mut length_bytes = [0; 4];
+    let read = read_fully(reader, &mut length_bytes)?;
+    chunk_bytes.extend_from_slice(&length_bytes[..read]);
+    match read {
+        0 => return Ok(None),
+        4 => (),
+        read => return Err(PngError::TruncatedChunk {
+            offset,
+            expected: 4,
+            found: read as u64
+        })
+    }
+
+    let length = u32::from_be_bytes(length_bytes);
+    if length > Chunk::MAX_LENGTH {
+        return Err(PngError::InvalidChunk {
+            offset,
+            source: ChunkError::LengthTooLarge { length: length as usize }
+        });
+    }
+
+    // Read through `take` so a corrupt length can't make us allocate gigabytes up front
+    let remaining_length = 8 + length as u64;
+    let read = reader.by_ref().take(remaining_length).read_to_end(chunk_bytes)?;
+    if (read as u64) < remaining_length {
+        return Err(PngError::TruncatedChunk {
+            offset,
+            expected: remaining_length,
+            found: read as u64
+        });
+    }
+
+    ChunkRef::parse(chunk_bytes)
+        .and_then(|(chunk, _)| chunk.to_chunk())
+        .map(Some)
+        .map_err(|source| PngError::InvalidChunk { offset, source })
+}
+
+pub(crate) fn read_fully<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(count) => read += count,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error)
+        }
+    }
+    Ok(read)
+}
+
+/// Like `Png::from_reader`, this copies every chunk's data out of `value`.
 impl TryFrom<&[u8]> for Png {
-    type Error = Error;
+    type Error = PngError;
 
     fn try_from(value: &[u8]) -> Result<Self> {
-        let bytes_length = value.len();
-        if bytes_length < 8 {
-            return Err(anyhow!("The given bytes are not long enough for a valid Png File"))
+        if value.len() < Png::STANDARD_HEADER.len() {
+            return Err(PngError::TruncatedSignature { read: value.len() });
         }
-        
-        let mut value = value.iter().peekable();
-        
-        let header: [u8; 8] = value
-            .by_ref()
-            .take(8)
-            .copied()
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap();
-        
+
+        let (header, rest) = value.split_at(Png::STANDARD_HEADER.len());
         if header != Png::STANDARD_HEADER {
-            return Err(anyhow!("The given header doesn't match the PNG standard header"));
+            return Err(PngError::InvalidSignature);
         }
-        
-        let mut chunks: Vec<Chunk> = Vec::new();
 
-        while value.peek().is_some() {
-            let length_bytes: [u8;4] = value
-                .by_ref()
-                .take(4)
-                .copied()
-                .collect::<Vec<u8>>()
-                .try_into()
-                .unwrap();
-            
-            let length = u32::from_be_bytes(length_bytes);
-
-            let length = length as usize;
-            let all_bytes: Box<[u8]> = length_bytes
-                .into_iter()
-                .chain(value
-                    .by_ref()
-                    .take(8+length)
-                    .copied()
-                )
-                .collect();
-            println!("{length}, {}", all_bytes.len());
-
-            chunks.push(Chunk::try_from(all_bytes.as_ref())?);
+        let mut chunks = Vec::new();
+        let mut rest = rest;
+        let mut offset = header.len();
+        let mut seen_iend = false;
+
+        while !rest.is_empty() {
+            let parsed = ChunkRef::parse(rest)
+                .and_then(|(chunk, remaining)| Ok((chunk.to_chunk()?, remaining)));
+            match parsed {
+                Ok((chunk, remaining)) => {
+                    trace!("{} chunk at offset {}, {} bytes of data", chunk.chunk_type(), offset, chunk.length());
+                    seen_iend |= chunk.chunk_type().bytes() == *b"IEND";
+                    offset += chunk.byte_len();
+                    chunks.push(chunk);
+                    rest = remaining;
+                },
+                // Whatever follows IEND and doesn't parse as a chunk is data appended to the file
+                Err(_) if seen_iend => {
+                    debug!("{} bytes of trailing data at offset {}", rest.len(), offset);
+                    break;
+                },
+                Err(source) => return Err(PngError::InvalidChunk { offset, source })
+            }
         }
 
         Ok(Png {
-            header,
-            chunks
+            header: Png::STANDARD_HEADER,
+            chunks,
+            trailing: rest.to_vec()
         })
     }
 }
@@ -127,22 +399,63 @@ impl fmt::Display for Png {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Png {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Png", 3)?;
+        state.serialize_field("header", &self.header)?;
+        state.serialize_field("chunks", &self.chunks)?;
+        if self.trailing.is_empty() {
+            state.skip_field("trailing")?;
+        } else {
+            state.serialize_field("trailing", &STANDARD.encode(&self.trailing))?;
+        }
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Png {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use serde::de::Error as _;
+
+        #[derive(serde::Deserialize)]
+        struct SerializedPng {
+            header: Option<[u8;8]>,
+            chunks: Vec<Chunk>,
+            trailing: Option<String>
+        }
+
+        let serialized = SerializedPng::deserialize(deserializer)?;
+        if serialized.header.is_some_and(|header| header != Png::STANDARD_HEADER) {
+            return Err(D::Error::custom("The given header doesn't match the PNG standard header"));
+        }
+
+        let mut png = Png::from_chunks(serialized.chunks);
+        if let Some(trailing) = serialized.trailing {
+            png.trailing = STANDARD.decode(&trailing).map_err(D::Error::custom)?;
+        }
+        Ok(png)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::chunk_type::ChunkType;
     use crate::chunk::Chunk;
-    use std::str::FromStr;
     use std::convert::TryFrom;
 
     fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
-
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
-
-        chunks
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap()
+        ]
     }
 
     fn testing_png() -> Png {
@@ -226,6 +539,41 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_from_reader() {
+        let png = Png::from_reader(std::io::Cursor::new(PNG_FILE.to_vec())).unwrap();
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_from_reader_truncated_signature() {
+        let error = Png::from_reader(&PNG_FILE[..5]).err().unwrap();
+        assert!(error.to_string().contains("after 5 bytes"));
+    }
+
+    #[test]
+    fn test_from_reader_truncated_chunk() {
+        let error = Png::from_reader(&PNG_FILE[..PNG_FILE.len() - 2]).err().unwrap();
+        assert!(error.to_string().contains("offset 4791"));
+        assert!(matches!(error, PngError::TruncatedChunk { offset: 4791, expected: 8, found: 6 }));
+
+        let error = Png::from_reader(&PNG_FILE[..10]).err().unwrap();
+        assert!(matches!(error, PngError::TruncatedChunk { offset: 8, expected: 4, found: 2 }));
+    }
+
+    #[test]
+    fn test_from_reader_bad_crc() {
+        let mut bytes = PNG_FILE.to_vec();
+        *bytes.last_mut().unwrap() ^= 0xff;
+
+        let error = Png::from_reader(bytes.as_slice()).err().unwrap();
+        assert!(matches!(
+            error,
+            PngError::InvalidChunk { offset: 4791, source: ChunkError::InvalidCrc { .. } }
+        ));
+    }
+
     #[test]
     fn test_list_chunks() {
         let png = testing_png();
@@ -242,6 +590,18 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am a second first chunk").unwrap());
+
+        let chunks = png.chunks_by_type("FrSt");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(&chunks[0].data_as_string().unwrap(), "I am the first chunk");
+        assert_eq!(&chunks[1].data_as_string().unwrap(), "I am a second first chunk");
+        assert!(png.chunks_by_type("NoNe").is_empty());
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
@@ -251,6 +611,66 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
+    #[test]
+    fn test_append_chunk_respects_ordering() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "").unwrap(),
+            chunk_from_strings("PLTE", "").unwrap(),
+            chunk_from_strings("IDAT", "").unwrap(),
+            chunk_from_strings("IEND", "").unwrap()
+        ]);
+        png.append_chunk(chunk_from_strings("tEXt", "").unwrap());
+        png.append_chunk(chunk_from_strings("gAMA", "").unwrap());
+        png.append_chunk(chunk_from_strings("tRNS", "").unwrap());
+        png.append_chunk(chunk_from_strings("IDAT", "").unwrap());
+
+        assert_eq!(
+            chunk_types(&png),
+            vec!["IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "tEXt", "IEND"]
+        );
+    }
+
+    fn chunk_types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_chunk_at() {
+        let mut png = testing_png();
+        png.insert_chunk_at(1, chunk_from_strings("TeSt", "Message").unwrap()).unwrap();
+        assert_eq!(chunk_types(&png), vec!["FrSt", "TeSt", "miDl", "LASt"]);
+
+        png.insert_chunk_at(4, chunk_from_strings("EnDs", "Message").unwrap()).unwrap();
+        assert_eq!(chunk_types(&png), vec!["FrSt", "TeSt", "miDl", "LASt", "EnDs"]);
+
+        assert!(png.insert_chunk_at(6, chunk_from_strings("TeSt", "Message").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_insert_before_iend() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.insert_before_iend(chunk_from_strings("TeSt", "Message").unwrap());
+        let types = chunk_types(&png);
+        assert_eq!(&types[types.len() - 2..], ["TeSt", "IEND"]);
+
+        let mut png = testing_png();
+        png.insert_before_iend(chunk_from_strings("TeSt", "Message").unwrap());
+        assert_eq!(chunk_types(&png), vec!["FrSt", "miDl", "LASt", "TeSt"]);
+    }
+
+    #[test]
+    fn test_insert_after_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am another middle").unwrap());
+        png.insert_after_type("miDl", chunk_from_strings("TeSt", "Message").unwrap()).unwrap();
+        assert_eq!(chunk_types(&png), vec!["FrSt", "miDl", "LASt", "miDl", "TeSt"]);
+
+        assert!(png.insert_after_type("NoNe", chunk_from_strings("TeSt", "Message").unwrap()).is_err());
+    }
+
     #[test]
     fn test_remove_chunk() {
         let mut png = testing_png();
@@ -260,12 +680,54 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_remove_nth_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am another middle").unwrap());
+
+        let removed = png.remove_nth_chunk("miDl", 1).unwrap();
+        assert_eq!(&removed.data_as_string().unwrap(), "I am another middle");
+        assert!(matches!(png.remove_nth_chunk("miDl", 1), Err(PngError::IndexOutOfRange { index: 1, len: 1 })));
+        assert!(matches!(png.remove_nth_chunk("TeSt", 0), Err(PngError::ChunkNotFound { .. })));
+    }
+
+    #[test]
+    fn test_remove_chunks_where() {
+        let mut png = testing_png();
+        let removed = png.remove_chunks_where(|chunk| !chunk.chunk_type().is_critical());
+        assert_eq!(removed.len(), 1);
+        assert_eq!(&removed[0].chunk_type().to_string(), "miDl");
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(&png.chunks()[0].chunk_type().to_string(), "FrSt");
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "LASt");
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
         assert!(png.is_ok());
     }
 
+    #[test]
+    fn test_ihdr() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.ihdr().is_ok());
+        assert!(matches!(testing_png().ihdr(), Err(IhdrError::Missing)));
+    }
+
+    #[test]
+    fn test_replace_idat() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let idat = |data: &str| chunk_from_strings("IDAT", data).unwrap();
+        png.replace_idat(vec![idat("new"), idat("data")]);
+
+        let types: Vec<String> = png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        let first = types.iter().position(|chunk_type| chunk_type == "IDAT").unwrap();
+        assert_eq!(types.iter().filter(|chunk_type| *chunk_type == "IDAT").count(), 2);
+        assert_eq!(png.chunks()[first].data(), b"new");
+        assert_eq!(png.chunks()[first + 1].data(), b"data");
+    }
+
     #[test]
     fn test_as_bytes() {
         let png = Png::try_from(&PNG_FILE[..]).unwrap();
@@ -274,6 +736,62 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_chunk_offsets() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let offsets: Vec<usize> = png.chunks_with_offsets()
+            .map(|(offset, _)| offset)
+            .collect();
+
+        assert_eq!(offsets, vec![8, 33, 46, 62, 83, 4776, 4791]);
+        assert_eq!(png.chunk_offset(5), Some(4776));
+        assert_eq!(png.chunk_offset(7), None);
+
+        let (offset, chunk) = png.chunks_with_offsets().last().unwrap();
+        assert_eq!(&PNG_FILE[offset..], chunk.as_bytes().as_slice());
+    }
+
+    #[test]
+    fn test_byte_len() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.byte_len(), PNG_FILE.len());
+    }
+
+    #[test]
+    fn test_write_to() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut bytes = Vec::new();
+        png.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_trailing_data_is_kept() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"PK\x03\x04 appended archive");
+
+        let png = Png::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.trailing_data(), b"PK\x03\x04 appended archive");
+        assert_eq!(png.as_bytes(), bytes);
+
+        let mut png = Png::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(png.byte_len(), bytes.len());
+        assert_eq!(png.strip_trailing_data().len(), 21);
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_junk_before_iend_is_an_error() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        let mut bytes = png.as_bytes();
+        let iend = bytes.len() - 12;
+        bytes.insert(iend, 0);
+
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -538,4 +1056,29 @@ mod tests {
         202, 28, 31, 66, 176, 235, 16, 0, 0, 0, 3, 82, 117, 83, 116, 104, 101, 121, 158, 176, 245,
         160, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
     ];
-}
\ No newline at end of file
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let png = testing_png();
+        let json = serde_json::to_string(&png).unwrap();
+        let restored: Png = serde_json::from_str(&json).unwrap();
+
+        assert!(json.contains("\"chunk_type\":\"FrSt\""));
+        assert_eq!(restored.as_bytes(), png.as_bytes());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_trailing_data() {
+        let mut png = testing_png();
+        assert!(!serde_json::to_string(&png).unwrap().contains("trailing"));
+
+        png.set_trailing_data(b"extra".to_vec());
+        let json = serde_json::to_string(&png).unwrap();
+        let restored: Png = serde_json::from_str(&json).unwrap();
+
+        assert!(json.contains("\"trailing\":\"ZXh0cmE=\""));
+        assert_eq!(restored.trailing_data(), b"extra");
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io::{self, BufReader, IsTerminal, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use log::LevelFilter;
+
+/// Files smaller than this are read before a bar would be worth drawing.
+const LARGE_FILE: u64 = 64 * 1024 * 1024;
+/// How often the bar is redrawn while bytes come in.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+#[derive(Clone, Copy)]
+pub enum Unit {
+    Bytes,
+    Files
+}
+
+/// A progress bar on the last line of standard error. It is only drawn when
+/// both standard output and standard error are terminals, and neither
+/// --quiet nor --verbose is given, as the log lines of the latter would
+/// break it up.
+pub struct Progress {
+    label: &'static str,
+    unit: Unit,
+    total: u64,
+    done: u64,
+    started: Instant,
+    drawn: Option<Instant>,
+    visible: bool
+}
+
+impl Progress {
+    pub fn new(label: &'static str, unit: Unit, total: u64) -> Progress {
+        let visible = io::stdout().is_terminal() && io::stderr().is_terminal() && log::max_level() == LevelFilter::Info;
+        Progress { label, unit, total, done: 0, started: Instant::now(), drawn: None, visible }
+    }
+
+    pub fn inc(&mut self, amount: u64) {
+        self.done += amount;
+        let due = match (self.unit, self.drawn) {
+            // Each file is a step worth showing, and output from it has
+            // usually just cleared the bar anyway
+            (Unit::Files, _) | (Unit::Bytes, None) => true,
+            (Unit::Bytes, Some(drawn)) => drawn.elapsed() >= REDRAW_INTERVAL
+        };
+        if due {
+            self.draw();
+        }
+    }
+
+    /// Takes the bar off the screen, so other output can be printed. It
+    /// comes back at the next `inc`.
+    pub fn clear(&mut self) {
+        if self.drawn.take().is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+
+    fn draw(&mut self) {
+        if !self.visible || self.total == 0 {
+            return;
+        }
+        let fraction = (self.done as f64 / self.total as f64).min(1.0);
+        let filled = (fraction * BAR_WIDTH as f64) as usize;
+        let eta = match self.done {
+            0 => String::new(),
+            done => {
+                let remaining = self.started.elapsed().as_secs_f64() * (self.total.saturating_sub(done)) as f64 / done as f64;
+                format!(", ETA {}", format_duration(remaining as u64))
+            }
+        };
+        let amount = match self.unit {
+            Unit::Bytes => format!("{}/{}", format_bytes(self.done), format_bytes(self.total)),
+            Unit::Files => format!("{}/{} files", self.done, self.total)
+        };
+        let mut stderr = io::stderr().lock();
+        // A bar that can't be drawn is no reason to fail the command
+        let _ = write!(
+            stderr,
+            "\r\x1b[K{} [{}{}] {:>3}% {}{}",
+            self.label,
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            (fraction * 100.0) as u32,
+            amount,
+            eta
+        );
+        let _ = stderr.flush();
+        self.drawn = Some(Instant::now());
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Counts the bytes read through it on a progress bar.
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: Progress
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.inc(read as u64);
+        Ok(read)
+    }
+}
+
+/// Opens a file for reading, with a progress bar if it is large enough to
+/// take a while.
+pub fn open(file_path: &Path) -> io::Result<BufReader<ProgressReader<File>>> {
+    let file = File::open(file_path)?;
+    let len = file.metadata()?.len();
+    let mut progress = Progress::new("Reading", Unit::Bytes, len);
+    // Small files are read before the bar would show anything useful
+    progress.visible &= len >= LARGE_FILE;
+    Ok(BufReader::new(ProgressReader { inner: file, progress }))
+}
+
+/// `fs::read`, with a progress bar for large files.
+pub fn read(file_path: &Path) -> io::Result<Vec<u8>> {
+    let mut reader = open(file_path)?;
+    let mut bytes = Vec::with_capacity(reader.get_ref().progress.total as usize);
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0)
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60)
+    }
+}
@@ -0,0 +1,266 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use pyo3::exceptions::{PyIOError, PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyIterator, PyList};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngError;
+use crate::png::Png;
+
+pyo3::create_exception!(pngme, PngmeError, PyValueError, "A PNG, chunk or chunk type pngme can't accept.");
+
+fn error(error: impl ToString) -> PyErr {
+    PngmeError::new_err(error.to_string())
+}
+
+/// I/O errors become `OSError` and missing chunks `IndexError`, like in the
+/// rest of Python. Everything else is a `PngmeError`.
+fn png_error(png_error: PngError) -> PyErr {
+    match png_error {
+        PngError::Io(io_error) => PyIOError::new_err(io_error.to_string()),
+        PngError::ChunkNotFound { .. } | PngError::IndexOutOfRange { .. } => PyIndexError::new_err(png_error.to_string()),
+        png_error => error(png_error)
+    }
+}
+
+/// A four-letter chunk type such as `ChunkType("ruSt")`.
+#[pyclass(name = "ChunkType", module = "pngme", frozen)]
+pub struct PyChunkType(ChunkType);
+
+#[pymethods]
+impl PyChunkType {
+    #[new]
+    fn new(chunk_type: &str) -> PyResult<PyChunkType> {
+        ChunkType::from_str(chunk_type).map(PyChunkType).map_err(error)
+    }
+
+    #[getter]
+    fn is_critical(&self) -> bool {
+        self.0.is_critical()
+    }
+
+    #[getter]
+    fn is_public(&self) -> bool {
+        self.0.is_public()
+    }
+
+    #[getter]
+    fn is_reserved_bit_valid(&self) -> bool {
+        self.0.is_reserved_bit_valid()
+    }
+
+    #[getter]
+    fn is_safe_to_copy(&self) -> bool {
+        self.0.is_safe_to_copy()
+    }
+
+    #[getter]
+    fn is_standard(&self) -> bool {
+        self.0.is_standard()
+    }
+
+    fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.bytes())
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ChunkType('{}')", self.0)
+    }
+
+    /// Chunk types also compare equal to their strings, so
+    /// `chunk.chunk_type == "IHDR"` works.
+    fn __eq__(&self, other: &Bound<'_, PyAny>) -> bool {
+        match other.extract::<PyRef<PyChunkType>>() {
+            Ok(other) => self.0 == other.0,
+            Err(_) => other.extract::<String>().is_ok_and(|other| self.0.to_string() == other)
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        u32::from_be_bytes(self.0.bytes()) as u64
+    }
+}
+
+/// Accepts either a `ChunkType` or its string.
+fn extract_chunk_type(chunk_type: &Bound<'_, PyAny>) -> PyResult<ChunkType> {
+    match chunk_type.extract::<PyRef<PyChunkType>>() {
+        Ok(chunk_type) => Ok(chunk_type.0.clone()),
+        Err(_) => ChunkType::from_str(&chunk_type.extract::<String>()?).map_err(error)
+    }
+}
+
+/// A chunk: its type, data and CRC. `Chunk("ruSt", b"hello")` computes the
+/// CRC.
+#[pyclass(name = "Chunk", module = "pngme", frozen)]
+pub struct PyChunk(Chunk);
+
+impl PyChunk {
+    fn copy(chunk: &Chunk) -> PyChunk {
+        PyChunk(Chunk::new(chunk.chunk_type().clone(), chunk.data().to_vec()))
+    }
+}
+
+#[pymethods]
+impl PyChunk {
+    #[new]
+    fn new(chunk_type: &Bound<'_, PyAny>, data: &[u8]) -> PyResult<PyChunk> {
+        Chunk::try_new(extract_chunk_type(chunk_type)?, data.to_vec()).map(PyChunk).map_err(error)
+    }
+
+    #[getter]
+    fn chunk_type(&self) -> PyChunkType {
+        PyChunkType(self.0.chunk_type().clone())
+    }
+
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.0.data())
+    }
+
+    #[getter]
+    fn length(&self) -> u32 {
+        self.0.length()
+    }
+
+    #[getter]
+    fn crc(&self) -> u32 {
+        self.0.crc()
+    }
+
+    /// The data decoded as UTF-8.
+    fn text(&self) -> PyResult<String> {
+        self.0.data_as_string().map_err(error)
+    }
+
+    /// The chunk as it is written in a file: length, type, data and CRC.
+    fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.as_bytes())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.data().len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Chunk {}, {} bytes>", self.0.chunk_type(), self.0.length())
+    }
+}
+
+/// A whole PNG. Read one with `Png.open(path)` or `Png.from_bytes(data)`,
+/// and iterate over it for its chunks.
+#[pyclass(name = "Png", module = "pngme")]
+pub struct PyPng(Png);
+
+#[pymethods]
+impl PyPng {
+    /// A PNG made of `chunks`, in order.
+    #[new]
+    fn new(chunks: Vec<PyRef<PyChunk>>) -> PyPng {
+        PyPng(Png::from_chunks(chunks.iter().map(|chunk| PyChunk::copy(&chunk.0).0).collect()))
+    }
+
+    #[staticmethod]
+    fn open(path: PathBuf) -> PyResult<PyPng> {
+        Png::try_from_path(&path).map(PyPng).map_err(png_error)
+    }
+
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<PyPng> {
+        Png::try_from(data).map(PyPng).map_err(png_error)
+    }
+
+    /// Writes the PNG to `path`, replacing whatever is there.
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        std::fs::write(path, self.0.as_bytes()).map_err(|io_error| PyIOError::new_err(io_error.to_string()))
+    }
+
+    /// Copies of the chunks, in order.
+    #[getter]
+    fn chunks(&self) -> Vec<PyChunk> {
+        self.0.chunks().iter().map(PyChunk::copy).collect()
+    }
+
+    /// The first chunk of type `chunk_type`, or None.
+    fn chunk_by_type(&self, chunk_type: &str) -> Option<PyChunk> {
+        self.0.chunk_by_type(chunk_type).map(PyChunk::copy)
+    }
+
+    fn chunks_by_type(&self, chunk_type: &str) -> Vec<PyChunk> {
+        self.0.chunks_by_type(chunk_type).into_iter().map(PyChunk::copy).collect()
+    }
+
+    /// Adds a copy of `chunk` before IEND.
+    fn append_chunk(&mut self, chunk: PyRef<PyChunk>) {
+        self.0.append_chunk(PyChunk::copy(&chunk.0).0);
+    }
+
+    /// Removes the first chunk of type `chunk_type` and returns it.
+    fn remove_chunk(&mut self, chunk_type: &str) -> PyResult<PyChunk> {
+        self.0.remove_chunk(chunk_type).map(PyChunk).map_err(png_error)
+    }
+
+    /// The whole file, signature included.
+    fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.as_bytes())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.chunks().len()
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.chunks())?.try_iter()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Png with {} chunks>", self.0.chunks().len())
+    }
+}
+
+/// The `pngme` Python module.
+#[pymodule]
+mod pngme {
+    #[pymodule_export]
+    use super::{PngmeError, PyChunk, PyChunkType, PyPng};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_round_trip() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "pngme").unwrap();
+            module.add_class::<PyChunkType>().unwrap();
+            module.add_class::<PyChunk>().unwrap();
+            module.add_class::<PyPng>().unwrap();
+            module.add("PngmeError", py.get_type::<PngmeError>()).unwrap();
+            pyo3::py_run!(py, module, r#"
+ihdr = module.Chunk("IHDR", bytes([0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]))
+png = module.Png([ihdr, module.Chunk("IEND", b"")])
+png.append_chunk(module.Chunk(module.ChunkType("ruSt"), b"hello"))
+assert [str(chunk.chunk_type) for chunk in png] == ["IHDR", "ruSt", "IEND"]
+
+png = module.Png.from_bytes(bytes(png))
+assert png.chunk_by_type("ruSt").text() == "hello"
+assert png.chunk_by_type("ruSt").chunk_type == "ruSt"
+assert not module.ChunkType("ruSt").is_public
+assert png.chunk_by_type("teSt") is None
+try:
+    module.ChunkType("r1St")
+    assert False
+except module.PngmeError:
+    pass
+"#);
+        });
+    }
+}
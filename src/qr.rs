@@ -0,0 +1,66 @@
+use qrcode::{Color, QrCode};
+
+use crate::builder::PngBuilder;
+use crate::error::QrError;
+use crate::ihdr::{ColorType, Ihdr};
+use crate::image_data::ImageData;
+use crate::png::Png;
+
+/// The light border around the code, in modules, that scanners need to find
+/// its edges.
+const QUIET_ZONE: usize = 4;
+
+/// Renders `data` as a QR code in a 1-bit grayscale PNG, with each module
+/// `scale` pixels wide.
+pub fn render(data: &[u8], scale: u32) -> Result<Png, QrError> {
+    let code = QrCode::new(data).map_err(|_| QrError::TooLong { len: data.len() })?;
+    let colors = code.to_colors();
+    let width = code.width();
+    let is_dark = |x: usize, y: usize| {
+        let (x, y) = (x.wrapping_sub(QUIET_ZONE), y.wrapping_sub(QUIET_ZONE));
+        x < width && y < width && colors[y * width + x] == Color::Dark
+    };
+
+    let scale = scale.max(1) as usize;
+    let side = (width + 2 * QUIET_ZONE) * scale;
+    let ihdr = Ihdr::new(side as u32, side as u32, 1, ColorType::Grayscale)?;
+    // Light pixels are 1 and dark ones 0, packed eight to a byte
+    let stride = side.div_ceil(8);
+    let mut pixels = vec![0xff; stride * side];
+    for y in 0..side {
+        for x in (0..side).filter(|x| is_dark(x / scale, y / scale)) {
+            pixels[y * stride + x / 8] &= !(0x80 >> (x % 8));
+        }
+    }
+
+    let image = ImageData { ihdr, filters: vec![0; side], pixels };
+    let builder = image
+        .to_chunks(1 << 20)?
+        .into_iter()
+        .fold(PngBuilder::from_ihdr(ihdr.to_chunk()), PngBuilder::chunk);
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let png = render(b"hello", 2).unwrap();
+        let image = ImageData::from_png(&png).unwrap();
+        // Version 1 is 21 modules wide, plus the quiet zone on both sides
+        assert_eq!(image.ihdr.width, (21 + 8) * 2);
+        assert_eq!(image.ihdr.bit_depth, 1);
+
+        let pixel = |x: usize, y: usize| image.pixels[y * image.stride() + x / 8] & (0x80 >> (x % 8)) != 0;
+        // The quiet zone is light and the finder pattern's corner dark
+        assert!(pixel(0, 0) && pixel(7, 7));
+        assert!(!pixel(8, 8) && !pixel(9, 9));
+    }
+
+    #[test]
+    fn test_render_too_long() {
+        assert!(matches!(render(&[0; 3000], 1), Err(QrError::TooLong { len: 3000 })));
+    }
+}
@@ -0,0 +1,31 @@
+use std::path::Path;
+use anyhow::anyhow;
+use anyhow::Result;
+
+/// Whether an input path is really an http:// or https:// URL.
+pub fn is_url(file_path: &Path) -> bool {
+    file_path
+        .to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Downloads the image at `url`, treating HTTP error statuses as failures.
+#[cfg(feature = "remote")]
+pub fn fetch(url: &Path) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let url = url.to_str().ok_or_else(|| anyhow!("{} is not a valid URL", url.display()))?;
+    let mut response = reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .map_err(|error| anyhow!("Could not download {}: {}", url, error))?;
+
+    // The body is read as it arrives rather than in one piece at the end
+    let mut bytes = Vec::with_capacity(response.content_length().unwrap_or(0).min(64 * 1024 * 1024) as usize);
+    response.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "remote"))]
+pub fn fetch(url: &Path) -> Result<Vec<u8>> {
+    Err(anyhow!("{} is a URL; reading from URLs needs pngme built with --features remote", url.display()))
+}
@@ -0,0 +1,157 @@
+use crate::{
+    Result,
+    chunk::Chunk,
+    chunk_ref::ChunkRefs,
+    chunk_type::ChunkType,
+    error::PngError,
+    png::Png
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CrcFix {
+    pub offset: usize,
+    pub chunk_type: ChunkType,
+    pub stored: u32,
+    pub computed: u32
+}
+
+/// A chunk `fix_crcs` left out, as its type isn't valid.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SkippedChunk {
+    pub offset: usize,
+    pub chunk_type: [u8;4],
+    pub length: u32
+}
+
+/// The image `fix_crcs` rebuilt and what it changed on the way.
+#[derive(Debug)]
+pub struct Repair {
+    pub png: Png,
+    pub fixes: Vec<CrcFix>,
+    pub skipped: Vec<SkippedChunk>
+}
+
+/// Parses a PNG without checking CRCs and recomputes every checksum. Chunks
+/// with an invalid type are skipped, as their length still says where the
+/// next one starts. Structural damage such as truncation is still an error,
+/// since there's no way to tell where the chunks are.
+pub fn fix_crcs(bytes: &[u8]) -> Result<Repair> {
+    if bytes.len() < Png::STANDARD_HEADER.len() {
+        return Err(PngError::TruncatedSignature { read: bytes.len() });
+    }
+    if bytes[..8] != Png::STANDARD_HEADER {
+        return Err(PngError::InvalidSignature);
+    }
+
+    let mut chunks = Vec::new();
+    let mut fixes = Vec::new();
+    let mut skipped = Vec::new();
+    let mut end = Png::STANDARD_HEADER.len();
+
+    for parsed in ChunkRefs::new(&bytes[8..], Png::STANDARD_HEADER.len()) {
+        let seen_iend = chunks.last().is_some_and(|chunk: &Chunk| chunk.chunk_type().bytes() == *b"IEND");
+        // Past IEND only intact chunks are kept, anything else is data appended to the file
+        let (offset, chunk) = match parsed {
+            Ok((_, chunk)) if seen_iend && chunk.to_chunk().is_err() => break,
+            Err(_) if seen_iend => break,
+            parsed => parsed?
+        };
+        end = offset + chunk.byte_len();
+        let chunk_type = match chunk.chunk_type() {
+            Ok(chunk_type) => chunk_type,
+            Err(_) => {
+                skipped.push(SkippedChunk { offset, chunk_type: chunk.chunk_type_bytes(), length: chunk.length() });
+                continue;
+            }
+        };
+
+        let repaired = Chunk::new(chunk_type.clone(), chunk.data().to_vec());
+        if repaired.crc() != chunk.crc() {
+            fixes.push(CrcFix {
+                offset,
+                chunk_type,
+                stored: chunk.crc(),
+                computed: repaired.crc()
+            });
+        }
+        chunks.push(repaired);
+    }
+
+    let mut png = Png::from_chunks(chunks);
+    png.set_trailing_data(bytes[end..].to_vec());
+    Ok(Repair { png, fixes, skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), b"first".to_vec()),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"second".to_vec())
+        ]).as_bytes()
+    }
+
+    #[test]
+    fn test_fix_crcs() {
+        let mut bytes = testing_bytes();
+        // Hand-edit the second chunk's data without touching its CRC
+        bytes[33] = b'S';
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+
+        let Repair { png, fixes, skipped } = fix_crcs(&bytes).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].offset, 25);
+        assert_eq!(fixes[0].chunk_type.to_string(), "ruSt");
+        assert_eq!(png.chunks()[1].data(), b"Second");
+        assert!(Png::try_from(png.as_bytes().as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_fix_crcs_nothing_to_fix() {
+        let bytes = testing_bytes();
+        let Repair { png, fixes, .. } = fix_crcs(&bytes).unwrap();
+
+        assert!(fixes.is_empty());
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_fix_crcs_skips_invalid_types() {
+        let mut bytes = testing_bytes();
+        // The first chunk's type becomes "F1St", and the second's CRC is broken
+        bytes[13] = b'1';
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        let Repair { png, fixes, skipped } = fix_crcs(&bytes).unwrap();
+        assert_eq!(skipped, vec![SkippedChunk { offset: 8, chunk_type: *b"F1St", length: 5 }]);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(png.chunks().len(), 1);
+        assert_eq!(png.chunks()[0].data(), b"second");
+    }
+
+    #[test]
+    fn test_fix_crcs_truncated() {
+        let bytes = testing_bytes();
+        assert!(fix_crcs(&bytes[..bytes.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn test_fix_crcs_keeps_trailing_data() {
+        let mut bytes = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"second".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new())
+        ]).as_bytes();
+        bytes[16] = b'S';
+        bytes.extend_from_slice(b"appended");
+
+        let Repair { png, fixes, .. } = fix_crcs(&bytes).unwrap();
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.trailing_data(), b"appended");
+    }
+}
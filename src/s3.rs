@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use anyhow::Result;
+#[cfg(feature = "s3")]
+use futures::TryStreamExt;
+#[cfg(feature = "s3")]
+use object_store::{aws::{AmazonS3, AmazonS3Builder}, path, ObjectStore, PutPayload};
+
+const S3_SCHEME: &str = "s3://";
+
+/// Whether a path is really an s3://bucket/key URL.
+pub fn is_s3(file_path: &Path) -> bool {
+    file_path.to_str().is_some_and(|path| path.starts_with(S3_SCHEME))
+}
+
+/// The store for the URL's bucket and the rest of the URL. Credentials and
+/// the region come from the usual AWS_* environment variables.
+#[cfg(feature = "s3")]
+fn locate(url: &Path) -> Result<(AmazonS3, &str, &str)> {
+    let url = url.to_str().unwrap_or_default();
+    let (bucket, key) = url[S3_SCHEME.len()..]
+        .split_once('/')
+        .filter(|(bucket, _)| !bucket.is_empty())
+        .ok_or_else(|| anyhow!("{} is not an s3://bucket/key URL", url))?;
+    let store = AmazonS3Builder::from_env().with_bucket_name(bucket).build()?;
+    Ok((store, bucket, key))
+}
+
+// The rest of pngme is synchronous, so each call gets a runtime of its own
+#[cfg(feature = "s3")]
+fn block_on<F: std::future::Future>(future: F) -> Result<F::Output> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    Ok(runtime.block_on(future))
+}
+
+#[cfg(not(feature = "s3"))]
+fn unsupported<T>(url: &Path) -> Result<T> {
+    Err(anyhow!("{} is an S3 URL; reading and writing S3 needs pngme built with --features s3", url.display()))
+}
+
+/// Downloads the object at an s3:// URL.
+#[cfg(feature = "s3")]
+pub fn get(url: &Path) -> Result<Vec<u8>> {
+    let (store, _, key) = locate(url)?;
+    let key = path::Path::parse(key)?;
+    let bytes = block_on(async { store.get(&key).await?.bytes().await })?
+        .map_err(|error| anyhow!("Could not download {}: {}", url.display(), error))?;
+    Ok(bytes.to_vec())
+}
+
+#[cfg(not(feature = "s3"))]
+pub fn get(url: &Path) -> Result<Vec<u8>> {
+    unsupported(url)
+}
+
+/// Uploads `bytes` to an s3:// URL, replacing whatever is there. S3 writes
+/// whole objects at once, so readers never see half of one.
+#[cfg(feature = "s3")]
+pub fn put(url: &Path, bytes: Vec<u8>) -> Result<()> {
+    let (store, _, key) = locate(url)?;
+    let key = path::Path::parse(key)?;
+    block_on(store.put(&key, PutPayload::from(bytes)))?
+        .map_err(|error| anyhow!("Could not upload {}: {}", url.display(), error))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "s3"))]
+pub fn put(url: &Path, _: Vec<u8>) -> Result<()> {
+    unsupported(url)
+}
+
+/// The s3:// URLs of the objects matching a pattern like
+/// `s3://bucket/images/*.png`. Only the objects under the part of the key
+/// before the first glob character are listed.
+#[cfg(feature = "s3")]
+pub fn glob(url: &Path, glob_characters: &[char]) -> Result<Vec<PathBuf>> {
+    let (store, bucket, pattern) = locate(url)?;
+    let literal = &pattern[..pattern.find(glob_characters).unwrap_or(pattern.len())];
+    let prefix = path::Path::parse(&literal[..literal.rfind('/').unwrap_or(0)])?;
+    let matcher = glob::Pattern::new(pattern)?;
+
+    let objects: Vec<_> = block_on(store.list(Some(&prefix)).try_collect())?
+        .map_err(|error| anyhow!("Could not list {}: {}", url.display(), error))?;
+    let mut urls: Vec<PathBuf> = objects
+        .into_iter()
+        .filter(|object| matcher.matches(object.location.as_ref()))
+        .map(|object| PathBuf::from(format!("{}{}/{}", S3_SCHEME, bucket, object.location)))
+        .collect();
+    urls.sort();
+    Ok(urls)
+}
+
+#[cfg(not(feature = "s3"))]
+pub fn glob(url: &Path, _: &[char]) -> Result<Vec<PathBuf>> {
+    unsupported(url)
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use aes_gcm::aead::{OsRng, rand_core::RngCore};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::crypto;
+use crate::error::ScatterError;
+
+/// The payload length stored at the start of the first piece.
+const LEN_PREFIX: usize = 4;
+/// The smallest piece, so the first one has room for the length.
+pub const MIN_PIECE_LEN: usize = 16;
+/// How many different types `crypto::derive_chunk_type` can give: three
+/// letters, then a fourth in either case.
+pub const MAX_PIECES: usize = 26 * 26 * 26 * 52;
+
+/// Everything else is derived from this, so the key itself is only hashed
+/// once.
+fn seed(key: &[u8]) -> Zeroizing<[u8;32]> {
+    let mut seed = Zeroizing::new([0; 32]);
+    Hkdf::<Sha256>::new(None, key)
+        .expand(b"pngme scatter", seed.as_mut())
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    seed
+}
+
+/// `len` bytes of SHA-256 in counter mode over the seed and `label`.
+fn keystream(seed: &[u8;32], label: &[u8], len: usize) -> Vec<u8> {
+    (0..len.div_ceil(32) as u64)
+        .flat_map(|block| Sha256::new().chain_update(seed).chain_update(label).chain_update(block.to_be_bytes()).finalize())
+        .take(len)
+        .collect()
+}
+
+/// The chunk type of each piece in turn, all different, ending once every
+/// type has been used.
+fn chunk_types(seed: &[u8;32]) -> impl Iterator<Item = ChunkType> + '_ {
+    let mut seen = HashSet::new();
+    (0u64..)
+        .map(|index| crypto::derive_chunk_type(&[&seed[..], &index.to_be_bytes()].concat()))
+        .filter(move |chunk_type| seen.insert(chunk_type.bytes()))
+        .take(MAX_PIECES)
+}
+
+/// The chunk type of the first piece of payloads scattered with `key`.
+pub fn first_chunk_type(key: &[u8]) -> ChunkType {
+    chunk_types(&seed(key)).next().expect("the chunk types never run out")
+}
+
+/// Splits `data` into pieces of `piece_len` bytes, kept between
+/// `MIN_PIECE_LEN` and `Chunk::MAX_LENGTH`, each in a private chunk whose
+/// type is derived from `key`, and returns them in an order derived from
+/// `key` too. The pieces are masked with a keystream and the last is
+/// padded with random bytes, so they all look alike and give away nothing
+/// about the payload but its rough size. Fails if one of the types is in
+/// `taken`, or if there would be more than `MAX_PIECES` pieces.
+pub fn scatter(data: &[u8], key: &[u8], piece_len: usize, taken: &[ChunkType]) -> Result<Vec<Chunk>, ScatterError> {
+    let seed = seed(key);
+    let piece_len = piece_len.clamp(MIN_PIECE_LEN, Chunk::MAX_LENGTH as usize);
+    let pieces = (LEN_PREFIX + data.len()).div_ceil(piece_len);
+    if pieces > MAX_PIECES {
+        return Err(ScatterError::TooManyPieces { pieces, max: MAX_PIECES });
+    }
+    let total = pieces * piece_len;
+
+    let mut stream = Zeroizing::new([&(data.len() as u32).to_be_bytes()[..], data].concat());
+    let padding_start = stream.len();
+    stream.resize(total, 0);
+    OsRng.fill_bytes(&mut stream[padding_start..]);
+    for (byte, mask) in stream.iter_mut().zip(keystream(&seed, b"mask", total)) {
+        *byte ^= mask;
+    }
+
+    let mut chunks = stream
+        .chunks(piece_len)
+        .zip(chunk_types(&seed))
+        .map(|(piece, chunk_type)| match taken.contains(&chunk_type) {
+            true => Err(ScatterError::TypeTaken { chunk_type: chunk_type.to_string() }),
+            false => Ok(Chunk::new(chunk_type, piece.to_vec()))
+        })
+        .collect::<Result<Vec<Chunk>, ScatterError>>()?;
+
+    // A Fisher-Yates shuffle driven by the key instead of chance
+    let order = keystream(&seed, b"order", 8 * chunks.len());
+    for index in (1..chunks.len()).rev() {
+        let random = u64::from_be_bytes(order[8 * index..8 * index + 8].try_into().unwrap());
+        chunks.swap(index, (random % (index as u64 + 1)) as usize);
+    }
+    Ok(chunks)
+}
+
+/// Reassembles a payload from `scatter`, asking `read_piece` for the data of
+/// the chunk of each type in turn, or None when there is none.
+pub fn gather<E: From<ScatterError>>(
+    key: &[u8],
+    mut read_piece: impl FnMut(&ChunkType) -> Result<Option<Vec<u8>>, E>
+) -> Result<Vec<u8>, E> {
+    let seed = seed(key);
+    let mut stream = Vec::new();
+    let mut len = None;
+    for (index, chunk_type) in chunk_types(&seed).enumerate() {
+        let piece = read_piece(&chunk_type)?
+            .ok_or_else(|| ScatterError::Missing { index, chunk_type: chunk_type.to_string() })?;
+        let piece_len = stream.len().checked_div(index).unwrap_or(piece.len());
+        if piece.len() != piece_len || piece_len < MIN_PIECE_LEN {
+            return Err(ScatterError::WrongLength { index, len: piece.len(), expected: piece_len.max(MIN_PIECE_LEN) }.into());
+        }
+        stream.extend_from_slice(&piece);
+
+        // The first piece says how many follow
+        let len = *len.get_or_insert_with(|| {
+            let mask = keystream(&seed, b"mask", LEN_PREFIX);
+            let prefix: Vec<u8> = stream.iter().zip(mask).map(|(byte, mask)| byte ^ mask).collect();
+            u32::from_be_bytes(prefix.try_into().unwrap()) as usize
+        });
+        if stream.len() >= LEN_PREFIX + len {
+            break;
+        }
+    }
+
+    for (byte, mask) in stream.iter_mut().zip(keystream(&seed, b"mask", LEN_PREFIX + len.unwrap_or(0))) {
+        *byte ^= mask;
+    }
+    stream.truncate(LEN_PREFIX + len.unwrap_or(0));
+    Ok(stream.split_off(LEN_PREFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn find(chunks: &[Chunk]) -> impl FnMut(&ChunkType) -> Result<Option<Vec<u8>>, ScatterError> + '_ {
+        |chunk_type| Ok(chunks.iter().find(|chunk| chunk.chunk_type() == chunk_type).map(|chunk| chunk.data().to_vec()))
+    }
+
+    #[test]
+    fn test_scatter_round_trip() {
+        let data: Vec<u8> = (0..1000u32).map(|byte| byte as u8).collect();
+        let chunks = scatter(&data, b"hunter2", 64, &[]).unwrap();
+
+        // 4 + 1000 bytes in pieces of 64
+        assert_eq!(chunks.len(), 16);
+        assert!(chunks.iter().all(|chunk| chunk.data().len() == 64 && !chunk.chunk_type().is_public()));
+        assert!(chunks.iter().any(|chunk| *chunk.chunk_type() == first_chunk_type(b"hunter2")));
+        assert_eq!(gather(b"hunter2", find(&chunks)).unwrap(), data);
+
+        // The order comes from the key, not from chance
+        let again = scatter(&data, b"hunter2", 64, &[]).unwrap();
+        let types = |chunks: &[Chunk]| chunks.iter().map(|chunk| chunk.chunk_type().to_string()).collect::<Vec<String>>();
+        assert_eq!(types(&chunks), types(&again));
+        // The padding is random, so the last piece masks differently each time
+        assert!(chunks.iter().zip(&again).any(|(chunk, other)| chunk.data() != other.data()));
+    }
+
+    #[test]
+    fn test_gather_errors() {
+        let chunks = scatter(b"short", b"hunter2", 16, &[]).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(matches!(gather(b"hunter3", find(&chunks)), Err(ScatterError::Missing { index: 0, .. })));
+
+        let mut chunks = scatter(&[1; 100], b"hunter2", 16, &[]).unwrap();
+        chunks.retain(|chunk| *chunk.chunk_type() == first_chunk_type(b"hunter2"));
+        assert!(matches!(gather(b"hunter2", find(&chunks)), Err(ScatterError::Missing { index: 1, .. })));
+
+        let taken = [first_chunk_type(b"hunter2"), ChunkType::from_str("ruSt").unwrap()];
+        assert!(matches!(scatter(b"short", b"hunter2", 16, &taken), Err(ScatterError::TypeTaken { .. })));
+
+        let data = vec![0; MAX_PIECES * 16];
+        assert!(matches!(scatter(&data, b"hunter2", 16, &[]), Err(ScatterError::TooManyPieces { max: MAX_PIECES, .. })));
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::Result;
+use crate::args::ServeArgs;
+
+/// Answers HTTP requests for list, encode, decode and remove until the
+/// process is stopped.
+#[cfg(feature = "serve")]
+pub fn serve(args: ServeArgs) -> Result<()> {
+    server::run(args)
+}
+
+#[cfg(not(feature = "serve"))]
+pub fn serve(_args: ServeArgs) -> Result<()> {
+    Err(anyhow::anyhow!("The HTTP server needs pngme built with --features serve"))
+}
+
+#[cfg(feature = "serve")]
+mod server {
+    use std::collections::HashMap;
+    use std::io::Read;
+    use anyhow::anyhow;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use log::{info, warn};
+    use serde::Serialize;
+    use zeroize::Zeroizing;
+    use pngme::crypto::{self, KdfParams};
+    use pngme::envelope::{self, Envelope};
+    use pngme::payload::{self, Options};
+    use pngme::text::{self, Text};
+    use pngme::{Chunk, ChunkType, Png, PngError};
+    use anyhow::Result;
+    use crate::args::ServeArgs;
+    use crate::commands::{check_writable, open_payload, DecodeKeys, DecodedView, MANIFEST_MAGIC, PART_MAGIC};
+    use crate::view::PngView;
+
+    /// The body of a successful response and its content type.
+    struct Reply {
+        content_type: &'static str,
+        body: Vec<u8>
+    }
+
+    impl Reply {
+        fn json<T: Serialize>(value: &T) -> Result<Reply> {
+            Ok(Reply { content_type: "application/json", body: serde_json::to_vec(value)? })
+        }
+
+        fn png(png: &Png) -> Reply {
+            Reply { content_type: "image/png", body: png.as_bytes() }
+        }
+    }
+
+    #[derive(Serialize)]
+    struct ServeErrorView {
+        error: String
+    }
+
+    /// The decoded query parameters of a request.
+    type Query = HashMap<String, String>;
+    /// Answers one endpoint, given the PNG from the request body and the
+    /// passphrase from the `X-Pngme-Passphrase` header.
+    type Endpoint = fn(Png, &Query, Option<Zeroizing<String>>) -> Result<Reply>;
+
+    pub fn run(ServeArgs {
+        listen,
+        threads,
+        max_body
+    }: ServeArgs) -> Result<()> {
+        let server = tiny_http::Server::http(listen.as_str())
+            .map_err(|error| anyhow!("Could not listen on {}: {}", listen, error))?;
+        info!("Serving on http://{}, press Ctrl-C to stop", server.server_addr());
+
+        // Each worker takes the next request as soon as it has answered its last
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    for request in server.incoming_requests() {
+                        respond(request, max_body);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn respond(mut request: tiny_http::Request, max_body: u64) {
+        let (status, reply) = match handle_request(&mut request, max_body) {
+            Ok(reply) => (200, reply),
+            Err((status, error)) => {
+                let view = ServeErrorView { error: error.to_string() };
+                (status, Reply::json(&view).expect("an error message always serializes"))
+            }
+        };
+        let line = format!("{} {} {}", request.method(), request.url(), status);
+
+        let content_type = tiny_http::Header::from_bytes("Content-Type", reply.content_type)
+            .expect("the content types are valid header values");
+        let response = tiny_http::Response::from_data(reply.body)
+            .with_status_code(status)
+            .with_header(content_type);
+        match request.respond(response) {
+            Ok(()) => info!("{}", line),
+            Err(error) => warn!("{} (not delivered: {})", line, error)
+        }
+    }
+
+    /// Reads the PNG in the request body and passes it to the endpoint for the
+    /// request's path, returning the status to answer with on failure.
+    fn handle_request(request: &mut tiny_http::Request, max_body: u64) -> Result<Reply, (u16, anyhow::Error)> {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((url.as_str(), ""));
+        let query: Query = form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+
+        let endpoint: Endpoint = match path {
+            "/list" => serve_list,
+            "/encode" => serve_encode,
+            "/decode" => serve_decode,
+            "/remove" => serve_remove,
+            _ => return Err((404, anyhow!("No such endpoint {}; use /list, /encode, /decode or /remove", path)))
+        };
+        if *request.method() != tiny_http::Method::Post {
+            return Err((405, anyhow!("Send the PNG in the body of a POST request")));
+        }
+
+        let too_large = || (413, anyhow!("The request body is larger than the limit of {} bytes", max_body));
+        if request.body_length().is_some_and(|len| len as u64 > max_body) {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        request
+            .as_reader()
+            .take(max_body + 1)
+            .read_to_end(&mut body)
+            .map_err(|error| (400, anyhow!("Could not read the request body: {}", error)))?;
+        if body.len() as u64 > max_body {
+            return Err(too_large());
+        }
+
+        let passphrase = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("X-Pngme-Passphrase"))
+            .map(|header| Zeroizing::new(header.value.to_string()));
+        let png = Png::try_from(body.as_slice()).map_err(|error| (400, error.into()))?;
+
+        endpoint(png, &query, passphrase).map_err(|error| {
+            let status = match error.downcast_ref::<PngError>() {
+                Some(PngError::ChunkNotFound { .. } | PngError::IndexOutOfRange { .. }) => 404,
+                _ => 400
+            };
+            (status, error)
+        })
+    }
+
+    fn query_param<'a>(query: &'a Query, name: &str) -> Result<&'a str> {
+        query
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("The {} query parameter is missing", name))
+    }
+
+    fn query_flag(query: &Query, name: &str) -> bool {
+        matches!(query.get(name).map(String::as_str), Some("true" | "1"))
+    }
+
+    fn query_index(query: &Query) -> Result<Option<usize>> {
+        query
+            .get("index")
+            .map(|index| index.parse().map_err(|_| anyhow!("The index query parameter must be a number, not {}", index)))
+            .transpose()
+    }
+
+    fn serve_list(png: Png, _: &Query, _: Option<Zeroizing<String>>) -> Result<Reply> {
+        Reply::json(&PngView::new(&png))
+    }
+
+    fn serve_encode(mut png: Png, query: &Query, passphrase: Option<Zeroizing<String>>) -> Result<Reply> {
+        let chunk_type = ChunkType::parse(query_param(query, "chunk_type")?, false)?;
+        check_writable(&chunk_type, query_flag(query, "force"))?;
+        let message = query_param(query, "message")?;
+        let message = match query_flag(query, "base64") {
+            true => STANDARD.decode(message).map_err(|error| anyhow!("The message is not valid base64: {}", error))?,
+            false => message.as_bytes().to_vec()
+        };
+
+        let options = Options { encrypted: passphrase.is_some(), ..Default::default() };
+        let data = match passphrase {
+            Some(passphrase) => crypto::encrypt(&message, passphrase.as_bytes())?,
+            None => message
+        };
+        png.append_chunk(Chunk::try_new(chunk_type, payload::wrap(options, &data))?);
+        Ok(Reply::png(&png))
+    }
+
+    /// The most Argon2id work a request may ask for, enough for everything
+    /// `/encode` writes.
+    const SERVE_MAX_KDF: KdfParams = KdfParams { memory_kib: 64 * 1024, iterations: 3, parallelism: 4 };
+    /// The most a compressed message may expand to in `/decode`.
+    const SERVE_MAX_DECODED: usize = 64 * 1024 * 1024;
+
+    /// Like `decode` for a single chunk, except that split and scattered
+    /// payloads and those needing a key file aren't supported.
+    fn serve_decode(png: Png, query: &Query, passphrase: Option<Zeroizing<String>>) -> Result<Reply> {
+        let chunk_type = query_param(query, "chunk_type")?;
+        let index = query_index(query)?;
+        let chunks = png.chunks_by_type(chunk_type);
+        let chunk = match chunks.get(index.unwrap_or(0)) {
+            Some(chunk) => *chunk,
+            None if chunks.is_empty() => return Err(PngError::ChunkNotFound { chunk_type: chunk_type.to_string() }.into()),
+            None => return Err(PngError::IndexOutOfRange { index: index.unwrap_or(0), len: chunks.len() }.into())
+        };
+
+        let payload = match text::is_text(chunk.chunk_type()) {
+            true => Zeroizing::new(Text::from_chunk(chunk)?.to_string().into_bytes()),
+            false => {
+                let data = chunk.data();
+                if data.starts_with(&MANIFEST_MAGIC) || data.starts_with(&PART_MAGIC) {
+                    return Err(anyhow!("Chunk {} is part of a payload split across chunks, which only the command line can decode", chunk_type));
+                }
+                let inner = match payload::is_pngme(data) {
+                    true => payload::parse(data).map_or(data, |(_, inner)| inner),
+                    false => data
+                };
+                // Without this the passphrase would be asked for on the server's terminal
+                if passphrase.is_none() && crypto::is_encrypted(inner) {
+                    return Err(anyhow!("Chunk {} is encrypted; send its passphrase in the X-Pngme-Passphrase header", chunk_type));
+                }
+                // Anyone can send a request, so the key derivation has to be cheap
+                if crypto::is_encrypted(inner) && !crypto::is_key_encrypted(inner) && !crypto::is_recipient_encrypted(inner) {
+                    let params = crypto::kdf_params(inner)?;
+                    if params.memory_kib > SERVE_MAX_KDF.memory_kib
+                        || params.iterations > SERVE_MAX_KDF.iterations
+                        || params.parallelism > SERVE_MAX_KDF.parallelism {
+                        return Err(anyhow!(
+                            "Chunk {} asks for more Argon2id work than the server allows ({} KiB, {} iterations and {} lanes at most)",
+                            chunk_type,
+                            SERVE_MAX_KDF.memory_kib,
+                            SERVE_MAX_KDF.iterations,
+                            SERVE_MAX_KDF.parallelism
+                        ));
+                    }
+                }
+                let mut keys = DecodeKeys {
+                    passphrase,
+                    key_file: None,
+                    private_key: None,
+                    age_identity: None,
+                    hmac_key: None
+                };
+                open_payload(chunk.chunk_type(), data, &mut keys, SERVE_MAX_DECODED)?
+            }
+        };
+
+        let (content_type, data) = match envelope::is_enveloped(&payload) {
+            true => {
+                let envelope = Envelope::parse(&payload)?;
+                (Some(envelope.content_type), envelope.data)
+            },
+            false => (None, &payload[..])
+        };
+        let text = match content_type {
+            Some(content_type) if !Envelope { content_type, data }.is_text() => None,
+            _ => std::str::from_utf8(data).ok()
+        };
+        let encoded;
+        let message = match (query_flag(query, "base64"), text) {
+            (true, _) => {
+                encoded = Zeroizing::new(STANDARD.encode(data));
+                encoded.as_str()
+            },
+            (false, Some(text)) => text,
+            (false, None) => return Err(anyhow!("Chunk {} holds binary data; pass base64=true to receive it", chunk_type))
+        };
+        Reply::json(&DecodedView { index: index.unwrap_or(0), chunk_type, content_type, message })
+    }
+
+    fn serve_remove(mut png: Png, query: &Query, _: Option<Zeroizing<String>>) -> Result<Reply> {
+        let chunk_type = query_param(query, "chunk_type")?;
+        match query_index(query)? {
+            Some(index) => png.remove_nth_chunk(chunk_type, index)?,
+            None => png.remove_chunk(chunk_type)?
+        };
+        Ok(Reply::png(&png))
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use ed25519_dalek::{
+    Signature, Signer, SigningKey, Verifier, VerifyingKey,
+    pkcs8::{DecodePrivateKey, DecodePublicKey}
+};
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::SignatureError,
+    png::Png
+};
+
+/// Ancillary, private and unsafe to copy, since editing the image
+/// invalidates an image signature.
+pub const SIGNATURE_CHUNK: &str = "siGN";
+
+const SCOPE_IMAGE: u8 = 0;
+const SCOPE_CHUNK: u8 = 1;
+
+/// What a signature covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signed {
+    /// The file header and every chunk except `siGN` chunks, so several
+    /// signatures can sit side by side.
+    Image,
+    /// The data of the first chunk of this type.
+    Chunk(ChunkType)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SignatureCheck {
+    pub signed: Signed,
+    pub valid: bool
+}
+
+/// Signs `signed` with `key` and appends the signature as a `siGN` chunk.
+pub fn sign(png: &mut Png, key: &SigningKey, signed: Signed) -> Result<(), SignatureError> {
+    let message = signed_message(png, &signed)?;
+    let signature = key.sign(&message);
+
+    let mut data = match &signed {
+        Signed::Image => vec![SCOPE_IMAGE],
+        Signed::Chunk(chunk_type) => [&[SCOPE_CHUNK][..], &chunk_type.bytes()].concat()
+    };
+    data.extend_from_slice(&signature.to_bytes());
+    png.append_chunk(Chunk::new(ChunkType::from_str(SIGNATURE_CHUNK).unwrap(), data));
+    Ok(())
+}
+
+/// Checks every `siGN` chunk against `key`. A signature made by another key
+/// is reported as invalid rather than skipped.
+pub fn verify(png: &Png, key: &VerifyingKey) -> Result<Vec<SignatureCheck>, SignatureError> {
+    let checks = png
+        .chunks_by_type(SIGNATURE_CHUNK)
+        .into_iter()
+        .map(|chunk| {
+            let (signed, signature) = parse_signature(chunk.data())?;
+            let message = signed_message(png, &signed)?;
+            Ok(SignatureCheck {
+                valid: key.verify(&message, &signature).is_ok(),
+                signed
+            })
+        })
+        .collect::<Result<Vec<SignatureCheck>, SignatureError>>()?;
+
+    if checks.is_empty() {
+        return Err(SignatureError::Unsigned);
+    }
+    Ok(checks)
+}
+
+/// Reads a PKCS#8 PEM private key, as written by
+/// `openssl genpkey -algorithm ed25519`.
+pub fn read_signing_key(path: &Path) -> Result<SigningKey, SignatureError> {
+    SigningKey::read_pkcs8_pem_file(path).map_err(|error| SignatureError::InvalidKey {
+        path: path.display().to_string(),
+        reason: error.to_string()
+    })
+}
+
+/// Reads a PEM public key, as written by `openssl pkey -pubout`.
+pub fn read_verifying_key(path: &Path) -> Result<VerifyingKey, SignatureError> {
+    VerifyingKey::read_public_key_pem_file(path).map_err(|error| SignatureError::InvalidKey {
+        path: path.display().to_string(),
+        reason: error.to_string()
+    })
+}
+
+fn parse_signature(data: &[u8]) -> Result<(Signed, Signature), SignatureError> {
+    let (signed, signature) = match data.first() {
+        Some(&SCOPE_IMAGE) => (Signed::Image, &data[1..]),
+        Some(&SCOPE_CHUNK) if data.len() >= 5 => {
+            let chunk_type: [u8;4] = data[1..5].try_into().unwrap();
+            (Signed::Chunk(ChunkType::try_from(chunk_type)?), &data[5..])
+        },
+        Some(&SCOPE_CHUNK) | None => return Err(SignatureError::Malformed { len: data.len() }),
+        Some(&scope) => return Err(SignatureError::UnknownScope { scope })
+    };
+
+    let signature = Signature::from_slice(signature)
+        .map_err(|_| SignatureError::Malformed { len: data.len() })?;
+    Ok((signed, signature))
+}
+
+/// The bytes a signature is computed over, prefixed with what they are so a
+/// signature can't be replayed against a different scope or chunk type.
+fn signed_message(png: &Png, signed: &Signed) -> Result<Vec<u8>, SignatureError> {
+    match signed {
+        Signed::Image => {
+            let mut message = vec![SCOPE_IMAGE];
+            message.extend_from_slice(png.header());
+            for chunk in png.chunks() {
+                if chunk.chunk_type().to_string() != SIGNATURE_CHUNK {
+                    message.extend(chunk.as_bytes());
+                }
+            }
+            Ok(message)
+        },
+        Signed::Chunk(chunk_type) => {
+            let chunk = png
+                .chunk_by_type(&chunk_type.to_string())
+                .ok_or_else(|| SignatureError::MissingChunk { chunk_type: chunk_type.to_string() })?;
+            Ok([&[SCOPE_CHUNK][..], &chunk_type.bytes(), chunk.data()].concat())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hello".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new())
+        ])
+    }
+
+    fn testing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn test_sign_and_verify_image() {
+        let mut png = testing_png();
+        sign(&mut png, &testing_key(1), Signed::Image).unwrap();
+
+        let types: Vec<String> = png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        assert_eq!(types, vec!["IHDR", "ruSt", "siGN", "IEND"]);
+        assert_eq!(
+            verify(&png, &testing_key(1).verifying_key()).unwrap(),
+            vec![SignatureCheck { signed: Signed::Image, valid: true }]
+        );
+        assert!(!verify(&png, &testing_key(2).verifying_key()).unwrap()[0].valid);
+    }
+
+    #[test]
+    fn test_image_signature_covers_every_chunk() {
+        let mut png = testing_png();
+        sign(&mut png, &testing_key(1), Signed::Image).unwrap();
+        png.append_chunk(Chunk::new(ChunkType::from_str("teXt").unwrap(), b"added".to_vec()));
+
+        assert!(!verify(&png, &testing_key(1).verifying_key()).unwrap()[0].valid);
+    }
+
+    #[test]
+    fn test_chunk_signature_survives_other_edits() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut png = testing_png();
+        sign(&mut png, &testing_key(1), Signed::Chunk(chunk_type.clone())).unwrap();
+        sign(&mut png, &testing_key(1), Signed::Image).unwrap();
+        png.append_chunk(Chunk::new(ChunkType::from_str("teXt").unwrap(), b"added".to_vec()));
+
+        let checks = verify(&png, &testing_key(1).verifying_key()).unwrap();
+        assert_eq!(checks[0], SignatureCheck { signed: Signed::Chunk(chunk_type), valid: true });
+        assert_eq!(checks[1], SignatureCheck { signed: Signed::Image, valid: false });
+    }
+
+    #[test]
+    fn test_verify_unsigned_and_missing_chunk() {
+        let mut png = testing_png();
+        assert!(matches!(verify(&png, &testing_key(1).verifying_key()), Err(SignatureError::Unsigned)));
+        assert!(matches!(
+            sign(&mut png, &testing_key(1), Signed::Chunk(ChunkType::from_str("miSs").unwrap())),
+            Err(SignatureError::MissingChunk { .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_signature_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str(SIGNATURE_CHUNK).unwrap(), vec![SCOPE_IMAGE, 1, 2]));
+        assert!(matches!(
+            verify(&png, &testing_key(1).verifying_key()),
+            Err(SignatureError::Malformed { len: 3 })
+        ));
+    }
+}
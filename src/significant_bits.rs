@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::SignificantBitsError;
+use crate::ihdr::{ColorType, Ihdr};
+
+/// The chunk type of the significant bits per channel.
+pub const SBIT: &str = "sBIT";
+
+/// How many bits of each channel carry information, e.g. 5, 6 and 5 for an
+/// image converted from RGB565. Indexed images give them for the palette's
+/// red, green and blue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignificantBits {
+    pub color_type: ColorType,
+    /// One value per entry of `channel_names`.
+    pub bits: Vec<u8>
+}
+
+impl SignificantBits {
+    pub fn parse(data: &[u8], color_type: ColorType) -> Result<SignificantBits, SignificantBitsError> {
+        let expected = channel_names(color_type).len();
+        if data.len() != expected {
+            return Err(SignificantBitsError::WrongLength { color_type, expected, len: data.len() });
+        }
+        Ok(SignificantBits { color_type, bits: data.to_vec() })
+    }
+
+    pub fn from_chunk(chunk: &Chunk, color_type: ColorType) -> Result<SignificantBits, SignificantBitsError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != SBIT {
+            return Err(SignificantBitsError::NotSbit { chunk_type });
+        }
+        SignificantBits::parse(chunk.data(), color_type)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::from_str(SBIT).unwrap(), self.bits.clone())
+    }
+
+    /// Checks every value is between 1 and the sample depth, which is 8 for
+    /// the palette of indexed images.
+    pub fn validate(&self, ihdr: &Ihdr) -> Result<(), SignificantBitsError> {
+        let max = match ihdr.color_type {
+            ColorType::Indexed => 8,
+            _ => ihdr.bit_depth
+        };
+        match self.bits.iter().find(|&&bits| bits == 0 || bits > max) {
+            Some(&bits) => Err(SignificantBitsError::OutOfRange { bits, max }),
+            None => Ok(())
+        }
+    }
+}
+
+/// The channels sBIT has a value for, in order.
+pub fn channel_names(color_type: ColorType) -> &'static [&'static str] {
+    match color_type {
+        ColorType::Grayscale => &["gray"],
+        ColorType::GrayscaleAlpha => &["gray", "alpha"],
+        ColorType::Rgb | ColorType::Indexed => &["red", "green", "blue"],
+        ColorType::Rgba => &["red", "green", "blue", "alpha"]
+    }
+}
+
+/// Shown as e.g. `significant bits: red 5, green 6, blue 5`.
+impl fmt::Display for SignificantBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = channel_names(self.color_type)
+            .iter()
+            .zip(&self.bits)
+            .map(|(name, bits)| format!("{} {}", name, bits))
+            .collect::<Vec<String>>();
+        write!(f, "significant bits: {}", channels.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_significant_bits_round_trip() {
+        let chunk = Chunk::new(ChunkType::from_str("sBIT").unwrap(), vec![5, 6, 5]);
+        let sbit = SignificantBits::from_chunk(&chunk, ColorType::Rgb).unwrap();
+
+        assert_eq!(sbit.bits, [5, 6, 5]);
+        assert_eq!(sbit.to_chunk().data(), chunk.data());
+        assert_eq!(sbit.to_string(), "significant bits: red 5, green 6, blue 5");
+        assert!(sbit.validate(&Ihdr::new(1, 1, 8, ColorType::Rgb).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_significant_bits_must_suit_image() {
+        assert!(matches!(
+            SignificantBits::parse(&[8, 8, 8], ColorType::GrayscaleAlpha),
+            Err(SignificantBitsError::WrongLength { expected: 2, len: 3, .. })
+        ));
+
+        let indexed = SignificantBits::parse(&[8, 8, 8], ColorType::Indexed).unwrap();
+        assert!(indexed.validate(&Ihdr::new(1, 1, 2, ColorType::Indexed).unwrap()).is_ok());
+        let gray = SignificantBits::parse(&[5], ColorType::Grayscale).unwrap();
+        assert!(matches!(
+            gray.validate(&Ihdr::new(1, 1, 4, ColorType::Grayscale).unwrap()),
+            Err(SignificantBitsError::OutOfRange { bits: 5, max: 4 })
+        ));
+        let zero = SignificantBits::parse(&[0, 8], ColorType::GrayscaleAlpha).unwrap();
+        assert!(zero.validate(&Ihdr::new(1, 1, 8, ColorType::GrayscaleAlpha).unwrap()).is_err());
+    }
+}
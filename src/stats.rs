@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use crate::chunk::Chunk;
+use crate::merge;
+use crate::png::Png;
+
+/// The length, type and CRC fields around the data of every chunk.
+pub const CHUNK_OVERHEAD: u64 = 12;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TypeStats {
+    pub count: usize,
+    /// The data of the chunks, without their length, type and CRC fields.
+    pub bytes: u64
+}
+
+/// Sizes and counts for one image, or summed over several with `add`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub images: usize,
+    /// The size of the files, signature and trailing data included.
+    pub bytes: u64,
+    pub types: BTreeMap<String, TypeStats>,
+    /// What the ancillary chunks take up, with their length, type and CRC
+    /// fields: all that could go without losing the pixels.
+    pub ancillary_bytes: u64,
+    /// Chunks of the types `merge::is_payload` counts as payloads, as pngme
+    /// writes them, with their length, type and CRC fields.
+    pub payload: TypeStats,
+    pub trailing_bytes: u64
+}
+
+impl Stats {
+    pub fn of(png: &Png) -> Stats {
+        let mut stats = Stats {
+            images: 1,
+            bytes: png.byte_len() as u64,
+            trailing_bytes: png.trailing_data().len() as u64,
+            ..Stats::default()
+        };
+        for chunk in png.chunks() {
+            let entry = stats.types.entry(chunk.chunk_type().to_string()).or_default();
+            entry.count += 1;
+            entry.bytes += chunk.length() as u64;
+
+            if !chunk.chunk_type().is_critical() {
+                stats.ancillary_bytes += byte_len(chunk);
+            }
+            if merge::is_payload(chunk.chunk_type()) {
+                stats.payload.count += 1;
+                stats.payload.bytes += byte_len(chunk);
+            }
+        }
+        stats
+    }
+
+    pub fn add(&mut self, other: &Stats) {
+        self.images += other.images;
+        self.bytes += other.bytes;
+        for (chunk_type, type_stats) in &other.types {
+            let entry = self.types.entry(chunk_type.clone()).or_default();
+            entry.count += type_stats.count;
+            entry.bytes += type_stats.bytes;
+        }
+        self.ancillary_bytes += other.ancillary_bytes;
+        self.payload.count += other.payload.count;
+        self.payload.bytes += other.payload.bytes;
+        self.trailing_bytes += other.trailing_bytes;
+    }
+}
+
+fn byte_len(chunk: &Chunk) -> u64 {
+    chunk.length() as u64 + CHUNK_OVERHEAD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        let chunk = |chunk_type: &str, data: &[u8]| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec());
+        let mut png = Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("IDAT", b"pixels"),
+            chunk("IDAT", b"more pixels"),
+            chunk("tEXt", b"Title\0x"),
+            chunk("ruSt", b"secret"),
+            chunk("IEND", b"")
+        ]);
+        png.set_trailing_data(b"appended".to_vec());
+        png
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::of(&testing_png());
+        assert_eq!(stats.images, 1);
+        assert_eq!(stats.bytes, 8 + 6 * 12 + 6 + 6 + 11 + 7 + 6 + 8);
+        assert_eq!(stats.types["IDAT"], TypeStats { count: 2, bytes: 17 });
+        assert_eq!(stats.types.len(), 5);
+        assert_eq!(stats.ancillary_bytes, 7 + 6 + 2 * CHUNK_OVERHEAD);
+        assert_eq!(stats.payload, TypeStats { count: 1, bytes: 6 + CHUNK_OVERHEAD });
+        assert_eq!(stats.trailing_bytes, 8);
+    }
+
+    #[test]
+    fn test_stats_add() {
+        let single = Stats::of(&testing_png());
+        let mut stats = single.clone();
+        stats.add(&single);
+
+        assert_eq!(stats.images, 2);
+        assert_eq!(stats.bytes, single.bytes * 2);
+        assert_eq!(stats.types["IDAT"], TypeStats { count: 4, bytes: 34 });
+        assert_eq!(stats.payload.count, 2);
+    }
+}
@@ -0,0 +1,248 @@
+use std::io::{self, Read, Write};
+
+use log::{debug, trace};
+
+use crate::{
+    Result,
+    chunk::{self, Chunk},
+    chunk_type::ChunkType,
+    error::{ChunkError, PngError},
+    png::{self, Png}
+};
+
+/// How much chunk data is held at a time while copying it through.
+const BUFFER_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Spliced {
+    /// Everything written, the spliced chunks included.
+    pub written: u64,
+    /// The bytes found after IEND, whether they were copied or dropped.
+    pub trailing_len: u64
+}
+
+/// Copies a PNG from `reader` to `writer` with `chunks` added, each where
+/// `Png::append_chunk` would put it, without ever holding more than one
+/// buffer of the image in memory. Every CRC is checked on the way through.
+/// Bytes after IEND are copied as they are if `keep_trailing` is set.
+pub fn splice<R: Read, W: Write>(mut reader: R, mut writer: W, chunks: Vec<Chunk>, keep_trailing: bool) -> Result<Spliced> {
+    let mut header = [0; 8];
+    let header_read = png::read_fully(&mut reader, &mut header)?;
+    if header_read < header.len() {
+        return Err(PngError::TruncatedSignature { read: header_read });
+    }
+    if header != Png::STANDARD_HEADER {
+        return Err(PngError::InvalidSignature);
+    }
+    writer.write_all(&header)?;
+
+    let mut spliced = Spliced { written: header.len() as u64, trailing_len: 0 };
+    let mut pending: Vec<Option<Chunk>> = chunks.into_iter().map(Some).collect();
+    let mut buffer = vec![0; BUFFER_LEN];
+    let mut offset = header.len();
+    let mut previous = None;
+    loop {
+        let mut chunk_header = [0; 8];
+        let read = png::read_fully(&mut reader, &mut chunk_header)?;
+        match read {
+            0 => break,
+            8 => (),
+            read => return Err(PngError::TruncatedChunk { offset, expected: 8, found: read as u64 })
+        }
+        let length = u32::from_be_bytes(chunk_header[..4].try_into().unwrap());
+        if length > Chunk::MAX_LENGTH {
+            return Err(PngError::InvalidChunk {
+                offset,
+                source: ChunkError::LengthTooLarge { length: length as usize }
+            });
+        }
+        let type_bytes: [u8;4] = chunk_header[4..].try_into().unwrap();
+        ChunkType::try_from(type_bytes).map_err(|error| PngError::InvalidChunk { offset, source: ChunkError::from(error) })?;
+        trace!("Copying {} chunk at offset {}, {} bytes of data", String::from_utf8_lossy(&type_bytes), offset, length);
+
+        // Chunks go in front of the first chunk they have to precede, IHDR
+        // first of all and IDAT after the last IDAT
+        let ends_idats = previous == Some(*b"IDAT") && type_bytes != *b"IDAT";
+        for chunk in pending.iter_mut().filter(|chunk| chunk.as_ref().is_some_and(|chunk| {
+            match &chunk.chunk_type().bytes() {
+                b"IHDR" => previous.is_none(),
+                _ if &type_bytes == b"IEND" => false,
+                b"IDAT" => ends_idats,
+                chunk_type => png::insert_before(chunk_type).contains(&&type_bytes)
+            }
+        })) {
+            spliced.written += write_chunk(&mut writer, chunk.take().unwrap())?;
+        }
+        // Whatever is left is ordered against a stand-in for the end of the
+        // image, so that IDAT added to an image without any still goes ahead
+        // of the chunks appended after it
+        if &type_bytes == b"IEND" {
+            let stand_in = |chunk_type: &[u8;4]| Chunk::new(ChunkType::try_from(*chunk_type).unwrap(), Vec::new());
+            let mut tail = Png::from_chunks(match previous == Some(*b"IDAT") {
+                true => vec![stand_in(b"IDAT"), stand_in(b"IEND")],
+                false => vec![stand_in(b"IEND")]
+            });
+            for chunk in pending.drain(..).flatten() {
+                tail.append_chunk(chunk);
+            }
+            let mut chunks = tail.into_chunks();
+            if previous == Some(*b"IDAT") {
+                chunks.remove(0);
+            }
+            let iend = chunks.iter().position(|chunk| chunk.chunk_type().bytes() == *b"IEND").unwrap();
+            pending = chunks.split_off(iend).into_iter().skip(1).map(Some).collect();
+            for chunk in chunks {
+                spliced.written += write_chunk(&mut writer, chunk)?;
+            }
+        }
+        previous = Some(type_bytes);
+
+        writer.write_all(&chunk_header)?;
+        let mut digest = chunk::CRC.digest();
+        digest.update(&type_bytes);
+        let mut remaining = length as usize;
+        while remaining > 0 {
+            let part = &mut buffer[..remaining.min(BUFFER_LEN)];
+            let read = png::read_fully(&mut reader, part)?;
+            if read < part.len() {
+                return Err(PngError::TruncatedChunk {
+                    offset,
+                    expected: length as u64 + 4,
+                    found: (length as usize - remaining + read) as u64
+                });
+            }
+            digest.update(part);
+            writer.write_all(part)?;
+            remaining -= read;
+        }
+
+        let mut crc = [0; 4];
+        let read = png::read_fully(&mut reader, &mut crc)?;
+        if read < crc.len() {
+            return Err(PngError::TruncatedChunk { offset, expected: length as u64 + 4, found: length as u64 + read as u64 });
+        }
+        let (expected, found) = (digest.finalize(), u32::from_be_bytes(crc));
+        if expected != found {
+            return Err(PngError::InvalidChunk { offset, source: ChunkError::InvalidCrc { expected, found } });
+        }
+        writer.write_all(&crc)?;
+
+        let byte_len = 12 + length as usize;
+        spliced.written += byte_len as u64;
+        offset += byte_len;
+        if &type_bytes == b"IEND" {
+            break;
+        }
+    }
+
+    // Without an IEND, whatever is left goes at the end
+    for chunk in pending.into_iter().flatten() {
+        spliced.written += write_chunk(&mut writer, chunk)?;
+    }
+
+    spliced.trailing_len = match keep_trailing {
+        true => io::copy(&mut reader, &mut writer)?,
+        false => io::copy(&mut reader, &mut io::sink())?
+    };
+    if keep_trailing {
+        spliced.written += spliced.trailing_len;
+    }
+    if spliced.trailing_len > 0 {
+        debug!("{} bytes of trailing data at offset {}", spliced.trailing_len, offset);
+    }
+    Ok(spliced)
+}
+
+fn write_chunk<W: Write>(writer: &mut W, chunk: Chunk) -> io::Result<u64> {
+    let bytes = chunk.as_bytes();
+    writer.write_all(&bytes)?;
+    Ok(bytes.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("IDAT", &[7; 3 * BUFFER_LEN / 2]),
+            chunk("tEXt", b"Title\0x"),
+            chunk("IEND", b"")
+        ])
+    }
+
+    fn new_chunks() -> Vec<Chunk> {
+        vec![chunk("ruSt", b"message"), chunk("pHYs", b"density"), chunk("noTe", b"note")]
+    }
+
+    #[test]
+    fn test_splice_matches_append_chunk() {
+        let mut png = testing_png();
+        let mut output = Vec::new();
+        let spliced = splice(png.as_bytes().as_slice(), &mut output, new_chunks(), true).unwrap();
+
+        for chunk in new_chunks() {
+            png.append_chunk(chunk);
+        }
+        assert_eq!(output, png.as_bytes());
+        assert_eq!(spliced, Spliced { written: output.len() as u64, trailing_len: 0 });
+    }
+
+    #[test]
+    fn test_splice_idat_matches_append_chunk() {
+        let extra = || vec![chunk("IDAT", b"more"), chunk("ruSt", b"message"), chunk("IDAT", b"last")];
+        let pngs = [
+            testing_png(),
+            Png::from_chunks(vec![chunk("IHDR", b"header"), chunk("IDAT", b"data"), chunk("IEND", b"")]),
+            Png::from_chunks(vec![chunk("IHDR", b"header"), chunk("IEND", b"")])
+        ];
+        for mut png in pngs {
+            let mut output = Vec::new();
+            splice(png.as_bytes().as_slice(), &mut output, extra(), true).unwrap();
+            for chunk in extra() {
+                png.append_chunk(chunk);
+            }
+            assert_eq!(output, png.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_splice_trailing_data() {
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend_from_slice(b"appended");
+
+        let mut output = Vec::new();
+        let spliced = splice(bytes.as_slice(), &mut output, new_chunks(), true).unwrap();
+        assert_eq!(spliced.trailing_len, 8);
+        assert!(output.ends_with(b"IEND\xae\x42\x60\x82appended"));
+
+        let mut stripped = Vec::new();
+        let spliced = splice(bytes.as_slice(), &mut stripped, new_chunks(), false).unwrap();
+        assert_eq!(spliced.trailing_len, 8);
+        assert_eq!(stripped.len(), output.len() - 8);
+    }
+
+    #[test]
+    fn test_splice_bad_crc() {
+        let mut bytes = testing_png().as_bytes();
+        // The last byte of the IDAT data
+        let end = bytes.len() - 12 - 19 - 4;
+        bytes[end - 1] ^= 1;
+
+        let result = splice(bytes.as_slice(), io::sink(), new_chunks(), true);
+        assert!(matches!(result, Err(PngError::InvalidChunk { source: ChunkError::InvalidCrc { .. }, .. })));
+    }
+
+    #[test]
+    fn test_splice_truncated() {
+        let bytes = testing_png().as_bytes();
+        assert!(splice(&bytes[..1000], io::sink(), new_chunks(), true).is_err());
+        assert!(matches!(splice(&b"GIF89a"[..], io::sink(), Vec::new(), true), Err(PngError::TruncatedSignature { .. })));
+    }
+}
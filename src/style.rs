@@ -0,0 +1,32 @@
+use anstream::ColorChoice;
+use anstyle::{AnsiColor, Effects, Style};
+
+pub const HEADER: Style = Style::new().effects(Effects::BOLD);
+pub const OK: Style = AnsiColor::Green.on_default();
+pub const WARNING: Style = AnsiColor::Yellow.on_default();
+pub const ERROR: Style = AnsiColor::Red.on_default().effects(Effects::BOLD);
+pub const DIM: Style = Style::new().effects(Effects::DIMMED);
+pub const ADDED: Style = AnsiColor::Green.on_default();
+pub const REMOVED: Style = AnsiColor::Red.on_default();
+pub const CHANGED: Style = AnsiColor::Yellow.on_default();
+
+/// Output is colored only on a terminal, and never with `--no-color` or
+/// the NO_COLOR environment variable set; anstream strips the styles
+/// everywhere else.
+pub fn init(no_color: bool) {
+    if no_color {
+        ColorChoice::Never.write_global();
+    }
+}
+
+/// Critical chunks in bold and private ones, where payloads usually are, in
+/// magenta, so both stand out in a long listing.
+pub fn chunk_type(chunk_type: &str) -> Style {
+    let bytes = chunk_type.as_bytes();
+    let uppercase = |position: usize| bytes.get(position).is_some_and(u8::is_ascii_uppercase);
+    match (uppercase(0), uppercase(1)) {
+        (true, _) => Style::new().effects(Effects::BOLD),
+        (false, false) => AnsiColor::Magenta.on_default(),
+        (false, true) => Style::new()
+    }
+}
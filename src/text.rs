@@ -0,0 +1,364 @@
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::TextError;
+
+/// The chunk type of uncompressed Latin-1 text.
+pub const TEXT: &str = "tEXt";
+/// The chunk type of deflate-compressed Latin-1 text.
+pub const COMPRESSED_TEXT: &str = "zTXt";
+/// The chunk type of UTF-8 text with a language tag, optionally compressed.
+pub const INTERNATIONAL_TEXT: &str = "iTXt";
+/// Keywords are 1 to 79 bytes long.
+pub const MAX_KEYWORD_LEN: usize = 79;
+
+/// Compression method 0, zlib-wrapped deflate, is the only one zTXt and iTXt
+/// allow.
+const DEFLATE: u8 = 0;
+/// The most compressed text may inflate to, as a few kilobytes of zTXt can
+/// otherwise claim gigabytes of memory.
+pub const MAX_INFLATED_LEN: usize = 16 * 1024 * 1024;
+
+/// A keyword/value pair from a tEXt, zTXt or iTXt chunk. All three start with
+/// the keyword in Latin-1 and a null separator. tEXt follows it with the text
+/// in Latin-1, zTXt with a compression method byte and the deflated text.
+/// iTXt follows it with a compression flag and method, the language tag and
+/// the translated keyword, each null-terminated, and the text in UTF-8,
+/// deflated when the flag is set. The registered keywords such as `Title`,
+/// `Author` and `Comment` are listed in the PNG specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Text {
+    pub keyword: String,
+    pub text: String,
+    /// Written as zTXt rather than tEXt, or as compressed iTXt.
+    pub compressed: bool,
+    /// Written as iTXt, which also allows text beyond Latin-1.
+    pub international: Option<International>
+}
+
+/// What iTXt adds to a keyword and its text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct International {
+    /// An RFC 3066 language tag such as `en` or `ja-JP`; empty when unknown.
+    pub language_tag: String,
+    /// The keyword in the text's language, which may be empty.
+    pub translated_keyword: String
+}
+
+impl Text {
+    /// Checks the keyword against the specification and that both the keyword
+    /// and the text can be written as Latin-1.
+    pub fn new(keyword: &str, text: &str) -> Result<Text, TextError> {
+        validate_keyword(keyword)?;
+        if text.contains('\0') {
+            return Err(TextError::NullInText);
+        }
+        to_latin1(text)?;
+        Ok(Text { keyword: keyword.to_string(), text: text.to_string(), compressed: false, international: None })
+    }
+
+    /// An iTXt entry, whose text and translated keyword may be any UTF-8
+    /// without null characters.
+    pub fn international(
+        keyword: &str,
+        text: &str,
+        language_tag: &str,
+        translated_keyword: &str
+    ) -> Result<Text, TextError> {
+        validate_keyword(keyword)?;
+        validate_language_tag(language_tag)?;
+        if text.contains('\0') || translated_keyword.contains('\0') {
+            return Err(TextError::NullInText);
+        }
+        Ok(Text {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+            compressed: false,
+            international: Some(International {
+                language_tag: language_tag.to_string(),
+                translated_keyword: translated_keyword.to_string()
+            })
+        })
+    }
+
+    /// The language tag of an iTXt entry, and an empty one for the others.
+    pub fn language_tag(&self) -> &str {
+        self.international.as_ref().map_or("", |international| &international.language_tag)
+    }
+
+    /// Reads a tEXt, zTXt or iTXt chunk, inflating compressed text.
+    pub fn from_chunk(chunk: &Chunk) -> Result<Text, TextError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if !is_text(chunk.chunk_type()) {
+            return Err(TextError::NotText { chunk_type });
+        }
+        let (keyword, rest) = split_at_null(chunk.data())?;
+        let keyword = from_latin1(keyword);
+        validate_keyword(&keyword)?;
+
+        match chunk_type.as_str() {
+            TEXT => Ok(Text { keyword, text: from_latin1(rest), compressed: false, international: None }),
+            COMPRESSED_TEXT => {
+                let (&method, deflated) = rest.split_first().ok_or(TextError::MissingCompressionMethod)?;
+                Ok(Text { keyword, text: from_latin1(&inflate(method, deflated)?), compressed: true, international: None })
+            },
+            _ => {
+                let (flags, rest) = rest.split_at_checked(2).ok_or(TextError::MissingCompressionMethod)?;
+                let compressed = match flags[0] {
+                    0 => false,
+                    1 => true,
+                    flag => return Err(TextError::InvalidCompressionFlag { flag })
+                };
+                let (language_tag, rest) = split_at_null(rest)?;
+                let (translated_keyword, text) = split_at_null(rest)?;
+
+                let language_tag = String::from_utf8(language_tag.to_vec())?;
+                validate_language_tag(&language_tag)?;
+                let text = match compressed {
+                    true => inflate(flags[1], text)?,
+                    false => text.to_vec()
+                };
+                Ok(Text {
+                    keyword,
+                    text: String::from_utf8(text)?,
+                    compressed,
+                    international: Some(International {
+                        language_tag,
+                        translated_keyword: String::from_utf8(translated_keyword.to_vec())?
+                    })
+                })
+            }
+        }
+    }
+
+    pub fn to_chunk(&self) -> Result<Chunk, TextError> {
+        let (chunk_type, rest) = match &self.international {
+            Some(International { language_tag, translated_keyword }) => {
+                let text = match self.compressed {
+                    true => deflate(self.text.as_bytes())?,
+                    false => self.text.as_bytes().to_vec()
+                };
+                let rest = [
+                    &[self.compressed as u8, DEFLATE][..],
+                    language_tag.as_bytes(),
+                    &[0],
+                    translated_keyword.as_bytes(),
+                    &[0],
+                    &text
+                ].concat();
+                (INTERNATIONAL_TEXT, rest)
+            },
+            None if self.compressed => (COMPRESSED_TEXT, [vec![DEFLATE], deflate(&to_latin1(&self.text)?)?].concat()),
+            None => (TEXT, to_latin1(&self.text)?)
+        };
+        let data = [to_latin1(&self.keyword)?, vec![0], rest].concat();
+        Ok(Chunk::try_new(ChunkType::from_str(chunk_type).unwrap(), data)?)
+    }
+}
+
+/// Shown as `keyword: text`, with the language tag and translated keyword
+/// of iTXt entries in brackets after the keyword when they're set.
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details: Vec<&str> = match &self.international {
+            Some(International { language_tag, translated_keyword }) => [language_tag, translated_keyword]
+                .into_iter()
+                .filter(|detail| !detail.is_empty())
+                .map(String::as_str)
+                .collect(),
+            None => Vec::new()
+        };
+        match details.is_empty() {
+            true => write!(f, "{}: {}", self.keyword, self.text),
+            false => write!(f, "{} [{}]: {}", self.keyword, details.join(", "), self.text)
+        }
+    }
+}
+
+/// Whether a chunk holds keyword/value text that `Text::from_chunk` reads.
+pub fn is_text(chunk_type: &ChunkType) -> bool {
+    [TEXT, COMPRESSED_TEXT, INTERNATIONAL_TEXT].contains(&chunk_type.to_string().as_str())
+}
+
+/// Keywords are printable Latin-1 without leading, trailing or consecutive
+/// spaces, so that readers can match them exactly.
+pub fn validate_keyword(keyword: &str) -> Result<(), TextError> {
+    let invalid = |reason| Err(TextError::InvalidKeyword { keyword: keyword.to_string(), reason });
+
+    let len = keyword.chars().count();
+    if len == 0 || len > MAX_KEYWORD_LEN {
+        return invalid("it must be 1 to 79 characters long");
+    }
+    if !keyword.chars().all(|c| matches!(c, ' '..='~' | '\u{a1}'..='\u{ff}')) {
+        return invalid("it may only contain printable Latin-1 characters");
+    }
+    if keyword.starts_with(' ') || keyword.ends_with(' ') || keyword.contains("  ") {
+        return invalid("it may not have leading, trailing or consecutive spaces");
+    }
+    Ok(())
+}
+
+/// Language tags are empty or hyphen-separated words of 1 to 8 ASCII
+/// letters and digits, e.g. `en`, `pt-BR` or `x-klingon`.
+pub fn validate_language_tag(language_tag: &str) -> Result<(), TextError> {
+    let valid = language_tag.is_empty() || language_tag
+        .split('-')
+        .all(|word| (1..=8).contains(&word.len()) && word.bytes().all(|byte| byte.is_ascii_alphanumeric()));
+    match valid {
+        true => Ok(()),
+        false => Err(TextError::InvalidLanguageTag { language_tag: language_tag.to_string() })
+    }
+}
+
+fn split_at_null(data: &[u8]) -> Result<(&[u8], &[u8]), TextError> {
+    let separator = data.iter().position(|&byte| byte == 0).ok_or(TextError::MissingSeparator)?;
+    Ok((&data[..separator], &data[separator + 1..]))
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, TextError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+fn inflate(method: u8, deflated: &[u8]) -> Result<Vec<u8>, TextError> {
+    if method != DEFLATE {
+        return Err(TextError::UnknownCompressionMethod { method });
+    }
+    let mut data = Vec::new();
+    ZlibDecoder::new(deflated).take(MAX_INFLATED_LEN as u64 + 1).read_to_end(&mut data)?;
+    if data.len() > MAX_INFLATED_LEN {
+        return Err(TextError::TooLong { limit: MAX_INFLATED_LEN });
+    }
+    Ok(data)
+}
+
+pub(crate) fn to_latin1(text: &str) -> Result<Vec<u8>, TextError> {
+    text.chars()
+        .map(|c| u8::try_from(c).map_err(|_| TextError::NotLatin1 { character: c }))
+        .collect()
+}
+
+pub(crate) fn from_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        let text = Text::new("Comment", "café\nau lait").unwrap();
+        let chunk = text.to_chunk().unwrap();
+
+        assert_eq!(chunk.chunk_type().to_string(), "tEXt");
+        assert_eq!(chunk.data(), b"Comment\0caf\xe9\nau lait");
+        assert!(is_text(chunk.chunk_type()));
+        assert_eq!(Text::from_chunk(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_invalid_keywords() {
+        for keyword in ["", " Comment", "Comment ", "Two  spaces", "Tab\there", "日本", &"a".repeat(80)] {
+            assert!(
+                matches!(validate_keyword(keyword), Err(TextError::InvalidKeyword { .. })),
+                "{:?}",
+                keyword
+            );
+        }
+        assert!(validate_keyword("Creation Time").is_ok());
+        assert!(validate_keyword(&"a".repeat(79)).is_ok());
+    }
+
+    #[test]
+    fn test_text_must_be_latin1() {
+        assert!(matches!(Text::new("Title", "日本"), Err(TextError::NotLatin1 { character: '日' })));
+        assert!(matches!(Text::new("Title", "a\0b"), Err(TextError::NullInText)));
+    }
+
+    #[test]
+    fn test_from_chunk_rejects_malformed_data() {
+        let chunk = |chunk_type, data: &[u8]| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec());
+
+        assert!(matches!(Text::from_chunk(&chunk("tEXt", b"Comment")), Err(TextError::MissingSeparator)));
+        assert!(matches!(Text::from_chunk(&chunk("tEXt", b"\0hello")), Err(TextError::InvalidKeyword { .. })));
+        assert!(matches!(Text::from_chunk(&chunk("ruSt", b"a\0b")), Err(TextError::NotText { .. })));
+    }
+
+    #[test]
+    fn test_compressed_text_round_trip() {
+        let text = Text { compressed: true, ..Text::new("Description", &"dice ".repeat(100)).unwrap() };
+        let chunk = text.to_chunk().unwrap();
+
+        assert_eq!(chunk.chunk_type().to_string(), "zTXt");
+        assert!(chunk.data().starts_with(b"Description\0\0"));
+        assert!(chunk.data().len() < 100);
+        assert!(is_text(chunk.chunk_type()));
+        assert_eq!(Text::from_chunk(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_compressed_text_checks_method() {
+        let chunk = |data: &[u8]| Chunk::new(ChunkType::from_str("zTXt").unwrap(), data.to_vec());
+
+        assert!(matches!(Text::from_chunk(&chunk(b"Comment\0")), Err(TextError::MissingCompressionMethod)));
+        assert!(matches!(
+            Text::from_chunk(&chunk(b"Comment\0\x01data")),
+            Err(TextError::UnknownCompressionMethod { method: 1 })
+        ));
+        assert!(matches!(Text::from_chunk(&chunk(b"Comment\0\0not zlib")), Err(TextError::Io(_))));
+
+        let bomb = [&b"Comment\0\0"[..], &deflate(&vec![b'a'; MAX_INFLATED_LEN + 1]).unwrap()].concat();
+        assert!(matches!(Text::from_chunk(&chunk(&bomb)), Err(TextError::TooLong { .. })));
+    }
+
+    #[test]
+    fn test_international_text_round_trip() {
+        for compressed in [false, true] {
+            let text = Text { compressed, ..Text::international("Title", "サイコロ", "ja-JP", "タイトル").unwrap() };
+            let chunk = text.to_chunk().unwrap();
+
+            assert_eq!(chunk.chunk_type().to_string(), "iTXt");
+            assert_eq!(chunk.data()[6], compressed as u8);
+            assert!(is_text(chunk.chunk_type()));
+            assert_eq!(Text::from_chunk(&chunk).unwrap(), text);
+            assert_eq!(text.to_string(), "Title [ja-JP, タイトル]: サイコロ");
+        }
+    }
+
+    #[test]
+    fn test_international_text_layout() {
+        let chunk = Chunk::new(ChunkType::from_str("iTXt").unwrap(), b"Comment\0\0\0en\0\0hello".to_vec());
+        let text = Text::from_chunk(&chunk).unwrap();
+
+        assert_eq!(text.text, "hello");
+        assert_eq!(text.language_tag(), "en");
+        assert_eq!(text.to_chunk().unwrap().data(), chunk.data());
+        assert_eq!(text.to_string(), "Comment [en]: hello");
+    }
+
+    #[test]
+    fn test_international_text_rejects_malformed_data() {
+        let chunk = |data: &[u8]| Chunk::new(ChunkType::from_str("iTXt").unwrap(), data.to_vec());
+
+        assert!(matches!(Text::from_chunk(&chunk(b"Comment\0\x02\0\0\0hi")), Err(TextError::InvalidCompressionFlag { flag: 2 })));
+        assert!(matches!(Text::from_chunk(&chunk(b"Comment\0\0\0en\0hi")), Err(TextError::MissingSeparator)));
+        assert!(matches!(Text::from_chunk(&chunk(b"Comment\0\0\0\0\0\xff")), Err(TextError::NotUtf8(_))));
+        assert!(matches!(Text::from_chunk(&chunk(b"Comment\0\0\0e n\0\0hi")), Err(TextError::InvalidLanguageTag { .. })));
+    }
+
+    #[test]
+    fn test_language_tags() {
+        for language_tag in ["", "en", "pt-BR", "x-klingon", "zh-Hant-TW"] {
+            assert!(validate_language_tag(language_tag).is_ok(), "{:?}", language_tag);
+        }
+        for language_tag in ["-", "en-", "en_US", "toolongword", "日本"] {
+            assert!(validate_language_tag(language_tag).is_err(), "{:?}", language_tag);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use crate::chunk_type::ChunkType;
+use crate::error::TiffError;
+
+const LITTLE_ENDIAN: &[u8;4] = b"II*\0";
+const BIG_ENDIAN: &[u8;4] = b"MM\0*";
+
+/// TIFF leaves tags from 65000 on for private use without registration.
+/// Each payload takes the next one that is free.
+const FIRST_PAYLOAD_TAG: u16 = 65000;
+/// Bytes with no particular interpretation.
+const TYPE_UNDEFINED: u16 = 7;
+const ENTRY_LEN: usize = 12;
+
+/// Whether `bytes` look like a classic TIFF rather than a PNG.
+pub fn is_tiff(bytes: &[u8]) -> bool {
+    bytes.starts_with(LITTLE_ENDIAN) || bytes.starts_with(BIG_ENDIAN)
+}
+
+/// One tag in an image file directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub tag: u16,
+    pub kind: u16,
+    pub count: u32,
+    /// The value itself if it fits in 4 bytes, and its offset otherwise, as
+    /// it is in the file.
+    pub value: [u8;4]
+}
+
+/// A TIFF with its first image file directory parsed. pngme payloads are
+/// tags in that directory, with their data appended to the file. Changing
+/// them writes a new directory at the end of the file and blanks the old
+/// one; the image data and every other tag's value stay where they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tiff {
+    data: Vec<u8>,
+    big_endian: bool,
+    ifd_offset: usize,
+    entries: Vec<Entry>,
+    next_ifd: [u8;4]
+}
+
+impl Tiff {
+    /// The tags of the first image file directory, in order.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Adds `data` under `chunk_type` in a new private tag.
+    pub fn add_payload(&mut self, chunk_type: &ChunkType, data: &[u8]) -> Result<(), TiffError> {
+        let tag = match self.entries.iter().map(|entry| entry.tag).filter(|&tag| tag >= FIRST_PAYLOAD_TAG).max() {
+            Some(u16::MAX) => return Err(TiffError::TooManyPayloads),
+            Some(tag) => tag + 1,
+            None => FIRST_PAYLOAD_TAG
+        };
+        let value = [&chunk_type.bytes()[..], data].concat();
+        let count = u32::try_from(value.len()).map_err(|_| TiffError::TooLarge)?;
+
+        // Values of 4 bytes or less go in the entry itself
+        let value = match value.len() <= 4 {
+            true => value[..].try_into().unwrap(),
+            false => {
+                let offset = self.append(&value)?;
+                self.u32_bytes(offset)
+            }
+        };
+        let position = self.entries.partition_point(|entry| entry.tag < tag);
+        self.entries.insert(position, Entry { tag, kind: TYPE_UNDEFINED, count, value });
+        self.write_ifd()
+    }
+
+    /// The payloads stored under `chunk_type`, in the order they were added.
+    pub fn payloads(&self, chunk_type: &str) -> Result<Vec<Vec<u8>>, TiffError> {
+        Ok(self
+            .stored()?
+            .into_iter()
+            .filter(|(_, data)| data[..4] == *chunk_type.as_bytes())
+            .map(|(_, data)| data[4..].to_vec())
+            .collect())
+    }
+
+    /// The chunk types payloads are stored under, in the order they first
+    /// appear.
+    pub fn payload_types(&self) -> Result<Vec<ChunkType>, TiffError> {
+        let mut chunk_types: Vec<ChunkType> = Vec::new();
+        for (_, data) in self.stored()? {
+            if let Ok(chunk_type) = ChunkType::try_from([data[0], data[1], data[2], data[3]]) {
+                if !chunk_types.contains(&chunk_type) {
+                    chunk_types.push(chunk_type);
+                }
+            }
+        }
+        Ok(chunk_types)
+    }
+
+    /// Removes the `index`th payload stored under `chunk_type` and returns
+    /// it, or None if there are fewer payloads. Its data is zeroed, as the
+    /// file isn't rearranged to reclaim the space.
+    pub fn remove_payload(&mut self, chunk_type: &str, index: usize) -> Result<Option<Vec<u8>>, TiffError> {
+        let found = self.stored()?.into_iter().filter(|(_, data)| data[..4] == *chunk_type.as_bytes()).nth(index);
+        let Some((position, data)) = found else {
+            return Ok(None);
+        };
+        let entry = self.entries.remove(position);
+        if entry.count > 4 {
+            let offset = self.u32(&entry.value) as usize;
+            self.data[offset..offset + entry.count as usize].fill(0);
+        }
+        self.write_ifd()?;
+        Ok(Some(data[4..].to_vec()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Every payload tag, with its position in the directory and its value.
+    fn stored(&self) -> Result<Vec<(usize, Vec<u8>)>, TiffError> {
+        let mut stored = Vec::new();
+        for (position, entry) in self.entries.iter().enumerate() {
+            if entry.tag < FIRST_PAYLOAD_TAG || entry.kind != TYPE_UNDEFINED {
+                continue;
+            }
+            let len = entry.count as usize;
+            let data = match len <= 4 {
+                true => entry.value[..len].to_vec(),
+                false => {
+                    let offset = self.u32(&entry.value) as usize;
+                    self.data
+                        .get(offset..offset.saturating_add(len))
+                        .ok_or(TiffError::Truncated { offset })?
+                        .to_vec()
+                }
+            };
+            if data.len() < 4 {
+                return Err(TiffError::BrokenPayload { tag: entry.tag });
+            }
+            stored.push((position, data));
+        }
+        Ok(stored)
+    }
+
+    /// Appends `bytes` on a word boundary, as TIFF wants, and returns where
+    /// they start.
+    fn append(&mut self, bytes: &[u8]) -> Result<u32, TiffError> {
+        if !self.data.len().is_multiple_of(2) {
+            self.data.push(0);
+        }
+        if u32::try_from(self.data.len() + bytes.len()).is_err() {
+            return Err(TiffError::TooLarge);
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(bytes);
+        Ok(offset)
+    }
+
+    /// Blanks the current directory and writes `entries` as the new one.
+    fn write_ifd(&mut self) -> Result<(), TiffError> {
+        // The directory was in bounds when parsed, and only ever moves to the end
+        let old_len = 2 + ifd_len(self.u16_at(self.ifd_offset));
+        self.data[self.ifd_offset..self.ifd_offset + old_len].fill(0);
+
+        let count = u16::try_from(self.entries.len()).map_err(|_| TiffError::TooManyPayloads)?;
+        let mut ifd = self.u16_bytes(count).to_vec();
+        for entry in &self.entries {
+            ifd.extend_from_slice(&self.u16_bytes(entry.tag));
+            ifd.extend_from_slice(&self.u16_bytes(entry.kind));
+            ifd.extend_from_slice(&self.u32_bytes(entry.count));
+            ifd.extend_from_slice(&entry.value);
+        }
+        ifd.extend_from_slice(&self.next_ifd);
+
+        let offset = self.append(&ifd)?;
+        let header_offset = self.u32_bytes(offset);
+        self.data[4..8].copy_from_slice(&header_offset);
+        self.ifd_offset = offset as usize;
+        Ok(())
+    }
+
+    fn u16_at(&self, offset: usize) -> u16 {
+        self.u16(&[self.data[offset], self.data[offset + 1]])
+    }
+
+    fn u16(&self, bytes: &[u8;2]) -> u16 {
+        if self.big_endian { u16::from_be_bytes(*bytes) } else { u16::from_le_bytes(*bytes) }
+    }
+
+    fn u32(&self, bytes: &[u8;4]) -> u32 {
+        if self.big_endian { u32::from_be_bytes(*bytes) } else { u32::from_le_bytes(*bytes) }
+    }
+
+    fn u16_bytes(&self, value: u16) -> [u8;2] {
+        if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+    }
+
+    fn u32_bytes(&self, value: u32) -> [u8;4] {
+        if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+    }
+}
+
+/// The length of a directory of `count` entries after its count, the
+/// next-directory offset included.
+fn ifd_len(count: u16) -> usize {
+    count as usize * ENTRY_LEN + 4
+}
+
+impl TryFrom<&[u8]> for Tiff {
+    type Error = TiffError;
+
+    fn try_from(bytes: &[u8]) -> Result<Tiff, TiffError> {
+        if !is_tiff(bytes) || bytes.len() < 8 {
+            return Err(TiffError::InvalidHeader);
+        }
+        let mut tiff = Tiff {
+            data: bytes.to_vec(),
+            big_endian: bytes.starts_with(BIG_ENDIAN),
+            ifd_offset: 0,
+            entries: Vec::new(),
+            next_ifd: [0;4]
+        };
+
+        let ifd_offset = tiff.u32(&bytes[4..8].try_into().unwrap()) as usize;
+        if bytes.len() < ifd_offset.saturating_add(2) {
+            return Err(TiffError::Truncated { offset: ifd_offset });
+        }
+        let count = tiff.u16_at(ifd_offset);
+        let end = ifd_offset + 2 + ifd_len(count);
+        let ifd = bytes.get(ifd_offset + 2..end).ok_or(TiffError::Truncated { offset: ifd_offset })?;
+
+        let (entries, next_ifd) = ifd.split_at(ifd.len() - 4);
+        tiff.entries = entries
+            .chunks(ENTRY_LEN)
+            .map(|entry| Entry {
+                tag: tiff.u16(&entry[0..2].try_into().unwrap()),
+                kind: tiff.u16(&entry[2..4].try_into().unwrap()),
+                count: tiff.u32(&entry[4..8].try_into().unwrap()),
+                value: entry[8..12].try_into().unwrap()
+            })
+            .collect();
+        tiff.next_ifd = next_ifd.try_into().unwrap();
+        tiff.ifd_offset = ifd_offset;
+        Ok(tiff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// A big-endian 1x1 TIFF whose directory holds ImageWidth, ImageLength
+    /// and a Software string stored out of line.
+    fn tiff() -> Vec<u8> {
+        let mut bytes = b"MM\0*\0\0\0\x08".to_vec();
+        bytes.extend_from_slice(&[0, 3]);
+        bytes.extend_from_slice(&[0x01, 0x00, 0, 3, 0, 0, 0, 1, 0, 1, 0, 0]);
+        bytes.extend_from_slice(&[0x01, 0x01, 0, 3, 0, 0, 0, 1, 0, 1, 0, 0]);
+        bytes.extend_from_slice(&[0x01, 0x31, 0, 2, 0, 0, 0, 6, 0, 0, 0, 50]);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(b"pngme\0");
+        bytes
+    }
+
+    #[test]
+    fn test_tiff_payloads() {
+        let mut parsed = Tiff::try_from(tiff().as_slice()).unwrap();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        parsed.add_payload(&chunk_type, b"hello").unwrap();
+        parsed.add_payload(&ChunkType::from_str("teSt").unwrap(), b"").unwrap();
+        parsed.add_payload(&chunk_type, b"world").unwrap();
+
+        let mut reparsed = Tiff::try_from(parsed.as_bytes()).unwrap();
+        let tags: Vec<u16> = reparsed.entries().iter().map(|entry| entry.tag).collect();
+        assert_eq!(tags, [0x0100, 0x0101, 0x0131, 65000, 65001, 65002]);
+        assert_eq!(reparsed.entries()[2], Tiff::try_from(tiff().as_slice()).unwrap().entries()[2]);
+        assert_eq!(reparsed.payloads("ruSt").unwrap(), vec![b"hello".to_vec(), b"world".to_vec()]);
+        assert_eq!(reparsed.payloads("teSt").unwrap(), vec![Vec::<u8>::new()]);
+        assert_eq!(reparsed.payload_types().unwrap().len(), 2);
+
+        assert_eq!(reparsed.remove_payload("ruSt", 0).unwrap(), Some(b"hello".to_vec()));
+        assert_eq!(reparsed.remove_payload("ruSt", 1).unwrap(), None);
+        assert!(!reparsed.as_bytes().windows(5).any(|window| window == b"hello"));
+        let reparsed = Tiff::try_from(reparsed.as_bytes()).unwrap();
+        assert_eq!(reparsed.payloads("ruSt").unwrap(), vec![b"world".to_vec()]);
+    }
+
+    #[test]
+    fn test_tiff_errors() {
+        assert!(matches!(Tiff::try_from(&b"GIF89a"[..]), Err(TiffError::InvalidHeader)));
+        assert!(matches!(Tiff::try_from(&tiff()[..30]), Err(TiffError::Truncated { offset: 8 })));
+    }
+}
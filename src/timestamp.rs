@@ -0,0 +1,256 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::TimestampError;
+
+/// The chunk type of the image's last-modification time.
+pub const TIME: &str = "tIME";
+/// A big-endian u16 year followed by month, day, hour, minute and second.
+pub const DATA_LEN: usize = 7;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// The time the image was last changed, in UTC, as stored in a tIME chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    /// Up to 60, to allow for leap seconds.
+    pub second: u8
+}
+
+impl Timestamp {
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Result<Timestamp, TimestampError> {
+        let out_of_range = |field, value: u8| Err(TimestampError::OutOfRange { field, value });
+        if !(1..=12).contains(&month) {
+            return out_of_range("month", month);
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return out_of_range("day", day);
+        }
+        if hour > 23 {
+            return out_of_range("hour", hour);
+        }
+        if minute > 59 {
+            return out_of_range("minute", minute);
+        }
+        if second > 60 {
+            return out_of_range("second", second);
+        }
+        Ok(Timestamp { year, month, day, hour, minute, second })
+    }
+
+    /// The current time from the system clock.
+    pub fn now() -> Timestamp {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        Timestamp::from_unix(seconds as i64).expect("the system clock is within the years tIME can hold")
+    }
+
+    /// Converts seconds since the Unix epoch, which must fall in the years 0 to 65535.
+    pub fn from_unix(seconds: i64) -> Result<Timestamp, TimestampError> {
+        let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+        let year = u16::try_from(year).map_err(|_| TimestampError::YearOutOfRange { year })?;
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        Timestamp::new(year, month, day, (time / 3600) as u8, (time / 60 % 60) as u8, (time % 60) as u8)
+    }
+
+    /// Seconds since the Unix epoch; a leap second counts as the next second.
+    pub fn to_unix(&self) -> i64 {
+        days_from_civil(self.year as i64, self.month, self.day) * SECONDS_PER_DAY
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Timestamp, TimestampError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != TIME {
+            return Err(TimestampError::NotTime { chunk_type });
+        }
+        let data: [u8;DATA_LEN] = chunk
+            .data()
+            .try_into()
+            .map_err(|_| TimestampError::WrongLength { len: chunk.data().len() })?;
+        Timestamp::new(u16::from_be_bytes([data[0], data[1]]), data[2], data[3], data[4], data[5], data[6])
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [
+            &self.year.to_be_bytes()[..],
+            &[self.month, self.day, self.hour, self.minute, self.second]
+        ].concat();
+        Chunk::new(ChunkType::from_str(TIME).unwrap(), data)
+    }
+}
+
+/// Shown in RFC 3339 form, e.g. `2023-07-21T09:30:00Z`.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Parses an RFC 3339 timestamp such as `2023-07-21T09:30:00Z` or
+/// `2023-07-21T11:30:00.5+02:00`, converting it to UTC and dropping any
+/// fraction of a second.
+impl FromStr for Timestamp {
+    type Err = TimestampError;
+
+    fn from_str(s: &str) -> Result<Timestamp, TimestampError> {
+        let invalid = || TimestampError::Invalid { timestamp: s.to_string() };
+        let bytes = s.as_bytes();
+        if !s.is_ascii()
+            || bytes.len() < 20
+            || bytes[4] != b'-' || bytes[7] != b'-'
+            || !matches!(bytes[10], b'T' | b't' | b' ')
+            || bytes[13] != b':' || bytes[16] != b':' {
+            return Err(invalid());
+        }
+        let number = |range: std::ops::Range<usize>| {
+            let digits = &s[range];
+            match digits.bytes().all(|byte| byte.is_ascii_digit()) {
+                true => digits.parse::<u16>().map_err(|_| invalid()),
+                false => Err(invalid())
+            }
+        };
+        let timestamp = Timestamp::new(
+            number(0..4)?,
+            number(5..7)? as u8,
+            number(8..10)? as u8,
+            number(11..13)? as u8,
+            number(14..16)? as u8,
+            number(17..19)? as u8
+        )?;
+
+        let mut rest = &s[19..];
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return Err(invalid());
+            }
+            rest = &fraction[digits..];
+        }
+        let offset = match rest.as_bytes() {
+            b"Z" | b"z" => 0,
+            [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+                let hours = rest[1..3].parse::<i64>().map_err(|_| invalid())?;
+                let minutes = rest[4..6].parse::<i64>().map_err(|_| invalid())?;
+                if hours > 23 || minutes > 59 {
+                    return Err(invalid());
+                }
+                let offset = hours * 3600 + minutes * 60;
+                if *sign == b'-' { -offset } else { offset }
+            },
+            _ => return Err(invalid())
+        };
+        match offset {
+            0 => Ok(timestamp),
+            offset => Timestamp::from_unix(timestamp.to_unix() - offset)
+        }
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date, after Howard
+/// Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u8;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_chunk_round_trip() {
+        let timestamp = Timestamp::new(2023, 7, 21, 9, 30, 5).unwrap();
+        let chunk = timestamp.to_chunk();
+
+        assert_eq!(chunk.chunk_type().to_string(), "tIME");
+        assert_eq!(chunk.data(), [0x07, 0xe7, 7, 21, 9, 30, 5]);
+        assert_eq!(Timestamp::from_chunk(&chunk).unwrap(), timestamp);
+        assert_eq!(timestamp.to_string(), "2023-07-21T09:30:05Z");
+    }
+
+    #[test]
+    fn test_from_chunk_validates_data() {
+        let chunk = |data: &[u8]| Chunk::new(ChunkType::from_str("tIME").unwrap(), data.to_vec());
+
+        assert!(matches!(Timestamp::from_chunk(&chunk(&[7, 231, 7, 21])), Err(TimestampError::WrongLength { len: 4 })));
+        assert!(matches!(
+            Timestamp::from_chunk(&chunk(&[7, 231, 2, 30, 0, 0, 0])),
+            Err(TimestampError::OutOfRange { field: "day", value: 30 })
+        ));
+        assert!(Timestamp::from_chunk(&chunk(&[7, 231, 12, 31, 23, 59, 60])).is_ok());
+    }
+
+    #[test]
+    fn test_unix_conversion() {
+        for (seconds, expected) in [
+            (0, "1970-01-01T00:00:00Z"),
+            (951_782_400, "2000-02-29T00:00:00Z"),
+            (1_689_931_805, "2023-07-21T09:30:05Z"),
+            (-1, "1969-12-31T23:59:59Z")
+        ] {
+            let timestamp = Timestamp::from_unix(seconds).unwrap();
+            assert_eq!(timestamp.to_string(), expected);
+            assert_eq!(timestamp.to_unix(), seconds);
+        }
+        assert!(Timestamp::from_unix(i64::MAX / 2).is_err());
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let parse = |s: &str| s.parse::<Timestamp>().map(|timestamp| timestamp.to_string());
+
+        assert_eq!(parse("2023-07-21T09:30:05Z").unwrap(), "2023-07-21T09:30:05Z");
+        assert_eq!(parse("2023-07-21t09:30:05.123z").unwrap(), "2023-07-21T09:30:05Z");
+        assert_eq!(parse("2023-07-21T11:30:05+02:00").unwrap(), "2023-07-21T09:30:05Z");
+        assert_eq!(parse("2023-12-31T22:00:00-03:00").unwrap(), "2024-01-01T01:00:00Z");
+        for invalid in ["2023-07-21", "2023-07-21T09:30:05", "2023-13-01T00:00:00Z", "2023-07-21T09:30:05+2:00", "2023-07-21T09:30:05.Z"] {
+            assert!(parse(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::TransparencyError;
+use crate::ihdr::{ColorType, Ihdr};
+
+/// The chunk type of simple transparency for images without an alpha channel.
+pub const TRNS: &str = "tRNS";
+
+/// Which pixels are transparent, laid out according to the image's color
+/// type. Images with an alpha channel can't have one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transparency {
+    /// For grayscale images, the one gray level that is fully transparent.
+    Gray(u16),
+    /// For RGB images, the one color that is fully transparent.
+    Rgb(u16, u16, u16),
+    /// For indexed images, the alpha of the first palette entries; the
+    /// entries after them are opaque.
+    PaletteAlpha(Vec<u8>)
+}
+
+impl Transparency {
+    pub fn parse(data: &[u8], color_type: ColorType) -> Result<Transparency, TransparencyError> {
+        let expected = match color_type {
+            ColorType::Grayscale => 2,
+            ColorType::Rgb => 6,
+            ColorType::Indexed => return Ok(Transparency::PaletteAlpha(data.to_vec())),
+            ColorType::GrayscaleAlpha | ColorType::Rgba => return Err(TransparencyError::NotAllowed { color_type })
+        };
+        if data.len() != expected {
+            return Err(TransparencyError::WrongLength { color_type, expected, len: data.len() });
+        }
+        let sample = |index: usize| u16::from_be_bytes([data[index * 2], data[index * 2 + 1]]);
+        Ok(match color_type {
+            ColorType::Grayscale => Transparency::Gray(sample(0)),
+            _ => Transparency::Rgb(sample(0), sample(1), sample(2))
+        })
+    }
+
+    pub fn from_chunk(chunk: &Chunk, color_type: ColorType) -> Result<Transparency, TransparencyError> {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk_type != TRNS {
+            return Err(TransparencyError::NotTrns { chunk_type });
+        }
+        Transparency::parse(chunk.data(), color_type)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match self {
+            Transparency::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Transparency::Rgb(red, green, blue) => [red, green, blue].iter().flat_map(|sample| sample.to_be_bytes()).collect(),
+            Transparency::PaletteAlpha(alpha) => alpha.clone()
+        };
+        Chunk::new(ChunkType::from_str(TRNS).unwrap(), data)
+    }
+
+    /// Checks the samples fit the image's bit depth and there are no more
+    /// alpha values than `palette_entries`.
+    pub fn validate(&self, ihdr: &Ihdr, palette_entries: usize) -> Result<(), TransparencyError> {
+        let samples = match self {
+            Transparency::Gray(gray) => vec![*gray],
+            Transparency::Rgb(red, green, blue) => vec![*red, *green, *blue],
+            Transparency::PaletteAlpha(alpha) if alpha.len() > palette_entries => {
+                return Err(TransparencyError::TooManyEntries { entries: alpha.len(), palette_entries });
+            },
+            Transparency::PaletteAlpha(_) => vec![]
+        };
+        match samples.into_iter().find(|&sample| sample > ihdr.max_sample()) {
+            Some(sample) => Err(TransparencyError::SampleOutOfRange { sample, bit_depth: ihdr.bit_depth }),
+            None => Ok(())
+        }
+    }
+}
+
+/// Shown as e.g. `gray 0 is transparent`, or as a summary of the palette alpha.
+impl fmt::Display for Transparency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transparency::Gray(gray) => write!(f, "gray {} is transparent", gray),
+            Transparency::Rgb(red, green, blue) => write!(f, "RGB ({}, {}, {}) is transparent", red, green, blue),
+            Transparency::PaletteAlpha(alpha) => write!(
+                f,
+                "alpha for {} palette entries, {} fully transparent",
+                alpha.len(),
+                alpha.iter().filter(|&&alpha| alpha == 0).count()
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transparency_round_trip() {
+        let transparency = Transparency::Gray(0x1234);
+        let chunk = transparency.to_chunk();
+
+        assert_eq!(chunk.chunk_type().to_string(), "tRNS");
+        assert_eq!(chunk.data(), [0x12, 0x34]);
+        assert_eq!(Transparency::from_chunk(&chunk, ColorType::Grayscale).unwrap(), transparency);
+
+        let palette = Transparency::parse(&[0, 128, 0, 255], ColorType::Indexed).unwrap();
+        assert_eq!(palette, Transparency::PaletteAlpha(vec![0, 128, 0, 255]));
+        assert_eq!(palette.to_string(), "alpha for 4 palette entries, 2 fully transparent");
+        assert_eq!(
+            Transparency::parse(&[0, 1, 0, 2, 0, 3], ColorType::Rgb).unwrap().to_string(),
+            "RGB (1, 2, 3) is transparent"
+        );
+    }
+
+    #[test]
+    fn test_transparency_must_suit_image() {
+        assert!(matches!(
+            Transparency::parse(&[0, 0], ColorType::Rgba),
+            Err(TransparencyError::NotAllowed { color_type: ColorType::Rgba })
+        ));
+        assert!(matches!(
+            Transparency::parse(&[0, 0, 0], ColorType::Grayscale),
+            Err(TransparencyError::WrongLength { expected: 2, len: 3, .. })
+        ));
+
+        let rgb = Ihdr::new(1, 1, 8, ColorType::Rgb).unwrap();
+        assert!(matches!(
+            Transparency::Rgb(0, 256, 0).validate(&rgb, 0),
+            Err(TransparencyError::SampleOutOfRange { sample: 256, bit_depth: 8 })
+        ));
+        let indexed = Ihdr::new(1, 1, 2, ColorType::Indexed).unwrap();
+        assert!(Transparency::PaletteAlpha(vec![0; 2]).validate(&indexed, 2).is_ok());
+        assert!(matches!(
+            Transparency::PaletteAlpha(vec![0; 3]).validate(&indexed, 2),
+            Err(TransparencyError::TooManyEntries { entries: 3, palette_entries: 2 })
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+use pngme::{Chunk, Png};
+use anyhow::Result;
+
+/// Lets the user browse the chunks of `png`, named `name`, and delete some.
+/// `describe` gives the text view of a chunk. Returns the edited image if
+/// the user saved it, or None if they quit without saving.
+#[cfg(feature = "tui")]
+pub fn browse(name: &str, png: Png, describe: &dyn Fn(&Chunk) -> String) -> Result<Option<Png>> {
+    browser::run(name, png, describe)
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn browse(_name: &str, _png: Png, _describe: &dyn Fn(&Chunk) -> String) -> Result<Option<Png>> {
+    Err(anyhow::anyhow!("The chunk browser needs pngme built with --features tui"))
+}
+
+#[cfg(feature = "tui")]
+mod browser {
+    use std::io::{self, IsTerminal};
+    use anyhow::anyhow;
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::Text;
+    use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+    use ratatui::{DefaultTerminal, Frame};
+    use pngme::hexdump::format_hexdump;
+    use pngme::{Chunk, Png};
+    use anyhow::Result;
+
+    /// Only the start of a chunk is shown, as formatting all of a large IDAT
+    /// would stall the browser; `pngme hexdump` prints the rest.
+    const MAX_SHOWN: usize = 64 * 1024;
+    const PAGE: u16 = 20;
+    const HELP: &str = "↑↓ select  Tab hex/text  PgUp/PgDn scroll  d delete  u undo  s save and quit  q quit";
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum View {
+        Hex,
+        Text
+    }
+
+    struct Browser<'a> {
+        name: &'a str,
+        chunks: Vec<Chunk>,
+        trailing: Vec<u8>,
+        describe: &'a dyn Fn(&Chunk) -> String,
+        list: ListState,
+        view: View,
+        scroll: u16,
+        // The detail pane's text, kept until the selection or view changes
+        detail: Option<String>,
+        // Deleted chunks and where they were, most recent last
+        deleted: Vec<(usize, Chunk)>,
+        status: Option<String>,
+        confirm_quit: bool
+    }
+
+    enum Action {
+        Continue,
+        Save,
+        Quit
+    }
+
+    pub fn run(name: &str, png: Png, describe: &dyn Fn(&Chunk) -> String) -> Result<Option<Png>> {
+        if !io::stdout().is_terminal() {
+            return Err(anyhow!("The chunk browser needs a terminal"));
+        }
+        let trailing = png.trailing_data().to_vec();
+        let mut browser = Browser {
+            name,
+            chunks: png.into_chunks(),
+            trailing,
+            describe,
+            list: ListState::default().with_selected(Some(0)),
+            view: View::Hex,
+            scroll: 0,
+            detail: None,
+            deleted: Vec::new(),
+            status: None,
+            confirm_quit: false
+        };
+
+        let mut terminal = ratatui::try_init()?;
+        let result = browser.event_loop(&mut terminal);
+        ratatui::try_restore()?;
+
+        Ok(match result? {
+            Action::Save => {
+                let mut png = Png::from_chunks(browser.chunks);
+                png.set_trailing_data(browser.trailing);
+                Some(png)
+            },
+            _ => None
+        })
+    }
+
+    impl Browser<'_> {
+        fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<Action> {
+            loop {
+                terminal.draw(|frame| self.draw(frame))?;
+                let Event::Key(key) = event::read()? else {
+                    continue;
+                };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match self.handle(key.code) {
+                    Action::Continue => {},
+                    action => return Ok(action)
+                }
+            }
+        }
+
+        fn handle(&mut self, code: KeyCode) -> Action {
+            let confirm_quit = std::mem::take(&mut self.confirm_quit);
+            self.status = None;
+            let last = self.chunks.len().saturating_sub(1);
+            let selected = self.list.selected().unwrap_or(0);
+            match code {
+                KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+                KeyCode::Down | KeyCode::Char('j') => self.select((selected + 1).min(last)),
+                KeyCode::Home | KeyCode::Char('g') => self.select(0),
+                KeyCode::End | KeyCode::Char('G') => self.select(last),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE),
+                KeyCode::PageDown | KeyCode::Char(' ') => self.scroll = self.scroll.saturating_add(PAGE),
+                KeyCode::Tab | KeyCode::Enter => {
+                    self.view = match self.view {
+                        View::Hex => View::Text,
+                        View::Text => View::Hex
+                    };
+                    self.scroll = 0;
+                    self.detail = None;
+                },
+                KeyCode::Char('d') | KeyCode::Delete => self.delete(selected),
+                KeyCode::Char('u') => self.undo(),
+                KeyCode::Char('s') if self.deleted.is_empty() => self.status = Some("Nothing to save".to_string()),
+                KeyCode::Char('s') => return Action::Save,
+                KeyCode::Char('q') | KeyCode::Esc if self.deleted.is_empty() || confirm_quit => return Action::Quit,
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    self.status = Some("Unsaved changes: press q again to discard them, or s to save".to_string());
+                    self.confirm_quit = true;
+                },
+                _ => {}
+            }
+            Action::Continue
+        }
+
+        fn select(&mut self, index: usize) {
+            if self.list.selected() != Some(index) {
+                self.list.select(Some(index));
+                self.scroll = 0;
+                self.detail = None;
+            }
+        }
+
+        fn delete(&mut self, index: usize) {
+            let Some(chunk) = self.chunks.get(index) else {
+                return;
+            };
+            // Without its critical chunks the image can't be decoded
+            if chunk.chunk_type().is_critical() {
+                self.status = Some(format!("{} is critical; removing it would break the image", chunk.chunk_type()));
+                return;
+            }
+            let chunk = self.chunks.remove(index);
+            self.status = Some(format!("Deleted {}", chunk.chunk_type()));
+            self.deleted.push((index, chunk));
+            self.list.select(Some(index.min(self.chunks.len().saturating_sub(1))));
+            self.scroll = 0;
+            self.detail = None;
+        }
+
+        fn undo(&mut self) {
+            match self.deleted.pop() {
+                Some((index, chunk)) => {
+                    self.status = Some(format!("Restored {}", chunk.chunk_type()));
+                    self.chunks.insert(index, chunk);
+                    self.list.select(Some(index));
+                    self.scroll = 0;
+                    self.detail = None;
+                },
+                None => self.status = Some("Nothing to undo".to_string())
+            }
+        }
+
+        fn detail(&mut self) -> &str {
+            let Some(chunk) = self.list.selected().and_then(|index| self.chunks.get(index)) else {
+                return "";
+            };
+            let (view, describe) = (self.view, self.describe);
+            self.detail.get_or_insert_with(|| {
+                let data = chunk.data();
+                let mut detail = match view {
+                    View::Hex => format_hexdump(&data[..data.len().min(MAX_SHOWN)]),
+                    View::Text => describe(chunk)
+                };
+                if view == View::Hex && data.len() > MAX_SHOWN {
+                    detail.push_str(&format!("… {} more bytes; use `pngme hexdump` for all of them\n", data.len() - MAX_SHOWN));
+                }
+                detail
+            })
+        }
+
+        fn draw(&mut self, frame: &mut Frame) {
+            let [main, status] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+            let [chunks, detail] = Layout::horizontal([Constraint::Length(30), Constraint::Min(0)]).areas(main);
+
+            let items: Vec<ListItem> = self
+                .chunks
+                .iter()
+                .enumerate()
+                .map(|(index, chunk)| {
+                    let chunk_type = chunk.chunk_type();
+                    // As in `pngme list`: critical in bold, private in magenta
+                    let style = match (chunk_type.is_critical(), chunk_type.is_public()) {
+                        (true, _) => Style::new().add_modifier(Modifier::BOLD),
+                        (false, false) => Style::new().fg(Color::Magenta),
+                        (false, true) => Style::new()
+                    };
+                    ListItem::new(format!("{:>4}  {}  {:>10}", index, chunk_type, chunk.length())).style(style)
+                })
+                .collect();
+            let modified = if self.deleted.is_empty() { "" } else { " [modified]" };
+            let list = List::new(items)
+                .block(Block::bordered().title(format!(" {}{} ", self.name, modified)))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(list, chunks, &mut self.list);
+
+            let title = match self.list.selected().and_then(|index| self.chunks.get(index)) {
+                Some(chunk) => format!(
+                    " {} ({}) ",
+                    chunk.chunk_type(),
+                    match self.view {
+                        View::Hex => "hex",
+                        View::Text => "text"
+                    }
+                ),
+                None => String::new()
+            };
+            let (view, scroll) = (self.view, self.scroll);
+            let mut paragraph = Paragraph::new(Text::raw(self.detail()))
+                .block(Block::bordered().title(title))
+                .scroll((scroll, 0));
+            if view == View::Text {
+                paragraph = paragraph.wrap(Wrap { trim: false });
+            }
+            frame.render_widget(paragraph, detail);
+
+            let line = self.status.as_deref().unwrap_or(HELP);
+            frame.render_widget(Paragraph::new(line).style(Style::new().add_modifier(Modifier::DIM)), status);
+        }
+    }
+}
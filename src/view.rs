@@ -0,0 +1,124 @@
+use serde::Serialize;
+use std::io::{Read, Seek};
+use pngme::{
+    check::{Problem, Severity},
+    ihdr::Ihdr,
+    lazy::ChunkHeader,
+    Chunk,
+    ChunkType,
+    LazyPng,
+    Png
+};
+
+#[derive(Serialize)]
+pub struct ChunkView {
+    pub index: usize,
+    pub chunk_type: String,
+    pub length: u32,
+    pub crc: u32,
+    pub offset: usize,
+    pub critical: bool,
+    pub public: bool,
+    pub safe_to_copy: bool
+}
+
+impl ChunkView {
+    pub fn new(index: usize, offset: usize, chunk: &Chunk) -> ChunkView {
+        ChunkView::from_parts(index, offset, chunk.chunk_type(), chunk.length(), chunk.crc())
+    }
+
+    pub fn from_header(index: usize, chunk: &ChunkHeader) -> ChunkView {
+        ChunkView::from_parts(index, chunk.offset(), chunk.chunk_type(), chunk.length(), chunk.crc())
+    }
+
+    fn from_parts(index: usize, offset: usize, chunk_type: &ChunkType, length: u32, crc: u32) -> ChunkView {
+        ChunkView {
+            index,
+            chunk_type: chunk_type.to_string(),
+            length,
+            crc,
+            offset,
+            critical: chunk_type.is_critical(),
+            public: chunk_type.is_public(),
+            safe_to_copy: chunk_type.is_safe_to_copy()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PngView {
+    pub header: [u8;8],
+    pub chunks: Vec<ChunkView>
+}
+
+impl PngView {
+    pub fn new(png: &Png) -> PngView {
+        let chunks = png
+            .chunks_with_offsets()
+            .enumerate()
+            .map(|(index, (offset, chunk))| ChunkView::new(index, offset, chunk))
+            .collect();
+
+        PngView {
+            header: *png.header(),
+            chunks
+        }
+    }
+
+    pub fn from_lazy<R: Read + Seek>(png: &LazyPng<R>) -> PngView {
+        let chunks = png
+            .chunk_headers()
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| ChunkView::from_header(index, chunk))
+            .collect();
+
+        PngView {
+            header: *png.header(),
+            chunks
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ImageView {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: u8,
+    pub color_type_name: String,
+    pub interlaced: bool
+}
+
+impl ImageView {
+    pub fn new(ihdr: &Ihdr) -> ImageView {
+        ImageView {
+            width: ihdr.width,
+            height: ihdr.height,
+            bit_depth: ihdr.bit_depth,
+            color_type: ihdr.color_type.to_byte(),
+            color_type_name: ihdr.color_type.to_string(),
+            interlaced: ihdr.interlaced
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProblemView<'a> {
+    pub offset: usize,
+    pub message: &'a str,
+    pub severity: &'static str
+}
+
+impl ProblemView<'_> {
+    pub fn new(problem: &Problem) -> ProblemView<'_> {
+        ProblemView {
+            offset: problem.offset,
+            message: &problem.message,
+            severity: match problem.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning"
+            }
+        }
+    }
+}
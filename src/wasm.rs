@@ -0,0 +1,119 @@
+use wasm_bindgen::prelude::*;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::PngError;
+use crate::png::Png;
+use crate::{compress, crypto, envelope, payload};
+
+/// One row of `list`, with the same fields as `pngme list --format json`.
+#[derive(serde::Serialize)]
+struct ListedChunk {
+    index: usize,
+    chunk_type: String,
+    length: u32,
+    crc: u32,
+    offset: usize,
+    critical: bool,
+    public: bool,
+    safe_to_copy: bool
+}
+
+/// Adds a chunk of type `chunk_type` holding `message` to the PNG in `png`
+/// and returns the new file. The message is encrypted like `encode --encrypt`
+/// when a passphrase is given. Critical and standard chunk types are refused,
+/// as `encode` does without `--force`.
+#[wasm_bindgen]
+pub fn encode(png: &[u8], chunk_type: &str, message: &[u8], passphrase: Option<String>) -> Result<Vec<u8>, JsError> {
+    let mut png = Png::try_from(png)?;
+    let chunk_type = ChunkType::parse(chunk_type, false)?;
+    if chunk_type.is_critical() || chunk_type.is_standard() {
+        return Err(JsError::new(&format!("Refusing to write chunk type {}; use a private ancillary type", chunk_type)));
+    }
+
+    let options = payload::Options { encrypted: passphrase.is_some(), ..Default::default() };
+    let data = match passphrase {
+        Some(passphrase) => crypto::encrypt(message, passphrase.as_bytes())?,
+        None => message.to_vec()
+    };
+    png.append_chunk(Chunk::try_new(chunk_type, payload::wrap(options, &data))?);
+    Ok(png.as_bytes())
+}
+
+/// The message in the first chunk of type `chunk_type`, decrypted with
+/// `passphrase` and decompressed as needed. Envelopes are unwrapped to their
+/// data. Payloads that need anything other than a passphrase, or that were
+/// split across chunks, aren't supported here.
+#[wasm_bindgen]
+pub fn decode(png: &[u8], chunk_type: &str, passphrase: Option<String>) -> Result<Vec<u8>, JsError> {
+    let png = Png::try_from(png)?;
+    let chunk = png
+        .chunk_by_type(chunk_type)
+        .ok_or_else(|| PngError::ChunkNotFound { chunk_type: chunk_type.to_string() })?;
+    let data = match payload::is_pngme(chunk.data()) {
+        true => payload::parse(chunk.data())?.1,
+        false => chunk.data()
+    };
+
+    let needs_key = crypto::is_key_encrypted(data) || crypto::is_recipient_encrypted(data) || crypto::is_age(data);
+    if needs_key || crypto::is_authenticated(data) {
+        return Err(JsError::new(&format!("Chunk {} needs a key that only the pngme command line can use", chunk_type)));
+    }
+    let data = match (crypto::is_encrypted(data), passphrase) {
+        (true, Some(passphrase)) => crypto::decrypt(data, passphrase.as_bytes())?.to_vec(),
+        (true, None) => return Err(JsError::new(&format!("Chunk {} is encrypted; a passphrase is needed", chunk_type))),
+        (false, _) => data.to_vec()
+    };
+    let data = match compress::is_compressed(&data) {
+        true => compress::decompress(&data)?,
+        false => data
+    };
+    match envelope::is_enveloped(&data) {
+        true => Ok(envelope::Envelope::parse(&data)?.data.to_vec()),
+        false => Ok(data)
+    }
+}
+
+/// The chunks of the PNG in `png` as a JSON array, for `JSON.parse`.
+#[wasm_bindgen]
+pub fn list(png: &[u8]) -> Result<String, JsError> {
+    let png = Png::try_from(png)?;
+    let chunks: Vec<ListedChunk> = png
+        .chunks_with_offsets()
+        .enumerate()
+        .map(|(index, (offset, chunk))| ListedChunk {
+            index,
+            chunk_type: chunk.chunk_type().to_string(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+            offset,
+            critical: chunk.chunk_type().is_critical(),
+            public: chunk.chunk_type().is_public(),
+            safe_to_copy: chunk.chunk_type().is_safe_to_copy()
+        })
+        .collect();
+    Ok(serde_json::to_string(&chunks)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only the success paths run natively, since building a JsError calls into JavaScript
+    #[test]
+    fn test_wasm_round_trip() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::parse("IHDR", false).unwrap(), vec![0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]),
+            Chunk::new(ChunkType::parse("IEND", false).unwrap(), vec![])
+        ]).as_bytes();
+
+        let encoded = encode(&png, "ruSt", b"hello", None).unwrap();
+        assert_eq!(decode(&encoded, "ruSt", None).unwrap(), b"hello");
+        let encrypted = encode(&png, "ruSt", b"secret", Some("hunter2".to_string())).unwrap();
+        assert_eq!(decode(&encrypted, "ruSt", Some("hunter2".to_string())).unwrap(), b"secret");
+
+        let listed = list(&encoded).unwrap();
+        assert!(listed.starts_with(r#"[{"index":0,"chunk_type":"IHDR","length":13"#));
+        assert!(listed.contains(r#""chunk_type":"ruSt","length":11"#));
+    }
+}